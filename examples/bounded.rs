@@ -1,3 +1,4 @@
+use swoop::constraints::Bounds;
 use swoop::minimise_scalar::{bounded, ScalarObjectiveFunction};
 use swoop::SwoopErrors;
 
@@ -22,7 +23,8 @@ impl ScalarObjectiveFunction for MyObjectiveFunction {
 #[tokio::main]
 async fn main() -> Result<(), SwoopErrors> {
     let objective_function = MyObjectiveFunction::new(3f64, 4f64, 50f64);
-    let result = bounded(objective_function, (-10f64, 10f64), 500usize).await?;
+    let bounds = Bounds::new(vec![-10f64], vec![10f64])?;
+    let result = bounded(objective_function, &bounds, 500usize).await?;
     println!("{:?}", result);
     Ok(())
 }
@@ -0,0 +1,290 @@
+//! Forward mode dual numbers
+//!
+//! A dual number `re + eps e` with `e^2 = 0` carries the derivative of a value along one
+//! direction, and nesting dual numbers carries second derivatives: the `eps.eps` part of
+//! `Dual<Dual>` is the mixed second derivative along the directions seeded in `re.eps` and
+//! `eps.re`.
+
+use super::Real;
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Dual number `re + eps e` over a real type, usually `f64` or another dual number
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Dual<T = f64> {
+    /// Value
+    pub re: T,
+    /// Derivative along the seeded direction
+    pub eps: T,
+}
+
+impl<T: Real> Dual<T> {
+    /// Create a new dual number
+    #[must_use]
+    pub fn new(re: T, eps: T) -> Self {
+        Self { re, eps }
+    }
+
+    /// Variable with unit derivative
+    #[must_use]
+    pub fn variable(re: T) -> Self {
+        Self::new(re, T::from(1f64))
+    }
+
+    /// Result of a function of this number with the given value and derivative, by the chain
+    /// rule
+    fn chain(self, value: T, derivative: T) -> Self {
+        Self::new(value, self.eps * derivative)
+    }
+}
+
+impl<T: Real> From<f64> for Dual<T> {
+    fn from(re: f64) -> Self {
+        Self::new(T::from(re), T::from(0f64))
+    }
+}
+
+/// Dual numbers are compared by their values, as branches of a function do not depend on the
+/// derivatives
+impl<T: Real> PartialEq for Dual<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.re == other.re
+    }
+}
+
+impl<T: Real> PartialOrd for Dual<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.re.partial_cmp(&other.re)
+    }
+}
+
+impl<T: Real> Neg for Dual<T> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.eps)
+    }
+}
+
+impl<T: Real> Add for Dual<T> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.eps + rhs.eps)
+    }
+}
+
+impl<T: Real> Sub for Dual<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.eps - rhs.eps)
+    }
+}
+
+impl<T: Real> Mul for Dual<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(self.re * rhs.re, self.eps * rhs.re + self.re * rhs.eps)
+    }
+}
+
+impl<T: Real> Div for Dual<T> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let re = self.re / rhs.re;
+        Self::new(re, (self.eps - re * rhs.eps) / rhs.re)
+    }
+}
+
+impl<T: Real> Add<f64> for Dual<T> {
+    type Output = Self;
+
+    fn add(self, rhs: f64) -> Self {
+        Self::new(self.re + rhs, self.eps)
+    }
+}
+
+impl<T: Real> Sub<f64> for Dual<T> {
+    type Output = Self;
+
+    fn sub(self, rhs: f64) -> Self {
+        Self::new(self.re - rhs, self.eps)
+    }
+}
+
+impl<T: Real> Mul<f64> for Dual<T> {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self {
+        Self::new(self.re * rhs, self.eps * rhs)
+    }
+}
+
+impl<T: Real> Div<f64> for Dual<T> {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self {
+        Self::new(self.re / rhs, self.eps / rhs)
+    }
+}
+
+/// Arithmetic with reals on the left and compound assignment
+macro_rules! real_ops {
+    ($($trait:ident $method:ident $assign_trait:ident $assign:ident),*) => {
+        $(
+            impl<T: Real> $trait<Dual<T>> for f64 {
+                type Output = Dual<T>;
+
+                fn $method(self, rhs: Dual<T>) -> Dual<T> {
+                    Dual::from(self).$method(rhs)
+                }
+            }
+
+            impl<T: Real> $assign_trait for Dual<T> {
+                fn $assign(&mut self, rhs: Self) {
+                    *self = (*self).$method(rhs);
+                }
+            }
+
+            impl<T: Real> $assign_trait<f64> for Dual<T> {
+                fn $assign(&mut self, rhs: f64) {
+                    *self = (*self).$method(rhs);
+                }
+            }
+        )*
+    };
+}
+
+real_ops!(
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign
+);
+
+impl<T: Real> Sum for Dual<T> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::from(0f64), Add::add)
+    }
+}
+
+impl<T: Real> Real for Dual<T> {
+    fn value(&self) -> f64 {
+        self.re.value()
+    }
+
+    fn exp(self) -> Self {
+        let exp = self.re.exp();
+        self.chain(exp, exp)
+    }
+
+    fn ln(self) -> Self {
+        self.chain(self.re.ln(), T::from(1f64) / self.re)
+    }
+
+    fn sqrt(self) -> Self {
+        let sqrt = self.re.sqrt();
+        self.chain(sqrt, T::from(0.5) / sqrt)
+    }
+
+    fn sin(self) -> Self {
+        self.chain(self.re.sin(), self.re.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.chain(self.re.cos(), -self.re.sin())
+    }
+
+    fn tan(self) -> Self {
+        let tan = self.re.tan();
+        self.chain(tan, tan * tan + 1f64)
+    }
+
+    fn atan(self) -> Self {
+        self.chain(self.re.atan(), T::from(1f64) / (self.re * self.re + 1f64))
+    }
+
+    fn tanh(self) -> Self {
+        let tanh = self.re.tanh();
+        self.chain(tanh, T::from(1f64) - tanh * tanh)
+    }
+
+    fn abs(self) -> Self {
+        if self.re.value() < 0f64 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Self::from(1f64);
+        }
+        self.chain(self.re.powi(n), self.re.powi(n - 1) * f64::from(n))
+    }
+
+    #[allow(clippy::float_cmp)]
+    fn powf(self, p: f64) -> Self {
+        if p == 0f64 {
+            return Self::from(1f64);
+        }
+        self.chain(self.re.powf(p), self.re.powf(p - 1f64) * p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_elementary() {
+        type Function = fn(Dual) -> Dual;
+        let x = 0.7;
+        let cases: [(Function, f64); 11] = [
+            (Real::exp, x.exp()),
+            (Real::ln, 1f64 / x),
+            (Real::sqrt, 0.5 / x.sqrt()),
+            (Real::sin, x.cos()),
+            (Real::cos, -x.sin()),
+            (Real::tan, 1f64 / x.cos().powi(2)),
+            (Real::atan, 1f64 / (1f64 + x * x)),
+            (Real::tanh, 1f64 / x.cosh().powi(2)),
+            (|d| (-d).abs(), 1f64),
+            (|d| d.powi(-3), -3f64 * x.powi(-4)),
+            (|d| d.powf(2.5), 2.5 * x.powf(1.5)),
+        ];
+        for (function, expected) in cases {
+            let result = function(Dual::variable(x));
+            assert!(relative_eq!(result.eps, expected, epsilon = 1e-14));
+        }
+
+        // quotient and products with reals on both sides
+        let d = Dual::variable(x);
+        let result = (2f64 - d) / (d * d + 1f64) * 3f64;
+        let expected = 3f64 * (x * x - 4f64 * x - 1f64) / (x * x + 1f64).powi(2);
+        assert!(relative_eq!(result.eps, expected, epsilon = 1e-14));
+    }
+
+    #[test]
+    fn test_nested() {
+        // second derivative of x^3 sin(x)
+        let x = 1.3;
+        let d = Dual::new(Dual::variable(x), Dual::from(1f64));
+        let result = d.powi(3) * d.sin();
+        let first = 3f64 * x * x * x.sin() + x.powi(3) * x.cos();
+        let second = 6f64 * x * x.sin() + 6f64 * x * x * x.cos() - x.powi(3) * x.sin();
+        assert!(relative_eq!(result.re.eps, first, epsilon = 1e-13));
+        assert!(relative_eq!(result.eps.re, first, epsilon = 1e-13));
+        assert!(relative_eq!(result.eps.eps, second, epsilon = 1e-13));
+
+        // comparisons ignore the derivatives
+        assert!(Dual::new(1f64, 5f64) == Dual::new(1f64, -5f64));
+        assert!(Dual::new(1f64, 5f64) < Dual::from(2f64));
+    }
+}
@@ -0,0 +1,389 @@
+//! Automatic differentiation of objective functions
+//!
+//! Functions written generically over the `Real` trait can be evaluated with `f64`, with the
+//! forward mode dual numbers `Dual`, or with the reverse mode variables `Var` of a `Tape`, giving
+//! derivatives exact to rounding error. `derivative` differentiates scalar functions in forward
+//! mode. `gradient` uses reverse mode, with one sweep whatever the number of variables,
+//! `hessian` nested forward mode and `jacobian` forward mode, with one evaluation per variable.
+//!
+//! The `AutoDiff` adapter wraps an `AutoDiffObjective` or an `AutoDiffResidual` and implements
+//! `MultivariateObjectiveFunction` or `ResidualFunction` with these derivatives, so they are used
+//! by the gradient based solvers in place of finite differences.
+//!
+//! ```
+//! use swoop::autodiff::{AutoDiff, AutoDiffObjective, Real};
+//! use swoop::minimise_multivariate::MultivariateObjectiveFunction;
+//!
+//! struct Rosenbrock {}
+//!
+//! impl AutoDiffObjective for Rosenbrock {
+//!     fn evaluate<T: Real>(&self, x: &[T]) -> T {
+//!         (x[1] - x[0] * x[0]).powi(2) * 100f64 + (T::from(1f64) - x[0]).powi(2)
+//!     }
+//! }
+//!
+//! let objective = AutoDiff::new(Rosenbrock {});
+//! assert_eq!(objective.gradient(&[1f64, 1f64]), Some(vec![0f64, 0f64]));
+//! ```
+//!
+//! # References
+//! * Griewank and Walther, "Evaluating Derivatives", 2nd edition, SIAM (2008)
+
+mod dual;
+mod reverse;
+
+pub use dual::Dual;
+pub use reverse::{Tape, Var};
+
+use crate::least_squares::ResidualFunction;
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use std::fmt::Debug;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Real numbers that objective functions can be written over, implemented by `f64`, `Dual` and
+/// `Var`
+///
+/// Arithmetic with `f64` is available on both sides for the concrete types, but generic code can
+/// only rely on it with the real on the right, `x * 2f64`, or converted with `T::from`.
+pub trait Real:
+    Copy
+    + Debug
+    + From<f64>
+    + PartialOrd
+    + Sum
+    + Neg<Output = Self>
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Add<f64, Output = Self>
+    + Sub<f64, Output = Self>
+    + Mul<f64, Output = Self>
+    + Div<f64, Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+{
+    /// Value without derivatives, for branching and comparisons with reals
+    fn value(&self) -> f64;
+
+    /// Exponential
+    #[must_use]
+    fn exp(self) -> Self;
+
+    /// Natural logarithm
+    #[must_use]
+    fn ln(self) -> Self;
+
+    /// Square root
+    #[must_use]
+    fn sqrt(self) -> Self;
+
+    /// Sine
+    #[must_use]
+    fn sin(self) -> Self;
+
+    /// Cosine
+    #[must_use]
+    fn cos(self) -> Self;
+
+    /// Tangent
+    #[must_use]
+    fn tan(self) -> Self;
+
+    /// Inverse tangent
+    #[must_use]
+    fn atan(self) -> Self;
+
+    /// Hyperbolic tangent
+    #[must_use]
+    fn tanh(self) -> Self;
+
+    /// Absolute value, with the derivative of the positive branch at zero
+    #[must_use]
+    fn abs(self) -> Self;
+
+    /// Integer power
+    #[must_use]
+    fn powi(self, n: i32) -> Self;
+
+    /// Real power
+    #[must_use]
+    fn powf(self, p: f64) -> Self;
+}
+
+impl Real for f64 {
+    fn value(&self) -> f64 {
+        *self
+    }
+
+    fn exp(self) -> Self {
+        f64::exp(self)
+    }
+
+    fn ln(self) -> Self {
+        f64::ln(self)
+    }
+
+    fn sqrt(self) -> Self {
+        f64::sqrt(self)
+    }
+
+    fn sin(self) -> Self {
+        f64::sin(self)
+    }
+
+    fn cos(self) -> Self {
+        f64::cos(self)
+    }
+
+    fn tan(self) -> Self {
+        f64::tan(self)
+    }
+
+    fn atan(self) -> Self {
+        f64::atan(self)
+    }
+
+    fn tanh(self) -> Self {
+        f64::tanh(self)
+    }
+
+    fn abs(self) -> Self {
+        f64::abs(self)
+    }
+
+    fn powi(self, n: i32) -> Self {
+        f64::powi(self, n)
+    }
+
+    fn powf(self, p: f64) -> Self {
+        f64::powf(self, p)
+    }
+}
+
+/// Trait to implement for a scalar objective function written over any `Real` type
+pub trait AutoDiffObjective {
+    /// Method to implement the objective function
+    fn evaluate<T: Real>(&self, x: &[T]) -> T;
+}
+
+/// Trait to implement for residuals written over any `Real` type
+pub trait AutoDiffResidual {
+    /// Method to implement the residuals, returning one value per residual
+    fn evaluate<T: Real>(&self, x: &[T]) -> Vec<T>;
+}
+
+/// Value and derivative of a scalar function at `x`, by forward mode
+pub fn derivative<F: Fn(Dual) -> Dual>(function: F, x: f64) -> (f64, f64) {
+    let result = function(Dual::variable(x));
+    (result.re, result.eps)
+}
+
+/// Value, first and second derivatives of a scalar function at `x`, by nested forward mode
+pub fn second_derivative<F>(function: F, x: f64) -> (f64, f64, f64)
+where
+    F: Fn(Dual<Dual>) -> Dual<Dual>,
+{
+    let result = function(Dual::new(Dual::variable(x), Dual::from(1f64)));
+    (result.re.re, result.re.eps, result.eps.eps)
+}
+
+/// Value and gradient of an objective function at `x`, by reverse mode
+pub fn gradient<F: AutoDiffObjective + ?Sized>(function: &F, x: &[f64]) -> (f64, Vec<f64>) {
+    let tape = Tape::new();
+    let variables: Vec<Var> = x.iter().map(|&xi| tape.var(xi)).collect();
+    let result = function.evaluate(&variables);
+    (result.value(), result.gradient(&variables))
+}
+
+/// Hessian of an objective function at `x`, by nested forward mode with one evaluation for each
+/// element of the upper triangle
+pub fn hessian<F: AutoDiffObjective + ?Sized>(function: &F, x: &[f64]) -> Vec<Vec<f64>> {
+    let n = x.len();
+    let mut hessian = vec![vec![0f64; n]; n];
+    let mut variables: Vec<Dual<Dual>> = x.iter().map(|&xi| Dual::from(xi)).collect();
+    for i in 0..n {
+        variables[i].re.eps = 1f64;
+        for j in i..n {
+            variables[j].eps.re = 1f64;
+            let value = function.evaluate(&variables).eps.eps;
+            hessian[i][j] = value;
+            hessian[j][i] = value;
+            variables[j].eps.re = 0f64;
+        }
+        variables[i].re.eps = 0f64;
+    }
+    hessian
+}
+
+/// Residuals and Jacobian, one row per residual, at `x`, by forward mode with one evaluation for
+/// each variable
+pub fn jacobian<F: AutoDiffResidual + ?Sized>(
+    function: &F,
+    x: &[f64],
+) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let mut variables: Vec<Dual> = x.iter().map(|&xi| Dual::from(xi)).collect();
+    let mut values = Vec::new();
+    let mut jacobian: Vec<Vec<f64>> = Vec::new();
+    for j in 0..x.len() {
+        variables[j].eps = 1f64;
+        let result = function.evaluate(&variables);
+        if j == 0 {
+            values = result.iter().map(|r| r.re).collect();
+            jacobian = vec![vec![0f64; x.len()]; result.len()];
+        }
+        for (row, r) in jacobian.iter_mut().zip(&result) {
+            row[j] = r.eps;
+        }
+        variables[j].eps = 0f64;
+    }
+    (values, jacobian)
+}
+
+/// Adapter providing the exact derivatives of an `AutoDiffObjective` to
+/// `MultivariateObjectiveFunction` and of an `AutoDiffResidual` to `ResidualFunction`
+#[derive(Debug, Clone)]
+pub struct AutoDiff<F> {
+    function: F,
+}
+
+impl<F> AutoDiff<F> {
+    /// Wrap a function written over `Real`
+    pub fn new(function: F) -> Self {
+        Self { function }
+    }
+
+    /// Unwrap the function
+    pub fn into_inner(self) -> F {
+        self.function
+    }
+}
+
+impl<F: AutoDiffObjective> MultivariateObjectiveFunction for AutoDiff<F> {
+    fn evaluate(&self, x: &[f64]) -> f64 {
+        self.function.evaluate(x)
+    }
+
+    fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+        Some(gradient(&self.function, x).1)
+    }
+
+    fn hessian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        Some(hessian(&self.function, x))
+    }
+}
+
+impl<F: AutoDiffResidual> ResidualFunction for AutoDiff<F> {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        self.function.evaluate(x)
+    }
+
+    fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        Some(jacobian(&self.function, x).1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::least_squares::{least_squares, LeastSquaresOptions};
+    use crate::minimise_multivariate::{minimise, MinimiseOptions, MultivariateMethod};
+    use crate::SwoopErrors;
+    use approx::relative_eq;
+
+    /// Rosenbrock function in any number of dimensions
+    struct Rosenbrock {}
+
+    impl AutoDiffObjective for Rosenbrock {
+        fn evaluate<T: Real>(&self, x: &[T]) -> T {
+            x.windows(2)
+                .map(|w| (w[1] - w[0] * w[0]).powi(2) * 100f64 + (T::from(1f64) - w[0]).powi(2))
+                .sum()
+        }
+    }
+
+    /// Residuals of an exponential decay model against exact data of `3 exp(-0.5 t)`
+    struct Decay {}
+
+    impl AutoDiffResidual for Decay {
+        fn evaluate<T: Real>(&self, x: &[T]) -> Vec<T> {
+            (0..10)
+                .map(|i| {
+                    let t = f64::from(i) * 0.5;
+                    x[0] * (x[1] * -t).exp() - 3f64 * (-0.5 * t).exp()
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let x = [-1.2, 1f64, 0.5];
+        let (value, grad) = gradient(&Rosenbrock {}, &x);
+        assert!(relative_eq!(value, Rosenbrock {}.evaluate(&x)));
+        let expected = [
+            -400f64 * x[0] * (x[1] - x[0] * x[0]) - 2f64 * (1f64 - x[0]),
+            200f64 * (x[1] - x[0] * x[0])
+                - 400f64 * x[1] * (x[2] - x[1] * x[1])
+                - 2f64 * (1f64 - x[1]),
+            200f64 * (x[2] - x[1] * x[1]),
+        ];
+        for (g, e) in grad.iter().zip(expected) {
+            assert!(relative_eq!(*g, e, epsilon = 1e-12));
+        }
+
+        let hess = hessian(&Rosenbrock {}, &x);
+        assert!(relative_eq!(
+            hess[0][0],
+            1200f64 * x[0] * x[0] - 400f64 * x[1] + 2f64
+        ));
+        assert!(relative_eq!(hess[0][1], -400f64 * x[0]));
+        assert!(relative_eq!(hess[1][0], -400f64 * x[0]));
+        assert!(relative_eq!(hess[0][2], 0f64));
+        assert!(relative_eq!(hess[2][2], 200f64));
+
+        let (values, jac) = jacobian(&Decay {}, &[2f64, 1f64]);
+        assert_eq!(values.len(), 10);
+        assert!(relative_eq!(jac[4][0], (-2f64).exp()));
+        assert!(relative_eq!(jac[4][1], -2f64 * 2f64 * (-2f64).exp()));
+
+        let (value, first) = derivative(|x| x.sin() * x, 2f64);
+        assert!(relative_eq!(value, 2f64 * 2f64.sin()));
+        assert!(relative_eq!(first, 2f64.sin() + 2f64 * 2f64.cos()));
+        let (_, _, second) = second_derivative(|x| x.exp() / x, 1f64);
+        assert!(relative_eq!(second, 1f64.exp(), epsilon = 1e-14));
+    }
+
+    #[tokio::test]
+    async fn test_adapter() -> Result<(), SwoopErrors> {
+        let result = minimise(
+            AutoDiff::new(Rosenbrock {}),
+            &[-1.2, 1f64],
+            MultivariateMethod::TrustConstr,
+            MinimiseOptions {
+                jac: true,
+                hess: true,
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-4));
+        assert!(relative_eq!(result.x[1], 1f64, epsilon = 1e-4));
+
+        let result = least_squares(
+            AutoDiff::new(Decay {}),
+            &[1f64, 1f64],
+            None,
+            LeastSquaresOptions::default(),
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 3f64, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 0.5, epsilon = 1e-6));
+        Ok(())
+    }
+}
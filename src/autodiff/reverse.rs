@@ -0,0 +1,357 @@
+//! Reverse mode differentiation on a tape
+//!
+//! Every operation on a `Var` records its local partial derivatives on the `Tape`, and one
+//! backward sweep over the tape accumulates the adjoints of all the inputs. The gradient of a
+//! scalar function costs a small multiple of one evaluation whatever the number of variables.
+
+use super::Real;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Operation recorded on the tape, with the indices of its operands and the partial derivatives
+/// with respect to them
+#[derive(Debug, Clone, Copy)]
+struct Node {
+    parents: [(usize, f64); 2],
+    arity: usize,
+}
+
+/// Record of the operations on variables, in evaluation order
+#[derive(Debug, Default)]
+pub struct Tape {
+    nodes: RefCell<Vec<Node>>,
+}
+
+impl Tape {
+    /// Create an empty tape
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an independent variable on the tape
+    pub fn var(&self, value: f64) -> Var<'_> {
+        Var {
+            tape: Some(self),
+            index: self.push(Node {
+                parents: [(0, 0f64); 2],
+                arity: 0,
+            }),
+            value,
+        }
+    }
+
+    /// Number of variables and operations recorded
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.nodes.borrow().len()
+    }
+
+    /// Whether nothing has been recorded
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn push(&self, node: Node) -> usize {
+        let mut nodes = self.nodes.borrow_mut();
+        nodes.push(node);
+        nodes.len() - 1
+    }
+}
+
+/// Variable recorded on a tape, or a constant that is not
+#[derive(Debug, Clone, Copy)]
+pub struct Var<'t> {
+    tape: Option<&'t Tape>,
+    index: usize,
+    value: f64,
+}
+
+impl<'t> Var<'t> {
+    /// Value of the variable
+    #[must_use]
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// Derivatives of this variable with respect to the `inputs`, by a backward sweep over the
+    /// tape. Constants have zero derivatives
+    #[must_use]
+    #[allow(clippy::float_cmp)]
+    pub fn gradient(&self, inputs: &[Var<'t>]) -> Vec<f64> {
+        let Some(tape) = self.tape else {
+            return vec![0f64; inputs.len()];
+        };
+        let nodes = tape.nodes.borrow();
+        let mut adjoints = vec![0f64; self.index + 1];
+        adjoints[self.index] = 1f64;
+        for i in (0..=self.index).rev() {
+            let adjoint = adjoints[i];
+            if adjoint == 0f64 {
+                continue;
+            }
+            let node = nodes[i];
+            for &(parent, partial) in &node.parents[..node.arity] {
+                adjoints[parent] += partial * adjoint;
+            }
+        }
+        inputs
+            .iter()
+            .map(|input| {
+                if input.tape.is_some() && input.index <= self.index {
+                    adjoints[input.index]
+                } else {
+                    0f64
+                }
+            })
+            .collect()
+    }
+
+    /// Result of a function of this variable with the given value and derivative
+    fn unary(self, value: f64, partial: f64) -> Self {
+        match self.tape {
+            Some(tape) => Var {
+                tape: Some(tape),
+                index: tape.push(Node {
+                    parents: [(self.index, partial), (0, 0f64)],
+                    arity: 1,
+                }),
+                value,
+            },
+            None => Var::from(value),
+        }
+    }
+
+    /// Result of a function of two variables with the given value and partial derivatives
+    fn binary(self, rhs: Self, value: f64, partial: f64, rhs_partial: f64) -> Self {
+        match (self.tape, rhs.tape) {
+            (Some(tape), Some(rhs_tape)) => {
+                debug_assert!(std::ptr::eq(tape, rhs_tape), "variables of different tapes");
+                Var {
+                    tape: Some(tape),
+                    index: tape.push(Node {
+                        parents: [(self.index, partial), (rhs.index, rhs_partial)],
+                        arity: 2,
+                    }),
+                    value,
+                }
+            }
+            (Some(_), None) => self.unary(value, partial),
+            (None, Some(_)) => rhs.unary(value, rhs_partial),
+            (None, None) => Var::from(value),
+        }
+    }
+}
+
+impl From<f64> for Var<'_> {
+    fn from(value: f64) -> Self {
+        Var {
+            tape: None,
+            index: 0,
+            value,
+        }
+    }
+}
+
+/// Variables are compared by their values
+impl PartialEq for Var<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl PartialOrd for Var<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.value.partial_cmp(&other.value)
+    }
+}
+
+impl Neg for Var<'_> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        self.unary(-self.value, -1f64)
+    }
+}
+
+impl Add for Var<'_> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        self.binary(rhs, self.value + rhs.value, 1f64, 1f64)
+    }
+}
+
+impl Sub for Var<'_> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        self.binary(rhs, self.value - rhs.value, 1f64, -1f64)
+    }
+}
+
+impl Mul for Var<'_> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        self.binary(rhs, self.value * rhs.value, rhs.value, self.value)
+    }
+}
+
+impl Div for Var<'_> {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let value = self.value / rhs.value;
+        self.binary(rhs, value, 1f64 / rhs.value, -value / rhs.value)
+    }
+}
+
+/// Arithmetic with reals on either side and compound assignment
+macro_rules! real_ops {
+    ($($trait:ident $method:ident $assign_trait:ident $assign:ident),*) => {
+        $(
+            impl $trait<f64> for Var<'_> {
+                type Output = Self;
+
+                fn $method(self, rhs: f64) -> Self {
+                    self.$method(Var::from(rhs))
+                }
+            }
+
+            impl<'t> $trait<Var<'t>> for f64 {
+                type Output = Var<'t>;
+
+                fn $method(self, rhs: Var<'t>) -> Var<'t> {
+                    Var::from(self).$method(rhs)
+                }
+            }
+
+            impl $assign_trait for Var<'_> {
+                fn $assign(&mut self, rhs: Self) {
+                    *self = (*self).$method(rhs);
+                }
+            }
+
+            impl $assign_trait<f64> for Var<'_> {
+                fn $assign(&mut self, rhs: f64) {
+                    *self = (*self).$method(rhs);
+                }
+            }
+        )*
+    };
+}
+
+real_ops!(
+    Add add AddAssign add_assign,
+    Sub sub SubAssign sub_assign,
+    Mul mul MulAssign mul_assign,
+    Div div DivAssign div_assign
+);
+
+impl Sum for Var<'_> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Var::from(0f64), Add::add)
+    }
+}
+
+impl Real for Var<'_> {
+    fn value(&self) -> f64 {
+        self.value
+    }
+
+    fn exp(self) -> Self {
+        let exp = self.value.exp();
+        self.unary(exp, exp)
+    }
+
+    fn ln(self) -> Self {
+        self.unary(self.value.ln(), 1f64 / self.value)
+    }
+
+    fn sqrt(self) -> Self {
+        let sqrt = self.value.sqrt();
+        self.unary(sqrt, 0.5 / sqrt)
+    }
+
+    fn sin(self) -> Self {
+        self.unary(self.value.sin(), self.value.cos())
+    }
+
+    fn cos(self) -> Self {
+        self.unary(self.value.cos(), -self.value.sin())
+    }
+
+    fn tan(self) -> Self {
+        let tan = self.value.tan();
+        self.unary(tan, tan * tan + 1f64)
+    }
+
+    fn atan(self) -> Self {
+        self.unary(self.value.atan(), 1f64 / (self.value * self.value + 1f64))
+    }
+
+    fn tanh(self) -> Self {
+        let tanh = self.value.tanh();
+        self.unary(tanh, 1f64 - tanh * tanh)
+    }
+
+    fn abs(self) -> Self {
+        if self.value < 0f64 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    fn powi(self, n: i32) -> Self {
+        if n == 0 {
+            return Var::from(1f64);
+        }
+        self.unary(self.value.powi(n), f64::from(n) * self.value.powi(n - 1))
+    }
+
+    #[allow(clippy::float_cmp)]
+    fn powf(self, p: f64) -> Self {
+        if p == 0f64 {
+            return Var::from(1f64);
+        }
+        self.unary(self.value.powf(p), p * self.value.powf(p - 1f64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_gradient() {
+        let tape = Tape::new();
+        let (x, y) = (tape.var(0.5), tape.var(-1.5));
+        let f = (x * y).sin() + x.powi(2) / y.exp() + 2f64;
+        assert!(relative_eq!(
+            f.value(),
+            (-0.75f64).sin() + 0.25 * 1.5f64.exp() + 2f64
+        ));
+        let gradient = f.gradient(&[x, y]);
+        let expected = [
+            -1.5 * (-0.75f64).cos() + 1.5f64.exp(),
+            0.5 * (-0.75f64).cos() - 0.25 * 1.5f64.exp(),
+        ];
+        assert!(relative_eq!(gradient[0], expected[0], epsilon = 1e-14));
+        assert!(relative_eq!(gradient[1], expected[1], epsilon = 1e-14));
+
+        // constants are not recorded, and a variable that is not used has no derivative
+        let recorded = tape.len();
+        let g = Var::from(3f64) * Var::from(2f64).ln() * x;
+        assert_eq!(tape.len(), recorded + 1);
+        assert!(relative_eq!(g.gradient(&[x])[0], 3f64 * 2f64.ln()));
+        let z = tape.var(1f64);
+        assert_eq!(f.gradient(&[z]), vec![0f64]);
+        assert_eq!(Var::from(1f64).gradient(&[x]), vec![0f64]);
+    }
+}
@@ -0,0 +1,340 @@
+//! Bounds and constraints shared by the constrained solvers
+//!
+//! `Bounds` are taken by `bounded`, `nelder_mead` and the other bounded solvers, and
+//! `Constraint`s by `trust_constr`, `differential_evolution` and `shgo`, and by `minimise` for the
+//! methods that support them. SLSQP, COBYLA and L-BFGS-B are deferred: they are not implemented
+//! yet, and will take these types rather than their own.
+
+use crate::SwoopErrors;
+
+/// Trait to implement for a vector valued constraint function `c(x)`
+pub trait ConstraintFunction {
+    /// Method to implement the constraint function, returning one value per constraint
+    fn evaluate(&self, x: &[f64]) -> Vec<f64>;
+
+    /// Method to implement the Jacobian of the constraint function, one row per constraint.
+    /// If `None` is returned the Jacobian is approximated using finite differences
+    fn jacobian(&self, _x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        None
+    }
+}
+
+/// Check that `lb` and `ub` have the same length and that no lower bound exceeds its upper
+/// bound
+fn check_lower_upper(lb: &[f64], ub: &[f64]) -> Result<(), SwoopErrors> {
+    if lb.len() != ub.len() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The lower and upper bounds have different lengths",
+        )));
+    }
+    for (l, u) in lb.iter().zip(ub) {
+        if l.is_nan() || u.is_nan() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Bounds cannot be NaN",
+            )));
+        }
+        if l > u {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The lower bound exceeds the upper bound",
+            )));
+        }
+        if *l == f64::INFINITY || *u == f64::NEG_INFINITY {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The lower bound cannot be +inf and the upper bound cannot be -inf",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Bounds on the variables `lb <= x <= ub`, use infinite values for unbounded sides
+#[derive(Debug, Clone)]
+pub struct Bounds {
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+    keep_feasible: Vec<bool>,
+}
+
+impl Bounds {
+    /// Create new bounds
+    ///
+    /// # Parameters
+    /// * `lb` - lower bound for each variable, `f64::NEG_INFINITY` if unbounded below
+    /// * `ub` - upper bound for each variable, `f64::INFINITY` if unbounded above
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `lb` and `ub` have different lengths or a
+    /// lower bound exceeds its upper bound
+    pub fn new(lb: Vec<f64>, ub: Vec<f64>) -> Result<Self, SwoopErrors> {
+        check_lower_upper(&lb, &ub)?;
+        let keep_feasible = vec![false; lb.len()];
+        Ok(Self {
+            lb,
+            ub,
+            keep_feasible,
+        })
+    }
+
+    /// Keep the iterates of the solver within the bounds for the variables that are flagged
+    ///
+    /// # Parameters
+    /// * `keep_feasible` - whether to keep each variable feasible
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `keep_feasible` does not have one entry per
+    /// variable
+    pub fn with_keep_feasible(mut self, keep_feasible: Vec<bool>) -> Result<Self, SwoopErrors> {
+        if keep_feasible.len() != self.lb.len() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "keep_feasible must have one entry per variable",
+            )));
+        }
+        self.keep_feasible = keep_feasible;
+        Ok(self)
+    }
+
+    /// Lower bound for each variable
+    #[must_use]
+    pub fn lb(&self) -> &[f64] {
+        &self.lb
+    }
+
+    /// Upper bound for each variable
+    #[must_use]
+    pub fn ub(&self) -> &[f64] {
+        &self.ub
+    }
+
+    /// Whether each variable is kept feasible
+    #[must_use]
+    pub fn keep_feasible(&self) -> &[bool] {
+        &self.keep_feasible
+    }
+}
+
+/// Linear constraint on the variables `lb <= A x <= ub`
+#[derive(Debug, Clone)]
+pub struct LinearConstraint {
+    a: Vec<Vec<f64>>,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+impl LinearConstraint {
+    /// Create a new linear constraint
+    ///
+    /// # Parameters
+    /// * `a` - constraint matrix, one row per constraint
+    /// * `lb` - lower bound for each constraint, `f64::NEG_INFINITY` if unbounded below
+    /// * `ub` - upper bound for each constraint, `f64::INFINITY` if unbounded above
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if the rows of `a` have different lengths, the
+    /// number of rows does not match the number of bounds or a lower bound exceeds its upper
+    /// bound
+    pub fn new(a: Vec<Vec<f64>>, lb: Vec<f64>, ub: Vec<f64>) -> Result<Self, SwoopErrors> {
+        if let Some(first) = a.first() {
+            if a.iter().any(|row| row.len() != first.len()) {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "The rows of the constraint matrix have different lengths",
+                )));
+            }
+        }
+        if a.len() != lb.len() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The constraint matrix must have one row per bound",
+            )));
+        }
+        check_lower_upper(&lb, &ub)?;
+        Ok(Self { a, lb, ub })
+    }
+
+    /// Constraint matrix, one row per constraint
+    #[must_use]
+    pub fn a(&self) -> &[Vec<f64>] {
+        &self.a
+    }
+
+    /// Lower bound for each constraint
+    #[must_use]
+    pub fn lb(&self) -> &[f64] {
+        &self.lb
+    }
+
+    /// Upper bound for each constraint
+    #[must_use]
+    pub fn ub(&self) -> &[f64] {
+        &self.ub
+    }
+}
+
+/// Nonlinear constraint on the variables `lb <= fun(x) <= ub`
+pub struct NonlinearConstraint {
+    fun: Box<dyn ConstraintFunction>,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+impl NonlinearConstraint {
+    /// Create a new nonlinear constraint
+    ///
+    /// # Parameters
+    /// * `fun` - struct that implements the trait `ConstraintFunction`, also supplies the
+    ///   Jacobian
+    /// * `lb` - lower bound for each constraint, `f64::NEG_INFINITY` if unbounded below
+    /// * `ub` - upper bound for each constraint, `f64::INFINITY` if unbounded above
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `lb` and `ub` have different lengths or a
+    /// lower bound exceeds its upper bound
+    pub fn new<F: ConstraintFunction + 'static>(
+        fun: F,
+        lb: Vec<f64>,
+        ub: Vec<f64>,
+    ) -> Result<Self, SwoopErrors> {
+        check_lower_upper(&lb, &ub)?;
+        Ok(Self {
+            fun: Box::new(fun),
+            lb,
+            ub,
+        })
+    }
+
+    /// Constraint function
+    #[must_use]
+    pub fn fun(&self) -> &dyn ConstraintFunction {
+        self.fun.as_ref()
+    }
+
+    /// Lower bound for each constraint
+    #[must_use]
+    pub fn lb(&self) -> &[f64] {
+        &self.lb
+    }
+
+    /// Upper bound for each constraint
+    #[must_use]
+    pub fn ub(&self) -> &[f64] {
+        &self.ub
+    }
+}
+
+/// A general constraint passed to a constrained solver
+pub enum Constraint {
+    /// Linear constraint
+    Linear(LinearConstraint),
+    /// Nonlinear constraint
+    Nonlinear(NonlinearConstraint),
+}
+
+impl From<LinearConstraint> for Constraint {
+    fn from(constraint: LinearConstraint) -> Self {
+        Self::Linear(constraint)
+    }
+}
+
+impl From<NonlinearConstraint> for Constraint {
+    fn from(constraint: NonlinearConstraint) -> Self {
+        Self::Nonlinear(constraint)
+    }
+}
+
+impl Constraint {
+    /// Lower bound for each constraint
+    #[must_use]
+    pub fn lb(&self) -> &[f64] {
+        match self {
+            Self::Linear(linear) => linear.lb(),
+            Self::Nonlinear(nonlinear) => nonlinear.lb(),
+        }
+    }
+
+    /// Upper bound for each constraint
+    #[must_use]
+    pub fn ub(&self) -> &[f64] {
+        match self {
+            Self::Linear(linear) => linear.ub(),
+            Self::Nonlinear(nonlinear) => nonlinear.ub(),
+        }
+    }
+
+    /// Evaluate the constraint function
+    #[must_use]
+    pub fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        match self {
+            Self::Linear(linear) => crate::linalg::mat_vec(linear.a(), x),
+            Self::Nonlinear(nonlinear) => nonlinear.fun().evaluate(x),
+        }
+    }
+
+    /// Violation of each constraint, the distance of the constraint function to its bounds and
+    /// zero where the constraint is satisfied
+    #[must_use]
+    pub fn violation(&self, x: &[f64]) -> Vec<f64> {
+        self.evaluate(x)
+            .iter()
+            .zip(self.lb().iter().zip(self.ub()))
+            .map(|(c, (l, u))| (l - c).max(c - u).max(0f64))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounds_validation() {
+        assert!(Bounds::new(vec![0f64, f64::NEG_INFINITY], vec![1f64, f64::INFINITY]).is_ok());
+        assert!(matches!(
+            Bounds::new(vec![0f64, 2f64], vec![1f64, 1f64]),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        assert!(matches!(
+            Bounds::new(vec![0f64], vec![1f64, 1f64]),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        assert!(matches!(
+            Bounds::new(vec![0f64], vec![1f64]).and_then(|b| b.with_keep_feasible(vec![true; 2])),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+    }
+
+    #[test]
+    fn test_linear_constraint_validation() {
+        assert!(LinearConstraint::new(vec![vec![1f64, 2f64]], vec![0f64], vec![1f64]).is_ok());
+        assert!(matches!(
+            LinearConstraint::new(
+                vec![vec![1f64, 2f64], vec![1f64]],
+                vec![0f64, 0f64],
+                vec![1f64, 1f64]
+            ),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        assert!(matches!(
+            LinearConstraint::new(vec![vec![1f64, 2f64]], vec![0f64, 0f64], vec![1f64, 1f64]),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        assert!(matches!(
+            LinearConstraint::new(vec![vec![1f64, 2f64]], vec![2f64], vec![1f64]),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+    }
+
+    #[test]
+    fn test_nonlinear_constraint_validation() {
+        struct Norm {}
+
+        impl ConstraintFunction for Norm {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                vec![x.iter().map(|xi| xi.powi(2)).sum()]
+            }
+        }
+
+        assert!(NonlinearConstraint::new(Norm {}, vec![f64::NEG_INFINITY], vec![1f64]).is_ok());
+        assert!(matches!(
+            NonlinearConstraint::new(Norm {}, vec![f64::INFINITY], vec![f64::INFINITY]),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+    }
+}
@@ -0,0 +1,284 @@
+//! Comparison of analytic derivatives with finite differences
+
+use super::{approx_derivative, approx_fprime, DifferentiateOptions};
+use crate::least_squares::ResidualFunction;
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use crate::SwoopErrors;
+
+/// Error of one component of an analytic gradient or Jacobian
+#[derive(Debug, Clone, Copy)]
+pub struct ComponentError {
+    /// Index of the point in the points checked
+    pub point: usize,
+    /// Row of the Jacobian, always zero for a gradient
+    pub row: usize,
+    /// Column of the Jacobian, the variable
+    pub column: usize,
+    /// Value of the analytic derivative
+    pub analytic: f64,
+    /// Value of the finite difference approximation
+    pub approx: f64,
+    /// Absolute error `|analytic - approx|`
+    pub abs_error: f64,
+    /// Relative error `|analytic - approx| / max(|analytic|, |approx|)`, zero if both are zero
+    pub rel_error: f64,
+}
+
+/// Struct to represent the result of `check_grad` and `check_jacobian`
+#[derive(Debug, Clone)]
+pub struct GradientCheck {
+    /// Error of every component at every point, in order of the points, rows and columns
+    pub errors: Vec<ComponentError>,
+    /// Euclidean norm of the difference between the analytic and approximate derivatives at each
+    /// point
+    pub error_norms: Vec<f64>,
+    /// Largest absolute error
+    pub max_abs_error: f64,
+    /// Largest relative error
+    pub max_rel_error: f64,
+}
+
+impl GradientCheck {
+    /// Compare analytic derivatives with their approximations, one matrix of each per point
+    fn new(analytic: &[Vec<Vec<f64>>], approx: &[Vec<Vec<f64>>]) -> Self {
+        let mut errors = Vec::new();
+        let mut error_norms = Vec::with_capacity(analytic.len());
+        for (point, (analytic, approx)) in analytic.iter().zip(approx).enumerate() {
+            let mut squares = 0f64;
+            for (row, (analytic, approx)) in analytic.iter().zip(approx).enumerate() {
+                for (column, (analytic, approx)) in analytic.iter().zip(approx).enumerate() {
+                    let abs_error = (analytic - approx).abs();
+                    let scale = analytic.abs().max(approx.abs());
+                    let rel_error = if scale == 0f64 {
+                        0f64
+                    } else {
+                        abs_error / scale
+                    };
+                    squares += abs_error.powi(2);
+                    errors.push(ComponentError {
+                        point,
+                        row,
+                        column,
+                        analytic: *analytic,
+                        approx: *approx,
+                        abs_error,
+                        rel_error,
+                    });
+                }
+            }
+            error_norms.push(squares.sqrt());
+        }
+        let max_abs_error = errors.iter().fold(0f64, |acc, e| acc.max(e.abs_error));
+        let max_rel_error = errors.iter().fold(0f64, |acc, e| acc.max(e.rel_error));
+        Self {
+            errors,
+            error_norms,
+            max_abs_error,
+            max_rel_error,
+        }
+    }
+
+    /// The `count` components with the largest errors, worst first
+    ///
+    /// Components are ranked by the smaller of their absolute and relative errors, so that
+    /// neither the rounding of large components nor the noise of components near zero hides a
+    /// wrong derivative.
+    #[must_use]
+    pub fn worst(&self, count: usize) -> Vec<ComponentError> {
+        let mut errors = self.errors.clone();
+        errors.sort_by(|a, b| {
+            let a = a.abs_error.min(a.rel_error);
+            let b = b.abs_error.min(b.rel_error);
+            b.total_cmp(&a)
+        });
+        errors.truncate(count);
+        errors
+    }
+}
+
+/// Check that there is at least one point and every point has the same dimension
+fn check_points(points: &[Vec<f64>]) -> Result<(), SwoopErrors> {
+    match points.first() {
+        Some(first) if points.iter().all(|x| x.len() == first.len()) => Ok(()),
+        _ => Err(SwoopErrors::ArgumentError(String::from(
+            "At least one point is required and every point must have the same dimension",
+        ))),
+    }
+}
+
+/// Check the gradient of an objective function against finite differences
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`, including the method `gradient`
+/// * `points` - points at which to check the gradient
+/// * `options` - options of the finite difference approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if there are no points, the points have different
+/// dimensions, the objective function has no gradient or its gradient does not have one entry per
+/// variable, or the options do not match the variables
+pub fn check_grad<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    points: &[Vec<f64>],
+    options: &DifferentiateOptions,
+) -> Result<GradientCheck, SwoopErrors> {
+    check_points(points)?;
+    let mut analytic = Vec::with_capacity(points.len());
+    let mut approx = Vec::with_capacity(points.len());
+    for x in points {
+        let gradient = objective_function.gradient(x).ok_or_else(|| {
+            SwoopErrors::ArgumentError(String::from(
+                "The objective function does not implement the gradient",
+            ))
+        })?;
+        if gradient.len() != x.len() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The gradient must have one entry per variable",
+            )));
+        }
+        analytic.push(vec![gradient]);
+        approx.push(vec![approx_fprime(
+            |x| objective_function.evaluate(x),
+            x,
+            options,
+        )?]);
+    }
+    Ok(GradientCheck::new(&analytic, &approx))
+}
+
+/// Check the Jacobian of a vector valued function against finite differences
+///
+/// # Parameters
+/// * `residual_function` - struct that implements the trait `ResidualFunction`, including the
+///   method `jacobian`
+/// * `points` - points at which to check the Jacobian
+/// * `options` - options of the finite difference approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if there are no points, the points have different
+/// dimensions, the function has no Jacobian or its Jacobian does not have one row per value and
+/// one column per variable, or the options do not match the variables
+pub fn check_jacobian<T: ResidualFunction>(
+    residual_function: T,
+    points: &[Vec<f64>],
+    options: &DifferentiateOptions,
+) -> Result<GradientCheck, SwoopErrors> {
+    check_points(points)?;
+    let mut analytic = Vec::with_capacity(points.len());
+    let mut approx = Vec::with_capacity(points.len());
+    for x in points {
+        let jacobian = residual_function.jacobian(x).ok_or_else(|| {
+            SwoopErrors::ArgumentError(String::from("The function does not implement the Jacobian"))
+        })?;
+        let jacobian_approx = approx_derivative(|x| residual_function.evaluate(x), x, options)?;
+        if jacobian.len() != jacobian_approx.len() || jacobian.iter().any(|r| r.len() != x.len()) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The Jacobian must have one row per value and one column per variable",
+            )));
+        }
+        analytic.push(jacobian);
+        approx.push(jacobian_approx);
+    }
+    Ok(GradientCheck::new(&analytic, &approx))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Rosenbrock function, with an error in the second component of the gradient if `buggy`
+    struct Rosenbrock {
+        buggy: bool,
+    }
+
+    impl MultivariateObjectiveFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            100f64 * (x[1] - x[0].powi(2)).powi(2) + (1f64 - x[0]).powi(2)
+        }
+
+        fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+            // the bug drops the factor 2 of the derivative of the square
+            let factor = if self.buggy { 100f64 } else { 200f64 };
+            Some(vec![
+                -400f64 * x[0] * (x[1] - x[0].powi(2)) - 2f64 * (1f64 - x[0]),
+                factor * (x[1] - x[0].powi(2)),
+            ])
+        }
+    }
+
+    /// Polar to Cartesian coordinates
+    struct Polar {}
+
+    impl ResidualFunction for Polar {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![x[0] * x[1].cos(), x[0] * x[1].sin()]
+        }
+
+        fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+            Some(vec![
+                vec![x[1].cos(), -x[0] * x[1].sin()],
+                vec![x[1].sin(), x[0] * x[1].cos()],
+            ])
+        }
+    }
+
+    /// Objective function without a gradient
+    struct NoGradient {}
+
+    impl MultivariateObjectiveFunction for NoGradient {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            x.iter().map(|xi| xi.powi(2)).sum()
+        }
+    }
+
+    #[test]
+    fn test_check_grad() -> Result<(), SwoopErrors> {
+        let points = vec![vec![-1.2, 1f64], vec![0.5, 0.5], vec![1f64, 1f64]];
+        let options = DifferentiateOptions::default();
+        let check = check_grad(Rosenbrock { buggy: false }, &points, &options)?;
+        assert_eq!(check.errors.len(), 6);
+        assert_eq!(check.error_norms.len(), 3);
+        assert!(check.max_abs_error < 1e-6);
+        // at the minimum the gradient is zero and the rounding of the approximation has a large
+        // relative error, which the ranking of `worst` discounts
+        let worst = check.worst(1)[0];
+        assert_eq!(worst.point, 2);
+        assert!(worst.abs_error.min(worst.rel_error) < 1e-6);
+
+        let check = check_grad(Rosenbrock { buggy: true }, &points, &options)?;
+        // the gradient of the second variable is half the approximation, except at the minimum
+        // where both are zero
+        let worst = check.worst(2);
+        assert!(worst.iter().all(|e| e.column == 1 && e.point < 2));
+        assert!(relative_eq!(worst[0].rel_error, 0.5, epsilon = 1e-8));
+        assert!(relative_eq!(check.error_norms[0], 44f64, epsilon = 1e-6));
+        assert!(check.error_norms[2] < 1e-6);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_jacobian() -> Result<(), SwoopErrors> {
+        let points = vec![vec![2f64, 0.3], vec![0.5, -2f64]];
+        let check = check_jacobian(Polar {}, &points, &DifferentiateOptions::default())?;
+        assert_eq!(check.errors.len(), 8);
+        assert_eq!((check.errors[6].point, check.errors[6].row), (1, 1));
+        assert!(check.max_abs_error < 1e-8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validation() {
+        let options = DifferentiateOptions::default();
+        let result = check_grad(NoGradient {}, &[vec![1f64]], &options);
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = check_grad(Rosenbrock { buggy: false }, &[], &options);
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let points = vec![vec![1f64, 1f64], vec![1f64]];
+        let result = check_jacobian(Polar {}, &points, &options);
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
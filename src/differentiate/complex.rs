@@ -0,0 +1,202 @@
+//! Complex numbers for complex step differentiation
+//!
+//! Only the operations needed to write analytic functions are provided. Functions that are not
+//! analytic, such as the absolute value, break the complex step approximation.
+
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+/// Complex number `re + i im`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Complex {
+    /// Real part
+    pub re: f64,
+    /// Imaginary part
+    pub im: f64,
+}
+
+impl Complex {
+    /// Create a new complex number
+    #[must_use]
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    /// Exponential
+    #[must_use]
+    pub fn exp(self) -> Self {
+        let r = self.re.exp();
+        Self::new(r * self.im.cos(), r * self.im.sin())
+    }
+
+    /// Principal branch of the natural logarithm
+    #[must_use]
+    pub fn ln(self) -> Self {
+        Self::new(self.re.hypot(self.im).ln(), self.im.atan2(self.re))
+    }
+
+    /// Principal branch of the square root
+    #[must_use]
+    pub fn sqrt(self) -> Self {
+        let modulus = self.re.hypot(self.im);
+        let re = ((modulus + self.re) / 2f64).sqrt();
+        let im = ((modulus - self.re) / 2f64).sqrt();
+        Self::new(re, if self.im < 0f64 { -im } else { im })
+    }
+
+    /// Sine
+    #[must_use]
+    pub fn sin(self) -> Self {
+        Self::new(
+            self.re.sin() * self.im.cosh(),
+            self.re.cos() * self.im.sinh(),
+        )
+    }
+
+    /// Cosine
+    #[must_use]
+    pub fn cos(self) -> Self {
+        Self::new(
+            self.re.cos() * self.im.cosh(),
+            -self.re.sin() * self.im.sinh(),
+        )
+    }
+
+    /// Integer power
+    #[must_use]
+    pub fn powi(self, n: i32) -> Self {
+        let mut result = Self::new(1f64, 0f64);
+        let mut base = if n < 0 {
+            Self::new(1f64, 0f64) / self
+        } else {
+            self
+        };
+        let mut exponent = n.unsigned_abs();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            exponent >>= 1;
+        }
+        result
+    }
+
+    /// Real power, using the principal branch of the logarithm
+    #[must_use]
+    pub fn powf(self, p: f64) -> Self {
+        if self.re == 0f64 && self.im == 0f64 {
+            return self;
+        }
+        (self.ln() * p).exp()
+    }
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Self {
+        Self::new(re, 0f64)
+    }
+}
+
+impl Neg for Complex {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        let denominator = rhs.re * rhs.re + rhs.im * rhs.im;
+        Self::new(
+            (self.re * rhs.re + self.im * rhs.im) / denominator,
+            (self.im * rhs.re - self.re * rhs.im) / denominator,
+        )
+    }
+}
+
+/// Arithmetic between complex numbers and reals, in both orders
+macro_rules! real_ops {
+    ($($trait:ident $method:ident),*) => {
+        $(
+            impl $trait<f64> for Complex {
+                type Output = Complex;
+
+                fn $method(self, rhs: f64) -> Complex {
+                    self.$method(Complex::from(rhs))
+                }
+            }
+
+            impl $trait<Complex> for f64 {
+                type Output = Complex;
+
+                fn $method(self, rhs: Complex) -> Complex {
+                    Complex::from(self).$method(rhs)
+                }
+            }
+        )*
+    };
+}
+
+real_ops!(Add add, Sub sub, Mul mul, Div div);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_complex() {
+        let z = Complex::new(0.5, -1.5);
+        let w = z.sqrt();
+        let square = w * w;
+        assert!(relative_eq!(square.re, z.re, epsilon = 1e-14));
+        assert!(relative_eq!(square.im, z.im, epsilon = 1e-14));
+
+        let round_trip = z.ln().exp();
+        assert!(relative_eq!(round_trip.re, z.re, epsilon = 1e-14));
+        assert!(relative_eq!(round_trip.im, z.im, epsilon = 1e-14));
+
+        // sin^2 + cos^2 = 1 holds off the real line
+        let one = z.sin().powi(2) + z.cos().powi(2);
+        assert!(relative_eq!(one.re, 1f64, epsilon = 1e-14));
+        assert!(relative_eq!(one.im, 0f64, epsilon = 1e-14));
+
+        let inverse = z.powi(-3) * z.powf(3f64);
+        assert!(relative_eq!(inverse.re, 1f64, epsilon = 1e-14));
+        assert!(relative_eq!(inverse.im, 0f64, epsilon = 1e-14));
+
+        let mixed = (2f64 - z) / 4f64 + 1f64;
+        assert!(relative_eq!(mixed.re, 1.375));
+        assert!(relative_eq!(mixed.im, 0.375));
+    }
+}
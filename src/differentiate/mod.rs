@@ -0,0 +1,695 @@
+//! Finite difference approximation of derivatives
+//!
+//! `approx_derivative` approximates the Jacobian of a vector valued function with forward,
+//! backward or central differences, and `approx_derivative_complex` with the complex step
+//! `Im(f(x + i h)) / h`, which has no subtractive cancellation and is accurate to machine
+//! precision for analytic functions. `approx_fprime` and `approx_hessian` approximate the
+//! gradient of a scalar function and the Hessian from the gradient. `approx_derivative_batch`
+//! evaluates the points of all the steps as one batch, which can be evaluated in parallel.
+//!
+//! Steps are relative to the variables, `rel_step * max(1, |x|)`, unless absolute steps are
+//! given. Steps that would leave the bounds are turned into one-sided steps within the bounds.
+//! When the sparsity structure of the Jacobian is known, columns that share no rows are
+//! perturbed together, so the number of evaluations is the number of groups rather than the
+//! number of variables.
+//!
+//! `check_grad` and `check_jacobian` compare the analytic derivatives of the objective traits with
+//! these approximations, reporting the error of every component.
+//!
+//! # References
+//! * Nocedal and Wright, "Numerical Optimization", 2nd edition, chapter 8 (2006)
+//! * Curtis, Powell and Reid, "On the estimation of sparse Jacobian matrices", IMA Journal of
+//!   Applied Mathematics 13 (1974)
+//! * Martins, Sturdza and Alonso, "The complex-step derivative approximation", ACM Transactions
+//!   on Mathematical Software 29.3 (2003)
+
+mod check_grad;
+pub use check_grad::{check_grad, check_jacobian, ComponentError, GradientCheck};
+
+mod complex;
+pub use complex::Complex;
+
+use crate::constraints::Bounds;
+use crate::SwoopErrors;
+
+/// Finite difference scheme used by `approx_derivative`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DifferenceMethod {
+    /// `(f(x + h) - f(x)) / h`, with an error of order `h`
+    Forward,
+    /// `(f(x) - f(x - h)) / h`, with an error of order `h`
+    Backward,
+    /// `(f(x + h) - f(x - h)) / 2h`, with an error of order `h^2`
+    #[default]
+    Central,
+}
+
+/// Options for the finite difference approximations
+#[derive(Debug, Clone, Default)]
+pub struct DifferentiateOptions {
+    /// Finite difference scheme, not used by `approx_derivative_complex`
+    pub method: DifferenceMethod,
+    /// Relative step size, defaults to the square root of machine epsilon for `Forward`,
+    /// `Backward` and the complex step, and to its cube root for `Central`
+    pub rel_step: Option<f64>,
+    /// Absolute step size for each variable, replacing `rel_step` except where the step does not
+    /// change the variable
+    pub abs_step: Option<Vec<f64>>,
+    /// Bounds on the variables that the steps must respect, not used by
+    /// `approx_derivative_complex` as the complex step does not change the real part
+    pub bounds: Option<Bounds>,
+    /// Positions `(row, column)` of the nonzero elements of the Jacobian, all other elements are
+    /// zero. Dense if not given
+    pub sparsity: Option<Vec<(usize, usize)>>,
+}
+
+/// Step of one variable, `one_sided` steps of `Central` use `x + h` and `x + 2h`
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    h: f64,
+    one_sided: bool,
+}
+
+/// Check the options against the variables
+fn check_options(x0: &[f64], options: &DifferentiateOptions) -> Result<(), SwoopErrors> {
+    if x0.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The point cannot be empty",
+        )));
+    }
+    if options
+        .rel_step
+        .is_some_and(|rel_step| !rel_step.is_finite() || rel_step <= 0f64)
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The relative step must be positive",
+        )));
+    }
+    if let Some(abs_step) = &options.abs_step {
+        if abs_step.len() != x0.len() || abs_step.iter().any(|h| !h.is_finite()) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The absolute step must be finite with one entry per variable",
+            )));
+        }
+    }
+    if let Some(bounds) = &options.bounds {
+        if bounds.lb().len() != x0.len() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The bounds must have one entry per variable",
+            )));
+        }
+        let outside = x0
+            .iter()
+            .zip(bounds.lb().iter().zip(bounds.ub()))
+            .any(|(x, (l, u))| x < l || x > u);
+        if outside {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The point must be within the bounds",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Absolute steps before the adjustment to the bounds
+///
+/// # Parameters
+/// * `x0` - point at which to differentiate
+/// * `options` - options of the approximation
+/// * `default_rel_step` - relative step of the scheme if none is given
+/// * `exact` - replace the steps by the representable change of the variables
+fn absolute_steps(
+    x0: &[f64],
+    options: &DifferentiateOptions,
+    default_rel_step: f64,
+    exact: bool,
+) -> Vec<f64> {
+    let rel_step = options.rel_step.unwrap_or(default_rel_step);
+    x0.iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let sign = if *x >= 0f64 { 1f64 } else { -1f64 };
+            let relative = rel_step * sign * x.abs().max(1f64);
+            let h = match &options.abs_step {
+                Some(abs_step) if (x + abs_step[i]) - x != 0f64 => abs_step[i],
+                _ => relative,
+            };
+            if exact {
+                (x + h) - x
+            } else {
+                h
+            }
+        })
+        .collect()
+}
+
+/// Turn the steps that leave the bounds into steps within them, port of scipy
+/// `_adjust_scheme_to_bounds`
+fn adjust_to_bounds(
+    x0: &[f64],
+    h: &[f64],
+    method: DifferenceMethod,
+    bounds: Option<&Bounds>,
+) -> Vec<Step> {
+    let one_sided = method != DifferenceMethod::Central;
+    let Some(bounds) = bounds else {
+        return h.iter().map(|h| Step { h: *h, one_sided }).collect();
+    };
+    x0.iter()
+        .zip(h)
+        .zip(bounds.lb().iter().zip(bounds.ub()))
+        .map(|((x, h), (l, u))| {
+            let (lb_dist, ub_dist) = (x - l, u - x);
+            if one_sided {
+                // flip the step if it fits on the other side, otherwise take the wider side
+                let fits = h.abs() <= lb_dist.max(ub_dist);
+                let h = if fits {
+                    if x + h < *l || x + h > *u {
+                        -h
+                    } else {
+                        *h
+                    }
+                } else if ub_dist >= lb_dist {
+                    ub_dist
+                } else {
+                    -lb_dist
+                };
+                Step { h, one_sided }
+            } else if lb_dist >= h.abs() && ub_dist >= h.abs() {
+                Step { h: *h, one_sided }
+            } else {
+                // one-sided steps towards the wider side, or a smaller central step if the
+                // one-sided step would be no larger
+                let h_one_sided = if ub_dist >= lb_dist {
+                    h.abs().min(0.5 * ub_dist)
+                } else {
+                    -h.abs().min(0.5 * lb_dist)
+                };
+                let min_dist = 0.5 * lb_dist.min(ub_dist);
+                if h_one_sided.abs() <= min_dist {
+                    Step {
+                        h: min_dist,
+                        one_sided: false,
+                    }
+                } else {
+                    Step {
+                        h: h_one_sided,
+                        one_sided: true,
+                    }
+                }
+            }
+        })
+        .collect()
+}
+
+/// Group the columns of a sparse Jacobian so that the columns of each group have no nonzero rows
+/// in common, and can be approximated together by one perturbation of all their variables
+///
+/// Columns are assigned greedily in order to the first group that they fit in.
+///
+/// # Parameters
+/// * `sparsity` - positions `(row, column)` of the nonzero elements of the Jacobian
+/// * `n` - number of columns
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if a column is out of range
+pub fn group_columns(sparsity: &[(usize, usize)], n: usize) -> Result<Vec<usize>, SwoopErrors> {
+    let rows = rows_of_columns(sparsity, usize::MAX, n)?;
+    let m = sparsity.iter().map(|(i, _)| i + 1).max().unwrap_or(0);
+    let mut used_rows: Vec<Vec<bool>> = Vec::new();
+    let mut groups = Vec::with_capacity(n);
+    for column_rows in &rows {
+        let group = used_rows
+            .iter()
+            .position(|used| column_rows.iter().all(|i| !used[*i]))
+            .unwrap_or_else(|| {
+                used_rows.push(vec![false; m]);
+                used_rows.len() - 1
+            });
+        for i in column_rows {
+            used_rows[group][*i] = true;
+        }
+        groups.push(group);
+    }
+    Ok(groups)
+}
+
+/// Nonzero rows of each column
+fn rows_of_columns(
+    sparsity: &[(usize, usize)],
+    m: usize,
+    n: usize,
+) -> Result<Vec<Vec<usize>>, SwoopErrors> {
+    let mut rows = vec![Vec::new(); n];
+    for (i, j) in sparsity {
+        if *i >= m || *j >= n {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The sparsity structure is out of range of the Jacobian",
+            )));
+        }
+        rows[*j].push(*i);
+    }
+    Ok(rows)
+}
+
+/// Nonzero rows of each column and the columns perturbed together
+struct Structure {
+    rows: Vec<Vec<usize>>,
+    groups: Vec<Vec<usize>>,
+}
+
+impl Structure {
+    /// Structure of the sparsity if given, and of a dense Jacobian with every column on its own
+    /// otherwise
+    fn new(sparsity: Option<&[(usize, usize)]>, m: usize, n: usize) -> Result<Self, SwoopErrors> {
+        let Some(sparsity) = sparsity else {
+            return Ok(Self {
+                rows: vec![(0..m).collect(); n],
+                groups: (0..n).map(|j| vec![j]).collect(),
+            });
+        };
+        let rows = rows_of_columns(sparsity, m, n)?;
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        for (j, group) in group_columns(sparsity, n)?.into_iter().enumerate() {
+            if group == groups.len() {
+                groups.push(Vec::new());
+            }
+            groups[group].push(j);
+        }
+        Ok(Self { rows, groups })
+    }
+}
+
+/// Check that an evaluation has as many values as the first
+fn check_len(len: usize, m: usize) -> Result<(), SwoopErrors> {
+    if len == m {
+        Ok(())
+    } else {
+        Err(SwoopErrors::ArgumentError(String::from(
+            "The function must return the same number of values at every point",
+        )))
+    }
+}
+
+/// Approximate the Jacobian of a vector valued function with finite differences, one row per
+/// function value
+///
+/// # Parameters
+/// * `fun` - function to differentiate
+/// * `x0` - point at which to approximate the Jacobian
+/// * `options` - options of the approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty or outside the bounds, the steps,
+/// bounds or sparsity structure do not match the variables, or the function does not return the
+/// same number of values at every point
+pub fn approx_derivative<F: FnMut(&[f64]) -> Vec<f64>>(
+    mut fun: F,
+    x0: &[f64],
+    options: &DifferentiateOptions,
+) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+    approx_derivative_batch(
+        |points| points.iter().map(|x| fun(x)).collect(),
+        x0,
+        None,
+        options,
+    )
+}
+
+/// Approximate the Jacobian of a vector valued function with finite differences, evaluating
+/// the points of all the steps as one batch, one row per function value
+///
+/// The batch can be evaluated in parallel, for example by the `evaluate_batch` method of a
+/// function wrapped in `Parallel`.
+///
+/// # Parameters
+/// * `fun` - function to differentiate, returning the values at each point of a batch
+/// * `x0` - point at which to approximate the Jacobian
+/// * `f0` - value of the function at `x0`, evaluated with the steps if not given
+/// * `options` - options of the approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty or outside the bounds, the steps,
+/// bounds or sparsity structure do not match the variables, or the function does not return the
+/// same number of values at every point
+pub fn approx_derivative_batch<F: FnMut(&[Vec<f64>]) -> Vec<Vec<f64>>>(
+    mut fun: F,
+    x0: &[f64],
+    f0: Option<&[f64]>,
+    options: &DifferentiateOptions,
+) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+    check_options(x0, options)?;
+    let central = options.method == DifferenceMethod::Central;
+    let default_rel_step = if central {
+        f64::EPSILON.cbrt()
+    } else {
+        f64::EPSILON.sqrt()
+    };
+    let mut h = absolute_steps(x0, options, default_rel_step, true);
+    if options.method == DifferenceMethod::Backward {
+        for hi in &mut h {
+            *hi = -*hi;
+        }
+    }
+    let steps = adjust_to_bounds(x0, &h, options.method, options.bounds.as_ref());
+
+    // the groups only depend on the columns, the rows are checked once `f0` is known
+    let n = x0.len();
+    let groups = match options.sparsity.as_deref() {
+        Some(sparsity) => Structure::new(Some(sparsity), usize::MAX, n)?.groups,
+        None => (0..n).map(|j| vec![j]).collect(),
+    };
+    let per_group = if central { 2 } else { 1 };
+    let mut points = Vec::with_capacity(per_group * groups.len() + 1);
+    if f0.is_none() {
+        points.push(x0.to_vec());
+    }
+    for columns in &groups {
+        let mut x1 = x0.to_vec();
+        let mut x2 = x0.to_vec();
+        for j in columns {
+            let Step { h, one_sided } = steps[*j];
+            if !central {
+                x1[*j] = x0[*j] + h;
+            } else if one_sided {
+                x1[*j] = x0[*j] + h;
+                x2[*j] = x0[*j] + 2f64 * h;
+            } else {
+                x1[*j] = x0[*j] - h;
+                x2[*j] = x0[*j] + h;
+            }
+        }
+        points.push(x1);
+        if central {
+            points.push(x2);
+        }
+    }
+    let mut values = fun(&points);
+    check_len(values.len(), points.len())?;
+    let f0 = if let Some(f0) = f0 {
+        f0.to_vec()
+    } else {
+        points.remove(0);
+        values.remove(0)
+    };
+    let m = f0.len();
+    for f in &values {
+        check_len(f.len(), m)?;
+    }
+    let rows = Structure::new(options.sparsity.as_deref(), m, n)?.rows;
+
+    let mut jacobian = vec![vec![0f64; x0.len()]; m];
+    for (k, columns) in groups.iter().enumerate() {
+        let (x1, f1) = (&points[per_group * k], &values[per_group * k]);
+        let (x2, f2) = if central {
+            (&points[2 * k + 1], &values[2 * k + 1])
+        } else {
+            (x1, f1)
+        };
+        for j in columns {
+            let one_sided = steps[*j].one_sided;
+            let dx = match (central, one_sided) {
+                (false, _) => x1[*j] - x0[*j],
+                (true, true) => x2[*j] - x0[*j],
+                (true, false) => x2[*j] - x1[*j],
+            };
+            for i in &rows[*j] {
+                let df = match (central, one_sided) {
+                    (false, _) => f1[*i] - f0[*i],
+                    (true, true) => -3f64 * f0[*i] + 4f64 * f1[*i] - f2[*i],
+                    (true, false) => f2[*i] - f1[*i],
+                };
+                jacobian[*i][*j] = df / dx;
+            }
+        }
+    }
+    Ok(jacobian)
+}
+
+/// Approximate the Jacobian of a vector valued function with the complex step, one row per
+/// function value
+///
+/// The function must be analytic and written for complex arguments, the Jacobian is
+/// `Im(f(x + i h)) / h` and the method and bounds of the options are not used.
+///
+/// # Parameters
+/// * `fun` - function to differentiate, evaluated at complex points
+/// * `x0` - point at which to approximate the Jacobian
+/// * `options` - options of the approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the steps or sparsity structure do
+/// not match the variables, or the function does not return the same number of values at every
+/// point
+pub fn approx_derivative_complex<F: FnMut(&[Complex]) -> Vec<Complex>>(
+    mut fun: F,
+    x0: &[f64],
+    options: &DifferentiateOptions,
+) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+    check_options(
+        x0,
+        &DifferentiateOptions {
+            bounds: None,
+            ..options.clone()
+        },
+    )?;
+    let h = absolute_steps(x0, options, f64::EPSILON.sqrt(), false);
+    let z0: Vec<Complex> = x0.iter().map(|x| Complex::from(*x)).collect();
+
+    let m = fun(&z0).len();
+    let Structure { rows, groups } = Structure::new(options.sparsity.as_deref(), m, x0.len())?;
+    let mut jacobian = vec![vec![0f64; x0.len()]; m];
+    for columns in &groups {
+        let mut z = z0.clone();
+        for j in columns {
+            z[*j].im = h[*j];
+        }
+        let f = fun(&z);
+        check_len(f.len(), m)?;
+        for j in columns {
+            for i in &rows[*j] {
+                jacobian[*i][*j] = f[*i].im / h[*j];
+            }
+        }
+    }
+    Ok(jacobian)
+}
+
+/// Approximate the gradient of a scalar function with finite differences
+///
+/// # Parameters
+/// * `fun` - function to differentiate
+/// * `x0` - point at which to approximate the gradient
+/// * `options` - options of the approximation, the sparsity structure has the single row `0`
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty or outside the bounds, or the steps,
+/// bounds or sparsity structure do not match the variables
+pub fn approx_fprime<F: FnMut(&[f64]) -> f64>(
+    mut fun: F,
+    x0: &[f64],
+    options: &DifferentiateOptions,
+) -> Result<Vec<f64>, SwoopErrors> {
+    let mut jacobian = approx_derivative(|x| vec![fun(x)], x0, options)?;
+    Ok(jacobian.swap_remove(0))
+}
+
+/// Approximate the Hessian of a scalar function with finite differences of its gradient
+///
+/// The Jacobian of the gradient is symmetrised by averaging it with its transpose.
+///
+/// # Parameters
+/// * `gradient` - gradient of the function
+/// * `x0` - point at which to approximate the Hessian
+/// * `options` - options of the approximation
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty or outside the bounds, the steps,
+/// bounds or sparsity structure do not match the variables, or the gradient does not have one
+/// entry per variable
+pub fn approx_hessian<F: FnMut(&[f64]) -> Vec<f64>>(
+    gradient: F,
+    x0: &[f64],
+    options: &DifferentiateOptions,
+) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+    let jacobian = approx_derivative(gradient, x0, options)?;
+    let n = x0.len();
+    check_len(jacobian.len(), n)?;
+    Ok((0..n)
+        .map(|i| {
+            (0..n)
+                .map(|j| 0.5 * (jacobian[i][j] + jacobian[j][i]))
+                .collect()
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// `f(x) = (sin(x0) x1^2, exp(x0 x1), x2 / x1)`
+    fn fun(x: &[f64]) -> Vec<f64> {
+        vec![x[0].sin() * x[1].powi(2), (x[0] * x[1]).exp(), x[2] / x[1]]
+    }
+
+    fn fun_complex(x: &[Complex]) -> Vec<Complex> {
+        vec![x[0].sin() * x[1].powi(2), (x[0] * x[1]).exp(), x[2] / x[1]]
+    }
+
+    fn jacobian(x: &[f64]) -> Vec<Vec<f64>> {
+        let e = (x[0] * x[1]).exp();
+        vec![
+            vec![x[0].cos() * x[1].powi(2), 2f64 * x[0].sin() * x[1], 0f64],
+            vec![x[1] * e, x[0] * e, 0f64],
+            vec![0f64, -x[2] / x[1].powi(2), 1f64 / x[1]],
+        ]
+    }
+
+    fn assert_close(approx: &[Vec<f64>], exact: &[Vec<f64>], epsilon: f64) {
+        for (approx_row, exact_row) in approx.iter().zip(exact) {
+            for (a, e) in approx_row.iter().zip(exact_row) {
+                assert!(
+                    relative_eq!(a, e, epsilon = epsilon, max_relative = epsilon),
+                    "{a} {e}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_methods() -> Result<(), SwoopErrors> {
+        let x0 = [0.7, -1.3, 2.1];
+        let exact = jacobian(&x0);
+        for (method, epsilon) in [
+            (DifferenceMethod::Forward, 1e-6),
+            (DifferenceMethod::Backward, 1e-6),
+            (DifferenceMethod::Central, 1e-9),
+        ] {
+            let options = DifferentiateOptions {
+                method,
+                ..DifferentiateOptions::default()
+            };
+            assert_close(&approx_derivative(fun, &x0, &options)?, &exact, epsilon);
+        }
+
+        // the complex step is exact to rounding even with a tiny step
+        let options = DifferentiateOptions {
+            rel_step: Some(1e-20),
+            ..DifferentiateOptions::default()
+        };
+        let complex = approx_derivative_complex(fun_complex, &x0, &options)?;
+        assert_close(&complex, &exact, 1e-14);
+
+        let options = DifferentiateOptions {
+            abs_step: Some(vec![1e-4; 3]),
+            ..DifferentiateOptions::default()
+        };
+        assert_close(&approx_derivative(fun, &x0, &options)?, &exact, 1e-7);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bounds() -> Result<(), SwoopErrors> {
+        // log is undefined below zero, so differences must stay within the bounds
+        let log = |x: &[f64]| vec![x[0].ln() + x[1].ln(), x[0] * x[1]];
+        let x0 = [1e-9, 1f64];
+        let bounds = Bounds::new(vec![0f64, 0f64], vec![f64::INFINITY, 1f64])?;
+        for (method, epsilon) in [
+            (DifferenceMethod::Forward, 1e-6),
+            (DifferenceMethod::Backward, 1e-6),
+            (DifferenceMethod::Central, 1e-6),
+        ] {
+            let options = DifferentiateOptions {
+                method,
+                bounds: Some(bounds.clone()),
+                ..DifferentiateOptions::default()
+            };
+            let jac = approx_derivative(log, &x0, &options)?;
+            assert!(jac.iter().flatten().all(|d| d.is_finite()));
+            assert!(relative_eq!(jac[0][1], 1f64, epsilon = epsilon));
+            assert!(relative_eq!(jac[1][0], 1f64, epsilon = epsilon));
+            assert!(relative_eq!(jac[1][1], 1e-9, epsilon = epsilon));
+            // x0 is much closer to zero than the step, so the first column is only positive
+            assert!(jac[0][0] > 0f64);
+        }
+
+        let outside = DifferentiateOptions {
+            bounds: Some(bounds),
+            ..DifferentiateOptions::default()
+        };
+        let result = approx_derivative(log, &[1f64, 2f64], &outside);
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sparsity() -> Result<(), SwoopErrors> {
+        // tridiagonal Jacobian of f_i = x_{i-1} + x_i^2 + x_{i+1}^3
+        let n = 20;
+        let tridiagonal = |x: &[f64]| -> Vec<f64> {
+            (0..n)
+                .map(|i| {
+                    let left = if i > 0 { x[i - 1] } else { 0f64 };
+                    let right = if i + 1 < n { x[i + 1].powi(3) } else { 0f64 };
+                    left + x[i].powi(2) + right
+                })
+                .collect()
+        };
+        let sparsity: Vec<(usize, usize)> = (0..n)
+            .flat_map(|i| (i.saturating_sub(1)..(i + 2).min(n)).map(move |j| (i, j)))
+            .collect();
+        let groups = group_columns(&sparsity, n)?;
+        assert_eq!(groups.iter().max(), Some(&2));
+        assert_eq!(groups[..4], [0, 1, 2, 0]);
+
+        let x0: Vec<f64> = (0..20u8).map(|i| f64::from(i) / 10f64).collect();
+        let mut nfev = 0;
+        let mut counted = |x: &[f64]| {
+            nfev += 1;
+            tridiagonal(x)
+        };
+        let options = DifferentiateOptions {
+            sparsity: Some(sparsity),
+            ..DifferentiateOptions::default()
+        };
+        let sparse = approx_derivative(&mut counted, &x0, &options)?;
+        // one evaluation at x0 and two for each of the three groups
+        assert_eq!(nfev, 7);
+        let dense = approx_derivative(tridiagonal, &x0, &DifferentiateOptions::default())?;
+        assert_close(&sparse, &dense, 1e-12);
+        assert!(relative_eq!(sparse[0][0], 0f64));
+        assert!(relative_eq!(sparse[5][6], 3f64 * 0.36, epsilon = 1e-9));
+        assert!(relative_eq!(sparse[5][9], 0f64));
+
+        let out_of_range = DifferentiateOptions {
+            sparsity: Some(vec![(0, n)]),
+            ..DifferentiateOptions::default()
+        };
+        let result = approx_derivative(tridiagonal, &x0, &out_of_range);
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_gradient_hessian() -> Result<(), SwoopErrors> {
+        // Rosenbrock function
+        let rosen = |x: &[f64]| 100f64 * (x[1] - x[0].powi(2)).powi(2) + (1f64 - x[0]).powi(2);
+        let rosen_der = |x: &[f64]| {
+            vec![
+                -400f64 * x[0] * (x[1] - x[0].powi(2)) - 2f64 * (1f64 - x[0]),
+                200f64 * (x[1] - x[0].powi(2)),
+            ]
+        };
+        let x0 = [-1.2, 1f64];
+        let options = DifferentiateOptions::default();
+        let grad = approx_fprime(rosen, &x0, &options)?;
+        assert!(relative_eq!(grad[0], -215.6, epsilon = 1e-6));
+        assert!(relative_eq!(grad[1], -88f64, epsilon = 1e-6));
+
+        let hess = approx_hessian(rosen_der, &x0, &options)?;
+        assert_close(&hess, &[vec![1330f64, 480f64], vec![480f64, 200f64]], 1e-6);
+        Ok(())
+    }
+}
@@ -0,0 +1,472 @@
+//! Functions shared by the least squares methods
+
+use super::LeastSquaresStatus;
+use crate::linalg::{dot, mat_vec, norm};
+use crate::SwoopErrors;
+
+/// Find the intersection of the line `x + t s` with the sphere `||y|| = delta`, returns the
+/// two values of `t` in increasing order. `x` must lie within the sphere.
+pub(super) fn intersect_trust_region(x: &[f64], s: &[f64], delta: f64) -> (f64, f64) {
+    let a = dot(s, s);
+    let b = dot(x, s);
+    let c = (dot(x, x) - delta.powi(2)).min(0f64);
+    let d = (b * b - a * c).sqrt();
+    let q = -(b + d.copysign(b));
+    if q == 0f64 {
+        return (0f64, 0f64);
+    }
+    let (t1, t2) = (q / a, c / q);
+    if t1 < t2 {
+        (t1, t2)
+    } else {
+        (t2, t1)
+    }
+}
+
+/// Value and derivative of `||(S^2 + alpha)^-1 S uf|| - delta` with respect to `alpha`
+fn phi_and_derivative(alpha: f64, suf: &[f64], s: &[f64], delta: f64) -> (f64, f64) {
+    let denom: Vec<f64> = s.iter().map(|si| si.powi(2) + alpha).collect();
+    let p_norm = suf
+        .iter()
+        .zip(&denom)
+        .map(|(sufi, di)| (sufi / di).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    let phi = p_norm - delta;
+    let phi_prime = -suf
+        .iter()
+        .zip(&denom)
+        .map(|(sufi, di)| sufi.powi(2) / di.powi(3))
+        .sum::<f64>()
+        / p_norm;
+    (phi, phi_prime)
+}
+
+/// Solve the trust region problem `min ||J p + f||` subject to `||p|| <= delta` given the
+/// singular value decomposition `J = U diag(s) V^T` and `uf = U^T f`.
+///
+/// The Levenberg-Marquardt parameter `alpha` is found with Newton iterations, `initial_alpha`
+/// is the parameter from the previous call. Returns the step and the final parameter.
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn solve_lsq_trust_region(
+    n: usize,
+    m: usize,
+    uf: &[f64],
+    s: &[f64],
+    v: &[Vec<f64>],
+    delta: f64,
+    initial_alpha: f64,
+) -> (Vec<f64>, f64) {
+    let suf: Vec<f64> = s.iter().zip(uf).map(|(si, ufi)| si * ufi).collect();
+    let step = |coefficients: Vec<f64>| -> Vec<f64> {
+        mat_vec(v, &coefficients).iter().map(|p| -p).collect()
+    };
+
+    let full_rank = m >= n && s.len() == n && s[n - 1] > f64::EPSILON * m as f64 * s[0];
+    if full_rank {
+        let p = step(uf.iter().zip(s).map(|(ufi, si)| ufi / si).collect());
+        if norm(&p) <= delta {
+            return (p, 0f64);
+        }
+    }
+
+    let mut alpha_upper = norm(&suf) / delta;
+    let mut alpha_lower = if full_rank {
+        let (phi, phi_prime) = phi_and_derivative(0f64, &suf, s, delta);
+        -phi / phi_prime
+    } else {
+        0f64
+    };
+    let safeguard = |lower: f64, upper: f64| f64::max(0.001 * upper, (lower * upper).sqrt());
+    let mut alpha = if initial_alpha == 0f64 && !full_rank {
+        safeguard(alpha_lower, alpha_upper)
+    } else {
+        initial_alpha
+    };
+
+    for _ in 0..10 {
+        if alpha < alpha_lower || alpha > alpha_upper {
+            alpha = safeguard(alpha_lower, alpha_upper);
+        }
+        let (phi, phi_prime) = phi_and_derivative(alpha, &suf, s, delta);
+        if phi < 0f64 {
+            alpha_upper = alpha;
+        }
+        let ratio = phi / phi_prime;
+        alpha_lower = alpha_lower.max(alpha - ratio);
+        alpha -= (phi + delta) * ratio / delta;
+        if phi.abs() < 0.01 * delta {
+            break;
+        }
+    }
+
+    let mut p = step(
+        suf.iter()
+            .zip(s)
+            .map(|(sufi, si)| sufi / (si.powi(2) + alpha))
+            .collect(),
+    );
+    let p_norm = norm(&p);
+    if p_norm > 0f64 {
+        for pi in &mut p {
+            *pi *= delta / p_norm;
+        }
+    }
+    (p, alpha)
+}
+
+/// Solve the trust region problem `min 0.5 p^T B p + g^T p` subject to `||p|| <= delta` in
+/// one or two dimensions
+pub(super) fn solve_trust_region_2d(b: &[Vec<f64>], g: &[f64], delta: f64) -> Vec<f64> {
+    let quadratic = |p: &[f64]| 0.5 * dot(p, &mat_vec(b, p)) + dot(g, p);
+    if g.len() == 1 {
+        let (t, _) = minimize_quadratic_1d(0.5 * b[0][0], g[0], -delta, delta, 0f64);
+        return vec![t];
+    }
+
+    // unconstrained minimum if B is positive definite
+    let det = b[0][0] * b[1][1] - b[0][1] * b[1][0];
+    if b[0][0] > 0f64 && det > 0f64 {
+        let p = vec![
+            -(b[1][1] * g[0] - b[0][1] * g[1]) / det,
+            -(b[0][0] * g[1] - b[1][0] * g[0]) / det,
+        ];
+        if dot(&p, &p) <= delta.powi(2) {
+            return p;
+        }
+    }
+
+    // minimum on the boundary, bracket the stationary points of the quadratic along the
+    // circle and refine them by bisection
+    let on_circle = |theta: f64| vec![delta * theta.cos(), delta * theta.sin()];
+    let derivative = |theta: f64| {
+        let p = on_circle(theta);
+        let grad: Vec<f64> = mat_vec(b, &p)
+            .iter()
+            .zip(g)
+            .map(|(bp, gi)| bp + gi)
+            .collect();
+        -p[1] * grad[0] + p[0] * grad[1]
+    };
+    let intervals = 64;
+    let width = 2f64 * std::f64::consts::PI / f64::from(intervals);
+    let mut best = on_circle(0f64);
+    for i in 0..intervals {
+        let (mut lo, mut hi) = (f64::from(i) * width, f64::from(i + 1) * width);
+        let (d_lo, d_hi) = (derivative(lo), derivative(hi));
+        if d_lo > 0f64 || d_hi < 0f64 {
+            continue;
+        }
+        for _ in 0..60 {
+            let mid = 0.5 * (lo + hi);
+            if derivative(mid) < 0f64 {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let candidate = on_circle(0.5 * (lo + hi));
+        if quadratic(&candidate) < quadratic(&best) {
+            best = candidate;
+        }
+    }
+    best
+}
+
+/// Update the trust region radius given the actual and predicted reductions of the cost,
+/// returns the new radius and the ratio of the reductions
+pub(super) fn update_tr_radius(
+    delta: f64,
+    actual_reduction: f64,
+    predicted_reduction: f64,
+    step_norm: f64,
+    bound_hit: bool,
+) -> (f64, f64) {
+    let ratio = if predicted_reduction > 0f64 {
+        actual_reduction / predicted_reduction
+    } else if predicted_reduction == 0f64 && actual_reduction == 0f64 {
+        1f64
+    } else {
+        0f64
+    };
+    let delta = if ratio < 0.25 {
+        0.25 * step_norm
+    } else if ratio > 0.75 && bound_hit {
+        2f64 * delta
+    } else {
+        delta
+    };
+    (delta, ratio)
+}
+
+/// Coefficients of the quadratic `a t^2 + b t + c` equal to the model
+/// `0.5 ||J (s0 + t s)||^2 + g^T (s0 + t s)`, plus `0.5 (s0 + t s)^T diag (s0 + t s)` if `diag`
+/// is given
+pub(super) fn build_quadratic_1d(
+    j: &[Vec<f64>],
+    g: &[f64],
+    s: &[f64],
+    diag: Option<&[f64]>,
+    s0: Option<&[f64]>,
+) -> (f64, f64, f64) {
+    let weighted = |x: &[f64], y: &[f64]| -> f64 {
+        diag.map_or(0f64, |d| {
+            d.iter()
+                .zip(x.iter().zip(y))
+                .map(|(di, (xi, yi))| di * xi * yi)
+                .sum()
+        })
+    };
+    let v = mat_vec(j, s);
+    let a = 0.5 * (dot(&v, &v) + weighted(s, s));
+    let mut b = dot(g, s);
+    let mut c = 0f64;
+    if let Some(s0) = s0 {
+        let u = mat_vec(j, s0);
+        b += dot(&u, &v) + weighted(s0, s);
+        c = 0.5 * dot(&u, &u) + dot(g, s0) + 0.5 * weighted(s0, s0);
+    }
+    (a, b, c)
+}
+
+/// Minimise `a t^2 + b t + c` for `lb <= t <= ub`, returns the minimiser and the minimum
+pub(super) fn minimize_quadratic_1d(a: f64, b: f64, lb: f64, ub: f64, c: f64) -> (f64, f64) {
+    let mut candidates = vec![lb, ub];
+    if a != 0f64 {
+        let extremum = -0.5 * b / a;
+        if lb < extremum && extremum < ub {
+            candidates.push(extremum);
+        }
+    }
+    candidates
+        .into_iter()
+        .map(|t| (t, t * (a * t + b) + c))
+        .fold((lb, f64::INFINITY), |best, candidate| {
+            if candidate.1 < best.1 {
+                candidate
+            } else {
+                best
+            }
+        })
+}
+
+/// Value of the quadratic model `0.5 ||J s||^2 + g^T s`, plus `0.5 s^T diag s` if `diag` is
+/// given
+pub(super) fn evaluate_quadratic(
+    j: &[Vec<f64>],
+    g: &[f64],
+    s: &[f64],
+    diag: Option<&[f64]>,
+) -> f64 {
+    let js = mat_vec(j, s);
+    let mut q = dot(&js, &js);
+    if let Some(diag) = diag {
+        q += diag.iter().zip(s).map(|(d, si)| d * si * si).sum::<f64>();
+    }
+    0.5 * q + dot(g, s)
+}
+
+/// Whether `lb <= x <= ub`
+pub(super) fn in_bounds(x: &[f64], lb: &[f64], ub: &[f64]) -> bool {
+    x.iter()
+        .zip(lb.iter().zip(ub))
+        .all(|(xi, (l, u))| xi >= l && xi <= u)
+}
+
+/// Smallest step `t` such that `x + t s` reaches a bound, along with the bounds that are hit,
+/// `-1` for a lower bound and `1` for an upper bound
+pub(super) fn step_size_to_bound(x: &[f64], s: &[f64], lb: &[f64], ub: &[f64]) -> (f64, Vec<i8>) {
+    let steps: Vec<f64> = x
+        .iter()
+        .zip(s)
+        .zip(lb.iter().zip(ub))
+        .map(|((xi, si), (l, u))| {
+            if *si == 0f64 {
+                f64::INFINITY
+            } else {
+                f64::max((l - xi) / si, (u - xi) / si)
+            }
+        })
+        .collect();
+    let min_step = steps.iter().copied().fold(f64::INFINITY, f64::min);
+    let hits = steps
+        .iter()
+        .zip(s)
+        .map(|(step, si)| {
+            if *step <= min_step && *si != 0f64 {
+                if *si > 0f64 {
+                    1
+                } else {
+                    -1
+                }
+            } else {
+                0
+            }
+        })
+        .collect();
+    (min_step, hits)
+}
+
+/// Determine which bounds are active, `-1` for a lower bound and `1` for an upper bound. A
+/// bound is active if the distance to it is below `rtol * max(1, |bound|)`
+pub(super) fn find_active_constraints(x: &[f64], lb: &[f64], ub: &[f64], rtol: f64) -> Vec<i8> {
+    x.iter()
+        .zip(lb.iter().zip(ub))
+        .map(|(xi, (l, u))| {
+            if rtol == 0f64 {
+                return if xi <= l { -1 } else { i8::from(xi >= u) };
+            }
+            let lower_dist = xi - l;
+            let upper_dist = u - xi;
+            if l.is_finite() && lower_dist <= f64::min(rtol * l.abs().max(1f64), upper_dist) {
+                -1
+            } else {
+                i8::from(
+                    u.is_finite() && upper_dist <= f64::min(rtol * u.abs().max(1f64), lower_dist),
+                )
+            }
+        })
+        .collect()
+}
+
+/// Next floating point number after `x` in the direction of `target`
+#[allow(clippy::float_cmp)]
+fn next_toward(x: f64, target: f64) -> f64 {
+    if x == target || x.is_nan() || target.is_nan() {
+        return x;
+    }
+    if x == 0f64 {
+        return f64::from_bits(1).copysign(target - x);
+    }
+    let bits = x.to_bits();
+    if (target > x) == (x > 0f64) {
+        f64::from_bits(bits + 1)
+    } else {
+        f64::from_bits(bits - 1)
+    }
+}
+
+/// Shift the variables that are on, or within `rstep` of, a bound into the interior. With
+/// `rstep` zero the variables are moved by one floating point number.
+pub(super) fn make_strictly_feasible(x: &[f64], lb: &[f64], ub: &[f64], rstep: f64) -> Vec<f64> {
+    let active = find_active_constraints(x, lb, ub, rstep);
+    x.iter()
+        .zip(&active)
+        .zip(lb.iter().zip(ub))
+        .map(|((xi, a), (l, u))| {
+            let shifted = match (*a, rstep == 0f64) {
+                (-1, true) => next_toward(*l, *u),
+                (-1, false) => l + rstep * l.abs().max(1f64),
+                (1, true) => next_toward(*u, *l),
+                (1, false) => u - rstep * u.abs().max(1f64),
+                _ => *xi,
+            };
+            if shifted < *l || shifted > *u {
+                0.5 * (l + u)
+            } else {
+                shifted
+            }
+        })
+        .collect()
+}
+
+/// Scaling vector of Coleman and Li, returns `(v, dv)` where `dv` is the derivative of `v`
+pub(super) fn cl_scaling_vector(
+    x: &[f64],
+    g: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+) -> (Vec<f64>, Vec<f64>) {
+    x.iter()
+        .zip(g)
+        .zip(lb.iter().zip(ub))
+        .map(|((xi, gi), (l, u))| {
+            if *gi < 0f64 && u.is_finite() {
+                (u - xi, -1f64)
+            } else if *gi > 0f64 && l.is_finite() {
+                (xi - l, 1f64)
+            } else {
+                (1f64, 0f64)
+            }
+        })
+        .unzip()
+}
+
+/// Scale the variables by the inverse norms of the columns of the Jacobian, the norms never
+/// decrease from `scale_inv_old`. Returns `(scale, scale_inv)`
+pub(super) fn compute_jac_scale(
+    j: &[Vec<f64>],
+    n: usize,
+    scale_inv_old: Option<&[f64]>,
+) -> (Vec<f64>, Vec<f64>) {
+    let scale_inv: Vec<f64> = (0..n)
+        .map(|k| {
+            let column_norm = j.iter().map(|row| row[k].powi(2)).sum::<f64>().sqrt();
+            match scale_inv_old {
+                Some(old) => column_norm.max(old[k]),
+                None if column_norm == 0f64 => 1f64,
+                None => column_norm,
+            }
+        })
+        .collect();
+    (scale_inv.iter().map(|s| 1f64 / s).collect(), scale_inv)
+}
+
+/// Check the `ftol` and `xtol` termination conditions
+pub(super) fn check_termination(
+    df: f64,
+    f: f64,
+    dx_norm: f64,
+    x_norm: f64,
+    ratio: f64,
+    ftol: f64,
+    xtol: f64,
+) -> Option<LeastSquaresStatus> {
+    let ftol_satisfied = df < ftol * f && ratio > 0.25;
+    let xtol_satisfied = dx_norm < xtol * (xtol + x_norm);
+    match (ftol_satisfied, xtol_satisfied) {
+        (true, true) => Some(LeastSquaresStatus::FunctionAndStepTolerance),
+        (true, false) => Some(LeastSquaresStatus::FunctionTolerance),
+        (false, true) => Some(LeastSquaresStatus::StepTolerance),
+        (false, false) => None,
+    }
+}
+
+/// Check that `a` is a non-empty matrix with one row per entry of `b` and that both are
+/// finite, returns the number of columns of `a`
+pub(super) fn check_linear_system(a: &[Vec<f64>], b: &[f64]) -> Result<usize, SwoopErrors> {
+    let n = a.first().map_or(0, Vec::len);
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Matrix must have at least one row and one column",
+        )));
+    }
+    if a.len() != b.len() || a.iter().any(|row| row.len() != n) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Matrix must have rows of equal length and one row per entry of the right hand side",
+        )));
+    }
+    if a.iter().flatten().chain(b).any(|v| !v.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Matrix and right hand side must be finite",
+        )));
+    }
+    Ok(n)
+}
+
+/// Map `y` into the bounds by reflecting it off them, repeatedly if both bounds are finite
+pub(super) fn reflective_transformation(y: &[f64], lb: &[f64], ub: &[f64]) -> Vec<f64> {
+    y.iter()
+        .zip(lb.iter().zip(ub))
+        .map(|(yi, (l, u))| match (l.is_finite(), u.is_finite()) {
+            (true, true) => {
+                let d = u - l;
+                let t = (yi - l).rem_euclid(2f64 * d);
+                l + t.min(2f64 * d - t)
+            }
+            (true, false) => yi.max(2f64 * l - yi),
+            (false, true) => yi.min(2f64 * u - yi),
+            (false, false) => *yi,
+        })
+        .collect()
+}
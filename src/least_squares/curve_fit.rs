@@ -0,0 +1,416 @@
+//! Fit a model to data with nonlinear least squares and estimate the covariance of the
+//! parameters
+
+use super::{
+    least_squares, LeastSquaresMethod, LeastSquaresOptions, LeastSquaresResult, ResidualFunction,
+};
+use crate::constraints::Bounds;
+use crate::linalg::{cholesky, solve_upper_triangular_transpose, svd, transpose};
+use crate::SwoopErrors;
+
+/// Trait to implement for the model fitted by `curve_fit`
+pub trait ModelFunction {
+    /// Method to implement the model `f(x, params)` at the independent variable `x`
+    fn evaluate(&self, x: f64, params: &[f64]) -> f64;
+
+    /// Method to implement the gradient of the model with respect to the parameters. If `None`
+    /// is returned the Jacobian is approximated using finite differences
+    fn gradient(&self, _x: f64, _params: &[f64]) -> Option<Vec<f64>> {
+        None
+    }
+}
+
+/// Uncertainty in `ydata`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sigma {
+    /// Standard deviation of each data point, the residuals become `(f - y) / sigma`
+    StandardDeviation(Vec<f64>),
+    /// Covariance matrix of the errors in `ydata`, the residuals are whitened with its Cholesky
+    /// factor
+    Covariance(Vec<Vec<f64>>),
+}
+
+/// Options for `curve_fit`
+#[derive(Debug, Clone, Default)]
+pub struct CurveFitOptions {
+    /// Uncertainty in `ydata`, by default every data point has unit weight
+    pub sigma: Option<Sigma>,
+    /// If `true` `sigma` is used in an absolute sense and the covariance of the parameters
+    /// reflects these absolute values. Otherwise only the relative magnitudes of `sigma` matter
+    /// and the covariance is scaled by the reduced chi squared of the fit
+    pub absolute_sigma: bool,
+    /// Options of the least squares solver, `Lm` is replaced by `Trf` when there are bounds
+    pub least_squares: LeastSquaresOptions,
+}
+
+/// Struct to represent the result of `curve_fit`
+#[derive(Debug, Clone)]
+pub struct CurveFitResult {
+    /// Optimal parameters
+    pub popt: Vec<f64>,
+    /// Estimated covariance of `popt`, the square roots of the diagonal are the standard
+    /// deviations of the parameters. Filled with infinity if it cannot be estimated because
+    /// there are no more data points than parameters and `absolute_sigma` is `false`
+    pub pcov: Vec<Vec<f64>>,
+    /// Result of the underlying least squares optimisation
+    pub least_squares: LeastSquaresResult,
+}
+
+/// Transformation of the residuals by the uncertainty in `ydata`
+enum Weights {
+    Unit,
+    StandardDeviation(Vec<f64>),
+    Cholesky(Vec<Vec<f64>>),
+}
+
+impl Weights {
+    /// Weighted residuals
+    fn apply(&self, r: Vec<f64>) -> Vec<f64> {
+        match self {
+            Self::Unit => r,
+            Self::StandardDeviation(sigma) => r.iter().zip(sigma).map(|(ri, si)| ri / si).collect(),
+            Self::Cholesky(factor) => solve_upper_triangular_transpose(factor, &r),
+        }
+    }
+}
+
+/// Weighted residuals of the model at `xdata`
+struct Residuals<'a, T: ModelFunction> {
+    model: &'a T,
+    xdata: &'a [f64],
+    ydata: &'a [f64],
+    weights: Weights,
+}
+
+impl<T: ModelFunction> ResidualFunction for Residuals<'_, T> {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        self.weights.apply(
+            self.xdata
+                .iter()
+                .zip(self.ydata)
+                .map(|(xi, yi)| self.model.evaluate(*xi, x) - yi)
+                .collect(),
+        )
+    }
+
+    fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        let n = x.len();
+        let jac = self
+            .xdata
+            .iter()
+            .map(|xi| self.model.gradient(*xi, x))
+            .collect::<Option<Vec<Vec<f64>>>>()?;
+        let columns: Vec<Vec<f64>> = transpose(&jac, n)
+            .into_iter()
+            .map(|column| self.weights.apply(column))
+            .collect();
+        Some(transpose(&columns, self.xdata.len()))
+    }
+}
+
+/// Covariance of the parameters `(J^T J)^-1` from the Jacobian at the solution, using the
+/// pseudo-inverse with singular values below `max(m, n) * eps * s_max` discarded
+#[allow(clippy::cast_precision_loss)]
+fn covariance(jac: &[Vec<f64>], n: usize) -> Vec<Vec<f64>> {
+    let (_, s, v) = svd(jac, n);
+    let threshold = s.first().copied().unwrap_or(0f64) * f64::EPSILON * jac.len().max(n) as f64;
+    (0..n)
+        .map(|a| {
+            (0..n)
+                .map(|b| {
+                    s.iter()
+                        .enumerate()
+                        .filter(|(_, sk)| **sk > threshold)
+                        .map(|(k, sk)| v[a][k] * v[b][k] / sk.powi(2))
+                        .sum()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Fit a model `f(x, params)` to data using nonlinear least squares
+///
+/// Minimises the sum of the squared, weighted residuals `f(xdata, params) - ydata` and
+/// estimates the covariance of the optimal parameters from the Jacobian at the solution.
+///
+/// # Parameters
+/// * `model` - struct that implements the trait `ModelFunction`
+/// * `xdata` - independent variable of each data point
+/// * `ydata` - dependent variable of each data point
+/// * `p0` - initial guess of the parameters
+/// * `bounds` - optional bounds on the parameters
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `xdata` and `ydata` are empty or have different
+/// lengths, `ydata` is not finite, `sigma` has the wrong size, a standard deviation is not
+/// positive or the covariance matrix is not positive definite, or if the least squares solver
+/// rejects its arguments
+pub async fn curve_fit<T: ModelFunction>(
+    model: T,
+    xdata: &[f64],
+    ydata: &[f64],
+    p0: &[f64],
+    bounds: Option<&Bounds>,
+    mut options: CurveFitOptions,
+) -> Result<CurveFitResult, SwoopErrors> {
+    let m = ydata.len();
+    let n = p0.len();
+    if m == 0 || xdata.len() != m {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "xdata and ydata must be non-empty with the same length",
+        )));
+    }
+    if ydata.iter().any(|yi| !yi.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "ydata must be finite",
+        )));
+    }
+    let weights = match &options.sigma {
+        None => Weights::Unit,
+        Some(Sigma::StandardDeviation(sigma)) => {
+            if sigma.len() != m || sigma.iter().any(|si| *si <= 0f64 || !si.is_finite()) {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "sigma must be positive and finite with one entry per data point",
+                )));
+            }
+            Weights::StandardDeviation(sigma.clone())
+        }
+        Some(Sigma::Covariance(covariance)) => {
+            if covariance.len() != m || covariance.iter().any(|row| row.len() != m) {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Covariance of ydata must be a square matrix with one row per data point",
+                )));
+            }
+            let factor = cholesky(covariance).ok_or_else(|| {
+                SwoopErrors::ArgumentError(String::from(
+                    "Covariance of ydata must be positive definite",
+                ))
+            })?;
+            Weights::Cholesky(factor)
+        }
+    };
+    if bounds.is_some() && options.least_squares.method == LeastSquaresMethod::Lm {
+        options.least_squares.method = LeastSquaresMethod::Trf;
+    }
+
+    let residuals = Residuals {
+        model: &model,
+        xdata,
+        ydata,
+        weights,
+    };
+    let result = least_squares(residuals, p0, bounds, options.least_squares).await?;
+
+    let mut pcov = covariance(&result.jac, n);
+    if !options.absolute_sigma {
+        if m > n {
+            #[allow(clippy::cast_precision_loss)]
+            let s_sq = 2f64 * result.cost / (m - n) as f64;
+            for pcov_ij in pcov.iter_mut().flatten() {
+                *pcov_ij *= s_sq;
+            }
+        } else {
+            pcov = vec![vec![f64::INFINITY; n]; n];
+        }
+    }
+    Ok(CurveFitResult {
+        popt: result.x.clone(),
+        pcov,
+        least_squares: result,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    struct Line {}
+
+    impl ModelFunction for Line {
+        fn evaluate(&self, x: f64, params: &[f64]) -> f64 {
+            params[0] + params[1] * x
+        }
+
+        fn gradient(&self, x: f64, _params: &[f64]) -> Option<Vec<f64>> {
+            Some(vec![1f64, x])
+        }
+    }
+
+    struct Decay {}
+
+    impl ModelFunction for Decay {
+        fn evaluate(&self, x: f64, params: &[f64]) -> f64 {
+            params[0] * (-params[1] * x).exp() + params[2]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_line_covariance() -> Result<(), SwoopErrors> {
+        let xdata = [0f64, 1f64, 2f64, 3f64, 4f64];
+        let ydata = [1.1f64, 2.9f64, 5.2f64, 6.8f64, 9.1f64];
+        let sigma = vec![0.1f64, 0.2f64, 0.1f64, 0.3f64, 0.2f64];
+
+        // weighted linear regression
+        let w: Vec<f64> = sigma.iter().map(|s| s.powi(-2)).collect();
+        let s0: f64 = w.iter().sum();
+        let s1: f64 = w.iter().zip(&xdata).map(|(w, x)| w * x).sum();
+        let s2: f64 = w.iter().zip(&xdata).map(|(w, x)| w * x * x).sum();
+        let det = s0 * s2 - s1 * s1;
+        let expected = [[s2 / det, -s1 / det], [-s1 / det, s0 / det]];
+
+        for sigma in [
+            Sigma::StandardDeviation(sigma.clone()),
+            Sigma::Covariance(
+                (0..5)
+                    .map(|i| {
+                        (0..5)
+                            .map(|j| if i == j { sigma[i].powi(2) } else { 0f64 })
+                            .collect()
+                    })
+                    .collect(),
+            ),
+        ] {
+            let result = curve_fit(
+                Line {},
+                &xdata,
+                &ydata,
+                &[0f64, 0f64],
+                None,
+                CurveFitOptions {
+                    sigma: Some(sigma),
+                    absolute_sigma: true,
+                    ..CurveFitOptions::default()
+                },
+            )
+            .await?;
+            assert!(result.least_squares.success);
+            for (row, expected_row) in result.pcov.iter().zip(&expected) {
+                for (pcov_ij, expected_ij) in row.iter().zip(expected_row) {
+                    assert!(relative_eq!(pcov_ij, expected_ij, epsilon = 1e-10));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relative_sigma() -> Result<(), SwoopErrors> {
+        let xdata: Vec<f64> = (0..40).map(|i| f64::from(i) * 0.1).collect();
+        let ydata: Vec<f64> = xdata
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                let noise = if i % 3 == 0 { 0.02 } else { -0.01 };
+                2.5 * (-1.3 * x).exp() + 0.5 + noise
+            })
+            .collect();
+        let result = curve_fit(
+            Decay {},
+            &xdata,
+            &ydata,
+            &[1f64, 1f64, 0f64],
+            None,
+            CurveFitOptions::default(),
+        )
+        .await?;
+        assert!(result.least_squares.success);
+        assert!(relative_eq!(result.popt[0], 2.5, epsilon = 0.05));
+        assert!(relative_eq!(result.popt[1], 1.3, epsilon = 0.05));
+        assert!(relative_eq!(result.popt[2], 0.5, epsilon = 0.05));
+        for i in 0..3 {
+            assert!(result.pcov[i][i] > 0f64 && result.pcov[i][i] < 1e-2);
+            for j in 0..3 {
+                assert!(relative_eq!(
+                    result.pcov[i][j],
+                    result.pcov[j][i],
+                    epsilon = 1e-12
+                ));
+            }
+        }
+
+        // the covariance does not depend on the scale of sigma
+        let scaled = curve_fit(
+            Decay {},
+            &xdata,
+            &ydata,
+            &[1f64, 1f64, 0f64],
+            None,
+            CurveFitOptions {
+                sigma: Some(Sigma::StandardDeviation(vec![3f64; 40])),
+                ..CurveFitOptions::default()
+            },
+        )
+        .await?;
+        for i in 0..3 {
+            assert!(relative_eq!(
+                scaled.pcov[i][i],
+                result.pcov[i][i],
+                max_relative = 1e-4
+            ));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bounds() -> Result<(), SwoopErrors> {
+        let xdata = [0f64, 1f64, 2f64, 3f64];
+        let ydata = [0f64, 1f64, 2f64, 3f64];
+        let bounds = Bounds::new(vec![0.5f64, f64::NEG_INFINITY], vec![1f64, 0.5f64])?;
+        let result = curve_fit(
+            Line {},
+            &xdata,
+            &ydata,
+            &[0.75f64, 0f64],
+            Some(&bounds),
+            CurveFitOptions::default(),
+        )
+        .await?;
+        assert!(result.least_squares.success);
+        assert!(result.popt[0] <= 1f64 && result.popt[0] >= 0.5);
+        assert!(relative_eq!(result.popt[1], 0.5, epsilon = 1e-8));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let result = curve_fit(
+            Line {},
+            &[0f64, 1f64],
+            &[0f64],
+            &[0f64, 0f64],
+            None,
+            CurveFitOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = curve_fit(
+            Line {},
+            &[0f64, 1f64],
+            &[0f64, 1f64],
+            &[0f64, 0f64],
+            None,
+            CurveFitOptions {
+                sigma: Some(Sigma::Covariance(vec![vec![1f64, 2f64], vec![2f64, 1f64]])),
+                ..CurveFitOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = curve_fit(
+            Line {},
+            &[0f64, 1f64],
+            &[0f64, 1f64],
+            &[0f64, 0f64],
+            None,
+            CurveFitOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(result.pcov[0][0].is_infinite());
+    }
+}
@@ -0,0 +1,296 @@
+//! Dogleg algorithm with rectangular trust regions
+//!
+//! The trust region is a box so that its intersection with the bounds is also a box, the
+//! variables on an active bound are removed from the subproblem.
+//!
+//! # References
+//! * Voglis and Lagaris, "A rectangular trust region dogleg approach for unconstrained and
+//!   bound constrained nonlinear optimization", WSEAS International Conference on Applied
+//!   Mathematics (2004)
+
+use super::common::{
+    build_quadratic_1d, check_termination, compute_jac_scale, evaluate_quadratic, in_bounds,
+    minimize_quadratic_1d, step_size_to_bound, update_tr_radius,
+};
+use super::lsmr::{lsmr, LsmrOptions};
+use super::{
+    LeastSquaresOptions, LeastSquaresStatus, Problem, ResidualFunction, Solution, TrSolver,
+};
+use crate::linalg::{lstsq, mat_t_vec, mat_vec, norm, norm_inf};
+use crate::SwoopErrors;
+
+/// Dogleg step within the intersection of the box trust region and the bounds.
+///
+/// Returns the step, the bounds that are hit by the step and whether the step reaches the
+/// boundary of the trust region.
+#[allow(clippy::too_many_arguments)]
+fn dogleg_step(
+    x: &[f64],
+    newton_step: &[f64],
+    g: &[f64],
+    a: f64,
+    b: f64,
+    tr_bounds: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+) -> (Vec<f64>, Vec<i8>, bool) {
+    let n = x.len();
+    let lb_centered: Vec<f64> = lb.iter().zip(x).map(|(l, xi)| l - xi).collect();
+    let ub_centered: Vec<f64> = ub.iter().zip(x).map(|(u, xi)| u - xi).collect();
+    let lb_total: Vec<f64> = lb_centered
+        .iter()
+        .zip(tr_bounds)
+        .map(|(l, t)| l.max(-t))
+        .collect();
+    let ub_total: Vec<f64> = ub_centered
+        .iter()
+        .zip(tr_bounds)
+        .map(|(u, t)| u.min(*t))
+        .collect();
+
+    let mut bound_hits = vec![0i8; n];
+    if in_bounds(newton_step, &lb_total, &ub_total) {
+        return (newton_step.to_vec(), bound_hits, false);
+    }
+
+    // constrained Cauchy step, improved towards the Newton step
+    let minus_g: Vec<f64> = g.iter().map(|gi| -gi).collect();
+    let (to_bounds, _) = step_size_to_bound(&vec![0f64; n], &minus_g, &lb_total, &ub_total);
+    let (t, _) = minimize_quadratic_1d(a, b, 0f64, to_bounds, 0f64);
+    let cauchy_step: Vec<f64> = g.iter().map(|gi| -t * gi).collect();
+    let step_diff: Vec<f64> = newton_step
+        .iter()
+        .zip(&cauchy_step)
+        .map(|(ni, ci)| ni - ci)
+        .collect();
+    let (step_size, hits) = step_size_to_bound(&cauchy_step, &step_diff, &lb_total, &ub_total);
+
+    let mut tr_hit = false;
+    for i in 0..n {
+        // the tighter of the bound and the trust region is the one that is hit
+        let lower_is_bound = lb_centered[i] >= -tr_bounds[i];
+        let upper_is_bound = ub_centered[i] <= tr_bounds[i];
+        match hits[i] {
+            -1 if lower_is_bound => bound_hits[i] = -1,
+            1 if upper_is_bound => bound_hits[i] = 1,
+            -1 | 1 => tr_hit = true,
+            _ => {}
+        }
+    }
+    let step = cauchy_step
+        .iter()
+        .zip(&step_diff)
+        .map(|(ci, di)| ci + step_size * di)
+        .collect();
+    (step, bound_hits, tr_hit)
+}
+
+/// Minimise the sum of squares of the residuals subject to bounds with the dogbox algorithm
+///
+/// # Parameters
+/// * `problem` - residual function
+/// * `x0` - initial guess, within the bounds
+/// * `f0` - residuals at `x0`
+/// * `j0` - Jacobian at `x0`
+/// * `lb` - lower bound for each variable
+/// * `ub` - upper bound for each variable
+/// * `options` - solver options
+/// * `max_nfev` - maximum evaluations of the residuals
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of residuals changes between the steps
+/// of the finite differences
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
+pub(super) fn dogbox<T: ResidualFunction>(
+    problem: &mut Problem<'_, T>,
+    x0: &[f64],
+    f0: Vec<f64>,
+    j0: Vec<Vec<f64>>,
+    lb: &[f64],
+    ub: &[f64],
+    options: &LeastSquaresOptions,
+    max_nfev: usize,
+) -> Result<Solution, SwoopErrors> {
+    let n = x0.len();
+    let mut x = x0.to_vec();
+    let mut cost = problem.cost(&f0);
+    let mut f = f0.clone();
+    let mut f_true = f0;
+    let mut j = j0;
+    problem.scale_for_loss(&mut j, &mut f);
+    let mut g = mat_t_vec(&j, &f, n);
+
+    let (mut scale, mut scale_inv) = options.x_scale.scale(&j, n);
+    let mut delta = norm_inf(
+        &x0.iter()
+            .zip(&scale_inv)
+            .map(|(xi, si)| xi * si)
+            .collect::<Vec<f64>>(),
+    );
+    if delta == 0f64 {
+        delta = 1f64;
+    }
+
+    let mut on_bound: Vec<i8> = x0
+        .iter()
+        .zip(lb.iter().zip(ub))
+        .map(|(xi, (l, u))| if xi <= l { -1 } else { i8::from(xi >= u) })
+        .collect();
+
+    let mut status = None;
+    let mut g_norm;
+
+    loop {
+        // variables on a bound with the gradient pointing out of the feasible region are
+        // removed from the subproblem
+        let free: Vec<usize> = (0..n)
+            .filter(|i| f64::from(on_bound[*i]) * g[*i] >= 0f64)
+            .collect();
+        g_norm = free.iter().fold(0f64, |acc, i| acc.max(g[*i].abs()));
+        if g_norm < options.gtol {
+            status = Some(LeastSquaresStatus::GradientTolerance);
+        }
+        if status.is_some() || problem.nfev >= max_nfev {
+            break;
+        }
+
+        let select = |v: &[f64]| -> Vec<f64> { free.iter().map(|i| v[*i]).collect() };
+        let x_free = select(&x);
+        let lb_free = select(lb);
+        let ub_free = select(ub);
+        let scale_free = select(&scale);
+        let g_free = select(&g);
+        let j_free: Vec<Vec<f64>> = j.iter().map(|row| select(row)).collect();
+        let n_free = free.len();
+
+        // Gauss-Newton step and the quadratic model along the anti-gradient
+        let minus_f: Vec<f64> = f.iter().map(|fi| -fi).collect();
+        let newton_step = match options.tr_solver {
+            TrSolver::Exact => lstsq(&j_free, &minus_f, n_free),
+            TrSolver::Lsmr => {
+                // solved in the scaled variables, which works better when the solution is
+                // inexact
+                let j_scaled: Vec<Vec<f64>> = j_free
+                    .iter()
+                    .map(|row| row.iter().zip(&scale_free).map(|(a, s)| a * s).collect())
+                    .collect();
+                lsmr(
+                    |v| mat_vec(&j_scaled, v),
+                    |u| mat_t_vec(&j_scaled, u, n_free),
+                    &minus_f,
+                    n_free,
+                    LsmrOptions::default(),
+                )
+                .iter()
+                .zip(&scale_free)
+                .map(|(ni, si)| ni * si)
+                .collect()
+            }
+        };
+        let minus_g_free: Vec<f64> = g_free.iter().map(|gi| -gi).collect();
+        let (a, b, _) = build_quadratic_1d(&j_free, &g_free, &minus_g_free, None, None);
+
+        let mut actual_reduction = -1f64;
+        let mut trial = None;
+        while actual_reduction <= 0f64 && problem.nfev < max_nfev {
+            let tr_bounds: Vec<f64> = scale_free.iter().map(|s| delta * s).collect();
+            let (step_free, on_bound_free, tr_hit) = dogleg_step(
+                &x_free,
+                &newton_step,
+                &g_free,
+                a,
+                b,
+                &tr_bounds,
+                &lb_free,
+                &ub_free,
+            );
+            let mut step = vec![0f64; n];
+            for (i, si) in free.iter().zip(&step_free) {
+                step[*i] = *si;
+            }
+            let predicted_reduction = -evaluate_quadratic(&j_free, &g_free, &step_free, None);
+
+            let x_new: Vec<f64> = x
+                .iter()
+                .zip(&step)
+                .zip(lb.iter().zip(ub))
+                .map(|((xi, si), (l, u))| (xi + si).max(*l).min(*u))
+                .collect();
+            let f_new = problem.evaluate(&x_new);
+            let step_h_norm = norm_inf(
+                &step
+                    .iter()
+                    .zip(&scale_inv)
+                    .map(|(si, ci)| si * ci)
+                    .collect::<Vec<f64>>(),
+            );
+            if f_new.iter().any(|fi| !fi.is_finite()) {
+                delta = 0.25 * step_h_norm;
+                continue;
+            }
+
+            let cost_new = problem.cost(&f_new);
+            actual_reduction = cost - cost_new;
+            let (delta_new, ratio) = update_tr_radius(
+                delta,
+                actual_reduction,
+                predicted_reduction,
+                step_h_norm,
+                tr_hit,
+            );
+            delta = delta_new;
+            trial = Some((x_new, f_new, cost_new, on_bound_free));
+            status = check_termination(
+                actual_reduction,
+                cost,
+                norm(&step),
+                norm(&x),
+                ratio,
+                options.ftol,
+                options.xtol,
+            );
+            if status.is_some() {
+                break;
+            }
+        }
+
+        if actual_reduction > 0f64 {
+            if let Some((x_new, f_new, cost_new, on_bound_free)) = trial {
+                for (i, hit) in free.iter().zip(on_bound_free) {
+                    on_bound[*i] = hit;
+                }
+                // set the variables exactly on the bounds they hit
+                x = x_new
+                    .iter()
+                    .zip(&on_bound)
+                    .zip(lb.iter().zip(ub))
+                    .map(|((xi, hit), (l, u))| match hit {
+                        -1 => *l,
+                        1 => *u,
+                        _ => *xi,
+                    })
+                    .collect();
+                f.clone_from(&f_new);
+                f_true = f_new;
+                cost = cost_new;
+                j = problem.jacobian(&x, &f_true)?;
+                problem.scale_for_loss(&mut j, &mut f);
+                g = mat_t_vec(&j, &f, n);
+                if options.x_scale.is_jac() {
+                    (scale, scale_inv) = compute_jac_scale(&j, n, Some(&scale_inv));
+                }
+            }
+        }
+    }
+
+    Ok(Solution {
+        active_mask: on_bound,
+        optimality: g_norm,
+        status: status.unwrap_or(LeastSquaresStatus::MaxFevExceeded),
+        grad: g,
+        jac: j,
+        fun: f_true,
+        x,
+    })
+}
@@ -0,0 +1,396 @@
+//! Levenberg-Marquardt algorithm, a port of `lmder` and `lmdif` from MINPACK
+//!
+//! # References
+//! * Moré, "The Levenberg-Marquardt algorithm: implementation and theory", Numerical
+//!   Analysis, Lecture Notes in Mathematics 630 (1978)
+
+use super::{LeastSquaresOptions, LeastSquaresStatus, Problem, ResidualFunction, XScale};
+use crate::linalg::{mat_t_vec, norm, qr_pivoted};
+use crate::SwoopErrors;
+
+/// Solution, residuals and Jacobian at the solution and the reason the solver terminated
+type LmSolution = (Vec<f64>, Vec<f64>, Vec<Vec<f64>>, LeastSquaresStatus);
+
+/// Factor of the initial step bound
+const FACTOR: f64 = 100.0;
+
+/// Element-wise product of two vectors
+fn scaled(diag: &[f64], x: &[f64]) -> Vec<f64> {
+    diag.iter().zip(x).map(|(d, xi)| d * xi).collect()
+}
+
+/// Solve `min ||(A; D) x - (b; 0)||` given the QR factorisation with column pivoting
+/// `A P = Q R` and `qtb = Q^T b`, port of MINPACK `qrsolv`.
+///
+/// On return the strict lower triangle of `r` holds the transpose of the strict upper
+/// triangle of `S`, where `P^T (A^T A + D D) P = S^T S`, and `sdiag` holds its diagonal.
+#[allow(clippy::needless_range_loop)]
+fn qrsolv(
+    r: &mut [Vec<f64>],
+    perm: &[usize],
+    diag: &[f64],
+    qtb: &[f64],
+    sdiag: &mut [f64],
+) -> Vec<f64> {
+    let n = qtb.len();
+    let mut x = vec![0f64; n];
+    let mut wa = qtb.to_vec();
+    for j in 0..n {
+        for i in j..n {
+            r[i][j] = r[j][i];
+        }
+        x[j] = r[j][j];
+    }
+
+    // eliminate the diagonal matrix D using Givens rotations
+    for j in 0..n {
+        let l = perm[j];
+        if diag[l] != 0f64 {
+            for s in sdiag.iter_mut().skip(j) {
+                *s = 0f64;
+            }
+            sdiag[j] = diag[l];
+            let mut qtbpj = 0f64;
+            for k in j..n {
+                if sdiag[k] == 0f64 {
+                    continue;
+                }
+                let (sin, cos) = if r[k][k].abs() < sdiag[k].abs() {
+                    let cotan = r[k][k] / sdiag[k];
+                    let sin = 0.5 / (0.25 + 0.25 * cotan.powi(2)).sqrt();
+                    (sin, sin * cotan)
+                } else {
+                    let tan = sdiag[k] / r[k][k];
+                    let cos = 0.5 / (0.25 + 0.25 * tan.powi(2)).sqrt();
+                    (cos * tan, cos)
+                };
+                r[k][k] = cos * r[k][k] + sin * sdiag[k];
+                let temp = cos * wa[k] + sin * qtbpj;
+                qtbpj = -sin * wa[k] + cos * qtbpj;
+                wa[k] = temp;
+                for i in k + 1..n {
+                    let temp = cos * r[i][k] + sin * sdiag[i];
+                    sdiag[i] = -sin * r[i][k] + cos * sdiag[i];
+                    r[i][k] = temp;
+                }
+            }
+        }
+        sdiag[j] = r[j][j];
+        r[j][j] = x[j];
+    }
+
+    // solve the triangular system, in the least squares sense if it is singular
+    let nsing = sdiag.iter().position(|s| *s == 0f64).unwrap_or(n);
+    for w in wa.iter_mut().skip(nsing) {
+        *w = 0f64;
+    }
+    for k in (0..nsing).rev() {
+        let sum: f64 = (k + 1..nsing).map(|i| r[i][k] * wa[i]).sum();
+        wa[k] = (wa[k] - sum) / sdiag[k];
+    }
+    for (j, w) in wa.iter().enumerate() {
+        x[perm[j]] = *w;
+    }
+    x
+}
+
+/// Determine the Levenberg-Marquardt parameter `par` such that the solution `x` of
+/// `min ||(A; sqrt(par) D) x - (b; 0)||` satisfies `||D x|| ~ delta`, port of MINPACK
+/// `lmpar`.
+///
+/// Returns the parameter and the solution `x`.
+#[allow(clippy::needless_range_loop)]
+fn lmpar(
+    r: &[Vec<f64>],
+    perm: &[usize],
+    diag: &[f64],
+    qtb: &[f64],
+    delta: f64,
+    par: f64,
+) -> (f64, Vec<f64>) {
+    let n = qtb.len();
+    let dwarf = f64::MIN_POSITIVE;
+    let mut r = r.to_vec();
+
+    // Gauss-Newton direction, a least squares solution if the Jacobian is rank deficient
+    let nsing = (0..n).position(|j| r[j][j] == 0f64).unwrap_or(n);
+    let mut wa1 = qtb.to_vec();
+    for w in wa1.iter_mut().skip(nsing) {
+        *w = 0f64;
+    }
+    for k in (0..nsing).rev() {
+        wa1[k] /= r[k][k];
+        let temp = wa1[k];
+        for i in 0..k {
+            wa1[i] -= r[i][k] * temp;
+        }
+    }
+    let mut x = vec![0f64; n];
+    for j in 0..n {
+        x[perm[j]] = wa1[j];
+    }
+
+    let mut wa2 = scaled(diag, &x);
+    let mut dxnorm = norm(&wa2);
+    let mut fp = dxnorm - delta;
+    if fp <= 0.1 * delta {
+        return (0f64, x);
+    }
+
+    // lower bound of the parameter, zero if the Jacobian is rank deficient
+    let mut parl = 0f64;
+    if nsing >= n {
+        for j in 0..n {
+            let l = perm[j];
+            wa1[j] = diag[l] * (wa2[l] / dxnorm);
+        }
+        for j in 0..n {
+            let sum: f64 = (0..j).map(|i| r[i][j] * wa1[i]).sum();
+            wa1[j] = (wa1[j] - sum) / r[j][j];
+        }
+        let temp = norm(&wa1);
+        parl = ((fp / delta) / temp) / temp;
+    }
+
+    // upper bound of the parameter
+    for j in 0..n {
+        let sum: f64 = (0..=j).map(|i| r[i][j] * qtb[i]).sum();
+        wa1[j] = sum / diag[perm[j]];
+    }
+    let gnorm = norm(&wa1);
+    let mut paru = gnorm / delta;
+    if paru == 0f64 {
+        paru = dwarf / delta.min(0.1);
+    }
+
+    let mut par = par.max(parl).min(paru);
+    if par == 0f64 {
+        par = gnorm / dxnorm;
+    }
+
+    let mut sdiag = vec![0f64; n];
+    for iter in 1.. {
+        if par == 0f64 {
+            par = dwarf.max(0.001 * paru);
+        }
+        let sqrt_par = par.sqrt();
+        let scaled_diag: Vec<f64> = diag.iter().map(|d| sqrt_par * d).collect();
+        x = qrsolv(&mut r, perm, &scaled_diag, qtb, &mut sdiag);
+        wa2 = scaled(diag, &x);
+        dxnorm = norm(&wa2);
+        let previous_fp = fp;
+        fp = dxnorm - delta;
+        if fp.abs() <= 0.1 * delta
+            || (parl == 0f64 && fp <= previous_fp && previous_fp < 0f64)
+            || iter == 10
+        {
+            break;
+        }
+
+        // Newton correction
+        for j in 0..n {
+            let l = perm[j];
+            wa1[j] = diag[l] * (wa2[l] / dxnorm);
+        }
+        for j in 0..n {
+            wa1[j] /= sdiag[j];
+            let temp = wa1[j];
+            for i in j + 1..n {
+                wa1[i] -= r[i][j] * temp;
+            }
+        }
+        let temp = norm(&wa1);
+        let parc = ((fp / delta) / temp) / temp;
+        if fp > 0f64 {
+            parl = parl.max(par);
+        }
+        if fp < 0f64 {
+            paru = paru.min(par);
+        }
+        par = parl.max(par + parc);
+    }
+    (par, x)
+}
+
+/// Minimise the sum of squares of the residuals with the Levenberg-Marquardt algorithm
+///
+/// # Parameters
+/// * `problem` - residual function
+/// * `x0` - initial guess
+/// * `f0` - residuals at `x0`
+/// * `j0` - Jacobian at `x0`
+/// * `options` - solver options
+/// * `max_nfev` - maximum evaluations of the residuals, including those of the Jacobian at the
+///   solution when it is approximated
+///
+/// Returns the solution, the residuals and the Jacobian at the solution and the reason the
+/// solver terminated.
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of residuals changes between the steps
+/// of the finite differences
+#[allow(clippy::too_many_lines)]
+pub(super) fn lm<T: ResidualFunction>(
+    problem: &mut Problem<'_, T>,
+    x0: &[f64],
+    f0: Vec<f64>,
+    j0: Vec<Vec<f64>>,
+    options: &LeastSquaresOptions,
+    max_nfev: usize,
+) -> Result<LmSolution, SwoopErrors> {
+    let n = x0.len();
+    let eps = f64::EPSILON;
+    let mut x = x0.to_vec();
+    let mut f = f0;
+    let mut fnorm = norm(&f);
+    let mut jac = j0;
+    // evaluations of the residuals needed by a Jacobian
+    let jac_nfev = if problem.analytic { 0 } else { n };
+
+    let scale_with_jac = options.x_scale == XScale::Jac;
+    let mut diag = match &options.x_scale {
+        XScale::Scalar(s) => vec![1f64 / s; n],
+        XScale::Vector(v) => v.iter().map(|s| 1f64 / s).collect(),
+        XScale::Jac => vec![1f64; n],
+    };
+
+    let mut par = 0f64;
+    let mut delta = 0f64;
+    let mut xnorm = 0f64;
+    let mut first_iteration = true;
+
+    loop {
+        let acnorm: Vec<f64> = (0..n)
+            .map(|j| jac.iter().map(|row| row[j].powi(2)).sum::<f64>().sqrt())
+            .collect();
+        let (q, r, perm) = qr_pivoted(&jac, n);
+        let qtf = mat_t_vec(&q, &f, n);
+
+        if first_iteration {
+            if scale_with_jac {
+                diag = acnorm
+                    .iter()
+                    .map(|a| if *a == 0f64 { 1f64 } else { *a })
+                    .collect();
+            }
+            xnorm = norm(&scaled(&diag, &x));
+            delta = FACTOR * xnorm;
+            if delta == 0f64 {
+                delta = FACTOR;
+            }
+        }
+
+        // norm of the scaled gradient
+        let mut gnorm = 0f64;
+        if fnorm != 0f64 {
+            for j in 0..n {
+                let l = perm[j];
+                if acnorm[l] != 0f64 {
+                    let sum: f64 = (0..=j).map(|i| r[i][j] * (qtf[i] / fnorm)).sum();
+                    gnorm = gnorm.max((sum / acnorm[l]).abs());
+                }
+            }
+        }
+        if gnorm <= options.gtol {
+            return Ok((x, f, jac, LeastSquaresStatus::GradientTolerance));
+        }
+
+        if scale_with_jac {
+            for (d, a) in diag.iter_mut().zip(&acnorm) {
+                *d = d.max(*a);
+            }
+        }
+
+        loop {
+            // the trial point and the Jacobian at the solution must fit in the evaluations
+            if problem.nfev + 1 + jac_nfev > max_nfev {
+                return Ok((x, f, jac, LeastSquaresStatus::MaxFevExceeded));
+            }
+            let (new_par, step) = lmpar(&r, &perm, &diag, &qtf, delta, par);
+            par = new_par;
+            let x_trial: Vec<f64> = x.iter().zip(&step).map(|(xi, pi)| xi - pi).collect();
+            let pnorm = norm(&scaled(&diag, &step));
+            if first_iteration {
+                delta = delta.min(pnorm);
+            }
+
+            let f_trial = problem.evaluate(&x_trial);
+            let fnorm_trial = norm(&f_trial);
+
+            // actual, predicted and directional reductions
+            let actred = if 0.1 * fnorm_trial < fnorm {
+                1f64 - (fnorm_trial / fnorm).powi(2)
+            } else {
+                -1f64
+            };
+            let mut rp = vec![0f64; n];
+            for j in 0..n {
+                let pj = -step[perm[j]];
+                for i in 0..=j {
+                    rp[i] += r[i][j] * pj;
+                }
+            }
+            let temp1 = norm(&rp) / fnorm;
+            let temp2 = par.sqrt() * pnorm / fnorm;
+            let prered = temp1.powi(2) + temp2.powi(2) / 0.5;
+            let dirder = -(temp1.powi(2) + temp2.powi(2));
+            let ratio = if prered == 0f64 {
+                0f64
+            } else {
+                actred / prered
+            };
+
+            // update the step bound
+            if ratio <= 0.25 {
+                let mut temp = if actred >= 0f64 {
+                    0.5
+                } else {
+                    0.5 * dirder / (dirder + 0.5 * actred)
+                };
+                if 0.1 * fnorm_trial >= fnorm || temp < 0.1 {
+                    temp = 0.1;
+                }
+                delta = temp * delta.min(pnorm / 0.1);
+                par /= temp;
+            } else if par == 0f64 || ratio >= 0.75 {
+                delta = pnorm / 0.5;
+                par *= 0.5;
+            }
+
+            let successful = ratio >= 1e-4;
+            if successful {
+                x = x_trial;
+                f = f_trial;
+                xnorm = norm(&scaled(&diag, &x));
+                fnorm = fnorm_trial;
+                first_iteration = false;
+            }
+
+            let ftol_reached =
+                actred.abs() <= options.ftol && prered <= options.ftol && ratio <= 2f64;
+            let xtol_reached = delta <= options.xtol * xnorm;
+            let status = match (ftol_reached, xtol_reached) {
+                (true, true) => Some(LeastSquaresStatus::FunctionAndStepTolerance),
+                (true, false) => Some(LeastSquaresStatus::FunctionTolerance),
+                (false, true) => Some(LeastSquaresStatus::StepTolerance),
+                // the tolerances are too small for any further improvement
+                (false, false) if actred.abs() <= eps && prered <= eps && ratio <= 2f64 => {
+                    Some(LeastSquaresStatus::FunctionTolerance)
+                }
+                (false, false) if delta <= eps * xnorm => Some(LeastSquaresStatus::StepTolerance),
+                (false, false) if gnorm <= eps => Some(LeastSquaresStatus::GradientTolerance),
+                (false, false) => None,
+            };
+
+            if successful {
+                jac = problem.jacobian(&x, &f)?;
+            }
+            if let Some(status) = status {
+                return Ok((x, f, jac, status));
+            }
+            if successful {
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+//! Robust loss functions to reduce the influence of outliers
+//!
+//! The cost becomes `0.5 * f_scale^2 * sum(rho((f_i / f_scale)^2))`, which is minimised by
+//! rescaling the residuals and the Jacobian in every iteration (iteratively reweighted least
+//! squares).
+
+use std::fmt;
+use std::sync::Arc;
+
+/// Trait to implement for a custom loss function
+pub trait LossFunction {
+    /// Method to implement `rho(z)` and its first and second derivatives, where `z` is the
+    /// square of a scaled residual. The loss should satisfy `rho(0) = 0`, `rho'(0) = 1` and
+    /// `rho''(0) <= 0`
+    fn rho(&self, z: f64) -> (f64, f64, f64);
+}
+
+/// Loss function applied to the squared residuals
+#[derive(Clone, Default)]
+pub enum Loss {
+    /// Standard least squares, `rho(z) = z`
+    #[default]
+    Linear,
+    /// Smooth approximation of the absolute value loss, `rho(z) = 2 * ((1 + z)^0.5 - 1)`
+    SoftL1,
+    /// Quadratic for small residuals and linear for large ones,
+    /// `rho(z) = z` if `z <= 1` else `2 * z^0.5 - 1`
+    Huber,
+    /// Strongly reduces the influence of outliers, `rho(z) = ln(1 + z)`
+    Cauchy,
+    /// Limits the loss of any residual, `rho(z) = arctan(z)`
+    Arctan,
+    /// User supplied loss function
+    Custom(Arc<dyn LossFunction + Send + Sync>),
+}
+
+impl fmt::Debug for Loss {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Linear => write!(f, "Linear"),
+            Self::SoftL1 => write!(f, "SoftL1"),
+            Self::Huber => write!(f, "Huber"),
+            Self::Cauchy => write!(f, "Cauchy"),
+            Self::Arctan => write!(f, "Arctan"),
+            Self::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl LossFunction for Loss {
+    fn rho(&self, z: f64) -> (f64, f64, f64) {
+        match self {
+            Self::Linear => (z, 1f64, 0f64),
+            Self::SoftL1 => {
+                let t = 1f64 + z;
+                (
+                    2f64 * (t.sqrt() - 1f64),
+                    1f64 / t.sqrt(),
+                    -0.5 / t.powf(1.5),
+                )
+            }
+            Self::Huber => {
+                if z <= 1f64 {
+                    (z, 1f64, 0f64)
+                } else {
+                    (2f64 * z.sqrt() - 1f64, 1f64 / z.sqrt(), -0.5 / z.powf(1.5))
+                }
+            }
+            Self::Cauchy => {
+                let t = 1f64 + z;
+                (z.ln_1p(), 1f64 / t, -1f64 / t.powi(2))
+            }
+            Self::Arctan => {
+                let t = 1f64 + z.powi(2);
+                (z.atan(), 1f64 / t, -2f64 * z / t.powi(2))
+            }
+            Self::Custom(loss) => loss.rho(z),
+        }
+    }
+}
+
+impl Loss {
+    /// Whether this is the standard least squares loss
+    pub(super) fn is_linear(&self) -> bool {
+        matches!(self, Self::Linear)
+    }
+
+    /// Values of `rho` and its derivatives for each residual, scaled such that the cost is
+    /// `0.5 * sum(rho)`
+    fn evaluate(&self, f: &[f64], f_scale: f64) -> Vec<(f64, f64, f64)> {
+        let c2 = f_scale.powi(2);
+        f.iter()
+            .map(|fi| {
+                let (rho, rho1, rho2) = self.rho((fi / f_scale).powi(2));
+                (rho * c2, rho1, rho2 / c2)
+            })
+            .collect()
+    }
+
+    /// Cost function `0.5 * f_scale^2 * sum(rho((f_i / f_scale)^2))`
+    pub(super) fn cost(&self, f: &[f64], f_scale: f64) -> f64 {
+        if self.is_linear() {
+            return 0.5 * f.iter().map(|fi| fi.powi(2)).sum::<f64>();
+        }
+        0.5 * self
+            .evaluate(f, f_scale)
+            .iter()
+            .map(|(rho, _, _)| rho)
+            .sum::<f64>()
+    }
+
+    /// Scale the residuals and the rows of the Jacobian such that `J^T J` is the Gauss-Newton
+    /// approximation of the Hessian of the robust cost and `J^T f` its gradient
+    pub(super) fn scale(&self, j: &mut [Vec<f64>], f: &mut [f64], f_scale: f64) {
+        if self.is_linear() {
+            return;
+        }
+        let rho = self.evaluate(f, f_scale);
+        for ((row, fi), (_, rho1, rho2)) in j.iter_mut().zip(f.iter_mut()).zip(rho) {
+            let j_scale = (rho1 + 2f64 * rho2 * fi.powi(2)).max(f64::EPSILON).sqrt();
+            *fi *= rho1 / j_scale;
+            for jij in row.iter_mut() {
+                *jij *= j_scale;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_derivatives() {
+        let h = 1e-6;
+        for loss in [
+            Loss::Linear,
+            Loss::SoftL1,
+            Loss::Huber,
+            Loss::Cauchy,
+            Loss::Arctan,
+        ] {
+            let (rho0, _, _) = loss.rho(0f64);
+            assert!(relative_eq!(rho0, 0f64));
+            for z in [0.3f64, 2f64, 7f64] {
+                let (_, rho1, rho2) = loss.rho(z);
+                let (rho_plus, rho1_plus, _) = loss.rho(z + h);
+                let (rho_minus, rho1_minus, _) = loss.rho(z - h);
+                assert!(relative_eq!(
+                    rho1,
+                    (rho_plus - rho_minus) / (2f64 * h),
+                    epsilon = 1e-6
+                ));
+                assert!(relative_eq!(
+                    rho2,
+                    (rho1_plus - rho1_minus) / (2f64 * h),
+                    epsilon = 1e-6
+                ));
+            }
+        }
+    }
+}
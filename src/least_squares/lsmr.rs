@@ -0,0 +1,272 @@
+//! LSMR iterative solver for sparse or matrix free least squares problems
+//!
+//! # References
+//! * Fong and Saunders, "LSMR: An iterative algorithm for sparse least-squares problems",
+//!   SIAM Journal on Scientific Computing 33.5 (2011)
+
+use crate::linalg::norm;
+
+/// Upper limit on the estimated condition number of `A`
+const CONLIM: f64 = 1e8;
+
+/// Sign of `x`, zero for zero
+fn sign(x: f64) -> f64 {
+    if x == 0f64 {
+        0f64
+    } else {
+        x.signum()
+    }
+}
+
+/// Stable construction of a Givens rotation, returns `(c, s, r)` such that
+/// `[c s; -s c] [a; b] = [r; 0]`
+fn sym_ortho(a: f64, b: f64) -> (f64, f64, f64) {
+    if b == 0f64 {
+        (sign(a), 0f64, a.abs())
+    } else if a == 0f64 {
+        (0f64, sign(b), b.abs())
+    } else if b.abs() > a.abs() {
+        let tau = a / b;
+        let s = sign(b) / (1f64 + tau * tau).sqrt();
+        let c = s * tau;
+        (c, s, b / s)
+    } else {
+        let tau = b / a;
+        let c = sign(a) / (1f64 + tau * tau).sqrt();
+        let s = c * tau;
+        (c, s, a / c)
+    }
+}
+
+/// Options for the LSMR solver
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct LsmrOptions {
+    /// Damping factor, solves `min ||(A; damp I) x - (b; 0)||`
+    pub(crate) damp: f64,
+    /// Relative tolerance of the estimate of `A`
+    pub(crate) atol: f64,
+    /// Relative tolerance of the estimate of `b`
+    pub(crate) btol: f64,
+    /// Maximum iterations, defaults to `min(m, n)`
+    pub(crate) maxiter: Option<usize>,
+}
+
+impl Default for LsmrOptions {
+    fn default() -> Self {
+        Self {
+            damp: 0f64,
+            atol: 1e-6,
+            btol: 1e-6,
+            maxiter: None,
+        }
+    }
+}
+
+/// Solve `min ||A x - b||`, optionally damped, where `A` is only accessed through products
+///
+/// # Parameters
+/// * `matvec` - product `A v` for a vector `v` of length `n`
+/// * `rmatvec` - product `A^T u` for a vector `u` of length `m`
+/// * `b` - right hand side of length `m`
+/// * `n` - number of columns of `A`
+/// * `options` - solver options
+#[allow(clippy::too_many_lines)]
+pub(crate) fn lsmr<A, AT>(
+    mut matvec: A,
+    mut rmatvec: AT,
+    b: &[f64],
+    n: usize,
+    options: LsmrOptions,
+) -> Vec<f64>
+where
+    A: FnMut(&[f64]) -> Vec<f64>,
+    AT: FnMut(&[f64]) -> Vec<f64>,
+{
+    let m = b.len();
+    let maxiter = options.maxiter.unwrap_or_else(|| m.min(n));
+    let damp = options.damp;
+    let mut x = vec![0f64; n];
+
+    let normb = norm(b);
+    let mut beta = normb;
+    let mut u: Vec<f64> = b.to_vec();
+    let mut v;
+    let mut alpha;
+    if beta > 0f64 {
+        for ui in &mut u {
+            *ui /= beta;
+        }
+        v = rmatvec(&u);
+        alpha = norm(&v);
+    } else {
+        v = vec![0f64; n];
+        alpha = 0f64;
+    }
+    if alpha > 0f64 {
+        for vi in &mut v {
+            *vi /= alpha;
+        }
+    }
+    if alpha * beta == 0f64 {
+        return x;
+    }
+
+    let mut zetabar = alpha * beta;
+    let mut alphabar = alpha;
+    let mut rho = 1f64;
+    let mut rhobar = 1f64;
+    let mut cbar = 1f64;
+    let mut sbar = 0f64;
+    let mut h = v.clone();
+    let mut hbar = vec![0f64; n];
+
+    // variables for the estimation of ||r||
+    let mut betadd = beta;
+    let mut betad = 0f64;
+    let mut rhodold = 1f64;
+    let mut tautildeold = 0f64;
+    let mut thetatilde = 0f64;
+    let mut zeta = 0f64;
+    let mut d = 0f64;
+
+    // variables for the estimation of ||A|| and cond(A)
+    let mut norm_a2 = alpha * alpha;
+    let mut maxrbar = 0f64;
+    let mut minrbar = 1e100;
+    let ctol = 1f64 / CONLIM;
+
+    for itn in 1..=maxiter {
+        // next step of the bidiagonalisation
+        let av = matvec(&v);
+        for (ui, avi) in u.iter_mut().zip(&av) {
+            *ui = avi - alpha * *ui;
+        }
+        beta = norm(&u);
+        if beta > 0f64 {
+            for ui in &mut u {
+                *ui /= beta;
+            }
+            let atu = rmatvec(&u);
+            for (vi, atui) in v.iter_mut().zip(&atu) {
+                *vi = atui - beta * *vi;
+            }
+            alpha = norm(&v);
+            if alpha > 0f64 {
+                for vi in &mut v {
+                    *vi /= alpha;
+                }
+            }
+        }
+
+        // rotations to turn B into R and R^T into R bar
+        let (chat, shat, alphahat) = sym_ortho(alphabar, damp);
+        let rhoold = rho;
+        let (c, s, rho_new) = sym_ortho(alphahat, beta);
+        rho = rho_new;
+        let thetanew = s * alpha;
+        alphabar = c * alpha;
+
+        let rhobarold = rhobar;
+        let zetaold = zeta;
+        let thetabar = sbar * rho;
+        let rhotemp = cbar * rho;
+        let (cbar_new, sbar_new, rhobar_new) = sym_ortho(cbar * rho, thetanew);
+        cbar = cbar_new;
+        sbar = sbar_new;
+        rhobar = rhobar_new;
+        zeta = cbar * zetabar;
+        zetabar *= -sbar;
+
+        // update h, hbar and x
+        let hbar_scale = -(thetabar * rho / (rhoold * rhobarold));
+        let x_scale = zeta / (rho * rhobar);
+        let h_scale = -(thetanew / rho);
+        for i in 0..n {
+            hbar[i] = hbar[i] * hbar_scale + h[i];
+            x[i] += x_scale * hbar[i];
+            h[i] = h[i] * h_scale + v[i];
+        }
+
+        // estimate ||r||
+        let betaacute = chat * betadd;
+        let betacheck = -shat * betadd;
+        let betahat = c * betaacute;
+        betadd = -s * betaacute;
+        let thetatildeold = thetatilde;
+        let (ctildeold, stildeold, rhotildeold) = sym_ortho(rhodold, thetabar);
+        thetatilde = stildeold * rhobar;
+        rhodold = ctildeold * rhobar;
+        betad = -stildeold * betad + ctildeold * betahat;
+        tautildeold = (zetaold - thetatildeold * tautildeold) / rhotildeold;
+        let taud = (zeta - thetatilde * tautildeold) / rhodold;
+        d += betacheck * betacheck;
+        let normr = (d + (betad - taud).powi(2) + betadd * betadd).sqrt();
+
+        // estimate ||A|| and cond(A)
+        norm_a2 += beta * beta;
+        let norm_a = norm_a2.sqrt();
+        norm_a2 += alpha * alpha;
+        maxrbar = f64::max(maxrbar, rhobarold);
+        if itn > 1 {
+            minrbar = f64::min(minrbar, rhobarold);
+        }
+        let cond_a = maxrbar.max(rhotemp) / minrbar.min(rhotemp);
+
+        // convergence tests
+        let normar = zetabar.abs();
+        let normx = norm(&x);
+        let test1 = normr / normb;
+        let test2 = if norm_a * normr == 0f64 {
+            f64::INFINITY
+        } else {
+            normar / (norm_a * normr)
+        };
+        let test3 = 1f64 / cond_a;
+        let t1 = test1 / (1f64 + norm_a * normx / normb);
+        let rtol = options.btol + options.atol * norm_a * normx / normb;
+        if 1f64 + test3 <= 1f64
+            || 1f64 + test2 <= 1f64
+            || 1f64 + t1 <= 1f64
+            || test3 <= ctol
+            || test2 <= options.atol
+            || test1 <= rtol
+        {
+            break;
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::{lstsq, mat_t_vec, mat_vec};
+    use approx::relative_eq;
+
+    #[test]
+    fn test_lsmr() {
+        let a = vec![
+            vec![1f64, 2f64, 0f64],
+            vec![0f64, 1f64, 3f64],
+            vec![4f64, 0f64, 1f64],
+            vec![1f64, 1f64, 1f64],
+        ];
+        let b = [1f64, -2f64, 3f64, 0.5f64];
+        let expected = lstsq(&a, &b, 3);
+        let x = lsmr(
+            |v| mat_vec(&a, v),
+            |u| mat_t_vec(&a, u, 3),
+            &b,
+            3,
+            LsmrOptions {
+                atol: 1e-12,
+                btol: 1e-12,
+                maxiter: Some(20),
+                ..LsmrOptions::default()
+            },
+        );
+        for (xi, ei) in x.iter().zip(&expected) {
+            assert!(relative_eq!(xi, ei, epsilon = 1e-8));
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Bounded-Variable Least Squares
+//!
+//! An active set method in the style of Lawson and Hanson's non-negative least squares, where
+//! each variable is either free or held at one of its bounds. The variable at a bound with the
+//! largest violation of the optimality conditions is freed and the least squares problem on the
+//! free variables is solved, moving towards its solution until a free variable hits a bound.
+//!
+//! # References
+//! * Stark and Parker, "Bounded-variable least-squares: an algorithm and applications",
+//!   Computational Statistics 10 (1995)
+
+use super::{residual, LsqLinearStatus, Solution};
+use crate::linalg::{dot, lstsq, mat_t_vec, mat_vec};
+
+/// Least squares solution for the free variables with the other variables held at their value
+/// in `x`
+fn free_solution(a: &[Vec<f64>], b: &[f64], x: &[f64], free: &[usize]) -> Vec<f64> {
+    let fixed: Vec<f64> = x
+        .iter()
+        .enumerate()
+        .map(|(j, xj)| if free.contains(&j) { 0f64 } else { *xj })
+        .collect();
+    let b_free: Vec<f64> = b
+        .iter()
+        .zip(mat_vec(a, &fixed))
+        .map(|(bi, ax)| bi - ax)
+        .collect();
+    let a_free: Vec<Vec<f64>> = a
+        .iter()
+        .map(|row| free.iter().map(|&j| row[j]).collect())
+        .collect();
+    lstsq(&a_free, &b_free, free.len())
+}
+
+/// Violation of the optimality conditions, the magnitude of the gradient for free variables
+/// and the gradient pointing into the bounds for the others
+fn kkt_optimality(g: &[f64], on_bound: &[i8]) -> f64 {
+    g.iter()
+        .zip(on_bound)
+        .map(|(gi, side)| match side {
+            0 => gi.abs(),
+            _ => gi * f64::from(*side),
+        })
+        .fold(0f64, f64::max)
+}
+
+/// Solve a bounded linear least squares problem with the BVLS method
+///
+/// # Parameters
+/// * `a` - matrix of the problem
+/// * `b` - right hand side
+/// * `x_lsq` - unconstrained least squares solution
+/// * `lb` - lower bounds
+/// * `ub` - upper bounds
+/// * `tol` - tolerance for the optimality and the relative change of the cost function
+/// * `max_iter` - maximum iterations after the initialisation
+#[allow(clippy::too_many_lines)]
+pub(super) fn bvls(
+    a: &[Vec<f64>],
+    b: &[f64],
+    x_lsq: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+    tol: f64,
+    max_iter: usize,
+) -> Solution {
+    let n = x_lsq.len();
+    let mut x = x_lsq.to_vec();
+    let mut on_bound = vec![0i8; n];
+    for j in 0..n {
+        if x[j] <= lb[j] {
+            x[j] = lb[j];
+            on_bound[j] = -1;
+        } else if x[j] >= ub[j] {
+            x[j] = ub[j];
+            on_bound[j] = 1;
+        }
+    }
+    let mut free: Vec<usize> = (0..n).filter(|&j| on_bound[j] == 0).collect();
+    let mut nit = 0;
+
+    // fix the variables whose least squares solution on the free set violates a bound, until
+    // the solution on the free set is feasible
+    while !free.is_empty() {
+        let z = free_solution(a, b, &x, &free);
+        let mut feasible = true;
+        for (&j, zj) in free.iter().zip(z) {
+            if zj < lb[j] {
+                x[j] = lb[j];
+                on_bound[j] = -1;
+                feasible = false;
+            } else if zj > ub[j] {
+                x[j] = ub[j];
+                on_bound[j] = 1;
+                feasible = false;
+            } else {
+                x[j] = zj;
+            }
+        }
+        nit += 1;
+        if feasible {
+            break;
+        }
+        free.retain(|&j| on_bound[j] == 0);
+    }
+
+    let mut r = residual(a, b, &x);
+    let mut cost = 0.5 * dot(&r, &r);
+    let mut g = mat_t_vec(a, &r, n);
+    let mut optimality = kkt_optimality(&g, &on_bound);
+    let mut status = None;
+    let max_iter = max_iter + nit;
+
+    while nit < max_iter {
+        if optimality < tol {
+            status = Some(LsqLinearStatus::GradientTolerance);
+        }
+        if status.is_some() {
+            break;
+        }
+
+        // free the bound variable that violates the optimality conditions the most
+        let move_to_free = (0..n)
+            .max_by(|i, j| {
+                (g[*i] * f64::from(on_bound[*i])).total_cmp(&(g[*j] * f64::from(on_bound[*j])))
+            })
+            .unwrap_or(0);
+        on_bound[move_to_free] = 0;
+
+        loop {
+            let free: Vec<usize> = (0..n).filter(|&j| on_bound[j] == 0).collect();
+            let z = free_solution(a, b, &x, &free);
+
+            // shortest step towards `z` to a violated bound
+            let mut blocking = None;
+            let mut alpha = f64::INFINITY;
+            for (&j, zj) in free.iter().zip(&z) {
+                let (bound, side) = if *zj < lb[j] {
+                    (lb[j], -1)
+                } else if *zj > ub[j] {
+                    (ub[j], 1)
+                } else {
+                    continue;
+                };
+                let length = (bound - x[j]) / (zj - x[j]);
+                if length < alpha {
+                    alpha = length;
+                    blocking = Some((j, side));
+                }
+            }
+            if let Some((j, side)) = blocking {
+                for (&k, zk) in free.iter().zip(&z) {
+                    x[k] += alpha * (zk - x[k]);
+                }
+                x[j] = if side < 0 { lb[j] } else { ub[j] };
+                on_bound[j] = side;
+            } else {
+                for (&k, zk) in free.iter().zip(z) {
+                    x[k] = zk;
+                }
+                break;
+            }
+        }
+
+        r = residual(a, b, &x);
+        let cost_new = 0.5 * dot(&r, &r);
+        if cost - cost_new < tol * cost {
+            status = Some(LsqLinearStatus::FunctionTolerance);
+        }
+        cost = cost_new;
+        g = mat_t_vec(a, &r, n);
+        optimality = kkt_optimality(&g, &on_bound);
+        nit += 1;
+    }
+    if status.is_none() && optimality < tol {
+        status = Some(LsqLinearStatus::GradientTolerance);
+    }
+
+    Solution {
+        x,
+        optimality,
+        active_mask: on_bound,
+        status: status.unwrap_or(LsqLinearStatus::MaxIterExceeded),
+        nit,
+    }
+}
@@ -0,0 +1,380 @@
+//! Linear least squares with bounds on the variables
+//!
+//! The unconstrained least squares solution is returned directly if it lies within the bounds,
+//! otherwise the problem is solved with the Trust Region Reflective method adapted to linear
+//! problems or the Bounded-Variable Least Squares active set method.
+
+mod bvls;
+mod trf;
+
+use super::common::{check_linear_system, in_bounds};
+use super::lsmr::{lsmr, LsmrOptions};
+use super::TrSolver;
+use crate::constraints::Bounds;
+use crate::linalg::{dot, lstsq, mat_t_vec, mat_vec, norm, norm_inf};
+use crate::SwoopErrors;
+
+/// Algorithm used by `lsq_linear`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LsqLinearMethod {
+    /// Trust Region Reflective adapted to linear least squares
+    #[default]
+    Trf,
+    /// Bounded-Variable Least Squares, an active set method that is usually faster when the
+    /// number of variables is small. Requires the `Exact` solver
+    Bvls,
+}
+
+/// Options for `lsq_linear`
+#[derive(Debug, Clone, Copy)]
+pub struct LsqLinearOptions {
+    /// Algorithm to use
+    pub method: LsqLinearMethod,
+    /// Tolerance for termination by the first order optimality and the relative change of the
+    /// cost function
+    pub tol: f64,
+    /// Solver for the unconstrained least squares problem and the steps of `Trf`
+    pub lsq_solver: TrSolver,
+    /// Tolerance of the LSMR solver, defaults to `0.01 * tol`
+    pub lsmr_tol: Option<f64>,
+    /// Maximum iterations, defaults to `100` for `Trf` and the number of variables for `Bvls`
+    pub max_iter: Option<usize>,
+}
+
+impl Default for LsqLinearOptions {
+    fn default() -> Self {
+        Self {
+            method: LsqLinearMethod::default(),
+            tol: 1e-10,
+            lsq_solver: TrSolver::default(),
+            lsmr_tol: None,
+            max_iter: None,
+        }
+    }
+}
+
+/// Reason `lsq_linear` terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LsqLinearStatus {
+    /// The step of the last iteration did not decrease the cost function
+    NoProgress,
+    /// The maximum number of iterations was exceeded
+    MaxIterExceeded,
+    /// The first order optimality is below `tol`
+    GradientTolerance,
+    /// The relative change of the cost function is below `tol`
+    FunctionTolerance,
+    /// The unconstrained solution lies within the bounds and is optimal
+    UnconstrainedSolution,
+}
+
+/// Struct to represent the result of `lsq_linear`
+#[derive(Debug, Clone)]
+pub struct LsqLinearResult {
+    /// Value of the cost function `0.5 * ||A x - b||^2`
+    pub cost: f64,
+    /// Residuals `A x - b` at the solution
+    pub fun: Vec<f64>,
+    /// Residual norm `||A x - b||`
+    pub rnorm: f64,
+    /// First order optimality measure, the infinity norm of the gradient scaled to account for
+    /// the bounds
+    pub optimality: f64,
+    /// For each variable, `0` if it is not at a bound, `-1` if it is at its lower bound and `1`
+    /// if it is at its upper bound
+    pub active_mask: Vec<i8>,
+    /// Unconstrained least squares solution
+    pub unbounded_sol: Vec<f64>,
+    /// Number of iterations, zero if the unconstrained solution is optimal
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: LsqLinearStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Solution found by the bounded methods
+struct Solution {
+    x: Vec<f64>,
+    optimality: f64,
+    active_mask: Vec<i8>,
+    status: LsqLinearStatus,
+    nit: usize,
+}
+
+/// Residuals `A x - b`
+fn residual(a: &[Vec<f64>], b: &[f64], x: &[f64]) -> Vec<f64> {
+    mat_vec(a, x)
+        .iter()
+        .zip(b)
+        .map(|(ax, bi)| ax - bi)
+        .collect()
+}
+
+/// Linear least squares with bounds on the variables
+///
+/// Minimise `0.5 * ||A x - b||^2` subject to `lb <= x <= ub`.
+///
+/// # Parameters
+/// * `a` - matrix of the problem, one row per entry of `b`
+/// * `b` - right hand side
+/// * `bounds` - optional bounds on the variables, every lower bound must be below its upper
+///   bound
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `a` is empty, its rows have different lengths or
+/// do not match `b`, `a` or `b` are not finite, the bounds have the wrong length or a lower
+/// bound is not below its upper bound, `tol` or `lsmr_tol` is not positive or `Bvls` is used
+/// with the `Lsmr` solver
+#[allow(clippy::too_many_lines)]
+pub async fn lsq_linear(
+    a: &[Vec<f64>],
+    b: &[f64],
+    bounds: Option<&Bounds>,
+    options: LsqLinearOptions,
+) -> Result<LsqLinearResult, SwoopErrors> {
+    let n = check_linear_system(a, b)?;
+    if options.tol <= 0f64 || !options.tol.is_finite() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "tol must be positive and finite",
+        )));
+    }
+    if options
+        .lsmr_tol
+        .is_some_and(|tol| tol <= 0f64 || !tol.is_finite())
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "lsmr_tol must be positive and finite",
+        )));
+    }
+    if options.method == LsqLinearMethod::Bvls && options.lsq_solver == TrSolver::Lsmr {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method Bvls requires the Exact solver",
+        )));
+    }
+    let (lb, ub) = match bounds {
+        Some(bounds) => {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            (bounds.lb().to_vec(), bounds.ub().to_vec())
+        }
+        None => (vec![f64::NEG_INFINITY; n], vec![f64::INFINITY; n]),
+    };
+    if lb.iter().zip(&ub).any(|(l, u)| l >= u) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Each lower bound must be strictly less than its upper bound",
+        )));
+    }
+    let lsmr_tol = options.lsmr_tol.unwrap_or(1e-2 * options.tol);
+
+    let x_lsq = match options.lsq_solver {
+        TrSolver::Exact => lstsq(a, b, n),
+        TrSolver::Lsmr => lsmr(
+            |v| mat_vec(a, v),
+            |u| mat_t_vec(a, u, n),
+            b,
+            n,
+            LsmrOptions {
+                atol: lsmr_tol,
+                btol: lsmr_tol,
+                ..LsmrOptions::default()
+            },
+        ),
+    };
+
+    let solution = if in_bounds(&x_lsq, &lb, &ub) {
+        let r = residual(a, b, &x_lsq);
+        Solution {
+            x: x_lsq.clone(),
+            optimality: norm_inf(&mat_t_vec(a, &r, n)),
+            active_mask: vec![0; n],
+            status: LsqLinearStatus::UnconstrainedSolution,
+            nit: 0,
+        }
+    } else {
+        match options.method {
+            LsqLinearMethod::Trf => trf::trf_linear(
+                a,
+                b,
+                &x_lsq,
+                &lb,
+                &ub,
+                options.tol,
+                options.lsq_solver,
+                lsmr_tol,
+                options.max_iter.unwrap_or(100),
+            ),
+            LsqLinearMethod::Bvls => bvls::bvls(
+                a,
+                b,
+                &x_lsq,
+                &lb,
+                &ub,
+                options.tol,
+                options.max_iter.unwrap_or(n),
+            ),
+        }
+    };
+
+    let fun = residual(a, b, &solution.x);
+    Ok(LsqLinearResult {
+        cost: 0.5 * dot(&fun, &fun),
+        rnorm: norm(&fun),
+        fun,
+        optimality: solution.optimality,
+        active_mask: solution.active_mask,
+        unbounded_sol: x_lsq,
+        nit: solution.nit,
+        success: !matches!(
+            solution.status,
+            LsqLinearStatus::NoProgress | LsqLinearStatus::MaxIterExceeded
+        ),
+        status: solution.status,
+        x: solution.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::least_squares::{nnls, NnlsOptions};
+    use approx::relative_eq;
+
+    fn options(method: LsqLinearMethod, lsq_solver: TrSolver) -> LsqLinearOptions {
+        LsqLinearOptions {
+            method,
+            lsq_solver,
+            ..LsqLinearOptions::default()
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64], epsilon: f64) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                relative_eq!(a, e, epsilon = epsilon),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    /// Deterministic matrix with entries in `[-1, 1]`
+    fn matrix(m: usize, n: usize, seed: u32) -> Vec<Vec<f64>> {
+        (0..m)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let k = f64::from(seed) + f64::from(u32::try_from(i * n + j).unwrap());
+                        ((k * 12.9898f64).sin() * 43758.5453f64).fract()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    const METHODS: [(LsqLinearMethod, TrSolver); 3] = [
+        (LsqLinearMethod::Trf, TrSolver::Exact),
+        (LsqLinearMethod::Trf, TrSolver::Lsmr),
+        (LsqLinearMethod::Bvls, TrSolver::Exact),
+    ];
+
+    #[tokio::test]
+    async fn test_bounded() -> Result<(), SwoopErrors> {
+        let a = matrix(20, 5, 1);
+        let b: Vec<f64> = (0..20).map(|i| f64::from(i) / 4f64 - 2f64).collect();
+        let bounds = Bounds::new(vec![-0.2f64; 5], vec![0.3f64; 5])?;
+        let reference =
+            lsq_linear(&a, &b, Some(&bounds), options(METHODS[2].0, METHODS[2].1)).await?;
+        assert_eq!(reference.status, LsqLinearStatus::GradientTolerance);
+        assert!(reference.active_mask.iter().any(|m| *m != 0));
+        assert!(!in_bounds(
+            &reference.unbounded_sol,
+            bounds.lb(),
+            bounds.ub()
+        ));
+        for (method, lsq_solver) in METHODS {
+            let result = lsq_linear(&a, &b, Some(&bounds), options(method, lsq_solver)).await?;
+            assert!(result.success, "{method:?} {lsq_solver:?}");
+            assert!(in_bounds(&result.x, bounds.lb(), bounds.ub()));
+            assert_close(&result.x, &reference.x, 1e-6);
+            assert_eq!(result.active_mask, reference.active_mask);
+            assert!(relative_eq!(result.cost, reference.cost, epsilon = 1e-8));
+            assert!(relative_eq!(
+                result.rnorm,
+                (2f64 * result.cost).sqrt(),
+                epsilon = 1e-12
+            ));
+            assert!(result.optimality < 1e-4);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_matches_nnls() -> Result<(), SwoopErrors> {
+        let a = matrix(8, 4, 7);
+        let b: Vec<f64> = (0..8).map(|i| f64::from(i % 3) - 1f64).collect();
+        let expected = nnls(&a, &b, NnlsOptions::default()).await?;
+        let bounds = Bounds::new(vec![0f64; 4], vec![f64::INFINITY; 4])?;
+        for (method, lsq_solver) in METHODS {
+            let result = lsq_linear(&a, &b, Some(&bounds), options(method, lsq_solver)).await?;
+            assert!(result.success, "{method:?} {lsq_solver:?}");
+            assert_close(&result.x, &expected.x, 1e-6);
+            assert!(relative_eq!(result.rnorm, expected.rnorm, epsilon = 1e-8));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unconstrained_solution() -> Result<(), SwoopErrors> {
+        let a = vec![vec![1f64, 1f64], vec![1f64, 2f64], vec![1f64, 3f64]];
+        let b = [1f64, 2f64, 2f64];
+        let bounds = Bounds::new(vec![-10f64, -10f64], vec![10f64, 10f64])?;
+        for (method, lsq_solver) in METHODS {
+            let result = lsq_linear(&a, &b, Some(&bounds), options(method, lsq_solver)).await?;
+            assert_eq!(result.status, LsqLinearStatus::UnconstrainedSolution);
+            assert_eq!(result.nit, 0);
+            assert_close(&result.x, &[2f64 / 3f64, 0.5f64], 1e-8);
+            assert_close(&result.x, &result.unbounded_sol, 1e-14);
+        }
+
+        let result = lsq_linear(&a, &b, None, LsqLinearOptions::default()).await?;
+        assert_eq!(result.status, LsqLinearStatus::UnconstrainedSolution);
+        assert!(result.optimality < 1e-12);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let a = vec![vec![1f64, 0f64], vec![0f64, 1f64]];
+        let b = [1f64, 1f64];
+
+        let result = lsq_linear(&[], &[], None, LsqLinearOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = lsq_linear(&a, &[1f64], None, LsqLinearOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let bounds = Bounds::new(vec![0f64], vec![1f64]).unwrap();
+        let result = lsq_linear(&a, &b, Some(&bounds), LsqLinearOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let bounds = Bounds::new(vec![0f64, 1f64], vec![1f64, 1f64]).unwrap();
+        let result = lsq_linear(&a, &b, Some(&bounds), LsqLinearOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let zero_tol = LsqLinearOptions {
+            tol: 0f64,
+            ..LsqLinearOptions::default()
+        };
+        let result = lsq_linear(&a, &b, None, zero_tol).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = lsq_linear(&a, &b, None, options(LsqLinearMethod::Bvls, TrSolver::Lsmr)).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
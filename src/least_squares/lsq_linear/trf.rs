@@ -0,0 +1,279 @@
+//! Trust Region Reflective method for linear least squares
+//!
+//! The quadratic model of a linear problem is exact, so no trust region is needed: every
+//! iteration takes the Newton step of the Coleman and Li scaled problem and selects between it,
+//! its reflection off the bounds and the anti-gradient as in the nonlinear method.
+//!
+//! # References
+//! * Branch, Coleman and Li, "A subspace, interior, and conjugate gradient method for
+//!   large-scale bound-constrained minimization problems", SIAM Journal on Scientific
+//!   Computing 21.1 (1999)
+
+use super::{residual, LsqLinearStatus, Solution};
+use crate::least_squares::common::{
+    build_quadratic_1d, cl_scaling_vector, evaluate_quadratic, find_active_constraints, in_bounds,
+    make_strictly_feasible, minimize_quadratic_1d, reflective_transformation, step_size_to_bound,
+};
+use crate::least_squares::lsmr::{lsmr, LsmrOptions};
+use crate::least_squares::TrSolver;
+use crate::linalg::{dot, lstsq, mat_t_vec, mat_vec, norm_inf};
+
+/// Select the best of the Newton step, the step reflected off the bounds and the constrained
+/// anti-gradient step, all kept strictly interior
+#[allow(clippy::too_many_arguments)]
+fn select_step(
+    x: &[f64],
+    a_h: &[Vec<f64>],
+    g_h: &[f64],
+    diag_h: &[f64],
+    mut p: Vec<f64>,
+    mut p_h: Vec<f64>,
+    d: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+    theta: f64,
+) -> Vec<f64> {
+    let x_p: Vec<f64> = x.iter().zip(&p).map(|(xi, pi)| xi + pi).collect();
+    if in_bounds(&x_p, lb, ub) {
+        return p;
+    }
+
+    let (p_stride, hits) = step_size_to_bound(x, &p, lb, ub);
+
+    // reflected direction
+    let mut r_h: Vec<f64> = p_h
+        .iter()
+        .zip(&hits)
+        .map(|(pi, hit)| if *hit == 0 { *pi } else { -pi })
+        .collect();
+    let mut r: Vec<f64> = d.iter().zip(&r_h).map(|(di, ri)| di * ri).collect();
+
+    // restrict the step such that it hits the bound
+    for pi in &mut p {
+        *pi *= p_stride;
+    }
+    for pi in &mut p_h {
+        *pi *= p_stride;
+    }
+    let x_on_bound: Vec<f64> = x.iter().zip(&p).map(|(xi, pi)| xi + pi).collect();
+
+    let (to_bound, _) = step_size_to_bound(&x_on_bound, &r, lb, ub);
+    let r_value = if to_bound > 0f64 {
+        let (a, b, c) = build_quadratic_1d(a_h, g_h, &r_h, Some(diag_h), Some(&p_h));
+        let (r_stride, r_value) =
+            minimize_quadratic_1d(a, b, (1f64 - theta) * to_bound, theta * to_bound, c);
+        for (ri, pi) in r_h.iter_mut().zip(&p_h) {
+            *ri = *ri * r_stride + pi;
+        }
+        r = d.iter().zip(&r_h).map(|(di, ri)| di * ri).collect();
+        r_value
+    } else {
+        f64::INFINITY
+    };
+
+    // make the Newton step strictly interior
+    for pi in &mut p {
+        *pi *= theta;
+    }
+    for pi in &mut p_h {
+        *pi *= theta;
+    }
+    let p_value = evaluate_quadratic(a_h, g_h, &p_h, Some(diag_h));
+
+    // constrained anti-gradient step
+    let ag_h: Vec<f64> = g_h.iter().map(|gi| -gi).collect();
+    let mut ag: Vec<f64> = d.iter().zip(&ag_h).map(|(di, ai)| di * ai).collect();
+    let (to_bound, _) = step_size_to_bound(x, &ag, lb, ub);
+    let (a, b, _) = build_quadratic_1d(a_h, g_h, &ag_h, Some(diag_h), None);
+    let (ag_stride, ag_value) = minimize_quadratic_1d(a, b, 0f64, theta * to_bound, 0f64);
+    for ai in &mut ag {
+        *ai *= ag_stride;
+    }
+
+    if p_value < r_value && p_value < ag_value {
+        p
+    } else if r_value < p_value && r_value < ag_value {
+        r
+    } else {
+        ag
+    }
+}
+
+/// Backtracking line search along the reflected path `x + alpha p`, for the rare case where
+/// the selected step increases the cost. Returns the new point, the step and the decrease of
+/// the cost
+#[allow(clippy::too_many_arguments)]
+fn backtracking(
+    a: &[Vec<f64>],
+    g: &[f64],
+    x: &[f64],
+    p: &[f64],
+    theta: f64,
+    p_dot_g: f64,
+    lb: &[f64],
+    ub: &[f64],
+) -> (Vec<f64>, Vec<f64>, f64) {
+    let along = |alpha: f64| -> (Vec<f64>, Vec<f64>, f64) {
+        let y: Vec<f64> = x.iter().zip(p).map(|(xi, pi)| xi + alpha * pi).collect();
+        let x_new = make_strictly_feasible(&reflective_transformation(&y, lb, ub), lb, ub, 0f64);
+        let step: Vec<f64> = x_new.iter().zip(x).map(|(xn, xi)| xn - xi).collect();
+        let cost_change = -evaluate_quadratic(a, g, &step, None);
+        (x_new, step, cost_change)
+    };
+    let mut alpha = 1f64;
+    loop {
+        let (x_new, step, cost_change) = along(alpha);
+        if cost_change > -0.1 * alpha * p_dot_g {
+            let active = find_active_constraints(&x_new, lb, ub, 1e-10);
+            if active.iter().any(|a| *a != 0) {
+                return along(theta * alpha);
+            }
+            return (x_new, step, cost_change);
+        }
+        alpha *= 0.5;
+        if alpha < f64::EPSILON {
+            return (x.to_vec(), vec![0f64; x.len()], 0f64);
+        }
+    }
+}
+
+/// Solve a bounded linear least squares problem with the Trust Region Reflective method
+///
+/// # Parameters
+/// * `a` - matrix of the problem
+/// * `b` - right hand side
+/// * `x_lsq` - unconstrained least squares solution, reflected into the bounds as the initial
+///   point
+/// * `lb` - lower bounds
+/// * `ub` - upper bounds
+/// * `tol` - tolerance for the optimality and the relative change of the cost function
+/// * `lsq_solver` - solver for the Newton steps
+/// * `lsmr_tol` - tolerance of the LSMR solver
+/// * `max_iter` - maximum iterations
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+pub(super) fn trf_linear(
+    a: &[Vec<f64>],
+    b: &[f64],
+    x_lsq: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+    tol: f64,
+    lsq_solver: TrSolver,
+    lsmr_tol: f64,
+    max_iter: usize,
+) -> Solution {
+    let m = b.len();
+    let n = x_lsq.len();
+    let mut x = make_strictly_feasible(&reflective_transformation(x_lsq, lb, ub), lb, ub, 0.1);
+    let mut r = residual(a, b, &x);
+    let mut g = mat_t_vec(a, &r, n);
+    let mut cost = 0.5 * dot(&r, &r);
+    let mut status = None;
+    let mut nit = 0;
+
+    let scaled_gradient_norm = |x: &[f64], g: &[f64]| {
+        let (v, _) = cl_scaling_vector(x, g, lb, ub);
+        norm_inf(
+            &g.iter()
+                .zip(&v)
+                .map(|(gi, vi)| gi * vi)
+                .collect::<Vec<f64>>(),
+        )
+    };
+
+    while nit < max_iter {
+        let (v, dv) = cl_scaling_vector(&x, &g, lb, ub);
+        let g_norm = scaled_gradient_norm(&x, &g);
+        if g_norm < tol {
+            status = Some(LsqLinearStatus::GradientTolerance);
+        }
+        if status.is_some() {
+            break;
+        }
+
+        let diag_h: Vec<f64> = g.iter().zip(&dv).map(|(gi, dvi)| gi * dvi).collect();
+        let diag_root_h: Vec<f64> = diag_h.iter().map(|h| h.sqrt()).collect();
+        let d: Vec<f64> = v.iter().map(|vi| vi.sqrt()).collect();
+        let g_h: Vec<f64> = d.iter().zip(&g).map(|(di, gi)| di * gi).collect();
+        let a_h: Vec<Vec<f64>> = a
+            .iter()
+            .map(|row| row.iter().zip(&d).map(|(aij, dj)| aij * dj).collect())
+            .collect();
+
+        // Newton step of the scaled problem, the regularised least squares solution of
+        // `(A_h; diag_root_h) p_h = (-r; 0)`
+        let mut rhs: Vec<f64> = r.iter().map(|ri| -ri).collect();
+        rhs.resize(m + n, 0f64);
+        let p_h = match lsq_solver {
+            TrSolver::Exact => {
+                let mut augmented = a_h.clone();
+                for (j, h) in diag_root_h.iter().enumerate() {
+                    let mut row = vec![0f64; n];
+                    row[j] = *h;
+                    augmented.push(row);
+                }
+                lstsq(&augmented, &rhs, n)
+            }
+            TrSolver::Lsmr => lsmr(
+                |u| {
+                    let mut product = mat_vec(&a_h, u);
+                    product.extend(diag_root_h.iter().zip(u).map(|(h, ui)| h * ui));
+                    product
+                },
+                |w| {
+                    mat_t_vec(&a_h, &w[..m], n)
+                        .iter()
+                        .zip(diag_root_h.iter().zip(&w[m..]))
+                        .map(|(aw, (h, wi))| aw + h * wi)
+                        .collect()
+                },
+                &rhs,
+                n,
+                LsmrOptions {
+                    atol: lsmr_tol,
+                    btol: lsmr_tol,
+                    ..LsmrOptions::default()
+                },
+            ),
+        };
+        let p: Vec<f64> = d.iter().zip(&p_h).map(|(di, pi)| di * pi).collect();
+        let p_dot_g = dot(&p, &g);
+        if p_dot_g > 0f64 {
+            status = Some(LsqLinearStatus::NoProgress);
+        }
+
+        let theta = 1f64 - g_norm.min(0.005);
+        let step = select_step(&x, &a_h, &g_h, &diag_h, p.clone(), p_h, &d, lb, ub, theta);
+        let cost_change = -evaluate_quadratic(a, &g, &step, None);
+        let cost_change = if cost_change < 0f64 {
+            let (x_new, _, cost_change) = backtracking(a, &g, &x, &p, theta, p_dot_g, lb, ub);
+            x = x_new;
+            cost_change
+        } else {
+            let y: Vec<f64> = x.iter().zip(&step).map(|(xi, si)| xi + si).collect();
+            x = make_strictly_feasible(&y, lb, ub, 0f64);
+            cost_change
+        };
+
+        r = residual(a, b, &x);
+        g = mat_t_vec(a, &r, n);
+        if cost_change < tol * cost {
+            status = Some(LsqLinearStatus::FunctionTolerance);
+        }
+        cost = 0.5 * dot(&r, &r);
+        nit += 1;
+    }
+
+    let optimality = scaled_gradient_norm(&x, &g);
+    if status.is_none() && optimality < tol {
+        status = Some(LsqLinearStatus::GradientTolerance);
+    }
+    Solution {
+        active_mask: find_active_constraints(&x, lb, ub, tol),
+        x,
+        optimality,
+        status: status.unwrap_or(LsqLinearStatus::MaxIterExceeded),
+        nit,
+    }
+}
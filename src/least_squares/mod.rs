@@ -0,0 +1,803 @@
+//! Algorithms for least squares problems
+//!
+//! Minimise `0.5 * sum(f_i(x)^2)` for a vector of residuals `f(x)`, which is linear in `x` for
+//! `nnls` and `lsq_linear`.
+
+mod common;
+mod curve_fit;
+mod dogbox;
+mod lm;
+mod loss;
+pub(crate) mod lsmr;
+mod lsq_linear;
+mod nnls;
+mod trf;
+
+pub use curve_fit::{curve_fit, CurveFitOptions, CurveFitResult, ModelFunction, Sigma};
+pub use loss::{Loss, LossFunction};
+pub use lsq_linear::{
+    lsq_linear, LsqLinearMethod, LsqLinearOptions, LsqLinearResult, LsqLinearStatus,
+};
+pub use nnls::{nnls, NnlsOptions, NnlsResult, NnlsStatus};
+
+use crate::constraints::Bounds;
+use crate::differentiate::{approx_derivative_batch, DifferenceMethod, DifferentiateOptions};
+use crate::linalg::{mat_t_vec, norm_inf};
+use crate::SwoopErrors;
+
+/// Trait to implement for the vector of residuals of a least squares problem or of a system of
+/// equations
+pub trait ResidualFunction {
+    /// Method to implement the residuals, returning one value per residual
+    fn evaluate(&self, x: &[f64]) -> Vec<f64>;
+
+    /// Method to implement the Jacobian of the residuals, one row per residual. If `None` is
+    /// returned the Jacobian is approximated using finite differences
+    fn jacobian(&self, _x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        None
+    }
+
+    /// Method to evaluate the residuals at a batch of independent points, such as the steps of
+    /// a finite difference Jacobian. The points are evaluated one after another unless this is
+    /// implemented, with vectorised code or by wrapping the residual function in `Parallel`
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        points.iter().map(|x| self.evaluate(x)).collect()
+    }
+}
+
+impl<T: ResidualFunction + ?Sized> ResidualFunction for &T {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        (**self).evaluate(x)
+    }
+
+    fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        (**self).jacobian(x)
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        (**self).evaluate_batch(points)
+    }
+}
+
+/// Algorithm used by `least_squares`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LeastSquaresMethod {
+    /// Levenberg-Marquardt as implemented in MINPACK, requires at least as many residuals as
+    /// variables
+    #[default]
+    Lm,
+    /// Trust Region Reflective, supports bounds and any number of residuals
+    Trf,
+    /// Dogleg with rectangular trust regions, supports bounds and any number of residuals
+    Dogbox,
+}
+
+/// Solver for the trust region subproblems of the `Trf` and `Dogbox` methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrSolver {
+    /// Dense solution using a singular value decomposition or least squares solve
+    #[default]
+    Exact,
+    /// Approximate solution with the iterative LSMR solver, which only uses products with the
+    /// Jacobian
+    Lsmr,
+}
+
+/// Characteristic scale of each variable
+#[derive(Debug, Clone, PartialEq)]
+pub enum XScale {
+    /// The same scale for every variable
+    Scalar(f64),
+    /// A scale for each variable
+    Vector(Vec<f64>),
+    /// Scale iteratively using the inverse norms of the columns of the Jacobian
+    Jac,
+}
+
+impl Default for XScale {
+    fn default() -> Self {
+        Self::Scalar(1f64)
+    }
+}
+
+impl XScale {
+    /// Whether the scale is updated from the Jacobian during the iterations
+    fn is_jac(&self) -> bool {
+        matches!(self, Self::Jac)
+    }
+
+    /// Initial scale and inverse scale of each variable, `j` is the Jacobian at the initial
+    /// point
+    fn scale(&self, j: &[Vec<f64>], n: usize) -> (Vec<f64>, Vec<f64>) {
+        match self {
+            Self::Scalar(s) => (vec![*s; n], vec![1f64 / s; n]),
+            Self::Vector(v) => (v.clone(), v.iter().map(|s| 1f64 / s).collect()),
+            Self::Jac => common::compute_jac_scale(j, n, None),
+        }
+    }
+}
+
+/// Options for the `least_squares` solver
+#[derive(Debug, Clone)]
+pub struct LeastSquaresOptions {
+    /// Algorithm to use
+    pub method: LeastSquaresMethod,
+    /// Tolerance for termination by the change of the cost function
+    pub ftol: f64,
+    /// Tolerance for termination by the change of the variables
+    pub xtol: f64,
+    /// Tolerance for termination by the norm of the gradient
+    pub gtol: f64,
+    /// Characteristic scale of each variable
+    pub x_scale: XScale,
+    /// Relative step size for the finite difference approximation of the Jacobian, defaults to
+    /// the square root of machine epsilon
+    pub diff_step: Option<f64>,
+    /// Maximum evaluations of the residuals, including those used for finite differences.
+    /// Defaults to `100 * n` with an analytic Jacobian and `100 * n * (n + 1)` otherwise
+    pub max_nfev: Option<usize>,
+    /// Solver for the trust region subproblems, ignored by `Lm`
+    pub tr_solver: TrSolver,
+    /// Loss function of the residuals, only `Linear` is supported by `Lm`
+    pub loss: Loss,
+    /// Soft margin between inlier and outlier residuals of the robust loss functions
+    pub f_scale: f64,
+}
+
+impl Default for LeastSquaresOptions {
+    fn default() -> Self {
+        Self {
+            method: LeastSquaresMethod::default(),
+            ftol: 1e-8,
+            xtol: 1e-8,
+            gtol: 1e-8,
+            x_scale: XScale::default(),
+            diff_step: None,
+            max_nfev: None,
+            tr_solver: TrSolver::default(),
+            loss: Loss::default(),
+            f_scale: 1f64,
+        }
+    }
+}
+
+/// Reason the `least_squares` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeastSquaresStatus {
+    /// The maximum number of evaluations of the residuals was exceeded
+    MaxFevExceeded,
+    /// The `gtol` termination condition is satisfied
+    GradientTolerance,
+    /// The `ftol` termination condition is satisfied
+    FunctionTolerance,
+    /// The `xtol` termination condition is satisfied
+    StepTolerance,
+    /// Both the `ftol` and `xtol` termination conditions are satisfied
+    FunctionAndStepTolerance,
+}
+
+/// Struct to represent the result of a `least_squares` optimisation
+#[derive(Debug, Clone)]
+pub struct LeastSquaresResult {
+    /// Value of the cost function `0.5 * f_scale^2 * sum(rho((f_i(x) / f_scale)^2))`, which is
+    /// `0.5 * sum(f_i(x)^2)` for the linear loss
+    pub cost: f64,
+    /// Residuals at the solution
+    pub fun: Vec<f64>,
+    /// Jacobian of the residuals at the solution
+    pub jac: Vec<Vec<f64>>,
+    /// Gradient of the cost function at the solution
+    pub grad: Vec<f64>,
+    /// First order optimality measure, the infinity norm of the gradient
+    pub optimality: f64,
+    /// For each variable, `0` if it is not at a bound, `-1` if it is at its lower bound and `1`
+    /// if it is at its upper bound
+    pub active_mask: Vec<i8>,
+    /// Number of evaluations of the residuals, including those used for finite differences
+    pub nfev: usize,
+    /// Number of evaluations of the Jacobian
+    pub njev: usize,
+    /// Reason the solver terminated
+    pub status: LeastSquaresStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Solution found by the bounded methods
+struct Solution {
+    x: Vec<f64>,
+    fun: Vec<f64>,
+    jac: Vec<Vec<f64>>,
+    grad: Vec<f64>,
+    optimality: f64,
+    active_mask: Vec<i8>,
+    status: LeastSquaresStatus,
+}
+
+/// Residual function with counters of the evaluations
+struct Problem<'a, T: ResidualFunction> {
+    fun: &'a T,
+    /// Forward differences within the bounds for the Jacobian when it is not given
+    diff_options: DifferentiateOptions,
+    loss: &'a Loss,
+    f_scale: f64,
+    nfev: usize,
+    njev: usize,
+    /// Whether the last Jacobian was given by the residual function
+    analytic: bool,
+}
+
+impl<T: ResidualFunction> Problem<'_, T> {
+    /// Evaluate the residuals at `x`
+    fn evaluate(&mut self, x: &[f64]) -> Vec<f64> {
+        self.nfev += 1;
+        self.fun.evaluate(x)
+    }
+
+    /// Jacobian of the residuals at `x`, where the residuals are `f`
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if the number of residuals changes between the
+    /// steps of the finite differences
+    fn jacobian(&mut self, x: &[f64], f: &[f64]) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+        self.njev += 1;
+        let jacobian = self.fun.jacobian(x);
+        self.analytic = jacobian.is_some();
+        if let Some(jacobian) = jacobian {
+            Ok(jacobian)
+        } else {
+            self.nfev += x.len();
+            let fun = self.fun;
+            approx_derivative_batch(
+                |points| fun.evaluate_batch(points),
+                x,
+                Some(f),
+                &self.diff_options,
+            )
+        }
+    }
+
+    /// Cost function of the residuals `f`
+    fn cost(&self, f: &[f64]) -> f64 {
+        self.loss.cost(f, self.f_scale)
+    }
+
+    /// Scale the Jacobian `j` and residuals `f` for the robust loss function
+    fn scale_for_loss(&self, j: &mut [Vec<f64>], f: &mut [f64]) {
+        self.loss.scale(j, f, self.f_scale);
+    }
+}
+
+/// Nonlinear least squares optimisation
+///
+/// Minimise `0.5 * sum(f_i(x)^2)` where `f` is the vector of residuals, optionally subject to
+/// bounds on the variables.
+///
+/// # Parameters
+/// * `residual_function` - struct that implements the trait `ResidualFunction`
+/// * `x0` - initial guess, within the bounds
+/// * `bounds` - optional bounds on the variables, not supported by `Lm`
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the residuals are not finite at
+/// `x0`, there are fewer residuals than variables for `Lm`, a tolerance is negative or all
+/// tolerances are below machine epsilon, `x_scale` is not positive or has the wrong length,
+/// `diff_step` or `f_scale` is not positive, the bounds have the wrong length, `x0` is outside
+/// the bounds, `Lm` is used with finite bounds or a robust loss, or the number of residuals
+/// changes between the steps of the finite differences
+#[allow(clippy::too_many_lines)]
+pub async fn least_squares<T: ResidualFunction>(
+    residual_function: T,
+    x0: &[f64],
+    bounds: Option<&Bounds>,
+    mut options: LeastSquaresOptions,
+) -> Result<LeastSquaresResult, SwoopErrors> {
+    let n = x0.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    if options.ftol < 0f64 || options.xtol < 0f64 || options.gtol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    if options.ftol < f64::EPSILON && options.xtol < f64::EPSILON && options.gtol < f64::EPSILON {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "At least one of the tolerances must be higher than machine epsilon",
+        )));
+    }
+    options.ftol = options.ftol.max(f64::EPSILON);
+    options.xtol = options.xtol.max(f64::EPSILON);
+    options.gtol = options.gtol.max(f64::EPSILON);
+    let valid_x_scale = match &options.x_scale {
+        XScale::Scalar(s) => *s > 0f64 && s.is_finite(),
+        XScale::Vector(v) => v.len() == n && v.iter().all(|s| *s > 0f64 && s.is_finite()),
+        XScale::Jac => true,
+    };
+    if !valid_x_scale {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "x_scale must be positive and finite with one entry per variable",
+        )));
+    }
+    if options.diff_step.is_some_and(|h| h <= 0f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "diff_step must be positive",
+        )));
+    }
+    if options.f_scale <= 0f64 || !options.f_scale.is_finite() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "f_scale must be positive and finite",
+        )));
+    }
+    if options.method == LeastSquaresMethod::Lm && !options.loss.is_linear() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method Lm only supports the linear loss",
+        )));
+    }
+
+    let (lb, ub) = match bounds {
+        Some(bounds) => {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            (bounds.lb().to_vec(), bounds.ub().to_vec())
+        }
+        None => (vec![f64::NEG_INFINITY; n], vec![f64::INFINITY; n]),
+    };
+    if !common::in_bounds(x0, &lb, &ub) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess is outside the bounds",
+        )));
+    }
+    let bounded = lb.iter().chain(&ub).any(|b| b.is_finite());
+    if options.method == LeastSquaresMethod::Lm && bounded {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method Lm does not support bounds",
+        )));
+    }
+    // Trf needs an initial guess in the interior of the feasible region
+    let x0 = match options.method {
+        LeastSquaresMethod::Trf => common::make_strictly_feasible(x0, &lb, &ub, 1e-10),
+        LeastSquaresMethod::Lm | LeastSquaresMethod::Dogbox => x0.to_vec(),
+    };
+
+    let mut problem = Problem {
+        fun: &residual_function,
+        diff_options: DifferentiateOptions {
+            method: DifferenceMethod::Forward,
+            rel_step: options.diff_step,
+            bounds: bounds.filter(|_| bounded).cloned(),
+            ..DifferentiateOptions::default()
+        },
+        loss: &options.loss,
+        f_scale: options.f_scale,
+        nfev: 0,
+        njev: 0,
+        analytic: false,
+    };
+    let f0 = problem.evaluate(&x0);
+    if f0.iter().any(|fi| !fi.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Residuals are not finite in the initial point",
+        )));
+    }
+    if options.method == LeastSquaresMethod::Lm && f0.len() < n {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method Lm requires at least as many residuals as variables",
+        )));
+    }
+    let j0 = problem.jacobian(&x0, &f0)?;
+    let max_nfev = options.max_nfev.unwrap_or(if problem.analytic {
+        100 * n
+    } else {
+        100 * n * (n + 1)
+    });
+
+    let solution = match options.method {
+        LeastSquaresMethod::Lm => {
+            let (x, fun, jac, status) = lm::lm(&mut problem, &x0, f0, j0, &options, max_nfev)?;
+            let grad = mat_t_vec(&jac, &fun, n);
+            Solution {
+                optimality: norm_inf(&grad),
+                active_mask: vec![0; n],
+                status,
+                x,
+                fun,
+                jac,
+                grad,
+            }
+        }
+        LeastSquaresMethod::Trf => {
+            trf::trf(&mut problem, &x0, f0, j0, &lb, &ub, &options, max_nfev)?
+        }
+        LeastSquaresMethod::Dogbox => {
+            dogbox::dogbox(&mut problem, &x0, f0, j0, &lb, &ub, &options, max_nfev)?
+        }
+    };
+
+    Ok(LeastSquaresResult {
+        cost: problem.cost(&solution.fun),
+        optimality: solution.optimality,
+        active_mask: solution.active_mask,
+        nfev: problem.nfev,
+        njev: problem.njev,
+        status: solution.status,
+        success: solution.status != LeastSquaresStatus::MaxFevExceeded,
+        fun: solution.fun,
+        jac: solution.jac,
+        grad: solution.grad,
+        x: solution.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+    use std::cell::Cell;
+    use std::sync::Arc;
+
+    struct Rosenbrock {
+        analytic: bool,
+    }
+
+    impl ResidualFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![10f64 * (x[1] - x[0].powi(2)), 1f64 - x[0]]
+        }
+
+        fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+            self.analytic
+                .then(|| vec![vec![-20f64 * x[0], 10f64], vec![-1f64, 0f64]])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rosenbrock() -> Result<(), SwoopErrors> {
+        for analytic in [true, false] {
+            let result = least_squares(
+                Rosenbrock { analytic },
+                &[2f64, 2f64],
+                None,
+                LeastSquaresOptions::default(),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-8));
+            assert!(relative_eq!(result.x[1], 1f64, epsilon = 1e-8));
+            assert!(result.cost < 1e-20);
+            assert!(result.optimality < 1e-8);
+            assert_eq!(result.active_mask, vec![0, 0]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exponential_decay() -> Result<(), SwoopErrors> {
+        struct Decay {
+            t: Vec<f64>,
+            y: Vec<f64>,
+        }
+
+        impl ResidualFunction for Decay {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                self.t
+                    .iter()
+                    .zip(&self.y)
+                    .map(|(t, y)| x[0] * (-x[1] * t).exp() + x[2] - y)
+                    .collect()
+            }
+        }
+
+        let t: Vec<f64> = (0..20).map(|i| f64::from(i) * 0.25).collect();
+        let y = t.iter().map(|t| 2.5 * (-1.3 * t).exp() + 0.5).collect();
+        let result = least_squares(
+            Decay { t, y },
+            &[1f64, 1f64, 0f64],
+            None,
+            LeastSquaresOptions {
+                x_scale: XScale::Jac,
+                ..LeastSquaresOptions::default()
+            },
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 2.5, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 1.3, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[2], 0.5, epsilon = 1e-6));
+        assert_eq!(result.fun.len(), 20);
+        assert_eq!(result.jac.len(), 20);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_nfev() -> Result<(), SwoopErrors> {
+        /// Rosenbrock residuals counting their evaluations
+        struct Counted {
+            analytic: bool,
+            nfev: Cell<usize>,
+            njev: Cell<usize>,
+        }
+
+        impl ResidualFunction for Counted {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                self.nfev.set(self.nfev.get() + 1);
+                Rosenbrock { analytic: false }.evaluate(x)
+            }
+
+            fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+                self.njev.set(self.njev.get() + 1);
+                Rosenbrock {
+                    analytic: self.analytic,
+                }
+                .jacobian(x)
+            }
+        }
+
+        for analytic in [true, false] {
+            for max_nfev in [None, Some(8)] {
+                let residuals = Counted {
+                    analytic,
+                    nfev: Cell::new(0),
+                    njev: Cell::new(0),
+                };
+                let result = least_squares(
+                    &residuals,
+                    &[-1.2, 1f64],
+                    None,
+                    LeastSquaresOptions {
+                        method: LeastSquaresMethod::Lm,
+                        max_nfev,
+                        ..LeastSquaresOptions::default()
+                    },
+                )
+                .await?;
+                // every evaluation is counted, including the Jacobian at the solution
+                assert_eq!(result.nfev, residuals.nfev.get());
+                assert_eq!(result.njev, residuals.njev.get());
+                assert!(result.nfev <= max_nfev.unwrap_or(usize::MAX));
+                assert_eq!(result.success, max_nfev.is_none());
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bounds() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![f64::NEG_INFINITY, 1.5f64], vec![f64::INFINITY; 2])?;
+        for method in [LeastSquaresMethod::Trf, LeastSquaresMethod::Dogbox] {
+            for tr_solver in [TrSolver::Exact, TrSolver::Lsmr] {
+                let result = least_squares(
+                    Rosenbrock { analytic: true },
+                    &[2f64, 2f64],
+                    Some(&bounds),
+                    LeastSquaresOptions {
+                        method,
+                        tr_solver,
+                        ..LeastSquaresOptions::default()
+                    },
+                )
+                .await?;
+                assert!(result.success);
+                assert!(relative_eq!(result.x[0], 1.224_370_75, epsilon = 1e-6));
+                assert!(relative_eq!(result.x[1], 1.5, epsilon = 1e-8));
+                assert_eq!(result.active_mask, vec![0, -1]);
+                assert!(result.optimality < 1e-6);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_steps_within_bounds() -> Result<(), SwoopErrors> {
+        /// Residual that is only defined up to its upper bound
+        struct Bounded {}
+
+        impl ResidualFunction for Bounded {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                if x[0] > 1f64 {
+                    vec![f64::NAN]
+                } else {
+                    vec![x[0] - 3f64]
+                }
+            }
+        }
+
+        let bounds = Bounds::new(vec![0f64], vec![1f64])?;
+        for method in [LeastSquaresMethod::Trf, LeastSquaresMethod::Dogbox] {
+            let result = least_squares(
+                Bounded {},
+                &[1f64],
+                Some(&bounds),
+                LeastSquaresOptions {
+                    method,
+                    ..LeastSquaresOptions::default()
+                },
+            )
+            .await?;
+            // the finite differences step back from the upper bound
+            assert!(relative_eq!(result.jac[0][0], 1f64, epsilon = 1e-6));
+            assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-6));
+            assert_eq!(result.active_mask, vec![1]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unbounded_methods() -> Result<(), SwoopErrors> {
+        for method in [LeastSquaresMethod::Trf, LeastSquaresMethod::Dogbox] {
+            let result = least_squares(
+                Rosenbrock { analytic: false },
+                &[2f64, 2f64],
+                None,
+                LeastSquaresOptions {
+                    method,
+                    x_scale: XScale::Jac,
+                    ..LeastSquaresOptions::default()
+                },
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-6));
+            assert!(relative_eq!(result.x[1], 1f64, epsilon = 1e-6));
+            assert_eq!(result.active_mask, vec![0, 0]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_robust_loss() -> Result<(), SwoopErrors> {
+        struct Line {
+            t: Vec<f64>,
+            y: Vec<f64>,
+        }
+
+        impl ResidualFunction for Line {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                self.t
+                    .iter()
+                    .zip(&self.y)
+                    .map(|(t, y)| x[0] + x[1] * t - y)
+                    .collect()
+            }
+        }
+
+        struct CustomCauchy {}
+
+        impl LossFunction for CustomCauchy {
+            fn rho(&self, z: f64) -> (f64, f64, f64) {
+                (z.ln_1p(), 1f64 / (1f64 + z), -1f64 / (1f64 + z).powi(2))
+            }
+        }
+
+        let t: Vec<f64> = (0..30).map(f64::from).collect();
+        let mut y: Vec<f64> = t
+            .iter()
+            .enumerate()
+            .map(|(i, t)| 1f64 + 0.5 * t + if i % 2 == 0 { 0.01 } else { -0.01 })
+            .collect();
+        y[3] += 20f64;
+        y[17] -= 15f64;
+        y[25] += 30f64;
+
+        let linear = least_squares(
+            Line {
+                t: t.clone(),
+                y: y.clone(),
+            },
+            &[0f64, 0f64],
+            None,
+            LeastSquaresOptions {
+                method: LeastSquaresMethod::Trf,
+                ..LeastSquaresOptions::default()
+            },
+        )
+        .await?;
+        assert!((linear.x[0] - 1f64).abs() > 0.1);
+
+        let losses = [
+            Loss::SoftL1,
+            Loss::Huber,
+            Loss::Cauchy,
+            Loss::Arctan,
+            Loss::Custom(Arc::new(CustomCauchy {})),
+        ];
+        for method in [LeastSquaresMethod::Trf, LeastSquaresMethod::Dogbox] {
+            for loss in losses.clone() {
+                let result = least_squares(
+                    Line {
+                        t: t.clone(),
+                        y: y.clone(),
+                    },
+                    &[0f64, 0f64],
+                    None,
+                    LeastSquaresOptions {
+                        method,
+                        loss,
+                        f_scale: 0.1,
+                        ..LeastSquaresOptions::default()
+                    },
+                )
+                .await?;
+                assert!(result.success);
+                assert!(relative_eq!(result.x[0], 1f64, epsilon = 0.05));
+                assert!(relative_eq!(result.x[1], 0.5, epsilon = 5e-3));
+                assert!(result.cost < linear.cost);
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        struct Underdetermined {}
+
+        impl ResidualFunction for Underdetermined {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                vec![x[0] + x[1]]
+            }
+        }
+
+        let result = least_squares(
+            Underdetermined {},
+            &[1f64, 1f64],
+            None,
+            LeastSquaresOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = least_squares(
+            Rosenbrock { analytic: true },
+            &[1f64, 1f64],
+            None,
+            LeastSquaresOptions {
+                x_scale: XScale::Vector(vec![1f64]),
+                ..LeastSquaresOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = least_squares(
+            Rosenbrock { analytic: true },
+            &[1f64, 1f64],
+            None,
+            LeastSquaresOptions {
+                loss: Loss::Huber,
+                ..LeastSquaresOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let bounds = Bounds::new(vec![0f64, 0f64], vec![1f64, 1f64]).unwrap();
+        let result = least_squares(
+            Rosenbrock { analytic: true },
+            &[0.5f64, 0.5f64],
+            Some(&bounds),
+            LeastSquaresOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = least_squares(
+            Rosenbrock { analytic: true },
+            &[2f64, 0.5f64],
+            Some(&bounds),
+            LeastSquaresOptions {
+                method: LeastSquaresMethod::Trf,
+                ..LeastSquaresOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
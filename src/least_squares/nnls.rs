@@ -0,0 +1,277 @@
+//! Non-negative least squares
+//!
+//! Lawson and Hanson's active set method: variables are moved one at a time from the active
+//! set, where they are held at zero, to the passive set, where they solve the unconstrained
+//! least squares problem. Whenever the passive solution turns negative the step is shortened to
+//! keep every variable non-negative and the variables that reach zero return to the active set.
+//!
+//! # References
+//! * Lawson and Hanson, "Solving least squares problems", SIAM (1995), chapter 23
+
+use super::common::check_linear_system;
+use crate::linalg::{lstsq, mat_t_vec, mat_vec, norm};
+use crate::SwoopErrors;
+
+/// Options for `nnls`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NnlsOptions {
+    /// Maximum iterations, each solution of a least squares problem on the passive set counts
+    /// as one. Defaults to `3 * n`
+    pub maxiter: Option<usize>,
+    /// Tolerance for the gradient and for variables reaching zero. Defaults to
+    /// `10 * max(m, n) * eps * ||A||_1`
+    pub atol: Option<f64>,
+}
+
+/// Reason `nnls` terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NnlsStatus {
+    /// The Karush-Kuhn-Tucker conditions are satisfied
+    Optimal,
+    /// The maximum number of iterations was reached
+    IterationLimit,
+}
+
+/// Struct to represent the result of `nnls`
+#[derive(Debug, Clone)]
+pub struct NnlsResult {
+    /// The solution, every entry is non-negative
+    pub x: Vec<f64>,
+    /// Residual norm `||A x - b||`
+    pub rnorm: f64,
+    /// Reason the solver terminated
+    pub status: NnlsStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// Number of iterations
+    pub nit: usize,
+}
+
+/// Least squares solution using only the columns of `a` in `passive`, zero elsewhere
+fn passive_solution(a: &[Vec<f64>], b: &[f64], passive: &[usize], n: usize) -> Vec<f64> {
+    let a_passive: Vec<Vec<f64>> = a
+        .iter()
+        .map(|row| passive.iter().map(|&j| row[j]).collect())
+        .collect();
+    let z = lstsq(&a_passive, b, passive.len());
+    let mut s = vec![0f64; n];
+    for (&j, zj) in passive.iter().zip(z) {
+        s[j] = zj;
+    }
+    s
+}
+
+/// Non-negative least squares
+///
+/// Minimise `||A x - b||` subject to `x >= 0`.
+///
+/// # Parameters
+/// * `a` - matrix of the problem, one row per entry of `b`
+/// * `b` - right hand side
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `a` is empty, its rows have different lengths or
+/// do not match `b`, `a` or `b` are not finite or `atol` is negative
+#[allow(clippy::cast_precision_loss)]
+pub async fn nnls(
+    a: &[Vec<f64>],
+    b: &[f64],
+    options: NnlsOptions,
+) -> Result<NnlsResult, SwoopErrors> {
+    let n = check_linear_system(a, b)?;
+    let m = b.len();
+    if options
+        .atol
+        .is_some_and(|atol| atol < 0f64 || atol.is_nan())
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "atol cannot be negative",
+        )));
+    }
+    let maxiter = options.maxiter.unwrap_or(3 * n);
+    let atol = options.atol.unwrap_or_else(|| {
+        let a_norm = (0..n)
+            .map(|j| a.iter().map(|row| row[j].abs()).sum::<f64>())
+            .fold(0f64, f64::max);
+        10f64 * m.max(n) as f64 * f64::EPSILON * a_norm
+    });
+
+    let residual = |x: &[f64]| -> Vec<f64> {
+        mat_vec(a, x)
+            .iter()
+            .zip(b)
+            .map(|(ax, bi)| bi - ax)
+            .collect()
+    };
+    let mut x = vec![0f64; n];
+    let mut passive: Vec<usize> = Vec::new();
+    // variables whose passive solution was not positive when entering, excluded until `x`
+    // changes
+    let mut rejected: Vec<usize> = Vec::new();
+    let mut w = mat_t_vec(a, b, n);
+    let mut nit = 0;
+    let mut status = NnlsStatus::Optimal;
+
+    loop {
+        let entering = (0..n)
+            .filter(|j| !passive.contains(j) && !rejected.contains(j))
+            .max_by(|i, j| w[*i].total_cmp(&w[*j]))
+            .filter(|&j| w[j] > atol);
+        let Some(entering) = entering else {
+            break;
+        };
+        if nit >= maxiter {
+            status = NnlsStatus::IterationLimit;
+            break;
+        }
+        passive.push(entering);
+        let mut s = passive_solution(a, b, &passive, n);
+        nit += 1;
+        if s[entering] <= 0f64 {
+            passive.pop();
+            rejected.push(entering);
+            continue;
+        }
+
+        // shorten the step until the passive solution is positive
+        while passive.iter().any(|&j| s[j] <= 0f64) && nit < maxiter {
+            let alpha = passive
+                .iter()
+                .filter(|&&j| s[j] <= 0f64)
+                .map(|&j| x[j] / (x[j] - s[j]))
+                .fold(f64::INFINITY, f64::min);
+            for (xj, sj) in x.iter_mut().zip(&s) {
+                *xj += alpha * (sj - *xj);
+            }
+            passive.retain(|&j| x[j] > atol);
+            for (j, xj) in x.iter_mut().enumerate() {
+                if !passive.contains(&j) {
+                    *xj = 0f64;
+                }
+            }
+            s = passive_solution(a, b, &passive, n);
+            nit += 1;
+        }
+        if passive.iter().any(|&j| s[j] <= 0f64) {
+            status = NnlsStatus::IterationLimit;
+            break;
+        }
+        x = s;
+        rejected.clear();
+        w = mat_t_vec(a, &residual(&x), n);
+    }
+
+    Ok(NnlsResult {
+        rnorm: norm(&residual(&x)),
+        success: status == NnlsStatus::Optimal,
+        status,
+        nit,
+        x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    fn assert_close(actual: &[f64], expected: &[f64], epsilon: f64) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                relative_eq!(a, e, epsilon = epsilon),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_nnls() -> Result<(), SwoopErrors> {
+        // example of the scipy documentation
+        let a = vec![vec![1f64, 0f64], vec![1f64, 0f64], vec![0f64, 1f64]];
+        let result = nnls(&a, &[2f64, 1f64, 1f64], NnlsOptions::default()).await?;
+        assert!(result.success);
+        assert_close(&result.x, &[1.5f64, 1f64], 1e-12);
+        assert!(relative_eq!(result.rnorm, 0.5f64.sqrt(), epsilon = 1e-12));
+
+        let result = nnls(&a, &[-1f64, -1f64, -1f64], NnlsOptions::default()).await?;
+        assert!(result.success);
+        assert_close(&result.x, &[0f64, 0f64], 1e-12);
+        assert!(relative_eq!(result.rnorm, 3f64.sqrt(), epsilon = 1e-12));
+        assert_eq!(result.nit, 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unmixing() -> Result<(), SwoopErrors> {
+        // mixture of three spectra, the unconstrained solution has a negative abundance
+        let spectra = [
+            [1f64, 0.8f64, 0.3f64, 0.1f64, 0f64, 0f64],
+            [0f64, 0.2f64, 0.9f64, 1f64, 0.4f64, 0.1f64],
+            [0f64, 0f64, 0.1f64, 0.5f64, 1f64, 0.7f64],
+        ];
+        let a: Vec<Vec<f64>> = (0..6)
+            .map(|i| spectra.iter().map(|s| s[i]).collect())
+            .collect();
+        let b = [0.9f64, 0.5f64, -0.2f64, -0.3f64, 0.6f64, 0.5f64];
+        let result = nnls(&a, &b, NnlsOptions::default()).await?;
+        assert!(result.success);
+        assert!(result.x.iter().all(|xi| *xi >= 0f64));
+
+        // Karush-Kuhn-Tucker conditions
+        let r: Vec<f64> = mat_vec(&a, &result.x)
+            .iter()
+            .zip(&b)
+            .map(|(ax, bi)| ax - bi)
+            .collect();
+        let g = mat_t_vec(&a, &r, 3);
+        for (xi, gi) in result.x.iter().zip(&g) {
+            if *xi > 0f64 {
+                assert!(gi.abs() < 1e-12);
+            } else {
+                assert!(*gi >= -1e-12);
+            }
+        }
+        assert!(relative_eq!(result.rnorm, norm(&r), epsilon = 1e-14));
+
+        let limited = nnls(
+            &a,
+            &b,
+            NnlsOptions {
+                maxiter: Some(1),
+                ..NnlsOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(limited.status, NnlsStatus::IterationLimit);
+        assert!(!limited.success);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let result = nnls(&[], &[], NnlsOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = nnls(
+            &[vec![1f64, 2f64], vec![1f64]],
+            &[1f64, 1f64],
+            NnlsOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = nnls(&[vec![1f64]], &[1f64, 1f64], NnlsOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = nnls(&[vec![f64::NAN]], &[1f64], NnlsOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let options = NnlsOptions {
+            atol: Some(-1f64),
+            ..NnlsOptions::default()
+        };
+        let result = nnls(&[vec![1f64]], &[1f64], options).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,430 @@
+//! Trust Region Reflective algorithm
+//!
+//! The bounds are handled with the scaling of Coleman and Li, steps that leave the feasible
+//! region are reflected off the bounds.
+//!
+//! # References
+//! * Branch, Coleman and Li, "A subspace, interior, and conjugate gradient method for
+//!   large-scale bound-constrained minimization problems", SIAM Journal on Scientific
+//!   Computing 21.1 (1999)
+
+use super::common::{
+    build_quadratic_1d, check_termination, cl_scaling_vector, compute_jac_scale,
+    evaluate_quadratic, find_active_constraints, in_bounds, intersect_trust_region,
+    make_strictly_feasible, minimize_quadratic_1d, solve_lsq_trust_region, solve_trust_region_2d,
+    step_size_to_bound, update_tr_radius,
+};
+use super::lsmr::{lsmr, LsmrOptions};
+use super::{
+    LeastSquaresOptions, LeastSquaresStatus, Problem, ResidualFunction, Solution, TrSolver,
+};
+use crate::linalg::{dot, mat_t_vec, mat_vec, norm, norm_inf, svd};
+use crate::SwoopErrors;
+
+/// Trust region subproblem in the scaled variables
+enum Subproblem {
+    /// Singular value decomposition of the augmented Jacobian and the projected residuals
+    Exact {
+        uf: Vec<f64>,
+        s: Vec<f64>,
+        v: Vec<Vec<f64>>,
+    },
+    /// Orthonormal basis of the subspace spanned by the gradient and the Gauss-Newton step, and
+    /// the quadratic model restricted to it
+    Subspace {
+        basis: Vec<Vec<f64>>,
+        b: Vec<Vec<f64>>,
+        g: Vec<f64>,
+    },
+}
+
+/// Orthonormal basis of the span of `vectors` using Gram-Schmidt, dependent vectors are
+/// dropped
+fn orthonormal_basis(vectors: &[&[f64]]) -> Vec<Vec<f64>> {
+    let mut basis: Vec<Vec<f64>> = Vec::new();
+    for v in vectors {
+        let mut w = v.to_vec();
+        for b in &basis {
+            let projection = dot(&w, b);
+            for (wi, bi) in w.iter_mut().zip(b) {
+                *wi -= projection * bi;
+            }
+        }
+        let w_norm = norm(&w);
+        if w_norm > f64::EPSILON.sqrt() * norm(v) && w_norm > 0f64 {
+            basis.push(w.iter().map(|wi| wi / w_norm).collect());
+        }
+    }
+    basis
+}
+
+/// Select the best of the trust region step, the step reflected off the bounds and the
+/// constrained Cauchy step. Returns the step, the step in the scaled variables and the
+/// predicted reduction of the cost.
+#[allow(clippy::too_many_arguments)]
+fn select_step(
+    x: &[f64],
+    j_h: &[Vec<f64>],
+    diag_h: &[f64],
+    g_h: &[f64],
+    mut p: Vec<f64>,
+    mut p_h: Vec<f64>,
+    d: &[f64],
+    delta: f64,
+    lb: &[f64],
+    ub: &[f64],
+    theta: f64,
+) -> (Vec<f64>, Vec<f64>, f64) {
+    let x_p: Vec<f64> = x.iter().zip(&p).map(|(xi, pi)| xi + pi).collect();
+    if in_bounds(&x_p, lb, ub) {
+        let p_value = evaluate_quadratic(j_h, g_h, &p_h, Some(diag_h));
+        return (p, p_h, -p_value);
+    }
+
+    let (p_stride, hits) = step_size_to_bound(x, &p, lb, ub);
+
+    // reflected direction
+    let mut r_h: Vec<f64> = p_h
+        .iter()
+        .zip(&hits)
+        .map(|(pi, hit)| if *hit == 0 { *pi } else { -pi })
+        .collect();
+    let mut r: Vec<f64> = d.iter().zip(&r_h).map(|(di, ri)| di * ri).collect();
+
+    // restrict the trust region step such that it hits the bound
+    for pi in &mut p {
+        *pi *= p_stride;
+    }
+    for pi in &mut p_h {
+        *pi *= p_stride;
+    }
+    let x_on_bound: Vec<f64> = x.iter().zip(&p).map(|(xi, pi)| xi + pi).collect();
+
+    // the reflected direction crosses either the feasible region or the trust region boundary
+    let (_, to_tr) = intersect_trust_region(&p_h, &r_h, delta);
+    let (to_bound, _) = step_size_to_bound(&x_on_bound, &r, lb, ub);
+    let r_stride = to_bound.min(to_tr);
+    let (r_stride_l, r_stride_u) = if r_stride > 0f64 {
+        let upper = if to_bound <= to_tr {
+            theta * to_bound
+        } else {
+            to_tr
+        };
+        ((1f64 - theta) * p_stride / r_stride, upper)
+    } else {
+        (0f64, -1f64)
+    };
+
+    let r_value = if r_stride_l <= r_stride_u {
+        let (a, b, c) = build_quadratic_1d(j_h, g_h, &r_h, Some(diag_h), Some(&p_h));
+        let (r_stride, r_value) = minimize_quadratic_1d(a, b, r_stride_l, r_stride_u, c);
+        for (ri, pi) in r_h.iter_mut().zip(&p_h) {
+            *ri = *ri * r_stride + pi;
+        }
+        r = d.iter().zip(&r_h).map(|(di, ri)| di * ri).collect();
+        r_value
+    } else {
+        f64::INFINITY
+    };
+
+    // make the trust region step strictly interior
+    for pi in &mut p {
+        *pi *= theta;
+    }
+    for pi in &mut p_h {
+        *pi *= theta;
+    }
+    let p_value = evaluate_quadratic(j_h, g_h, &p_h, Some(diag_h));
+
+    // constrained Cauchy step
+    let mut ag_h: Vec<f64> = g_h.iter().map(|gi| -gi).collect();
+    let mut ag: Vec<f64> = d.iter().zip(&ag_h).map(|(di, ai)| di * ai).collect();
+    let to_tr = delta / norm(&ag_h);
+    let (to_bound, _) = step_size_to_bound(x, &ag, lb, ub);
+    let ag_stride = if to_bound < to_tr {
+        theta * to_bound
+    } else {
+        to_tr
+    };
+    let (a, b, _) = build_quadratic_1d(j_h, g_h, &ag_h, Some(diag_h), None);
+    let (ag_stride, ag_value) = minimize_quadratic_1d(a, b, 0f64, ag_stride, 0f64);
+    for ai in &mut ag_h {
+        *ai *= ag_stride;
+    }
+    for ai in &mut ag {
+        *ai *= ag_stride;
+    }
+
+    if p_value < r_value && p_value < ag_value {
+        (p, p_h, -p_value)
+    } else if r_value < p_value && r_value < ag_value {
+        (r, r_h, -r_value)
+    } else {
+        (ag, ag_h, -ag_value)
+    }
+}
+
+/// Build the trust region subproblem in the scaled variables
+fn subproblem(
+    j_h: &[Vec<f64>],
+    f: &[f64],
+    g_h: &[f64],
+    diag_h: &[f64],
+    delta: f64,
+    tr_solver: TrSolver,
+) -> Subproblem {
+    let n = g_h.len();
+    let m = f.len();
+    let mut f_augmented = f.to_vec();
+    f_augmented.resize(m + n, 0f64);
+    match tr_solver {
+        TrSolver::Exact => {
+            let mut j_augmented = j_h.to_vec();
+            j_augmented.extend(diag_h.iter().enumerate().map(|(i, di)| {
+                let mut row = vec![0f64; n];
+                row[i] = di.sqrt();
+                row
+            }));
+            let (u, s, v) = svd(&j_augmented, n);
+            let uf = mat_t_vec(&u, &f_augmented, s.len());
+            Subproblem::Exact { uf, s, v }
+        }
+        TrSolver::Lsmr => {
+            // regularise the least squares problem based on the Cauchy step
+            let minus_g_h: Vec<f64> = g_h.iter().map(|gi| -gi).collect();
+            let (a, b, _) = build_quadratic_1d(j_h, g_h, &minus_g_h, Some(diag_h), None);
+            let to_tr = delta / norm(g_h);
+            let (_, ag_value) = minimize_quadratic_1d(a, b, 0f64, to_tr, 0f64);
+            let reg_term = -ag_value / delta.powi(2);
+            let damp: Vec<f64> = diag_h.iter().map(|di| (di + reg_term).sqrt()).collect();
+            let gn_h = lsmr(
+                |p| {
+                    let mut out = mat_vec(j_h, p);
+                    out.extend(damp.iter().zip(p).map(|(di, pi)| di * pi));
+                    out
+                },
+                |y| {
+                    let mut out = mat_t_vec(j_h, &y[..m], n);
+                    for (o, (di, yi)) in out.iter_mut().zip(damp.iter().zip(&y[m..])) {
+                        *o += di * yi;
+                    }
+                    out
+                },
+                &f_augmented,
+                n,
+                LsmrOptions::default(),
+            );
+
+            let basis = orthonormal_basis(&[g_h, &gn_h]);
+            let js: Vec<Vec<f64>> = basis.iter().map(|b| mat_vec(j_h, b)).collect();
+            let b = basis
+                .iter()
+                .zip(&js)
+                .map(|(bk, jk)| {
+                    basis
+                        .iter()
+                        .zip(&js)
+                        .map(|(bl, jl)| {
+                            dot(jk, jl)
+                                + bk.iter()
+                                    .zip(bl)
+                                    .zip(diag_h)
+                                    .map(|((x, y), di)| x * di * y)
+                                    .sum::<f64>()
+                        })
+                        .collect()
+                })
+                .collect();
+            let g = basis.iter().map(|bk| dot(bk, g_h)).collect();
+            Subproblem::Subspace { basis, b, g }
+        }
+    }
+}
+
+/// Minimise the sum of squares of the residuals subject to bounds with the Trust Region
+/// Reflective algorithm
+///
+/// # Parameters
+/// * `problem` - residual function
+/// * `x0` - initial guess, strictly within the bounds
+/// * `f0` - residuals at `x0`
+/// * `j0` - Jacobian at `x0`
+/// * `lb` - lower bound for each variable
+/// * `ub` - upper bound for each variable
+/// * `options` - solver options
+/// * `max_nfev` - maximum evaluations of the residuals
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of residuals changes between the steps
+/// of the finite differences
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::too_many_arguments)]
+pub(super) fn trf<T: ResidualFunction>(
+    problem: &mut Problem<'_, T>,
+    x0: &[f64],
+    f0: Vec<f64>,
+    j0: Vec<Vec<f64>>,
+    lb: &[f64],
+    ub: &[f64],
+    options: &LeastSquaresOptions,
+    max_nfev: usize,
+) -> Result<Solution, SwoopErrors> {
+    let n = x0.len();
+    let m = f0.len();
+    let mut x = x0.to_vec();
+    let mut cost = problem.cost(&f0);
+    let mut f = f0.clone();
+    let mut f_true = f0;
+    let mut j = j0;
+    problem.scale_for_loss(&mut j, &mut f);
+    let mut g = mat_t_vec(&j, &f, n);
+
+    let (mut scale, mut scale_inv) = options.x_scale.scale(&j, n);
+    let (mut v, dv) = cl_scaling_vector(&x, &g, lb, ub);
+    for ((vi, dvi), si) in v.iter_mut().zip(&dv).zip(&scale_inv) {
+        if *dvi != 0f64 {
+            *vi *= si;
+        }
+    }
+    let mut delta = x0
+        .iter()
+        .zip(scale_inv.iter().zip(&v))
+        .map(|(xi, (si, vi))| (xi * si / vi.sqrt()).powi(2))
+        .sum::<f64>()
+        .sqrt();
+    if delta == 0f64 {
+        delta = 1f64;
+    }
+
+    // Levenberg-Marquardt parameter of the exact subproblem
+    let mut alpha = 0f64;
+    let mut status = None;
+    let mut g_norm;
+
+    loop {
+        let (mut v, dv) = cl_scaling_vector(&x, &g, lb, ub);
+        g_norm = norm_inf(
+            &g.iter()
+                .zip(&v)
+                .map(|(gi, vi)| gi * vi)
+                .collect::<Vec<f64>>(),
+        );
+        if g_norm < options.gtol {
+            status = Some(LeastSquaresStatus::GradientTolerance);
+        }
+        if status.is_some() || problem.nfev >= max_nfev {
+            break;
+        }
+
+        // variables in the scaled space, first x_scale is applied and then the Coleman-Li
+        // scaling in the new variables
+        for ((vi, dvi), si) in v.iter_mut().zip(&dv).zip(&scale_inv) {
+            if *dvi != 0f64 {
+                *vi *= si;
+            }
+        }
+        let d: Vec<f64> = v
+            .iter()
+            .zip(&scale)
+            .map(|(vi, si)| vi.sqrt() * si)
+            .collect();
+        let diag_h: Vec<f64> = g
+            .iter()
+            .zip(&dv)
+            .zip(&scale)
+            .map(|((gi, dvi), si)| gi * dvi * si)
+            .collect();
+        let g_h: Vec<f64> = d.iter().zip(&g).map(|(di, gi)| di * gi).collect();
+        let j_h: Vec<Vec<f64>> = j
+            .iter()
+            .map(|row| row.iter().zip(&d).map(|(jij, di)| jij * di).collect())
+            .collect();
+        let subproblem = subproblem(&j_h, &f, &g_h, &diag_h, delta, options.tr_solver);
+
+        // controls how far the steps stay from the bounds
+        let theta = f64::max(0.995, 1f64 - g_norm);
+        let mut actual_reduction = -1f64;
+        let mut trial = None;
+        while actual_reduction <= 0f64 && problem.nfev < max_nfev {
+            let p_h = match &subproblem {
+                Subproblem::Exact { uf, s, v } => {
+                    let (p_h, new_alpha) = solve_lsq_trust_region(n, m, uf, s, v, delta, alpha);
+                    alpha = new_alpha;
+                    p_h
+                }
+                Subproblem::Subspace { basis, b, g } => {
+                    let p_s = solve_trust_region_2d(b, g, delta);
+                    let mut p_h = vec![0f64; n];
+                    for (bk, pk) in basis.iter().zip(&p_s) {
+                        for (pi, bki) in p_h.iter_mut().zip(bk) {
+                            *pi += pk * bki;
+                        }
+                    }
+                    p_h
+                }
+            };
+            let p: Vec<f64> = d.iter().zip(&p_h).map(|(di, pi)| di * pi).collect();
+            let (step, step_h, predicted_reduction) =
+                select_step(&x, &j_h, &diag_h, &g_h, p, p_h, &d, delta, lb, ub, theta);
+
+            let x_step: Vec<f64> = x.iter().zip(&step).map(|(xi, si)| xi + si).collect();
+            let x_new = make_strictly_feasible(&x_step, lb, ub, 0f64);
+            let f_new = problem.evaluate(&x_new);
+            let step_h_norm = norm(&step_h);
+            if f_new.iter().any(|fi| !fi.is_finite()) {
+                delta = 0.25 * step_h_norm;
+                continue;
+            }
+
+            let cost_new = problem.cost(&f_new);
+            actual_reduction = cost - cost_new;
+            let (delta_new, ratio) = update_tr_radius(
+                delta,
+                actual_reduction,
+                predicted_reduction,
+                step_h_norm,
+                step_h_norm > 0.95 * delta,
+            );
+            trial = Some((x_new, f_new, cost_new));
+            status = check_termination(
+                actual_reduction,
+                cost,
+                norm(&step),
+                norm(&x),
+                ratio,
+                options.ftol,
+                options.xtol,
+            );
+            if status.is_some() {
+                break;
+            }
+            alpha *= delta / delta_new;
+            delta = delta_new;
+        }
+
+        if actual_reduction > 0f64 {
+            if let Some((x_new, f_new, cost_new)) = trial {
+                x = x_new;
+                f.clone_from(&f_new);
+                f_true = f_new;
+                cost = cost_new;
+                j = problem.jacobian(&x, &f_true)?;
+                problem.scale_for_loss(&mut j, &mut f);
+                g = mat_t_vec(&j, &f, n);
+                if options.x_scale.is_jac() {
+                    (scale, scale_inv) = compute_jac_scale(&j, n, Some(&scale_inv));
+                }
+            }
+        }
+    }
+
+    Ok(Solution {
+        active_mask: find_active_constraints(&x, lb, ub, options.xtol),
+        optimality: g_norm,
+        status: status.unwrap_or(LeastSquaresStatus::MaxFevExceeded),
+        grad: g,
+        jac: j,
+        fun: f_true,
+        x,
+    })
+}
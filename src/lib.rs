@@ -5,8 +5,20 @@
 #![allow(clippy::unused_async)]
 #![allow(clippy::many_single_char_names)]
 
+pub mod autodiff;
+pub mod constraints;
+pub mod differentiate;
+pub mod least_squares;
+pub(crate) mod linalg;
+pub mod line_search;
+pub mod linprog;
+pub mod minimise_global;
 pub mod minimise_multivariate;
 pub mod minimise_scalar;
+pub mod parallel;
+pub mod quadprog;
+pub(crate) mod random;
+pub mod root;
 
 use thiserror::Error;
 
@@ -0,0 +1,448 @@
+//! Dense linear algebra routines shared by the optimisation algorithms
+//!
+//! Matrices are stored row major as a `Vec` of rows.
+
+/// Dot product of two vectors
+pub(crate) fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+/// Euclidean norm of a vector
+pub(crate) fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Infinity norm of a vector, zero for an empty vector
+pub(crate) fn norm_inf(a: &[f64]) -> f64 {
+    a.iter().fold(0f64, |acc, x| acc.max(x.abs()))
+}
+
+/// Matrix vector product `A x`
+pub(crate) fn mat_vec(a: &[Vec<f64>], x: &[f64]) -> Vec<f64> {
+    a.iter().map(|row| dot(row, x)).collect()
+}
+
+/// Transposed matrix vector product `A^T y`
+///
+/// # Parameters
+/// * `a` - matrix with `y.len()` rows
+/// * `y` - vector
+/// * `n` - number of columns of `a`, required as `a` may have no rows
+pub(crate) fn mat_t_vec(a: &[Vec<f64>], y: &[f64], n: usize) -> Vec<f64> {
+    let mut out = vec![0f64; n];
+    for (row, yi) in a.iter().zip(y) {
+        for (o, aij) in out.iter_mut().zip(row) {
+            *o += aij * yi;
+        }
+    }
+    out
+}
+
+/// Transpose of a matrix
+///
+/// # Parameters
+/// * `a` - matrix to transpose
+/// * `n` - number of columns of `a`, required as `a` may have no rows
+pub(crate) fn transpose(a: &[Vec<f64>], n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|j| a.iter().map(|row| row[j]).collect())
+        .collect()
+}
+
+/// Thin QR factorisation of an `m x n` matrix with `m >= n` using Householder reflections
+///
+/// Returns `(Q, R)` where `Q` is `m x n` with orthonormal columns and `R` is `n x n` upper
+/// triangular.
+pub(crate) fn qr(a: &[Vec<f64>], n: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+    let (q, r, _) = householder_qr(a, n, false);
+    (q, r)
+}
+
+/// Thin QR factorisation with column pivoting `A P = Q R` of an `m x n` matrix with `m >= n`
+///
+/// Returns `(Q, R, perm)` where `perm[j]` is the column of `A` moved to column `j`, the
+/// diagonal of `R` is non-increasing in magnitude.
+pub(crate) fn qr_pivoted(a: &[Vec<f64>], n: usize) -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<usize>) {
+    householder_qr(a, n, true)
+}
+
+#[allow(clippy::needless_range_loop)]
+fn householder_qr(
+    a: &[Vec<f64>],
+    n: usize,
+    pivot: bool,
+) -> (Vec<Vec<f64>>, Vec<Vec<f64>>, Vec<usize>) {
+    let m = a.len();
+    let mut r = a.to_vec();
+    let mut perm: Vec<usize> = (0..n).collect();
+    let mut reflectors: Vec<Vec<f64>> = Vec::with_capacity(n);
+
+    for j in 0..n.min(m) {
+        if pivot {
+            let column_norm = |k: usize| (j..m).map(|i| r[i][k].powi(2)).sum::<f64>();
+            let kmax = (j..n).fold(j, |best, k| {
+                if column_norm(k) > column_norm(best) {
+                    k
+                } else {
+                    best
+                }
+            });
+            if kmax != j {
+                for row in &mut r {
+                    row.swap(j, kmax);
+                }
+                perm.swap(j, kmax);
+            }
+        }
+        let mut v: Vec<f64> = (j..m).map(|i| r[i][j]).collect();
+        let alpha = -v[0].signum() * norm(&v);
+        v[0] -= alpha;
+        let v_norm = norm(&v);
+        if v_norm > 0f64 {
+            for vi in &mut v {
+                *vi /= v_norm;
+            }
+            for k in j..n {
+                let proj: f64 = (j..m).map(|i| v[i - j] * r[i][k]).sum();
+                for i in j..m {
+                    r[i][k] -= 2f64 * v[i - j] * proj;
+                }
+            }
+        }
+        reflectors.push(v);
+    }
+
+    // accumulate the reflectors applied to the first n columns of the identity
+    let mut q = vec![vec![0f64; n]; m];
+    for (i, row) in q.iter_mut().enumerate().take(n) {
+        row[i] = 1f64;
+    }
+    for (j, v) in reflectors.iter().enumerate().rev() {
+        for k in 0..n {
+            let proj: f64 = (j..m).map(|i| v[i - j] * q[i][k]).sum();
+            for i in j..m {
+                q[i][k] -= 2f64 * v[i - j] * proj;
+            }
+        }
+    }
+
+    let r = r
+        .into_iter()
+        .take(n)
+        .enumerate()
+        .map(|(i, mut row)| {
+            row.iter_mut().take(i).for_each(|x| *x = 0f64);
+            row
+        })
+        .collect();
+    (q, r, perm)
+}
+
+/// Tolerance below which the diagonal of a triangular factor is considered to be zero
+#[allow(clippy::cast_precision_loss)]
+fn singular_threshold(r: &[Vec<f64>]) -> f64 {
+    let max_diag = (0..r.len()).fold(0f64, |acc, i| acc.max(r[i][i].abs()));
+    max_diag * f64::EPSILON * r.len() as f64
+}
+
+/// Solve `R x = b` for upper triangular `R`, components associated with a zero diagonal are set
+/// to zero
+pub(crate) fn solve_upper_triangular(r: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let n = b.len();
+    let threshold = singular_threshold(r);
+    let mut x = vec![0f64; n];
+    for i in (0..n).rev() {
+        if r[i][i].abs() <= threshold {
+            continue;
+        }
+        let s: f64 = (i + 1..n).map(|j| r[i][j] * x[j]).sum();
+        x[i] = (b[i] - s) / r[i][i];
+    }
+    x
+}
+
+/// Solve `R^T x = b` for upper triangular `R`, components associated with a zero diagonal are
+/// set to zero
+pub(crate) fn solve_upper_triangular_transpose(r: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    let n = b.len();
+    let threshold = singular_threshold(r);
+    let mut x = vec![0f64; n];
+    for i in 0..n {
+        if r[i][i].abs() <= threshold {
+            continue;
+        }
+        let s: f64 = (0..i).map(|j| r[j][i] * x[j]).sum();
+        x[i] = (b[i] - s) / r[i][i];
+    }
+    x
+}
+
+/// Thin singular value decomposition `A = U diag(s) V^T` of an `m x n` matrix using one-sided
+/// Jacobi rotations
+///
+/// Returns `(U, s, V)` where `U` is `m x k`, `V` is `n x k` and `k = min(m, n)`. The singular
+/// values are in decreasing order, columns of `U` associated with a zero singular value are zero.
+pub(crate) fn svd(a: &[Vec<f64>], n: usize) -> (Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+    let m = a.len();
+    if m < n {
+        let (u, s, v) = svd(&transpose(a, n), m);
+        return (v, s, u);
+    }
+
+    // rotate pairs of columns of A until they are orthogonal, accumulating the rotations in V
+    let mut u = a.to_vec();
+    let mut v: Vec<Vec<f64>> = (0..n)
+        .map(|i| {
+            let mut row = vec![0f64; n];
+            row[i] = 1f64;
+            row
+        })
+        .collect();
+    for _ in 0..100 {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let (alpha, beta, gamma) = u.iter().fold((0f64, 0f64, 0f64), |acc, row| {
+                    (
+                        acc.0 + row[p] * row[p],
+                        acc.1 + row[q] * row[q],
+                        acc.2 + row[p] * row[q],
+                    )
+                });
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == 0f64 {
+                    continue;
+                }
+                rotated = true;
+                let zeta = (beta - alpha) / (2f64 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1f64 + zeta.powi(2)).sqrt());
+                let c = 1f64 / (1f64 + t.powi(2)).sqrt();
+                let s = c * t;
+                for row in u.iter_mut().chain(v.iter_mut()) {
+                    let (up, uq) = (row[p], row[q]);
+                    row[p] = c * up - s * uq;
+                    row[q] = s * up + c * uq;
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let sigma: Vec<f64> = (0..n)
+        .map(|j| u.iter().map(|row| row[j].powi(2)).sum::<f64>().sqrt())
+        .collect();
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|i, j| sigma[*j].total_cmp(&sigma[*i]));
+    let u_sorted = u
+        .iter()
+        .map(|row| {
+            order
+                .iter()
+                .map(|j| {
+                    if sigma[*j] > 0f64 {
+                        row[*j] / sigma[*j]
+                    } else {
+                        0f64
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    let v_sorted = v
+        .iter()
+        .map(|row| order.iter().map(|j| row[*j]).collect())
+        .collect();
+    let s_sorted = order.iter().map(|j| sigma[*j]).collect();
+    (u_sorted, s_sorted, v_sorted)
+}
+
+/// Minimum norm least squares solution of `A x = b` for an `m x n` matrix, singular values
+/// below `max(m, n) * eps * s_max` are treated as zero
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn lstsq(a: &[Vec<f64>], b: &[f64], n: usize) -> Vec<f64> {
+    let (u, s, v) = svd(a, n);
+    let threshold = s.first().copied().unwrap_or(0f64) * f64::EPSILON * a.len().max(n) as f64;
+    let utb = mat_t_vec(&u, b, s.len());
+    let coefficients: Vec<f64> = utb
+        .iter()
+        .zip(&s)
+        .map(|(c, si)| if *si > threshold { c / si } else { 0f64 })
+        .collect();
+    mat_vec(&v, &coefficients)
+}
+
+/// Cholesky factorisation `A = R^T R` of a symmetric positive definite matrix
+///
+/// Returns the upper triangular `R`, or `None` if `A` is not positive definite.
+pub(crate) fn cholesky(a: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let mut r = vec![vec![0f64; n]; n];
+    for j in 0..n {
+        let diag = a[j][j] - (0..j).map(|k| r[k][j].powi(2)).sum::<f64>();
+        if diag <= 0f64 || !diag.is_finite() {
+            return None;
+        }
+        r[j][j] = diag.sqrt();
+        for i in j + 1..n {
+            let s: f64 = (0..j).map(|k| r[k][j] * r[k][i]).sum();
+            r[j][i] = (a[j][i] - s) / r[j][j];
+        }
+    }
+    Some(r)
+}
+
+/// Inverse of a square matrix using Gauss-Jordan elimination with partial pivoting, `None` if
+/// the matrix is singular
+pub(crate) fn inverse(a: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = a.len();
+    let mut lhs = a.to_vec();
+    let mut inv: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1f64 } else { 0f64 }).collect())
+        .collect();
+    let scale = a.iter().flatten().fold(0f64, |acc, v| acc.max(v.abs()));
+    for k in 0..n {
+        let pivot = (k..n).max_by(|i, j| lhs[*i][k].abs().total_cmp(&lhs[*j][k].abs()))?;
+        if lhs[pivot][k].abs() <= scale * f64::EPSILON {
+            return None;
+        }
+        lhs.swap(k, pivot);
+        inv.swap(k, pivot);
+        let p = lhs[k][k];
+        for j in 0..n {
+            lhs[k][j] /= p;
+            inv[k][j] /= p;
+        }
+        for i in 0..n {
+            let factor = lhs[i][k];
+            if i == k || factor == 0f64 {
+                continue;
+            }
+            for j in 0..n {
+                lhs[i][j] -= factor * lhs[k][j];
+                inv[i][j] -= factor * inv[k][j];
+            }
+        }
+    }
+    Some(inv)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_qr() {
+        let a = vec![
+            vec![12f64, -51f64, 4f64],
+            vec![6f64, 167f64, -68f64],
+            vec![-4f64, 24f64, -41f64],
+            vec![1f64, 2f64, 3f64],
+        ];
+        let (q, r) = qr(&a, 3);
+        for i in 0..4 {
+            for j in 0..3 {
+                let qr_ij: f64 = (0..3).map(|k| q[i][k] * r[k][j]).sum();
+                assert!(relative_eq!(qr_ij, a[i][j], epsilon = 1e-10));
+            }
+        }
+        for i in 0..3 {
+            for j in 0..3 {
+                let qtq_ij: f64 = (0..4).map(|k| q[k][i] * q[k][j]).sum();
+                let expected = if i == j { 1f64 } else { 0f64 };
+                assert!(relative_eq!(qtq_ij, expected, epsilon = 1e-10));
+            }
+        }
+    }
+
+    #[test]
+    fn test_qr_pivoted() {
+        let a = vec![
+            vec![1f64, 10f64, 0f64],
+            vec![0f64, 20f64, 1f64],
+            vec![1f64, 0f64, 2f64],
+            vec![0f64, 5f64, 0f64],
+        ];
+        let (q, r, perm) = qr_pivoted(&a, 3);
+        assert_eq!(perm[0], 1);
+        for i in 0..4 {
+            for j in 0..3 {
+                let qr_ij: f64 = (0..3).map(|k| q[i][k] * r[k][j]).sum();
+                assert!(relative_eq!(qr_ij, a[i][perm[j]], epsilon = 1e-10));
+            }
+        }
+        assert!(r[0][0].abs() >= r[1][1].abs() && r[1][1].abs() >= r[2][2].abs());
+    }
+
+    #[test]
+    fn test_svd() {
+        for a in [
+            vec![
+                vec![3f64, 2f64, 2f64],
+                vec![2f64, 3f64, -2f64],
+                vec![1f64, 0f64, 4f64],
+                vec![0f64, 1f64, 1f64],
+            ],
+            vec![vec![3f64, 2f64, 2f64, 0f64], vec![2f64, 3f64, -2f64, 1f64]],
+        ] {
+            let n = a[0].len();
+            let (u, s, v) = svd(&a, n);
+            assert!(s.windows(2).all(|w| w[0] >= w[1]));
+            for i in 0..a.len() {
+                for j in 0..n {
+                    let usv_ij: f64 = (0..s.len()).map(|k| u[i][k] * s[k] * v[j][k]).sum();
+                    assert!(relative_eq!(usv_ij, a[i][j], epsilon = 1e-10));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_cholesky() {
+        let a = vec![
+            vec![4f64, 12f64, -16f64],
+            vec![12f64, 37f64, -43f64],
+            vec![-16f64, -43f64, 98f64],
+        ];
+        let r = cholesky(&a).unwrap();
+        let expected = [[2f64, 6f64, -8f64], [0f64, 1f64, 5f64], [0f64, 0f64, 3f64]];
+        for i in 0..3 {
+            for j in 0..3 {
+                assert!(relative_eq!(r[i][j], expected[i][j], epsilon = 1e-12));
+            }
+        }
+        assert!(cholesky(&[vec![1f64, 2f64], vec![2f64, 1f64]]).is_none());
+    }
+
+    #[test]
+    fn test_inverse() {
+        let a = vec![
+            vec![0f64, 2f64, 1f64],
+            vec![1f64, 1f64, 0f64],
+            vec![3f64, 0f64, 4f64],
+        ];
+        let inv = inverse(&a).unwrap();
+        for (i, row) in a.iter().enumerate() {
+            for j in 0..3 {
+                let product: f64 = row
+                    .iter()
+                    .zip(&inv)
+                    .map(|(a_ik, inv_k)| a_ik * inv_k[j])
+                    .sum();
+                let expected = if i == j { 1f64 } else { 0f64 };
+                assert!(relative_eq!(product, expected, epsilon = 1e-12));
+            }
+        }
+        assert!(inverse(&[vec![1f64, 2f64], vec![2f64, 4f64]]).is_none());
+    }
+
+    #[test]
+    fn test_lstsq() {
+        // rank deficient, the minimum norm solution splits the weight between equal columns
+        let a = vec![vec![1f64, 1f64], vec![1f64, 1f64], vec![1f64, 1f64]];
+        let x = lstsq(&a, &[1f64, 2f64, 3f64], 2);
+        assert!(relative_eq!(x[0], 1f64, epsilon = 1e-12));
+        assert!(relative_eq!(x[1], 1f64, epsilon = 1e-12));
+    }
+}
@@ -0,0 +1,107 @@
+//! Armijo backtracking line search, a port of scipy `scalar_search_armijo`
+//!
+//! The first backtracking step minimises the quadratic interpolating `phi(0)`, `phi'(0)` and
+//! `phi(alpha0)`, and the later steps the cubic interpolating `phi(0)`, `phi'(0)` and the last
+//! two steps. A step is halved when the interpolation shortens it too much or too little.
+
+use super::{LineFunction, LineSearchOptions, LineSearchStatus, Outcome};
+
+/// Search for a step that satisfies the sufficient decrease condition
+pub(super) fn search<T: LineFunction>(
+    function: &T,
+    phi0: f64,
+    derphi0: f64,
+    options: &LineSearchOptions,
+    maxiter: usize,
+) -> Outcome {
+    let c1 = options.c1;
+    let sufficient = |alpha: f64, phi: f64| phi <= phi0 + c1 * alpha * derphi0;
+    let converged = |alpha: f64, phi: f64| Outcome {
+        alpha,
+        phi,
+        derphi: None,
+        status: LineSearchStatus::Converged,
+    };
+
+    let mut alpha0 = options.alpha0;
+    let mut phi_a0 = function.evaluate(alpha0);
+    if sufficient(alpha0, phi_a0) {
+        return converged(alpha0, phi_a0);
+    }
+    let mut alpha1 = -derphi0 * alpha0.powi(2) / 2f64 / (phi_a0 - phi0 - derphi0 * alpha0);
+    if !(alpha1.is_finite() && alpha1 > 0f64 && alpha1 < alpha0) {
+        alpha1 = alpha0 / 2f64;
+    }
+    let mut phi_a1 = function.evaluate(alpha1);
+
+    for _ in 0..maxiter {
+        if alpha1 < options.amin {
+            break;
+        }
+        if sufficient(alpha1, phi_a1) {
+            return converged(alpha1, phi_a1);
+        }
+        let factor = alpha0.powi(2) * alpha1.powi(2) * (alpha1 - alpha0);
+        let e1 = phi_a1 - phi0 - derphi0 * alpha1;
+        let e0 = phi_a0 - phi0 - derphi0 * alpha0;
+        let a = (alpha0.powi(2) * e1 - alpha1.powi(2) * e0) / factor;
+        let b = (-alpha0.powi(3) * e1 + alpha1.powi(3) * e0) / factor;
+        let mut alpha2 = (-b + (b.powi(2) - 3f64 * a * derphi0).abs().sqrt()) / (3f64 * a);
+        if !alpha2.is_finite() || alpha1 - alpha2 > alpha1 / 2f64 || 1f64 - alpha2 / alpha1 < 0.04 {
+            alpha2 = alpha1 / 2f64;
+        }
+        (alpha0, phi_a0) = (alpha1, phi_a1);
+        alpha1 = alpha2;
+        phi_a1 = function.evaluate(alpha1);
+    }
+    let status = if alpha1 < options.amin {
+        LineSearchStatus::StepAtMinimum
+    } else {
+        LineSearchStatus::MaxIterExceeded
+    };
+    Outcome {
+        alpha: alpha1,
+        phi: phi_a1,
+        derphi: None,
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Steep quadratic with its minimum at `0.01`, undefined beyond `0.5`
+    struct Steep {}
+
+    impl LineFunction for Steep {
+        fn evaluate(&self, alpha: f64) -> f64 {
+            if alpha > 0.5 {
+                f64::NAN
+            } else {
+                (alpha - 0.01).powi(2)
+            }
+        }
+
+        fn derivative(&self, alpha: f64) -> f64 {
+            2f64 * (alpha - 0.01)
+        }
+    }
+
+    #[test]
+    fn test_backtracking() {
+        let options = LineSearchOptions::default();
+        let outcome = search(&Steep {}, 1e-4, -0.02, &options, 100);
+        assert_eq!(outcome.status, LineSearchStatus::Converged);
+        assert!(outcome.alpha < 0.02);
+        assert!(relative_eq!(outcome.phi, Steep {}.evaluate(outcome.alpha)));
+
+        let options = LineSearchOptions {
+            amin: 0.1,
+            ..options
+        };
+        let outcome = search(&Steep {}, 1e-4, -0.02, &options, 100);
+        assert_eq!(outcome.status, LineSearchStatus::StepAtMinimum);
+    }
+}
@@ -0,0 +1,276 @@
+//! Hager-Zhang line search, the line search of `CG_DESCENT`
+//!
+//! The minimum is bracketed by expanding the initial step, then the bracketing interval
+//! `[a, b]`, where `phi(a) <= phi(0) + epsilon |phi(0)|` with `phi'(a) < 0` and `phi'(b) >= 0`, is
+//! shrunk by double secant steps and bisection. The search stops at the first step that
+//! satisfies the Wolfe conditions or the approximate Wolfe conditions
+//! `(2 c1 - 1) phi'(0) >= phi'(alpha) >= c2 phi'(0)` with
+//! `phi(alpha) <= phi(0) + epsilon |phi(0)|`.
+
+use super::{LineFunction, LineSearchOptions, LineSearchStatus, Outcome};
+
+/// Expansion factor of the initial step until the minimum is bracketed
+const RHO: f64 = 5f64;
+
+/// Parameters of the Hager-Zhang line search
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Parameters {
+    pub(super) epsilon: f64,
+    pub(super) theta: f64,
+    pub(super) gamma: f64,
+}
+
+/// Step with its function value and derivative
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    alpha: f64,
+    phi: f64,
+    derphi: f64,
+}
+
+/// State of the search, whose evaluations return the terminating step as an error to stop the
+/// search
+struct HagerZhang<'a, T> {
+    function: &'a T,
+    options: &'a LineSearchOptions,
+    parameters: Parameters,
+    zero: Point,
+    /// Upper bound of the function values of the left ends of the intervals
+    phi_limit: f64,
+    best: Option<Point>,
+    evaluations: usize,
+    maxiter: usize,
+}
+
+impl<T: LineFunction> HagerZhang<'_, T> {
+    /// Evaluate the function at a step, stopping the search if it satisfies the Wolfe or the
+    /// approximate Wolfe conditions or the evaluations are exhausted
+    fn evaluate(&mut self, alpha: f64) -> Result<Point, Outcome> {
+        if self.evaluations >= self.maxiter {
+            let best = self.best.unwrap_or(self.zero);
+            return Err(Outcome {
+                alpha: best.alpha,
+                phi: best.phi,
+                derphi: Some(best.derphi),
+                status: LineSearchStatus::MaxIterExceeded,
+            });
+        }
+        self.evaluations += 1;
+        let point = Point {
+            alpha,
+            phi: self.function.evaluate(alpha),
+            derphi: self.function.derivative(alpha),
+        };
+        if !point.phi.is_finite() || !point.derphi.is_finite() {
+            return Ok(point);
+        }
+        if self.best.map_or(true, |best| point.phi < best.phi) {
+            self.best = Some(point);
+        }
+
+        let (c1, c2) = (self.options.c1, self.options.c2);
+        let (phi0, derphi0) = (self.zero.phi, self.zero.derphi);
+        let curvature = point.derphi >= c2 * derphi0;
+        let wolfe = point.phi - phi0 <= c1 * alpha * derphi0 && curvature;
+        let approximate_wolfe = (2f64 * c1 - 1f64) * derphi0 >= point.derphi
+            && curvature
+            && point.phi <= self.phi_limit;
+        if wolfe || approximate_wolfe {
+            return Err(Outcome {
+                alpha,
+                phi: point.phi,
+                derphi: Some(point.derphi),
+                status: LineSearchStatus::Converged,
+            });
+        }
+        Ok(point)
+    }
+
+    /// Shrink `[a, b]` where `phi(b)` is too large by repeated division at `theta`, step U3
+    fn bisect(&mut self, mut a: Point, mut b: Point) -> Result<(Point, Point), Outcome> {
+        loop {
+            let theta = self.parameters.theta;
+            let d = self.evaluate((1f64 - theta) * a.alpha + theta * b.alpha)?;
+            if d.derphi >= 0f64 {
+                return Ok((a, d));
+            }
+            if d.phi <= self.phi_limit {
+                a = d;
+            } else {
+                b = d;
+            }
+        }
+    }
+
+    /// Update the interval `[a, b]` with the step `c`, procedure `update`
+    fn update(&mut self, a: Point, b: Point, c: Point) -> Result<(Point, Point), Outcome> {
+        if !(c.alpha > a.alpha && c.alpha < b.alpha) {
+            Ok((a, b))
+        } else if c.derphi >= 0f64 {
+            Ok((a, c))
+        } else if c.phi <= self.phi_limit {
+            Ok((c, b))
+        } else {
+            self.bisect(a, c)
+        }
+    }
+
+    /// Evaluate a step and update the interval with it if it lies within
+    fn update_at(&mut self, a: Point, b: Point, alpha: f64) -> Result<(Point, Point), Outcome> {
+        if alpha > a.alpha && alpha < b.alpha {
+            let c = self.evaluate(alpha)?;
+            self.update(a, b, c)
+        } else {
+            Ok((a, b))
+        }
+    }
+
+    /// Double secant step, procedure `secant2`
+    #[allow(clippy::float_cmp)]
+    fn secant2(&mut self, a: Point, b: Point) -> Result<(Point, Point), Outcome> {
+        let c = secant(a, b);
+        let (new_a, new_b) = self.update_at(a, b, c)?;
+        if c == new_b.alpha {
+            self.update_at(new_a, new_b, secant(b, new_b))
+        } else if c == new_a.alpha {
+            self.update_at(new_a, new_b, secant(a, new_a))
+        } else {
+            Ok((new_a, new_b))
+        }
+    }
+
+    /// Expand the initial step until the minimum is bracketed, procedure `bracket`
+    fn bracket(&mut self) -> Result<(Point, Point), Outcome> {
+        let mut last = self.zero;
+        let mut alpha = self.options.alpha0;
+        loop {
+            let c = self.evaluate(alpha)?;
+            if !c.phi.is_finite() || !c.derphi.is_finite() {
+                // step back towards the last finite step
+                alpha = 0.5 * (last.alpha + alpha);
+                continue;
+            }
+            if c.derphi >= 0f64 {
+                return Ok((last, c));
+            }
+            if c.phi > self.phi_limit {
+                return self.bisect(last, c);
+            }
+            if alpha >= self.options.amax {
+                return Err(Outcome {
+                    alpha,
+                    phi: c.phi,
+                    derphi: Some(c.derphi),
+                    status: LineSearchStatus::StepAtMaximum,
+                });
+            }
+            last = c;
+            alpha = (RHO * alpha).min(self.options.amax);
+        }
+    }
+
+    /// Run the search until a step is found, returning the last interval if it becomes too
+    /// small
+    fn run(&mut self) -> Result<Outcome, Outcome> {
+        let (mut a, mut b) = self.bracket()?;
+        loop {
+            let (mut new_a, mut new_b) = self.secant2(a, b)?;
+            if new_b.alpha - new_a.alpha > self.parameters.gamma * (b.alpha - a.alpha) {
+                let middle = 0.5 * (new_a.alpha + new_b.alpha);
+                (new_a, new_b) = self.update_at(new_a, new_b, middle)?;
+            }
+            (a, b) = (new_a, new_b);
+            if b.alpha - a.alpha <= f64::EPSILON * b.alpha {
+                return Ok(Outcome {
+                    alpha: a.alpha,
+                    phi: a.phi,
+                    derphi: Some(a.derphi),
+                    status: LineSearchStatus::IntervalTooSmall,
+                });
+            }
+        }
+    }
+}
+
+/// Step of the secant of the derivative through `a` and `b`
+fn secant(a: Point, b: Point) -> f64 {
+    (a.alpha * b.derphi - b.alpha * a.derphi) / (b.derphi - a.derphi)
+}
+
+/// Search for a step that satisfies the Wolfe or approximate Wolfe conditions
+pub(super) fn search<T: LineFunction>(
+    function: &T,
+    phi0: f64,
+    derphi0: f64,
+    options: &LineSearchOptions,
+    parameters: Parameters,
+    maxiter: usize,
+) -> Outcome {
+    let mut search = HagerZhang {
+        function,
+        options,
+        parameters,
+        zero: Point {
+            alpha: 0f64,
+            phi: phi0,
+            derphi: derphi0,
+        },
+        phi_limit: phi0 + parameters.epsilon * phi0.abs(),
+        best: None,
+        evaluations: 0,
+        maxiter,
+    };
+    match search.run() {
+        Ok(outcome) | Err(outcome) => outcome,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_search::LineSearchMethod;
+    use approx::relative_eq;
+
+    /// `phi(alpha) = (alpha - 1)^4 - 1`, which is flat near its minimum
+    struct Quartic {}
+
+    impl LineFunction for Quartic {
+        fn evaluate(&self, alpha: f64) -> f64 {
+            (alpha - 1f64).powi(4) - 1f64
+        }
+
+        fn derivative(&self, alpha: f64) -> f64 {
+            4f64 * (alpha - 1f64).powi(3)
+        }
+    }
+
+    #[test]
+    fn test_secant() {
+        let parameters = Parameters {
+            epsilon: 1e-6,
+            theta: 0.5,
+            gamma: 0.66,
+        };
+        let options = LineSearchOptions {
+            method: LineSearchMethod::HagerZhang {
+                epsilon: parameters.epsilon,
+                theta: parameters.theta,
+                gamma: parameters.gamma,
+            },
+            c1: 0.1,
+            c2: 0.1,
+            alpha0: 0.01,
+            ..LineSearchOptions::default()
+        };
+        // expansion brackets the minimum and the secant steps converge towards it
+        let outcome = search(&Quartic {}, 0f64, -4f64, &options, parameters, 100);
+        assert_eq!(outcome.status, LineSearchStatus::Converged);
+        assert!(outcome.alpha > 0.5 && outcome.alpha < 2f64);
+        assert!(outcome.derphi.is_some_and(|d| d >= -0.4));
+
+        // the evaluations run out before a step is found
+        let outcome = search(&Quartic {}, 0f64, -4f64, &options, parameters, 2);
+        assert_eq!(outcome.status, LineSearchStatus::MaxIterExceeded);
+        assert!(relative_eq!(outcome.alpha, 0.05));
+    }
+}
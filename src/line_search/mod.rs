@@ -0,0 +1,583 @@
+//! Line searches for the step length along a search direction
+//!
+//! A line search looks for a step `alpha > 0` that sufficiently decreases
+//! `phi(alpha) = f(x + alpha p)` along a descent direction `p`, where `phi'(0) < 0`. The
+//! sufficient decrease (Armijo) condition is `phi(alpha) <= phi(0) + c1 alpha phi'(0)`, and the
+//! strong Wolfe conditions add the curvature condition `|phi'(alpha)| <= c2 |phi'(0)|`, which
+//! keeps the steps from being too short and the updates of quasi-Newton methods positive
+//! definite.
+//!
+//! `line_search` searches along a function of the step that implements `LineFunction`, and
+//! `line_search_along` along a direction of a `MultivariateObjectiveFunction`.
+//!
+//! # References
+//! * Nocedal and Wright, "Numerical Optimization", 2nd edition, chapter 3 (2006)
+//! * Moré and Thuente, "Line search algorithms with guaranteed sufficient decrease", ACM
+//!   Transactions on Mathematical Software 20.3 (1994)
+//! * Hager and Zhang, "A new conjugate gradient method with guaranteed descent and an efficient
+//!   line search", SIAM Journal on Optimization 16.1 (2005)
+
+mod armijo;
+mod hager_zhang;
+mod more_thuente;
+mod strong_wolfe;
+
+use std::cell::{Cell, RefCell};
+
+use crate::differentiate::{approx_fprime, DifferenceMethod, DifferentiateOptions};
+use crate::linalg::dot;
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use crate::SwoopErrors;
+
+/// Trait to implement for the function of the step length `phi(alpha) = f(x + alpha p)`
+pub trait LineFunction {
+    /// Method to implement the function of the step length
+    fn evaluate(&self, alpha: f64) -> f64;
+
+    /// Method to implement the derivative of the function with respect to the step length
+    fn derivative(&self, alpha: f64) -> f64;
+}
+
+impl<T: LineFunction + ?Sized> LineFunction for &T {
+    fn evaluate(&self, alpha: f64) -> f64 {
+        (**self).evaluate(alpha)
+    }
+
+    fn derivative(&self, alpha: f64) -> f64 {
+        (**self).derivative(alpha)
+    }
+}
+
+/// Algorithm used by the line searches
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LineSearchMethod {
+    /// Strong Wolfe conditions by expanding the step until the minimum is bracketed and zooming
+    /// in with cubic and quadratic interpolation, as in scipy `line_search`
+    #[default]
+    StrongWolfe,
+    /// Strong Wolfe conditions by the safeguarded interval updates of Moré and Thuente, a port of
+    /// MINPACK-2 `dcsrch`, usually with `1e-14` for `xtol`
+    MoreThuente {
+        /// Relative width of the bracketing interval below which the search stops
+        xtol: f64,
+    },
+    /// Wolfe or approximate Wolfe conditions by the secant steps of Hager and Zhang, usually with
+    /// `1e-6` for `epsilon`, `0.5` for `theta` and `0.66` for `gamma`. The approximate Wolfe
+    /// conditions are accurate near the minimum, where rounding errors make the sufficient
+    /// decrease condition unreliable
+    HagerZhang {
+        /// Relative error of `phi(0)` allowed by the approximate Wolfe conditions
+        epsilon: f64,
+        /// Position of the bisection of the interval when the secant steps fail
+        theta: f64,
+        /// Factor by which the secant steps must shrink the interval to avoid a bisection
+        gamma: f64,
+    },
+    /// Backtracking from the initial step with quadratic and then cubic interpolation until the
+    /// sufficient decrease condition holds, which only evaluates the derivative at zero
+    Armijo,
+}
+
+/// Options for the line searches
+#[derive(Debug, Clone, Copy)]
+pub struct LineSearchOptions {
+    /// Algorithm to use
+    pub method: LineSearchMethod,
+    /// Parameter of the sufficient decrease condition
+    pub c1: f64,
+    /// Parameter of the curvature condition, not used by `Armijo`
+    pub c2: f64,
+    /// Initial step
+    pub alpha0: f64,
+    /// Smallest step of `MoreThuente` and `Armijo`, the search fails below it
+    pub amin: f64,
+    /// Largest step
+    pub amax: f64,
+    /// Maximum iterations, or evaluations for `HagerZhang`. Defaults to `10` for `StrongWolfe`,
+    /// which also limits the iterations of the zoom, and to `100` otherwise
+    pub maxiter: Option<usize>,
+}
+
+impl Default for LineSearchOptions {
+    fn default() -> Self {
+        Self {
+            method: LineSearchMethod::default(),
+            c1: 1e-4,
+            c2: 0.9,
+            alpha0: 1f64,
+            amin: 0f64,
+            amax: f64::INFINITY,
+            maxiter: None,
+        }
+    }
+}
+
+/// Reason the line search terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineSearchStatus {
+    /// The conditions of the method are satisfied
+    Converged,
+    /// The maximum number of iterations was reached
+    MaxIterExceeded,
+    /// The interval of uncertainty is too small for further progress, due to rounding errors or
+    /// `xtol`
+    IntervalTooSmall,
+    /// The step is `amax` and the conditions are not satisfied
+    StepAtMaximum,
+    /// The step is at or below `amin` and the conditions are not satisfied
+    StepAtMinimum,
+}
+
+/// Struct to represent the result of a `line_search`
+#[derive(Debug, Clone)]
+pub struct LineSearchResult {
+    /// The step, the last step tried if the search failed
+    pub alpha: f64,
+    /// Value of the function at the step
+    pub fun: f64,
+    /// Derivative of the function at the step, if it was evaluated
+    pub derphi: Option<f64>,
+    /// Number of evaluations of the function, including `phi(0)` if not given
+    pub nfev: usize,
+    /// Number of evaluations of the derivative, including `phi'(0)` if not given
+    pub ngev: usize,
+    /// Reason the search terminated
+    pub status: LineSearchStatus,
+    /// Whether the conditions of the method are satisfied
+    pub success: bool,
+}
+
+/// Struct to represent the result of a `line_search_along`
+#[derive(Debug, Clone)]
+pub struct MultivariateLineSearchResult {
+    /// The step, the last step tried if the search failed
+    pub alpha: f64,
+    /// Value of the objective function at the new point
+    pub fun: f64,
+    /// Gradient of the objective function at the new point
+    pub grad: Vec<f64>,
+    /// Number of evaluations of the objective function, including those used for finite
+    /// differences
+    pub nfev: usize,
+    /// Number of evaluations of the gradient
+    pub ngev: usize,
+    /// Reason the search terminated
+    pub status: LineSearchStatus,
+    /// Whether the conditions of the method are satisfied
+    pub success: bool,
+    /// The new point `x + alpha p`
+    pub x: Vec<f64>,
+}
+
+/// Step found by one of the methods
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Outcome {
+    pub(super) alpha: f64,
+    pub(super) phi: f64,
+    pub(super) derphi: Option<f64>,
+    pub(super) status: LineSearchStatus,
+}
+
+/// Line function that counts its evaluations
+struct Counted<T> {
+    function: T,
+    nfev: Cell<usize>,
+    ngev: Cell<usize>,
+}
+
+impl<T: LineFunction> LineFunction for Counted<T> {
+    fn evaluate(&self, alpha: f64) -> f64 {
+        self.nfev.set(self.nfev.get() + 1);
+        self.function.evaluate(alpha)
+    }
+
+    fn derivative(&self, alpha: f64) -> f64 {
+        self.ngev.set(self.ngev.get() + 1);
+        self.function.derivative(alpha)
+    }
+}
+
+/// Search for a step along a function of the step length
+///
+/// # Parameters
+/// * `function` - struct that implements the trait `LineFunction` for `phi`
+/// * `phi0` - value `phi(0)`, evaluated if not given
+/// * `derphi0` - derivative `phi'(0)`, evaluated if not given
+/// * `options` - line search options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `phi'(0)` is not negative, the steps are not
+/// positive with `amin <= alpha0 <= amax`, or `0 < c1 < c2 < 1` does not hold
+pub async fn line_search<T: LineFunction>(
+    function: T,
+    phi0: Option<f64>,
+    derphi0: Option<f64>,
+    options: &LineSearchOptions,
+) -> Result<LineSearchResult, SwoopErrors> {
+    if !(options.c1 > 0f64 && options.c1 < options.c2 && options.c2 < 1f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The parameters must satisfy 0 < c1 < c2 < 1",
+        )));
+    }
+    if !(options.alpha0 > 0f64 && options.amin <= options.alpha0 && options.alpha0 <= options.amax)
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The initial step must be positive and within amin and amax",
+        )));
+    }
+    let function = Counted {
+        function,
+        nfev: Cell::new(0),
+        ngev: Cell::new(0),
+    };
+    let phi0 = phi0.unwrap_or_else(|| function.evaluate(0f64));
+    let derphi0 = derphi0.unwrap_or_else(|| function.derivative(0f64));
+    if derphi0.is_nan() || derphi0 >= 0f64 || !phi0.is_finite() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The search direction must be a descent direction from a finite value",
+        )));
+    }
+
+    let outcome = match options.method {
+        LineSearchMethod::StrongWolfe => strong_wolfe::search(
+            &function,
+            phi0,
+            derphi0,
+            options,
+            options.maxiter.unwrap_or(10),
+        ),
+        LineSearchMethod::MoreThuente { xtol } => more_thuente::search(
+            &function,
+            phi0,
+            derphi0,
+            options,
+            xtol,
+            options.maxiter.unwrap_or(100),
+        ),
+        LineSearchMethod::HagerZhang {
+            epsilon,
+            theta,
+            gamma,
+        } => hager_zhang::search(
+            &function,
+            phi0,
+            derphi0,
+            options,
+            hager_zhang::Parameters {
+                epsilon,
+                theta,
+                gamma,
+            },
+            options.maxiter.unwrap_or(100),
+        ),
+        LineSearchMethod::Armijo => armijo::search(
+            &function,
+            phi0,
+            derphi0,
+            options,
+            options.maxiter.unwrap_or(100),
+        ),
+    };
+    Ok(LineSearchResult {
+        alpha: outcome.alpha,
+        fun: outcome.phi,
+        derphi: outcome.derphi,
+        nfev: function.nfev.get(),
+        ngev: function.ngev.get(),
+        status: outcome.status,
+        success: outcome.status == LineSearchStatus::Converged,
+    })
+}
+
+/// Objective function along a direction, which keeps the last gradient
+struct Along<'a, T> {
+    objective: &'a T,
+    x: &'a [f64],
+    p: &'a [f64],
+    last_gradient: RefCell<Option<(f64, Vec<f64>)>>,
+    nfev: Cell<usize>,
+    ngev: Cell<usize>,
+}
+
+impl<T: MultivariateObjectiveFunction> Along<'_, T> {
+    /// Point `x + alpha p`
+    fn point(&self, alpha: f64) -> Vec<f64> {
+        self.x
+            .iter()
+            .zip(self.p)
+            .map(|(x, p)| x + alpha * p)
+            .collect()
+    }
+
+    /// Gradient at `x + alpha p`, approximated with forward differences if the objective has no
+    /// gradient
+    fn gradient(&self, alpha: f64) -> Vec<f64> {
+        if let Some((last, gradient)) = &*self.last_gradient.borrow() {
+            if last.to_bits() == alpha.to_bits() {
+                return gradient.clone();
+            }
+        }
+        self.ngev.set(self.ngev.get() + 1);
+        let x = self.point(alpha);
+        let gradient = self.objective.gradient(&x).unwrap_or_else(|| {
+            let options = DifferentiateOptions {
+                method: DifferenceMethod::Forward,
+                ..DifferentiateOptions::default()
+            };
+            self.nfev.set(self.nfev.get() + x.len() + 1);
+            approx_fprime(|x| self.objective.evaluate(x), &x, &options)
+                .unwrap_or_else(|_| vec![f64::NAN; x.len()])
+        });
+        *self.last_gradient.borrow_mut() = Some((alpha, gradient.clone()));
+        gradient
+    }
+}
+
+impl<T: MultivariateObjectiveFunction> LineFunction for Along<'_, T> {
+    fn evaluate(&self, alpha: f64) -> f64 {
+        self.nfev.set(self.nfev.get() + 1);
+        self.objective.evaluate(&self.point(alpha))
+    }
+
+    fn derivative(&self, alpha: f64) -> f64 {
+        dot(&self.gradient(alpha), self.p)
+    }
+}
+
+/// Search for a step along a direction of an objective function
+///
+/// The gradient at the new point is taken from the search when it was evaluated there, and
+/// evaluated otherwise.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`, the gradient is approximated with forward differences if
+///   it is not implemented
+/// * `x` - starting point
+/// * `p` - search direction, which must be a descent direction
+/// * `f0` - objective function at `x`, evaluated if not given
+/// * `g0` - gradient at `x`, evaluated if not given
+/// * `options` - line search options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x`, `p` and `g0` have different lengths, `p` is
+/// not a descent direction, or the options are invalid as for `line_search`
+pub async fn line_search_along<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    x: &[f64],
+    p: &[f64],
+    f0: Option<f64>,
+    g0: Option<&[f64]>,
+    options: &LineSearchOptions,
+) -> Result<MultivariateLineSearchResult, SwoopErrors> {
+    if x.len() != p.len() || g0.is_some_and(|g0| g0.len() != x.len()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The point, direction and gradient must have the same length",
+        )));
+    }
+    let along = Along {
+        objective: &objective_function,
+        x,
+        p,
+        last_gradient: RefCell::new(g0.map(|g0| (0f64, g0.to_vec()))),
+        nfev: Cell::new(0),
+        ngev: Cell::new(0),
+    };
+    let derphi0 = g0.map(|g0| dot(g0, p));
+    let result = line_search(&along, f0, derphi0, options).await?;
+    let grad = along.gradient(result.alpha);
+    Ok(MultivariateLineSearchResult {
+        alpha: result.alpha,
+        fun: result.fun,
+        grad,
+        nfev: along.nfev.get(),
+        ngev: along.ngev.get(),
+        status: result.status,
+        success: result.success,
+        x: along.point(result.alpha),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// `phi(alpha) = -alpha / (alpha^2 + beta)`, the first function of Moré and Thuente, with its
+    /// minimum at `sqrt(beta)`
+    struct MoreThuente1 {
+        beta: f64,
+    }
+
+    impl LineFunction for MoreThuente1 {
+        fn evaluate(&self, alpha: f64) -> f64 {
+            -alpha / (alpha.powi(2) + self.beta)
+        }
+
+        fn derivative(&self, alpha: f64) -> f64 {
+            (alpha.powi(2) - self.beta) / (alpha.powi(2) + self.beta).powi(2)
+        }
+    }
+
+    /// Rosenbrock function, with or without its gradient
+    struct Rosenbrock {
+        with_gradient: bool,
+    }
+
+    impl MultivariateObjectiveFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            100f64 * (x[1] - x[0].powi(2)).powi(2) + (1f64 - x[0]).powi(2)
+        }
+
+        fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+            self.with_gradient.then(|| {
+                vec![
+                    -400f64 * x[0] * (x[1] - x[0].powi(2)) - 2f64 * (1f64 - x[0]),
+                    200f64 * (x[1] - x[0].powi(2)),
+                ]
+            })
+        }
+    }
+
+    fn methods() -> [LineSearchMethod; 4] {
+        [
+            LineSearchMethod::StrongWolfe,
+            LineSearchMethod::MoreThuente { xtol: 1e-14 },
+            LineSearchMethod::HagerZhang {
+                epsilon: 1e-6,
+                theta: 0.5,
+                gamma: 0.66,
+            },
+            LineSearchMethod::Armijo,
+        ]
+    }
+
+    /// Check the conditions of the method at the result
+    fn assert_conditions<T: LineFunction>(
+        function: &T,
+        result: &LineSearchResult,
+        options: &LineSearchOptions,
+    ) {
+        assert!(result.success, "{:?} {:?}", options.method, result.status);
+        let (phi0, derphi0) = (function.evaluate(0f64), function.derivative(0f64));
+        assert!(relative_eq!(result.fun, function.evaluate(result.alpha)));
+        let armijo = result.fun <= phi0 + options.c1 * result.alpha * derphi0;
+        let curvature = function.derivative(result.alpha).abs() <= -options.c2 * derphi0;
+        match options.method {
+            LineSearchMethod::Armijo => assert!(armijo),
+            LineSearchMethod::HagerZhang { .. } => assert!(
+                function.derivative(result.alpha) >= options.c2 * derphi0,
+                "{:?}",
+                options.method
+            ),
+            _ => assert!(armijo && curvature, "{:?}", options.method),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_more_thuente_functions() -> Result<(), SwoopErrors> {
+        // steps from far below and far above the minimum, which need expansion and backtracking
+        for beta in [2f64, 0.004] {
+            for alpha0 in [1e-3, 1e-1, 1e1, 1e3] {
+                for method in methods() {
+                    let options = LineSearchOptions {
+                        method,
+                        alpha0,
+                        c1: 1e-3,
+                        c2: 0.1,
+                        // the doubling of the strong Wolfe search takes 11 steps from 1e-3
+                        maxiter: (method == LineSearchMethod::StrongWolfe).then_some(20),
+                        ..LineSearchOptions::default()
+                    };
+                    let function = MoreThuente1 { beta };
+                    let result = line_search(&function, None, None, &options).await?;
+                    // far beyond the minimum the function is flat enough for the curvature
+                    // condition, so the steps are not necessarily near the minimum
+                    assert_conditions(&function, &result, &options);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_line_search_along() -> Result<(), SwoopErrors> {
+        let x = [-1.2, 1f64];
+        let objective = Rosenbrock {
+            with_gradient: true,
+        };
+        let g0 = objective.gradient(&x).unwrap_or_default();
+        let p: Vec<f64> = g0.iter().map(|g| -g).collect();
+        for method in methods() {
+            let options = LineSearchOptions {
+                method,
+                ..LineSearchOptions::default()
+            };
+            let result = line_search_along(&objective, &x, &p, None, Some(&g0), &options).await?;
+            assert!(result.success, "{method:?}");
+            assert!(result.fun < objective.evaluate(&x));
+            assert!(relative_eq!(result.fun, objective.evaluate(&result.x)));
+            let exact = objective.gradient(&result.x).unwrap_or_default();
+            assert!(relative_eq!(result.grad[0], exact[0]));
+            assert!(relative_eq!(result.grad[1], exact[1]));
+
+            // the same step without the gradient, approximated by finite differences
+            let approximate = line_search_along(
+                Rosenbrock {
+                    with_gradient: false,
+                },
+                &x,
+                &p,
+                None,
+                None,
+                &options,
+            )
+            .await?;
+            assert!(approximate.success, "{method:?}");
+            assert!(relative_eq!(
+                approximate.alpha,
+                result.alpha,
+                max_relative = 1e-3
+            ));
+            assert!(approximate.nfev > result.nfev);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let function = MoreThuente1 { beta: 2f64 };
+        // an ascent direction
+        let result = line_search(&function, None, Some(1f64), &LineSearchOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let options = LineSearchOptions {
+            c1: 0.5,
+            c2: 0.1,
+            ..LineSearchOptions::default()
+        };
+        let result = line_search(&function, None, None, &options).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let options = LineSearchOptions {
+            alpha0: 2f64,
+            amax: 1f64,
+            ..LineSearchOptions::default()
+        };
+        let result = line_search(&function, None, None, &options).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = line_search_along(
+            Rosenbrock {
+                with_gradient: true,
+            },
+            &[0f64, 0f64],
+            &[1f64],
+            None,
+            None,
+            &LineSearchOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,278 @@
+//! Moré-Thuente line search, a port of MINPACK-2 `dcsrch` and `dcstep`
+//!
+//! The interval of uncertainty `[stx, sty]` is updated by safeguarded cubic and quadratic steps
+//! until a step satisfies the strong Wolfe conditions. While no step has satisfied the
+//! sufficient decrease condition with a nonnegative derivative the steps are taken on the
+//! modified function `phi(alpha) - phi(0) - c1 alpha phi'(0)`.
+
+use super::{LineFunction, LineSearchOptions, LineSearchStatus, Outcome};
+
+/// Lower and upper extrapolation factors of the steps before the minimum is bracketed
+const XTRAPL: f64 = 1.1;
+const XTRAPU: f64 = 4f64;
+
+/// End of the interval of uncertainty with its function value and derivative
+#[derive(Debug, Clone, Copy)]
+struct End {
+    st: f64,
+    f: f64,
+    g: f64,
+}
+
+/// Safeguarded step of the interval of uncertainty, port of `dcstep`
+///
+/// Updates the ends `x`, the best step so far, and `y` with the trial step `p`, and returns the
+/// next trial step within `[stpmin, stpmax]`.
+fn dcstep(x: &mut End, y: &mut End, p: End, brackt: &mut bool, stpmin: f64, stpmax: f64) -> f64 {
+    let sgnd = p.g * x.g.signum();
+    let theta = 3f64 * (x.f - p.f) / (p.st - x.st) + x.g + p.g;
+    let s = theta.abs().max(x.g.abs()).max(p.g.abs());
+
+    let stpf = if p.f > x.f {
+        // higher function value, the minimum is bracketed
+        let mut gamma = s * ((theta / s).powi(2) - (x.g / s) * (p.g / s)).sqrt();
+        if p.st < x.st {
+            gamma = -gamma;
+        }
+        let q = ((gamma - x.g) + gamma) + p.g;
+        let r = ((gamma - x.g) + theta) / q;
+        let stpc = x.st + r * (p.st - x.st);
+        let stpq = x.st + ((x.g / ((x.f - p.f) / (p.st - x.st) + x.g)) / 2f64) * (p.st - x.st);
+        *brackt = true;
+        if (stpc - x.st).abs() <= (stpq - x.st).abs() {
+            stpc
+        } else {
+            stpc + (stpq - stpc) / 2f64
+        }
+    } else if sgnd < 0f64 {
+        // derivatives of opposite sign, the minimum is bracketed
+        let mut gamma = s * ((theta / s).powi(2) - (x.g / s) * (p.g / s)).sqrt();
+        if p.st > x.st {
+            gamma = -gamma;
+        }
+        let q = ((gamma - p.g) + gamma) + x.g;
+        let r = ((gamma - p.g) + theta) / q;
+        let stpc = p.st + r * (x.st - p.st);
+        let stpq = p.st + (p.g / (p.g - x.g)) * (x.st - p.st);
+        *brackt = true;
+        if (stpc - p.st).abs() > (stpq - p.st).abs() {
+            stpc
+        } else {
+            stpq
+        }
+    } else if p.g.abs() < x.g.abs() {
+        // the derivative decreases in magnitude, the cubic step is only used if the cubic tends
+        // to infinity in the direction of the step or its minimum is beyond the step
+        let mut gamma = s
+            * ((theta / s).powi(2) - (x.g / s) * (p.g / s))
+                .max(0f64)
+                .sqrt();
+        if p.st > x.st {
+            gamma = -gamma;
+        }
+        let q = (gamma + (x.g - p.g)) + gamma;
+        let r = ((gamma - p.g) + theta) / q;
+        let stpc = if r < 0f64 && gamma != 0f64 {
+            p.st + r * (x.st - p.st)
+        } else if p.st > x.st {
+            stpmax
+        } else {
+            stpmin
+        };
+        let stpq = p.st + (p.g / (p.g - x.g)) * (x.st - p.st);
+        if *brackt {
+            let stpf = if (stpc - p.st).abs() < (stpq - p.st).abs() {
+                stpc
+            } else {
+                stpq
+            };
+            if p.st > x.st {
+                stpf.min(p.st + 0.66 * (y.st - p.st))
+            } else {
+                stpf.max(p.st + 0.66 * (y.st - p.st))
+            }
+        } else {
+            let stpf = if (stpc - p.st).abs() > (stpq - p.st).abs() {
+                stpc
+            } else {
+                stpq
+            };
+            stpf.min(stpmax).max(stpmin)
+        }
+    } else if *brackt {
+        // the derivative does not decrease in magnitude, cubic step towards the other end
+        let theta = 3f64 * (p.f - y.f) / (y.st - p.st) + y.g + p.g;
+        let s = theta.abs().max(y.g.abs()).max(p.g.abs());
+        let mut gamma = s * ((theta / s).powi(2) - (y.g / s) * (p.g / s)).sqrt();
+        if p.st > y.st {
+            gamma = -gamma;
+        }
+        let q = ((gamma - p.g) + gamma) + y.g;
+        let r = ((gamma - p.g) + theta) / q;
+        p.st + r * (y.st - p.st)
+    } else if p.st > x.st {
+        stpmax
+    } else {
+        stpmin
+    };
+
+    if p.f > x.f {
+        *y = p;
+    } else {
+        if sgnd < 0f64 {
+            *y = *x;
+        }
+        *x = p;
+    }
+    stpf
+}
+
+/// Search for a step that satisfies the strong Wolfe conditions, port of `dcsrch`
+#[allow(clippy::float_cmp)]
+pub(super) fn search<T: LineFunction>(
+    function: &T,
+    phi0: f64,
+    derphi0: f64,
+    options: &LineSearchOptions,
+    xtol: f64,
+    maxiter: usize,
+) -> Outcome {
+    let (ftol, gtol) = (options.c1, options.c2);
+    let (stpmin, stpmax) = (options.amin, options.amax);
+    let gtest = ftol * derphi0;
+    let mut width = stpmax - stpmin;
+    let mut width1 = width / 0.5;
+    let mut brackt = false;
+    let mut stage1 = true;
+    let start = End {
+        st: 0f64,
+        f: phi0,
+        g: derphi0,
+    };
+    let (mut x, mut y) = (start, start);
+    let mut stp = options.alpha0;
+    let (mut stmin, mut stmax) = (0f64, stp + XTRAPU * stp);
+
+    for _ in 0..maxiter {
+        let f = function.evaluate(stp);
+        let g = function.derivative(stp);
+        let ftest = phi0 + stp * gtest;
+        if stage1 && f <= ftest && g >= 0f64 {
+            stage1 = false;
+        }
+
+        let status = if f <= ftest && g.abs() <= gtol * (-derphi0) {
+            Some(LineSearchStatus::Converged)
+        } else if brackt && (stp <= stmin || stp >= stmax || stmax - stmin <= xtol * stmax) {
+            Some(LineSearchStatus::IntervalTooSmall)
+        } else if stp == stpmax && f <= ftest && g <= gtest {
+            Some(LineSearchStatus::StepAtMaximum)
+        } else if stp == stpmin && (f > ftest || g >= gtest) {
+            Some(LineSearchStatus::StepAtMinimum)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            return Outcome {
+                alpha: stp,
+                phi: f,
+                derphi: Some(g),
+                status,
+            };
+        }
+
+        let trial = End { st: stp, f, g };
+        stp = if stage1 && f <= x.f && f > ftest {
+            // steps on the modified function, which has a nonpositive derivative at zero
+            let modified = |end: End| End {
+                st: end.st,
+                f: end.f - end.st * gtest,
+                g: end.g - gtest,
+            };
+            let (mut xm, mut ym) = (modified(x), modified(y));
+            let next = dcstep(&mut xm, &mut ym, modified(trial), &mut brackt, stmin, stmax);
+            let restored = |end: End| End {
+                st: end.st,
+                f: end.f + end.st * gtest,
+                g: end.g + gtest,
+            };
+            (x, y) = (restored(xm), restored(ym));
+            next
+        } else {
+            dcstep(&mut x, &mut y, trial, &mut brackt, stmin, stmax)
+        };
+
+        // bisect if the interval does not shrink enough
+        if brackt {
+            if (y.st - x.st).abs() >= 0.66 * width1 {
+                stp = x.st + 0.5 * (y.st - x.st);
+            }
+            width1 = width;
+            width = (y.st - x.st).abs();
+            stmin = x.st.min(y.st);
+            stmax = x.st.max(y.st);
+        } else {
+            stmin = stp + XTRAPL * (stp - x.st);
+            stmax = stp + XTRAPU * (stp - x.st);
+        }
+        stp = stp.max(stpmin).min(stpmax);
+        // with no further progress possible the best step is the last one tried
+        if brackt && (stp <= stmin || stp >= stmax || stmax - stmin <= xtol * stmax) {
+            stp = x.st;
+        }
+    }
+    Outcome {
+        alpha: x.st,
+        phi: x.f,
+        derphi: Some(x.g),
+        status: LineSearchStatus::MaxIterExceeded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::line_search::LineSearchMethod;
+
+    /// Quadratic with its minimum at `2`
+    struct Quadratic {}
+
+    impl LineFunction for Quadratic {
+        fn evaluate(&self, alpha: f64) -> f64 {
+            (alpha - 2f64).powi(2)
+        }
+
+        fn derivative(&self, alpha: f64) -> f64 {
+            2f64 * (alpha - 2f64)
+        }
+    }
+
+    #[test]
+    fn test_bounds() {
+        let options = LineSearchOptions {
+            method: LineSearchMethod::MoreThuente { xtol: 1e-14 },
+            c2: 0.1,
+            amax: 1f64,
+            ..LineSearchOptions::default()
+        };
+        // the minimum is beyond the largest step
+        let outcome = search(&Quadratic {}, 4f64, -4f64, &options, 1e-14, 100);
+        assert_eq!(outcome.status, LineSearchStatus::StepAtMaximum);
+        assert!((outcome.alpha - 1f64).abs() < f64::EPSILON);
+
+        let options = LineSearchOptions {
+            alpha0: 1e-3,
+            ..options
+        };
+        let outcome = search(&Quadratic {}, 4f64, -4f64, &options, 1e-14, 100);
+        assert_eq!(outcome.status, LineSearchStatus::StepAtMaximum);
+
+        let options = LineSearchOptions {
+            amax: f64::INFINITY,
+            ..options
+        };
+        let outcome = search(&Quadratic {}, 4f64, -4f64, &options, 1e-14, 100);
+        assert_eq!(outcome.status, LineSearchStatus::Converged);
+        assert!((outcome.alpha - 2f64).abs() <= 0.2);
+    }
+}
@@ -0,0 +1,220 @@
+//! Strong Wolfe line search, a port of scipy `scalar_search_wolfe2`
+//!
+//! The step is doubled until it brackets a step satisfying the strong Wolfe conditions, which is
+//! then found by the zoom of Nocedal and Wright, algorithm 3.6, with cubic interpolation
+//! safeguarded by quadratic interpolation and bisection.
+
+use super::{LineFunction, LineSearchOptions, LineSearchStatus, Outcome};
+
+/// Minimiser of the cubic through `(a, fa)`, `(b, fb)` and `(c, fc)` with derivative `fpa` at
+/// `a`, if it exists
+fn cubicmin(a: f64, fa: f64, fpa: f64, b: f64, fb: f64, c: f64, fc: f64) -> Option<f64> {
+    let db = b - a;
+    let dc = c - a;
+    let denom = (db * dc).powi(2) * (db - dc);
+    let (eb, ec) = (fb - fa - fpa * db, fc - fa - fpa * dc);
+    let coef_a = (dc.powi(2) * eb - db.powi(2) * ec) / denom;
+    let coef_b = (-dc.powi(3) * eb + db.powi(3) * ec) / denom;
+    let radical = coef_b * coef_b - 3f64 * coef_a * fpa;
+    let xmin = a + (-coef_b + radical.sqrt()) / (3f64 * coef_a);
+    xmin.is_finite().then_some(xmin)
+}
+
+/// Minimiser of the quadratic through `(a, fa)` and `(b, fb)` with derivative `fpa` at `a`, if
+/// it exists
+fn quadmin(a: f64, fa: f64, fpa: f64, b: f64, fb: f64) -> Option<f64> {
+    let db = b - a;
+    let coef_b = (fb - fa - fpa * db) / (db * db);
+    let xmin = a - fpa / (2f64 * coef_b);
+    xmin.is_finite().then_some(xmin)
+}
+
+/// End of the zoom interval with its function value and derivative
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    alpha: f64,
+    phi: f64,
+    derphi: f64,
+}
+
+/// Zoom into the interval between `lo`, which satisfies the sufficient decrease condition, and
+/// `hi` to find a step that satisfies the strong Wolfe conditions
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::float_cmp)]
+fn zoom<T: LineFunction>(
+    function: &T,
+    mut lo: Point,
+    mut hi: (f64, f64),
+    phi0: f64,
+    derphi0: f64,
+    c1: f64,
+    c2: f64,
+    maxiter: usize,
+) -> Outcome {
+    // checks that the interpolated step is not too close to the ends of the interval
+    let (delta1, delta2) = (0.2, 0.1);
+    let (mut a_rec, mut phi_rec) = (0f64, phi0);
+    for i in 0..=maxiter {
+        let dalpha = hi.0 - lo.alpha;
+        let (a, b) = if dalpha < 0f64 {
+            (hi.0, lo.alpha)
+        } else {
+            (lo.alpha, hi.0)
+        };
+        let cchk = delta1 * dalpha.abs();
+        let cubic = if i > 0 {
+            cubicmin(lo.alpha, lo.phi, lo.derphi, hi.0, hi.1, a_rec, phi_rec)
+                .filter(|a_j| *a_j <= b - cchk && *a_j >= a + cchk)
+        } else {
+            None
+        };
+        let a_j = cubic.unwrap_or_else(|| {
+            let qchk = delta2 * dalpha.abs();
+            quadmin(lo.alpha, lo.phi, lo.derphi, hi.0, hi.1)
+                .filter(|a_j| *a_j <= b - qchk && *a_j >= a + qchk)
+                .unwrap_or(lo.alpha + 0.5 * dalpha)
+        });
+
+        let phi_j = function.evaluate(a_j);
+        if phi_j > phi0 + c1 * a_j * derphi0 || phi_j >= lo.phi {
+            (a_rec, phi_rec) = hi;
+            hi = (a_j, phi_j);
+        } else {
+            let derphi_j = function.derivative(a_j);
+            if derphi_j.abs() <= -c2 * derphi0 {
+                return Outcome {
+                    alpha: a_j,
+                    phi: phi_j,
+                    derphi: Some(derphi_j),
+                    status: LineSearchStatus::Converged,
+                };
+            }
+            if derphi_j * (hi.0 - lo.alpha) >= 0f64 {
+                (a_rec, phi_rec) = hi;
+                hi = (lo.alpha, lo.phi);
+            } else {
+                (a_rec, phi_rec) = (lo.alpha, lo.phi);
+            }
+            lo = Point {
+                alpha: a_j,
+                phi: phi_j,
+                derphi: derphi_j,
+            };
+        }
+        if hi.0 == lo.alpha {
+            break;
+        }
+    }
+    // the best step found satisfies the sufficient decrease condition
+    let status = if hi.0 == lo.alpha {
+        LineSearchStatus::IntervalTooSmall
+    } else {
+        LineSearchStatus::MaxIterExceeded
+    };
+    Outcome {
+        alpha: lo.alpha,
+        phi: lo.phi,
+        derphi: Some(lo.derphi),
+        status,
+    }
+}
+
+/// Search for a step that satisfies the strong Wolfe conditions
+pub(super) fn search<T: LineFunction>(
+    function: &T,
+    phi0: f64,
+    derphi0: f64,
+    options: &LineSearchOptions,
+    maxiter: usize,
+) -> Outcome {
+    let (c1, c2) = (options.c1, options.c2);
+    let mut previous = Point {
+        alpha: 0f64,
+        phi: phi0,
+        derphi: derphi0,
+    };
+    let mut alpha = options.alpha0;
+    let mut phi = function.evaluate(alpha);
+    for i in 0..maxiter {
+        if phi > phi0 + c1 * alpha * derphi0 || (phi >= previous.phi && i > 0) || phi.is_nan() {
+            return zoom(
+                function,
+                previous,
+                (alpha, phi),
+                phi0,
+                derphi0,
+                c1,
+                c2,
+                maxiter,
+            );
+        }
+        let derphi = function.derivative(alpha);
+        if derphi.abs() <= -c2 * derphi0 {
+            return Outcome {
+                alpha,
+                phi,
+                derphi: Some(derphi),
+                status: LineSearchStatus::Converged,
+            };
+        }
+        let current = Point { alpha, phi, derphi };
+        if derphi >= 0f64 {
+            return zoom(
+                function,
+                current,
+                (previous.alpha, previous.phi),
+                phi0,
+                derphi0,
+                c1,
+                c2,
+                maxiter,
+            );
+        }
+        if alpha >= options.amax {
+            return Outcome {
+                alpha,
+                phi,
+                derphi: Some(derphi),
+                status: LineSearchStatus::StepAtMaximum,
+            };
+        }
+        previous = current;
+        alpha = (2f64 * alpha).min(options.amax);
+        phi = function.evaluate(alpha);
+    }
+    Outcome {
+        alpha,
+        phi,
+        derphi: None,
+        status: LineSearchStatus::MaxIterExceeded,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_interpolation() {
+        // exact for a cubic and a quadratic
+        let cubic = |x: f64| (x - 1f64).powi(3) - 3f64 * (x - 1f64);
+        let xmin = cubicmin(
+            0f64,
+            cubic(0f64),
+            0f64,
+            1f64,
+            cubic(1f64),
+            3f64,
+            cubic(3f64),
+        );
+        assert!(xmin.is_some_and(|x| relative_eq!(x, 2f64, epsilon = 1e-12)));
+
+        let quadratic = |x: f64| (x - 0.3).powi(2);
+        let xmin = quadmin(0f64, quadratic(0f64), -0.6, 1f64, quadratic(1f64));
+        assert!(xmin.is_some_and(|x| relative_eq!(x, 0.3, epsilon = 1e-12)));
+
+        // a line has no minimum
+        assert!(quadmin(0f64, 0f64, -1f64, 1f64, -1f64).is_none());
+    }
+}
@@ -0,0 +1,434 @@
+//! Revised dual simplex method with bounded variables
+//!
+//! A logical variable is added to every row, `[0, inf)` for inequalities and `[0, 0]` for
+//! equalities, such that the logicals form the initial basis. A dual feasible basis is found
+//! with the subproblem approach: the dual simplex method is applied to an auxiliary problem in
+//! which every bound is finite, its optimal basis is dual feasible for the original problem
+//! unless the original problem is dual infeasible.
+//!
+//! # References
+//! * Koberstein, "The dual simplex method, techniques for a fast and stable implementation",
+//!   dissertation, Universität Paderborn (2005)
+
+use super::{LinearProgram, LinprogStatus, Solution};
+use crate::linalg::{dot, inverse};
+
+/// Number of iterations after which the basis inverse is recomputed from scratch
+const REFACTOR_FREQUENCY: usize = 50;
+
+/// Smallest magnitude of a pivot element
+const PIVOT_TOL: f64 = 1e-9;
+
+/// Position of a variable relative to its bounds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VariableStatus {
+    Basic,
+    AtLower,
+    AtUpper,
+    /// Nonbasic free variable fixed at zero
+    AtZero,
+}
+
+/// Outcome of running the dual simplex iterations
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Optimal,
+    /// The dual is unbounded, so the primal problem is infeasible
+    PrimalInfeasible,
+    IterationLimit,
+    Singular,
+}
+
+/// State of the revised dual simplex method, variables `0..n` are the structurals and `n..n+m`
+/// the logicals
+struct DualSimplex<'a> {
+    lp: &'a LinearProgram,
+    n: usize,
+    m: usize,
+    cost: Vec<f64>,
+    lower: Vec<f64>,
+    upper: Vec<f64>,
+    rhs: Vec<f64>,
+    basis: Vec<usize>,
+    status: Vec<VariableStatus>,
+    basis_inverse: Vec<Vec<f64>>,
+    x: Vec<f64>,
+    d: Vec<f64>,
+    tol: f64,
+    nit: usize,
+}
+
+impl DualSimplex<'_> {
+    /// Column `j` of `[A I]`
+    fn column(&self, j: usize) -> Vec<f64> {
+        if j < self.n {
+            self.lp.a.iter().map(|row| row[j]).collect()
+        } else {
+            let mut e = vec![0f64; self.m];
+            e[j - self.n] = 1f64;
+            e
+        }
+    }
+
+    /// Product of `v` with column `j` of `[A I]`
+    fn column_dot(&self, v: &[f64], j: usize) -> f64 {
+        if j < self.n {
+            self.lp.a.iter().zip(v).map(|(row, vi)| row[j] * vi).sum()
+        } else {
+            v[j - self.n]
+        }
+    }
+
+    /// Whether variable `j` has equal bounds
+    fn is_fixed(&self, j: usize) -> bool {
+        self.lower[j] >= self.upper[j]
+    }
+
+    /// Recompute the basis inverse, the basic variables and the reduced costs
+    fn refactor(&mut self) -> bool {
+        let columns: Vec<Vec<f64>> = self.basis.iter().map(|j| self.column(*j)).collect();
+        let basis_matrix: Vec<Vec<f64>> = (0..self.m)
+            .map(|i| columns.iter().map(|column| column[i]).collect())
+            .collect();
+        match inverse(&basis_matrix) {
+            Some(basis_inverse) => self.basis_inverse = basis_inverse,
+            None => return false,
+        }
+        self.compute_primal();
+        self.compute_duals();
+        true
+    }
+
+    /// Set the nonbasic variables to their bounds and solve for the basic variables
+    fn compute_primal(&mut self) {
+        let mut residual = self.rhs.clone();
+        for j in 0..self.n + self.m {
+            self.x[j] = match self.status[j] {
+                VariableStatus::Basic => continue,
+                VariableStatus::AtLower => self.lower[j],
+                VariableStatus::AtUpper => self.upper[j],
+                VariableStatus::AtZero => 0f64,
+            };
+            if self.x[j] != 0f64 {
+                for (i, ai) in self.column(j).iter().enumerate() {
+                    residual[i] -= ai * self.x[j];
+                }
+            }
+        }
+        for (i, j) in self.basis.iter().enumerate() {
+            self.x[*j] = dot(&self.basis_inverse[i], &residual);
+        }
+    }
+
+    /// Simplex multipliers `y = B^-T c_B`
+    fn row_duals(&self) -> Vec<f64> {
+        (0..self.m)
+            .map(|i| {
+                self.basis
+                    .iter()
+                    .enumerate()
+                    .map(|(k, j)| self.cost[*j] * self.basis_inverse[k][i])
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Reduced costs `d = c - [A I]^T y`
+    fn compute_duals(&mut self) {
+        let y = self.row_duals();
+        for j in 0..self.n + self.m {
+            self.d[j] = if self.status[j] == VariableStatus::Basic {
+                0f64
+            } else {
+                self.cost[j] - self.column_dot(&y, j)
+            };
+        }
+    }
+
+    /// Whether the reduced costs of the nonbasic variables have the correct sign for the bounds
+    /// `lower` and `upper`
+    fn dual_feasible(&self, lower: &[f64], upper: &[f64]) -> bool {
+        (0..self.n + self.m).all(|j| {
+            if self.status[j] == VariableStatus::Basic {
+                return true;
+            }
+            match (lower[j].is_finite(), upper[j].is_finite()) {
+                (true, true) => true,
+                (true, false) => self.d[j] >= -self.tol,
+                (false, true) => self.d[j] <= self.tol,
+                (false, false) => self.d[j].abs() <= self.tol,
+            }
+        })
+    }
+
+    /// Place the nonbasic variables on the bound that makes their reduced cost dual feasible
+    fn set_nonbasic_status(&mut self) {
+        for j in 0..self.n + self.m {
+            if self.status[j] == VariableStatus::Basic {
+                continue;
+            }
+            self.status[j] = match (self.lower[j].is_finite(), self.upper[j].is_finite()) {
+                (true, true) if self.d[j] < 0f64 && self.lower[j] < self.upper[j] => {
+                    VariableStatus::AtUpper
+                }
+                (true, _) => VariableStatus::AtLower,
+                (false, true) => VariableStatus::AtUpper,
+                (false, false) => VariableStatus::AtZero,
+            };
+        }
+    }
+
+    /// Basic variable with the largest bound violation, the leaving row
+    fn select_leaving(&self) -> Option<usize> {
+        let mut leaving = None;
+        let mut largest = 0f64;
+        for (i, j) in self.basis.iter().enumerate() {
+            let violation = if self.x[*j] < self.lower[*j] {
+                self.lower[*j] - self.x[*j]
+            } else if self.x[*j] > self.upper[*j] {
+                self.x[*j] - self.upper[*j]
+            } else {
+                0f64
+            };
+            let tol = self.tol * f64::max(1f64, self.x[*j].abs());
+            if violation > tol && violation > largest {
+                largest = violation;
+                leaving = Some(i);
+            }
+        }
+        leaving
+    }
+
+    /// Run the dual simplex iterations from a dual feasible basis
+    #[allow(clippy::needless_range_loop)]
+    fn iterate(&mut self, maxiter: usize) -> Outcome {
+        let mut since_refactor = 0;
+        loop {
+            if since_refactor >= REFACTOR_FREQUENCY {
+                if !self.refactor() {
+                    return Outcome::Singular;
+                }
+                since_refactor = 0;
+            }
+            let Some(r) = self.select_leaving() else {
+                return Outcome::Optimal;
+            };
+            if self.nit >= maxiter {
+                return Outcome::IterationLimit;
+            }
+            let p = self.basis[r];
+            let to_lower = self.x[p] < self.lower[p];
+            let delta = if to_lower {
+                self.x[p] - self.lower[p]
+            } else {
+                self.x[p] - self.upper[p]
+            };
+
+            // pivot row and ratio test, ties are broken by the largest pivot
+            let rho = self.basis_inverse[r].clone();
+            let mut alpha_row = vec![0f64; self.n + self.m];
+            let mut entering: Option<(usize, f64, f64)> = None;
+            for j in 0..self.n + self.m {
+                if self.status[j] == VariableStatus::Basic || self.is_fixed(j) {
+                    continue;
+                }
+                let alpha = self.column_dot(&rho, j);
+                alpha_row[j] = alpha;
+                if alpha.abs() < PIVOT_TOL {
+                    continue;
+                }
+                let alpha_t = if to_lower { -alpha } else { alpha };
+                let ratio = match self.status[j] {
+                    VariableStatus::AtLower if alpha_t > 0f64 => self.d[j].max(0f64) / alpha_t,
+                    VariableStatus::AtUpper if alpha_t < 0f64 => self.d[j].min(0f64) / alpha_t,
+                    VariableStatus::AtZero => self.d[j].abs() / alpha_t.abs(),
+                    _ => continue,
+                };
+                let better = entering.map_or(true, |(_, best_ratio, best_alpha)| {
+                    ratio < best_ratio - self.tol
+                        || (ratio <= best_ratio + self.tol && alpha.abs() > best_alpha.abs())
+                });
+                if better {
+                    entering = Some((j, ratio, alpha));
+                }
+            }
+            let Some((q, _, alpha_q)) = entering else {
+                return Outcome::PrimalInfeasible;
+            };
+
+            // update the reduced costs
+            let theta_d = self.d[q] / alpha_q;
+            for j in 0..self.n + self.m {
+                if self.status[j] != VariableStatus::Basic {
+                    self.d[j] -= theta_d * alpha_row[j];
+                }
+            }
+            self.d[q] = 0f64;
+            self.d[p] = -theta_d;
+
+            // update the primal variables
+            let column = self.column(q);
+            let alpha_column: Vec<f64> = self
+                .basis_inverse
+                .iter()
+                .map(|row| dot(row, &column))
+                .collect();
+            let theta_p = delta / alpha_column[r];
+            for (i, j) in self.basis.iter().enumerate() {
+                self.x[*j] -= theta_p * alpha_column[i];
+            }
+            self.x[q] += theta_p;
+            if to_lower {
+                self.x[p] = self.lower[p];
+                self.status[p] = VariableStatus::AtLower;
+            } else {
+                self.x[p] = self.upper[p];
+                self.status[p] = VariableStatus::AtUpper;
+            }
+            self.status[q] = VariableStatus::Basic;
+            self.basis[r] = q;
+
+            // update the basis inverse
+            let pivot = alpha_column[r];
+            for v in &mut self.basis_inverse[r] {
+                *v /= pivot;
+            }
+            let pivot_row = self.basis_inverse[r].clone();
+            for (i, row) in self.basis_inverse.iter_mut().enumerate() {
+                if i != r && alpha_column[i] != 0f64 {
+                    for (v, pv) in row.iter_mut().zip(&pivot_row) {
+                        *v -= alpha_column[i] * pv;
+                    }
+                }
+            }
+
+            self.nit += 1;
+            since_refactor += 1;
+        }
+    }
+}
+
+/// Solve a linear program with the revised dual simplex method
+///
+/// # Parameters
+/// * `lp` - linear program
+/// * `tol` - tolerance for primal and dual feasibility
+/// * `maxiter` - maximum iterations
+pub(super) fn dual_simplex(lp: &LinearProgram, tol: f64, maxiter: usize) -> Solution {
+    let n = lp.c.len();
+    let m = lp.b.len();
+
+    let mut lower = lp.lb.clone();
+    let mut upper = lp.ub.clone();
+    lower.resize(n + m, 0f64);
+    upper.extend((0..m).map(|i| if i < lp.n_ub { f64::INFINITY } else { 0f64 }));
+    let mut cost = lp.c.clone();
+    cost.resize(n + m, 0f64);
+
+    let mut simplex = DualSimplex {
+        lp,
+        n,
+        m,
+        d: cost.clone(),
+        cost,
+        lower: lower.clone(),
+        upper: upper.clone(),
+        rhs: lp.b.clone(),
+        basis: (n..n + m).collect(),
+        status: (0..n + m)
+            .map(|j| {
+                if j < n {
+                    VariableStatus::AtLower
+                } else {
+                    VariableStatus::Basic
+                }
+            })
+            .collect(),
+        basis_inverse: (0..m)
+            .map(|i| (0..m).map(|j| if i == j { 1f64 } else { 0f64 }).collect())
+            .collect(),
+        x: vec![0f64; n + m],
+        tol,
+        nit: 0,
+    };
+
+    // phase 1, every bound is made finite and the right hand side zero
+    let mut dual_feasible = simplex.dual_feasible(&lower, &upper);
+    if !dual_feasible {
+        simplex.lower = lower
+            .iter()
+            .map(|l| if l.is_finite() { 0f64 } else { -1f64 })
+            .collect();
+        simplex.upper = upper
+            .iter()
+            .map(|u| if u.is_finite() { 0f64 } else { 1f64 })
+            .collect();
+        simplex.rhs = vec![0f64; m];
+        simplex.set_nonbasic_status();
+        simplex.compute_primal();
+        match simplex.iterate(maxiter) {
+            Outcome::Optimal => {}
+            Outcome::IterationLimit => return solution(&simplex, LinprogStatus::IterationLimit),
+            Outcome::PrimalInfeasible | Outcome::Singular => {
+                return solution(&simplex, LinprogStatus::NumericalDifficulties)
+            }
+        }
+        if !simplex.refactor() {
+            return solution(&simplex, LinprogStatus::NumericalDifficulties);
+        }
+        dual_feasible = simplex.dual_feasible(&lower, &upper);
+        simplex.lower = lower;
+        simplex.upper = upper;
+        simplex.rhs.clone_from(&lp.b);
+    }
+
+    // without a dual feasible basis the problem is either infeasible or unbounded, which is
+    // decided by looking for a feasible point with a zero objective
+    if !dual_feasible {
+        simplex.cost = vec![0f64; n + m];
+        simplex.d = vec![0f64; n + m];
+    }
+    simplex.set_nonbasic_status();
+    simplex.compute_primal();
+    let status = match simplex.iterate(maxiter) {
+        Outcome::Optimal if dual_feasible => LinprogStatus::Optimal,
+        Outcome::Optimal => LinprogStatus::Unbounded,
+        Outcome::PrimalInfeasible => LinprogStatus::Infeasible,
+        Outcome::IterationLimit => LinprogStatus::IterationLimit,
+        Outcome::Singular => LinprogStatus::NumericalDifficulties,
+    };
+    if status == LinprogStatus::Optimal && !simplex.refactor() {
+        return solution(&simplex, LinprogStatus::NumericalDifficulties);
+    }
+    solution(&simplex, status)
+}
+
+/// Extract the solution and the marginals from the state of the simplex method
+fn solution(simplex: &DualSimplex<'_>, status: LinprogStatus) -> Solution {
+    let n = simplex.n;
+    let mut lower_marginals = vec![0f64; n];
+    let mut upper_marginals = vec![0f64; n];
+    for j in 0..n {
+        match simplex.status[j] {
+            VariableStatus::AtLower | VariableStatus::AtUpper if simplex.is_fixed(j) => {
+                if simplex.d[j] > 0f64 {
+                    lower_marginals[j] = simplex.d[j];
+                } else {
+                    upper_marginals[j] = simplex.d[j];
+                }
+            }
+            VariableStatus::AtLower => lower_marginals[j] = simplex.d[j],
+            VariableStatus::AtUpper => upper_marginals[j] = simplex.d[j],
+            VariableStatus::Basic | VariableStatus::AtZero => {}
+        }
+    }
+    Solution {
+        x: simplex.x[..n].to_vec(),
+        row_duals: simplex.row_duals(),
+        lower_marginals,
+        upper_marginals,
+        status,
+        nit: simplex.nit,
+        basis: simplex.basis.clone(),
+    }
+}
@@ -0,0 +1,416 @@
+//! Primal-dual interior point method on the homogeneous self-dual formulation
+//!
+//! The problem is converted to the standard form `min c^T x` subject to `A x = b` and
+//! `x >= 0`: variables are shifted onto their lower bound or mirrored onto their upper bound,
+//! free variables are split into two non-negative parts, and inequality rows and finite upper
+//! bounds get slack variables. The homogeneous self-dual embedding detects infeasible and
+//! unbounded problems without a phase 1.
+//!
+//! # References
+//! * Andersen and Andersen, "The MOSEK interior point optimizer for linear programming: an
+//!   implementation of the homogeneous algorithm", High Performance Optimization (2000)
+//! * Mehrotra, "On the implementation of a primal-dual interior point method", SIAM Journal on
+//!   Optimization 2.4 (1992)
+
+use super::{LinearProgram, LinprogStatus, Solution};
+use crate::linalg::{
+    cholesky, dot, mat_t_vec, mat_vec, norm, solve_upper_triangular,
+    solve_upper_triangular_transpose,
+};
+
+/// Fraction of the step to the boundary of the positive orthant that is taken
+const STEP_FACTOR: f64 = 0.995;
+
+/// How a variable of the linear program maps to the standard form
+#[derive(Debug, Clone, Copy)]
+enum Transform {
+    /// `x = lb + x'`, with the column of the slack of its upper bound row if it has one
+    Shift(usize, Option<usize>),
+    /// `x = ub - x'`
+    Mirror(usize),
+    /// `x = x+ - x-`
+    Split(usize, usize),
+}
+
+/// Linear program in standard form
+struct StandardForm {
+    c: Vec<f64>,
+    a: Vec<Vec<f64>>,
+    b: Vec<f64>,
+    transforms: Vec<Transform>,
+}
+
+impl StandardForm {
+    /// Convert a linear program to standard form
+    fn new(lp: &LinearProgram) -> Self {
+        let n = lp.c.len();
+        let m = lp.b.len();
+        let mut transforms = Vec::with_capacity(n);
+        let mut columns = 0;
+        for (l, u) in lp.lb.iter().zip(&lp.ub) {
+            transforms.push(match (l.is_finite(), u.is_finite()) {
+                (true, _) => Transform::Shift(columns, None),
+                (false, true) => Transform::Mirror(columns),
+                (false, false) => {
+                    columns += 1;
+                    Transform::Split(columns - 1, columns)
+                }
+            });
+            columns += 1;
+        }
+        let n_slack = columns;
+        columns += lp.n_ub;
+        for (transform, (l, u)) in transforms.iter_mut().zip(lp.lb.iter().zip(&lp.ub)) {
+            if let Transform::Shift(j, None) = transform {
+                if l.is_finite() && u.is_finite() {
+                    *transform = Transform::Shift(*j, Some(columns));
+                    columns += 1;
+                }
+            }
+        }
+
+        let mut c = vec![0f64; columns];
+        let mut a = vec![vec![0f64; columns]; m];
+        let mut b = lp.b.clone();
+        for (k, transform) in transforms.iter().enumerate() {
+            let (columns_k, signs, offset) = match transform {
+                Transform::Shift(j, _) => (vec![*j], vec![1f64], lp.lb[k]),
+                Transform::Mirror(j) => (vec![*j], vec![-1f64], lp.ub[k]),
+                Transform::Split(j_plus, j_minus) => {
+                    (vec![*j_plus, *j_minus], vec![1f64, -1f64], 0f64)
+                }
+            };
+            for (j, sign) in columns_k.iter().zip(&signs) {
+                c[*j] = sign * lp.c[k];
+                for (row, lp_row) in a.iter_mut().zip(&lp.a) {
+                    row[*j] = sign * lp_row[k];
+                }
+            }
+            for (bi, lp_row) in b.iter_mut().zip(&lp.a) {
+                *bi -= lp_row[k] * offset;
+            }
+        }
+        for (i, row) in a.iter_mut().take(lp.n_ub).enumerate() {
+            row[n_slack + i] = 1f64;
+        }
+        for (k, transform) in transforms.iter().enumerate() {
+            if let Transform::Shift(j, Some(w)) = transform {
+                let mut row = vec![0f64; columns];
+                row[*j] = 1f64;
+                row[*w] = 1f64;
+                a.push(row);
+                b.push(lp.ub[k] - lp.lb[k]);
+            }
+        }
+        Self {
+            c,
+            a,
+            b,
+            transforms,
+        }
+    }
+
+    /// Variables, row duals and bound marginals of the linear program from the standard form
+    /// variables `x`, row duals `y` and reduced costs `z`
+    fn recover(&self, lp: &LinearProgram, x: &[f64], y: &[f64], z: &[f64]) -> Solution {
+        let n = lp.c.len();
+        let m = lp.b.len();
+        let mut lp_x = vec![0f64; n];
+        let mut lower_marginals = vec![0f64; n];
+        let mut upper_marginals = vec![0f64; n];
+        let mut upper_row = m;
+        for (k, transform) in self.transforms.iter().enumerate() {
+            match transform {
+                Transform::Shift(j, w) => {
+                    lp_x[k] = lp.lb[k] + x[*j];
+                    lower_marginals[k] = z[*j];
+                    if w.is_some() {
+                        upper_marginals[k] = y[upper_row];
+                        upper_row += 1;
+                    }
+                }
+                Transform::Mirror(j) => {
+                    lp_x[k] = lp.ub[k] - x[*j];
+                    upper_marginals[k] = -z[*j];
+                }
+                Transform::Split(j_plus, j_minus) => lp_x[k] = x[*j_plus] - x[*j_minus],
+            }
+        }
+        Solution {
+            x: lp_x,
+            row_duals: y[..m].to_vec(),
+            lower_marginals,
+            upper_marginals,
+            status: LinprogStatus::Optimal,
+            nit: 0,
+            basis: Vec::new(),
+        }
+    }
+}
+
+/// Search direction of the homogeneous self-dual system
+struct Direction {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    z: Vec<f64>,
+    tau: f64,
+    kappa: f64,
+}
+
+/// Current iterate and the quantities shared by the predictor and corrector directions
+struct Iterate<'a> {
+    sf: &'a StandardForm,
+    x: Vec<f64>,
+    y: Vec<f64>,
+    z: Vec<f64>,
+    tau: f64,
+    kappa: f64,
+    r_p: Vec<f64>,
+    r_d: Vec<f64>,
+    r_g: f64,
+}
+
+impl Iterate<'_> {
+    /// Solve the Newton system for the residual reduction `eta`, with right hand sides `r_xz`
+    /// and `r_tk` of the complementarity conditions. `factor` is the Cholesky factor of
+    /// `A D A^T` and `q` solves `A D A^T q = A D c + b`
+    fn direction(
+        &self,
+        d: &[f64],
+        factor: &[Vec<f64>],
+        q: &[f64],
+        eta: f64,
+        r_xz: &[f64],
+        r_tk: f64,
+    ) -> Direction {
+        let a = &self.sf.a;
+        let c = &self.sf.c;
+        let b = &self.sf.b;
+        let n = c.len();
+        let h: Vec<f64> = self
+            .r_d
+            .iter()
+            .zip(r_xz.iter().zip(&self.x))
+            .map(|(rd, (rxz, xi))| -eta * rd + rxz / xi)
+            .collect();
+        let dh: Vec<f64> = d.iter().zip(&h).map(|(di, hi)| di * hi).collect();
+        let rhs: Vec<f64> = self
+            .r_p
+            .iter()
+            .zip(mat_vec(a, &dh))
+            .map(|(rp, adh)| eta * rp - adh)
+            .collect();
+        let p = cholesky_solve(factor, &rhs);
+        let u: Vec<f64> = mat_t_vec(a, &p, n)
+            .iter()
+            .zip(d.iter().zip(&h))
+            .map(|(atp, (di, hi))| di * (atp + hi))
+            .collect();
+        let w: Vec<f64> = mat_t_vec(a, q, n)
+            .iter()
+            .zip(d.iter().zip(c))
+            .map(|(atq, (di, ci))| di * (atq - ci))
+            .collect();
+        let dtau = (eta * self.r_g - dot(b, &p) + dot(c, &u) + r_tk / self.tau)
+            / (dot(b, q) - dot(c, &w) + self.kappa / self.tau);
+        let dx: Vec<f64> = u.iter().zip(&w).map(|(ui, wi)| ui + wi * dtau).collect();
+        let dy: Vec<f64> = p.iter().zip(q).map(|(pi, qi)| pi + qi * dtau).collect();
+        let dz = r_xz
+            .iter()
+            .zip(self.z.iter().zip(dx.iter().zip(&self.x)))
+            .map(|(rxz, (zi, (dxi, xi)))| (rxz - zi * dxi) / xi)
+            .collect();
+        Direction {
+            x: dx,
+            y: dy,
+            z: dz,
+            tau: dtau,
+            kappa: (r_tk - self.kappa * dtau) / self.tau,
+        }
+    }
+
+    /// Largest step in `(0, 1]` that keeps `x`, `z`, `tau` and `kappa` non-negative
+    fn max_step(&self, direction: &Direction) -> f64 {
+        let ratio = |v: f64, dv: f64| if dv < 0f64 { -v / dv } else { f64::INFINITY };
+        let mut alpha = ratio(self.tau, direction.tau).min(ratio(self.kappa, direction.kappa));
+        for (v, dv) in self
+            .x
+            .iter()
+            .zip(&direction.x)
+            .chain(self.z.iter().zip(&direction.z))
+        {
+            alpha = alpha.min(ratio(*v, *dv));
+        }
+        alpha.min(1f64)
+    }
+}
+
+/// Solve `R^T R x = b` for upper triangular `R`
+fn cholesky_solve(factor: &[Vec<f64>], b: &[f64]) -> Vec<f64> {
+    solve_upper_triangular(factor, &solve_upper_triangular_transpose(factor, b))
+}
+
+/// Cholesky factor of the normal equations `A D A^T`, regularised when they are singular
+fn factor_normal_equations(a: &[Vec<f64>], d: &[f64]) -> Option<Vec<Vec<f64>>> {
+    let mut normal: Vec<Vec<f64>> = a
+        .iter()
+        .map(|ai| {
+            a.iter()
+                .map(|aj| {
+                    ai.iter()
+                        .zip(aj)
+                        .zip(d)
+                        .map(|((x, y), di)| x * di * y)
+                        .sum()
+                })
+                .collect()
+        })
+        .collect();
+    let max_diag = (0..a.len()).fold(1f64, |acc, i| acc.max(normal[i][i]));
+    let mut regularisation = 1e-14 * max_diag;
+    for _ in 0..6 {
+        if let Some(factor) = cholesky(&normal) {
+            return Some(factor);
+        }
+        for (i, row) in normal.iter_mut().enumerate() {
+            row[i] += regularisation;
+        }
+        regularisation *= 100f64;
+    }
+    None
+}
+
+/// Solve a linear program with the homogeneous self-dual interior point method
+///
+/// # Parameters
+/// * `lp` - linear program
+/// * `tol` - tolerance for the relative residuals and the duality gap
+/// * `maxiter` - maximum iterations
+#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::too_many_lines)]
+pub(super) fn interior_point(lp: &LinearProgram, tol: f64, maxiter: usize) -> Solution {
+    let sf = StandardForm::new(lp);
+    let n = sf.c.len();
+    let a = &sf.a;
+    let b = &sf.b;
+    let c = &sf.c;
+
+    let mut it = Iterate {
+        sf: &sf,
+        x: vec![1f64; n],
+        y: vec![0f64; b.len()],
+        z: vec![1f64; n],
+        tau: 1f64,
+        kappa: 1f64,
+        r_p: Vec::new(),
+        r_d: Vec::new(),
+        r_g: 0f64,
+    };
+    let mut initial = None;
+    let mut status = LinprogStatus::IterationLimit;
+    let mut nit = 0;
+    loop {
+        it.r_p = b
+            .iter()
+            .zip(mat_vec(a, &it.x))
+            .map(|(bi, axi)| bi * it.tau - axi)
+            .collect();
+        it.r_d = c
+            .iter()
+            .zip(mat_t_vec(a, &it.y, n).iter().zip(&it.z))
+            .map(|(ci, (atyi, zi))| ci * it.tau - atyi - zi)
+            .collect();
+        it.r_g = dot(c, &it.x) - dot(b, &it.y) + it.kappa;
+        let mu = (dot(&it.x, &it.z) + it.tau * it.kappa) / (n + 1) as f64;
+
+        // convergence and infeasibility indicators relative to the initial point
+        let (r_p0, r_d0, r_g0, mu0) =
+            *initial.get_or_insert((norm(&it.r_p), norm(&it.r_d), it.r_g.abs(), mu));
+        let rho_p = norm(&it.r_p) / f64::max(1f64, r_p0);
+        let rho_d = norm(&it.r_d) / f64::max(1f64, r_d0);
+        let rho_g = it.r_g.abs() / f64::max(1f64, r_g0);
+        let rho_mu = mu / mu0;
+        let rho_a = (dot(c, &it.x) - dot(b, &it.y)).abs() / (it.tau + dot(b, &it.y).abs());
+        if rho_p < tol && rho_d < tol && rho_a < tol {
+            status = LinprogStatus::Optimal;
+            break;
+        }
+        if (rho_p < tol && rho_d < tol && rho_g < tol && it.tau < tol * it.kappa.max(1f64))
+            || (rho_mu < tol && it.tau < tol * it.kappa.min(1f64))
+        {
+            // a primal ray with negative cost or a dual ray with positive objective
+            status = if dot(b, &it.y) > tol * dot(c, &it.x).abs() {
+                LinprogStatus::Infeasible
+            } else {
+                LinprogStatus::Unbounded
+            };
+            break;
+        }
+        if nit >= maxiter {
+            break;
+        }
+
+        let d: Vec<f64> = it.x.iter().zip(&it.z).map(|(xi, zi)| xi / zi).collect();
+        let Some(factor) = factor_normal_equations(a, &d) else {
+            status = LinprogStatus::NumericalDifficulties;
+            break;
+        };
+        let dc: Vec<f64> = d.iter().zip(c).map(|(di, ci)| di * ci).collect();
+        let rhs: Vec<f64> = mat_vec(a, &dc).iter().zip(b).map(|(x, y)| x + y).collect();
+        let q = cholesky_solve(&factor, &rhs);
+
+        // predictor, the affine scaling direction
+        let r_xz: Vec<f64> = it.x.iter().zip(&it.z).map(|(xi, zi)| -xi * zi).collect();
+        let affine = it.direction(&d, &factor, &q, 1f64, &r_xz, -it.tau * it.kappa);
+        let alpha = it.max_step(&affine);
+        let mu_affine = (it
+            .x
+            .iter()
+            .zip(&affine.x)
+            .zip(it.z.iter().zip(&affine.z))
+            .map(|((xi, dxi), (zi, dzi))| (xi + alpha * dxi) * (zi + alpha * dzi))
+            .sum::<f64>()
+            + (it.tau + alpha * affine.tau) * (it.kappa + alpha * affine.kappa))
+            / (n + 1) as f64;
+        let gamma = (mu_affine / mu).powi(3).min(1f64);
+
+        // corrector, centring towards gamma * mu with a second order correction
+        let r_xz: Vec<f64> =
+            it.x.iter()
+                .zip(&it.z)
+                .zip(affine.x.iter().zip(&affine.z))
+                .map(|((xi, zi), (dxi, dzi))| gamma * mu - xi * zi - dxi * dzi)
+                .collect();
+        let r_tk = gamma * mu - it.tau * it.kappa - affine.tau * affine.kappa;
+        let direction = it.direction(&d, &factor, &q, 1f64 - gamma, &r_xz, r_tk);
+        if direction
+            .x
+            .iter()
+            .chain(&direction.z)
+            .any(|v| !v.is_finite())
+        {
+            status = LinprogStatus::NumericalDifficulties;
+            break;
+        }
+        let alpha = (STEP_FACTOR * it.max_step(&direction)).min(1f64);
+
+        for (xi, dxi) in it.x.iter_mut().zip(&direction.x) {
+            *xi += alpha * dxi;
+        }
+        for (yi, dyi) in it.y.iter_mut().zip(&direction.y) {
+            *yi += alpha * dyi;
+        }
+        for (zi, dzi) in it.z.iter_mut().zip(&direction.z) {
+            *zi += alpha * dzi;
+        }
+        it.tau += alpha * direction.tau;
+        it.kappa += alpha * direction.kappa;
+        nit += 1;
+    }
+
+    let scale = |v: &[f64]| -> Vec<f64> { v.iter().map(|vi| vi / it.tau).collect() };
+    let mut solution = sf.recover(lp, &scale(&it.x), &scale(&it.y), &scale(&it.z));
+    solution.status = status;
+    solution.nit = nit;
+    solution
+}
@@ -0,0 +1,796 @@
+//! Mixed-integer linear programming
+//!
+//! Branch and bound on the linear programming relaxation, which is solved with the dual simplex
+//! method. The open node with the lowest bound is explored first, branching on the most
+//! fractional integer variable and then on semi-continuous variables that are neither zero nor
+//! within their bounds. Rounds of Gomory mixed-integer cuts from the optimal tableau tighten the
+//! relaxation at the root.
+//!
+//! # References
+//! * Wolsey, "Integer programming", Wiley (1998)
+//! * Cornuéjols, "Revival of the Gomory cuts in the 1990's", Annals of Operations Research 149
+//!   (2007)
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+
+use super::dual_simplex::dual_simplex;
+use super::{LinearProgram, LinprogStatus, Solution};
+use crate::constraints::{Bounds, LinearConstraint};
+use crate::linalg::{dot, inverse, mat_vec};
+use crate::SwoopErrors;
+
+/// Largest distance to the nearest integer of an integer variable
+const INTEGRALITY_TOL: f64 = 1e-6;
+
+/// Tolerance of the linear programming relaxations
+const LP_TOL: f64 = 1e-9;
+
+/// Smallest fractional part of the basic variable from which a cut is derived
+const MIN_CUT_FRACTION: f64 = 0.01;
+
+/// Integrality constraint on a variable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Integrality {
+    /// The variable is continuous
+    #[default]
+    Continuous,
+    /// The variable is restricted to integer values
+    Integer,
+    /// The variable is either zero or within its bounds
+    SemiContinuous,
+}
+
+/// Options for the `milp` solver
+#[derive(Debug, Clone, Copy)]
+pub struct MilpOptions {
+    /// Terminate once the relative gap between the objective of the best solution and the best
+    /// bound falls below this value
+    pub mip_rel_gap: f64,
+    /// Maximum time spent solving the problem
+    pub time_limit: Option<Duration>,
+    /// Maximum number of branch and bound nodes
+    pub node_limit: Option<usize>,
+    /// Maximum rounds of Gomory mixed-integer cuts added at the root node
+    pub cut_rounds: usize,
+}
+
+impl Default for MilpOptions {
+    fn default() -> Self {
+        Self {
+            mip_rel_gap: 1e-4,
+            time_limit: None,
+            node_limit: None,
+            cut_rounds: 10,
+        }
+    }
+}
+
+/// Reason the `milp` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MilpStatus {
+    /// A solution within the relative gap tolerance of the optimum was found
+    Optimal,
+    /// The time limit was reached
+    TimeLimit,
+    /// The node limit was reached
+    NodeLimit,
+    /// The problem has no feasible solution
+    Infeasible,
+    /// The linear programming relaxation is unbounded
+    Unbounded,
+    /// The linear programming relaxation of a node could not be solved
+    NumericalDifficulties,
+}
+
+/// Struct to represent the result of a `milp` optimisation
+#[derive(Debug, Clone)]
+pub struct MilpResult {
+    /// The best solution found, if any
+    pub x: Option<Vec<f64>>,
+    /// Value of the objective `c^T x` of the best solution
+    pub fun: Option<f64>,
+    /// Lower bound on the optimal objective
+    pub mip_dual_bound: f64,
+    /// Relative gap `(fun - mip_dual_bound) / |fun|`, infinite without a solution
+    pub mip_gap: f64,
+    /// Reason the solver terminated
+    pub status: MilpStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// Number of branch and bound nodes solved
+    pub mip_node_count: usize,
+}
+
+/// Node of the branch and bound tree, the bounds of the variables replace those of the root
+struct Node {
+    /// Objective of the relaxation of the parent node
+    bound: f64,
+    depth: usize,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+// ordered such that the node with the lowest bound, then the deepest node, is popped first
+impl Ord for Node {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .bound
+            .total_cmp(&self.bound)
+            .then(self.depth.cmp(&other.depth))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Node {}
+
+/// Variable to branch on
+enum Branch {
+    /// Integer variable with a fractional value
+    Integer(usize, f64),
+    /// Semi-continuous variable that is neither zero nor within its bounds
+    SemiContinuous(usize),
+}
+
+/// Relative gap between the objective of a solution and a lower bound
+fn relative_gap(fun: f64, bound: f64) -> f64 {
+    if !fun.is_finite() {
+        return f64::INFINITY;
+    }
+    let difference = fun - bound;
+    if difference <= 0f64 {
+        0f64
+    } else {
+        difference / fun.abs()
+    }
+}
+
+/// Solve the relaxation with the current bounds
+fn solve_relaxation(lp: &LinearProgram) -> Solution {
+    dual_simplex(lp, LP_TOL, usize::max(1000, 10 * (lp.a.len() + lp.c.len())))
+}
+
+/// Choose the variable to branch on, `None` if the solution satisfies the integrality
+/// constraints
+fn select_branch(
+    x: &[f64],
+    integrality: &[Integrality],
+    semi_lb: &[f64],
+    semi_ub: &[f64],
+) -> Option<Branch> {
+    let mut branch = None;
+    let mut largest = INTEGRALITY_TOL;
+    for (j, (&xj, integrality)) in x.iter().zip(integrality).enumerate() {
+        if *integrality == Integrality::Integer {
+            let fraction = xj - xj.floor();
+            let infeasibility = fraction.min(1f64 - fraction);
+            if infeasibility > largest {
+                largest = infeasibility;
+                branch = Some(Branch::Integer(j, xj));
+            }
+        }
+    }
+    if branch.is_some() {
+        return branch;
+    }
+    x.iter()
+        .zip(integrality)
+        .enumerate()
+        .position(|(j, (&xj, integrality))| {
+            *integrality == Integrality::SemiContinuous
+                && xj.abs() > INTEGRALITY_TOL
+                && (xj < semi_lb[j] - INTEGRALITY_TOL || xj > semi_ub[j] + INTEGRALITY_TOL)
+        })
+        .map(Branch::SemiContinuous)
+}
+
+/// Gomory mixed-integer cuts `a x <= b` derived from the rows of the optimal tableau whose basic
+/// variable is integer with a fractional value
+///
+/// Every nonbasic variable is written as a non-negative distance `t` to the bound it is at, a
+/// tableau row then reads `x_k + sum(a_j t_j) = x_k*` and the cut `sum(g_j t_j) >= 1` is mapped
+/// back to the structural variables.
+#[allow(clippy::too_many_lines)]
+fn gomory_cuts(lp: &LinearProgram, solution: &Solution, integer: &[bool]) -> Vec<(Vec<f64>, f64)> {
+    let n = lp.c.len();
+    let m = lp.b.len();
+    let column = |j: usize, i: usize| {
+        if j < n {
+            lp.a[i][j]
+        } else if j - n == i {
+            1f64
+        } else {
+            0f64
+        }
+    };
+    let basis_matrix: Vec<Vec<f64>> = (0..m)
+        .map(|i| solution.basis.iter().map(|&j| column(j, i)).collect())
+        .collect();
+    let Some(basis_inverse) = inverse(&basis_matrix) else {
+        return Vec::new();
+    };
+    let mut is_basic = vec![false; n + m];
+    for &j in &solution.basis {
+        is_basic[j] = true;
+    }
+    let lower = |j: usize| if j < n { lp.lb[j] } else { 0f64 };
+    let upper = |j: usize| {
+        if j < n {
+            lp.ub[j]
+        } else if j - n < lp.n_ub {
+            f64::INFINITY
+        } else {
+            0f64
+        }
+    };
+    let ax = mat_vec(&lp.a, &solution.x);
+    let value = |j: usize| {
+        if j < n {
+            solution.x[j]
+        } else {
+            lp.b[j - n] - ax[j - n]
+        }
+    };
+
+    let mut cuts = Vec::new();
+    'rows: for (r, &k) in solution.basis.iter().enumerate() {
+        if k >= n || !integer[k] {
+            continue;
+        }
+        let f0 = solution.x[k] - solution.x[k].floor();
+        if !(MIN_CUT_FRACTION..=1f64 - MIN_CUT_FRACTION).contains(&f0) {
+            continue;
+        }
+        let rho = &basis_inverse[r];
+        let mut alpha = vec![0f64; n];
+        let mut gamma = 1f64;
+        for j in 0..n + m {
+            let (l, u) = (lower(j), upper(j));
+            if is_basic[j] || u <= l {
+                continue;
+            }
+            let a_bar = if j < n {
+                lp.a.iter()
+                    .zip(rho)
+                    .map(|(row, rho_i)| row[j] * rho_i)
+                    .sum()
+            } else {
+                rho[j - n]
+            };
+            let at_upper = match (l.is_finite(), u.is_finite()) {
+                (true, true) => (value(j) - u).abs() < (value(j) - l).abs(),
+                (false, true) => true,
+                (true, false) => false,
+                // a free nonbasic variable cannot be written as a distance to a bound
+                (false, false) => continue 'rows,
+            };
+            let a_prime = if at_upper { -a_bar } else { a_bar };
+            let g = if j < n && integer[j] {
+                let fj = a_prime - a_prime.floor();
+                if fj <= f0 {
+                    fj / f0
+                } else {
+                    (1f64 - fj) / (1f64 - f0)
+                }
+            } else if a_prime >= 0f64 {
+                a_prime / f0
+            } else {
+                -a_prime / (1f64 - f0)
+            };
+            if j >= n {
+                // the logical of an inequality row is its slack b_i - a_i x
+                for (alpha_i, a_ij) in alpha.iter_mut().zip(&lp.a[j - n]) {
+                    *alpha_i -= g * a_ij;
+                }
+                gamma -= g * lp.b[j - n];
+            } else if at_upper {
+                alpha[j] -= g;
+                gamma -= g * u;
+            } else {
+                alpha[j] += g;
+                gamma += g * l;
+            }
+        }
+
+        // drop negligible coefficients of bounded variables, relaxing the right hand side
+        let largest = alpha.iter().fold(0f64, |acc, a| acc.max(a.abs()));
+        if largest <= LP_TOL {
+            continue;
+        }
+        for (j, alpha_j) in alpha.iter_mut().enumerate() {
+            if alpha_j.abs() <= 1e-12 * largest {
+                let worst = (*alpha_j * lp.lb[j]).max(*alpha_j * lp.ub[j]);
+                if worst.is_finite() {
+                    gamma -= worst;
+                    *alpha_j = 0f64;
+                }
+            }
+        }
+        let violation = (gamma - dot(&alpha, &solution.x)) / largest;
+        if violation > INTEGRALITY_TOL {
+            cuts.push((
+                alpha.iter().map(|a| -a / largest).collect(),
+                -gamma / largest,
+            ));
+        }
+    }
+    cuts
+}
+
+/// Mixed-integer linear programming
+///
+/// Minimise `c^T x` subject to linear constraints `lb_i <= A_i x <= ub_i`, bounds
+/// `lb <= x <= ub` and the integrality constraint of each variable. Semi-continuous variables
+/// are either zero or within their bounds, which defaults to the continuous relaxation when the
+/// bounds contain zero.
+///
+/// # Parameters
+/// * `c` - coefficients of the objective
+/// * `integrality` - integrality constraint of each variable
+/// * `constraints` - linear constraints on the variables
+/// * `bounds` - optional bounds on the variables, defaults to `x >= 0`
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `c` is empty, `integrality`, the constraint
+/// matrices or bounds have the wrong size, the coefficients are not finite or the gap tolerance
+/// is negative
+#[allow(clippy::too_many_lines)]
+pub async fn milp(
+    c: &[f64],
+    integrality: &[Integrality],
+    constraints: &[LinearConstraint],
+    bounds: Option<&Bounds>,
+    options: MilpOptions,
+) -> Result<MilpResult, SwoopErrors> {
+    let n = c.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must have at least one variable",
+        )));
+    }
+    if c.iter().any(|ci| !ci.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must be finite",
+        )));
+    }
+    if integrality.len() != n {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Integrality must have one entry per variable",
+        )));
+    }
+    for constraint in constraints {
+        if constraint.a().iter().any(|row| row.len() != n) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Constraint matrices must have one column per variable",
+            )));
+        }
+        if constraint.a().iter().flatten().any(|v| !v.is_finite()) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Constraint matrices must be finite",
+            )));
+        }
+    }
+    let (semi_lb, semi_ub) = match bounds {
+        Some(bounds) => {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            (bounds.lb().to_vec(), bounds.ub().to_vec())
+        }
+        None => (vec![0f64; n], vec![f64::INFINITY; n]),
+    };
+    if options.mip_rel_gap.is_nan() || options.mip_rel_gap < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The relative gap tolerance must be non-negative",
+        )));
+    }
+
+    let start = Instant::now();
+    let out_of_time = || {
+        options
+            .time_limit
+            .is_some_and(|limit| start.elapsed() >= limit)
+    };
+
+    // the relaxation of a semi-continuous variable includes zero, integer variables have
+    // integer bounds
+    let mut lb = semi_lb.clone();
+    let mut ub = semi_ub.clone();
+    for (j, integrality) in integrality.iter().enumerate() {
+        match integrality {
+            Integrality::Continuous => {}
+            Integrality::Integer => {
+                lb[j] = (lb[j] - INTEGRALITY_TOL).ceil();
+                ub[j] = (ub[j] + INTEGRALITY_TOL).floor();
+            }
+            Integrality::SemiContinuous => {
+                lb[j] = lb[j].min(0f64);
+                ub[j] = ub[j].max(0f64);
+            }
+        }
+    }
+    if lb.iter().zip(&ub).any(|(l, u)| l > u) {
+        return Ok(MilpResult {
+            x: None,
+            fun: None,
+            mip_dual_bound: f64::INFINITY,
+            mip_gap: f64::INFINITY,
+            status: MilpStatus::Infeasible,
+            success: false,
+            mip_node_count: 0,
+        });
+    }
+
+    // two sided constraints are split into inequalities, equal bounds give an equality
+    let mut a_ub = Vec::new();
+    let mut b_ub = Vec::new();
+    let mut a_eq = Vec::new();
+    let mut b_eq = Vec::new();
+    for constraint in constraints {
+        for ((row, &l), &u) in constraint
+            .a()
+            .iter()
+            .zip(constraint.lb())
+            .zip(constraint.ub())
+        {
+            if l >= u {
+                a_eq.push(row.clone());
+                b_eq.push(u);
+                continue;
+            }
+            if u.is_finite() {
+                a_ub.push(row.clone());
+                b_ub.push(u);
+            }
+            if l.is_finite() {
+                a_ub.push(row.iter().map(|v| -v).collect());
+                b_ub.push(-l);
+            }
+        }
+    }
+    let mut lp = LinearProgram {
+        c: c.to_vec(),
+        n_ub: a_ub.len(),
+        a: a_ub.into_iter().chain(a_eq).collect(),
+        b: b_ub.into_iter().chain(b_eq).collect(),
+        lb: lb.clone(),
+        ub: ub.clone(),
+    };
+
+    // rounds of cuts at the root until the bound stalls
+    let integer: Vec<bool> = integrality
+        .iter()
+        .map(|integrality| *integrality == Integrality::Integer)
+        .collect();
+    let mut root = solve_relaxation(&lp);
+    for _ in 0..options.cut_rounds {
+        if root.status != LinprogStatus::Optimal || out_of_time() {
+            break;
+        }
+        let cuts = gomory_cuts(&lp, &root, &integer);
+        if cuts.is_empty() {
+            break;
+        }
+        for (row, rhs) in cuts {
+            lp.a.insert(lp.n_ub, row);
+            lp.b.insert(lp.n_ub, rhs);
+            lp.n_ub += 1;
+        }
+        let objective = dot(c, &root.x);
+        root = solve_relaxation(&lp);
+        if dot(c, &root.x) - objective <= 1e-6 * objective.abs().max(1f64) {
+            break;
+        }
+    }
+
+    let mut root = Some(root);
+    let mut nodes = BinaryHeap::from([Node {
+        bound: f64::NEG_INFINITY,
+        depth: 0,
+        lb,
+        ub,
+    }]);
+    let mut best: Option<Vec<f64>> = None;
+    let mut best_fun = f64::INFINITY;
+    // lowest bound of the nodes whose relaxation could not be solved
+    let mut unresolved_bound = f64::INFINITY;
+    let mut mip_node_count = 0;
+    let mut status = None;
+    while let Some(node) = nodes.pop() {
+        if relative_gap(best_fun, node.bound.min(unresolved_bound)) <= options.mip_rel_gap {
+            nodes.push(node);
+            status = Some(MilpStatus::Optimal);
+            break;
+        }
+        if out_of_time() {
+            nodes.push(node);
+            status = Some(MilpStatus::TimeLimit);
+            break;
+        }
+        if options
+            .node_limit
+            .is_some_and(|limit| mip_node_count >= limit)
+        {
+            nodes.push(node);
+            status = Some(MilpStatus::NodeLimit);
+            break;
+        }
+
+        lp.lb.clone_from(&node.lb);
+        lp.ub.clone_from(&node.ub);
+        let solution = root.take().unwrap_or_else(|| solve_relaxation(&lp));
+        mip_node_count += 1;
+        match solution.status {
+            LinprogStatus::Optimal => {}
+            LinprogStatus::Infeasible => continue,
+            LinprogStatus::Unbounded if node.depth == 0 => {
+                status = Some(MilpStatus::Unbounded);
+                unresolved_bound = f64::NEG_INFINITY;
+                break;
+            }
+            _ => {
+                unresolved_bound = unresolved_bound.min(node.bound);
+                continue;
+            }
+        }
+        let objective = dot(c, &solution.x);
+        if relative_gap(best_fun, objective) <= options.mip_rel_gap {
+            continue;
+        }
+
+        match select_branch(&solution.x, integrality, &semi_lb, &semi_ub) {
+            None => {
+                let mut x = solution.x;
+                for (xj, integrality) in x.iter_mut().zip(integrality) {
+                    match integrality {
+                        Integrality::Integer => *xj = xj.round(),
+                        Integrality::SemiContinuous if xj.abs() <= INTEGRALITY_TOL => *xj = 0f64,
+                        Integrality::Continuous | Integrality::SemiContinuous => {}
+                    }
+                }
+                best_fun = dot(c, &x);
+                best = Some(x);
+            }
+            Some(branch) => {
+                let mut down = (node.lb.clone(), node.ub.clone());
+                let mut up = down.clone();
+                match branch {
+                    Branch::Integer(j, xj) => {
+                        down.1[j] = xj.floor();
+                        up.0[j] = xj.ceil();
+                    }
+                    Branch::SemiContinuous(j) => {
+                        down.0[j] = 0f64;
+                        down.1[j] = 0f64;
+                        up.0[j] = semi_lb[j];
+                        up.1[j] = semi_ub[j];
+                    }
+                }
+                for (lb, ub) in [down, up] {
+                    nodes.push(Node {
+                        bound: objective,
+                        depth: node.depth + 1,
+                        lb,
+                        ub,
+                    });
+                }
+            }
+        }
+    }
+
+    let status = status.unwrap_or(if unresolved_bound < f64::INFINITY {
+        MilpStatus::NumericalDifficulties
+    } else if best.is_some() {
+        MilpStatus::Optimal
+    } else {
+        MilpStatus::Infeasible
+    });
+    let mip_dual_bound = nodes
+        .peek()
+        .map_or(f64::INFINITY, |node| node.bound)
+        .min(unresolved_bound)
+        .min(best_fun);
+    Ok(MilpResult {
+        fun: best.as_ref().map(|_| best_fun),
+        x: best,
+        mip_dual_bound,
+        mip_gap: relative_gap(best_fun, mip_dual_bound),
+        status,
+        success: status == MilpStatus::Optimal,
+        mip_node_count,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    fn options(cut_rounds: usize) -> MilpOptions {
+        MilpOptions {
+            cut_rounds,
+            ..MilpOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scipy_example() -> Result<(), SwoopErrors> {
+        // example of the scipy documentation, the relaxation has the optimum (1.8, 2.8)
+        let constraints = [LinearConstraint::new(
+            vec![vec![-1f64, 1f64], vec![3f64, 2f64], vec![2f64, 3f64]],
+            vec![f64::NEG_INFINITY; 3],
+            vec![1f64, 12f64, 12f64],
+        )?];
+        for cut_rounds in [0, 10] {
+            let result = milp(
+                &[0f64, -1f64],
+                &[Integrality::Integer; 2],
+                &constraints,
+                None,
+                options(cut_rounds),
+            )
+            .await?;
+            assert!(result.success);
+            assert_eq!(result.status, MilpStatus::Optimal);
+            assert!(relative_eq!(result.fun.unwrap(), -2f64));
+            assert!(relative_eq!(result.x.unwrap()[1], 2f64));
+            assert!(result.mip_dual_bound <= -2f64 + 1e-9);
+            assert!(result.mip_gap <= 1e-4);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_knapsack() -> Result<(), SwoopErrors> {
+        let values = [10f64, 13f64, 18f64, 31f64, 7f64, 15f64];
+        let constraints = [LinearConstraint::new(
+            vec![vec![11f64, 15f64, 20f64, 35f64, 10f64, 33f64]],
+            vec![f64::NEG_INFINITY],
+            vec![47f64],
+        )?];
+        let bounds = Bounds::new(vec![0f64; 6], vec![1f64; 6])?;
+        for cut_rounds in [0, 10] {
+            let result = milp(
+                &values.map(|v| -v),
+                &[Integrality::Integer; 6],
+                &constraints,
+                Some(&bounds),
+                options(cut_rounds),
+            )
+            .await?;
+            assert!(result.success);
+            // (1, 1, 1, 0, 0, 0) and (1, 0, 0, 1, 0, 0) are both optimal
+            assert!(relative_eq!(result.fun.unwrap(), -41f64));
+            let x = result.x.unwrap();
+            assert!(x.iter().all(|xj| relative_eq!(xj * (1f64 - xj), 0f64)));
+            assert!(dot(&x, &constraints[0].a()[0]) <= 47f64);
+        }
+
+        let result = milp(
+            &values.map(|v| -v),
+            &[Integrality::Integer; 6],
+            &constraints,
+            Some(&bounds),
+            MilpOptions {
+                node_limit: Some(1),
+                cut_rounds: 0,
+                ..MilpOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(result.status, MilpStatus::NodeLimit);
+        assert!(!result.success);
+        assert_eq!(result.mip_node_count, 1);
+        assert!(result.mip_dual_bound <= -41f64);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semi_continuous() -> Result<(), SwoopErrors> {
+        // x0 is either zero or in [2, 5], the relaxation has the optimum (1, 0)
+        let constraints = [LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![1f64],
+            vec![f64::INFINITY],
+        )?];
+        let bounds = Bounds::new(vec![2f64, 0f64], vec![5f64, 10f64])?;
+        let integrality = [Integrality::SemiContinuous, Integrality::Continuous];
+        let result = milp(
+            &[1f64, 3f64],
+            &integrality,
+            &constraints,
+            Some(&bounds),
+            MilpOptions::default(),
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.fun.unwrap(), 2f64, epsilon = 1e-9));
+        assert!(relative_eq!(result.x.unwrap()[0], 2f64, epsilon = 1e-9));
+
+        // with a cheaper x1 the semi-continuous variable is switched off
+        let result = milp(
+            &[1f64, 1.5],
+            &integrality,
+            &constraints,
+            Some(&bounds),
+            MilpOptions::default(),
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.fun.unwrap(), 1.5, epsilon = 1e-9));
+        assert!(relative_eq!(result.x.unwrap()[0], 0f64));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_infeasible_and_unbounded() -> Result<(), SwoopErrors> {
+        // 2 x = 1 has no integer solution although its relaxation does
+        let constraints = [LinearConstraint::new(
+            vec![vec![2f64]],
+            vec![1f64],
+            vec![1f64],
+        )?];
+        let result = milp(
+            &[1f64],
+            &[Integrality::Integer],
+            &constraints,
+            None,
+            MilpOptions::default(),
+        )
+        .await?;
+        assert_eq!(result.status, MilpStatus::Infeasible);
+        assert!(result.x.is_none());
+        assert!(result.mip_gap.is_infinite());
+
+        let result = milp(
+            &[-1f64],
+            &[Integrality::Integer],
+            &[],
+            None,
+            MilpOptions::default(),
+        )
+        .await?;
+        assert_eq!(result.status, MilpStatus::Unbounded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let options = MilpOptions::default();
+        assert!(milp(&[], &[], &[], None, options).await.is_err());
+        assert!(milp(&[1f64], &[], &[], None, options).await.is_err());
+        let constraints =
+            [LinearConstraint::new(vec![vec![1f64, 1f64]], vec![0f64], vec![1f64]).unwrap()];
+        assert!(milp(
+            &[1f64],
+            &[Integrality::Integer],
+            &constraints,
+            None,
+            options
+        )
+        .await
+        .is_err());
+        let options = MilpOptions {
+            mip_rel_gap: -1f64,
+            ..MilpOptions::default()
+        };
+        assert!(milp(&[1f64], &[Integrality::Integer], &[], None, options)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,418 @@
+//! Linear programming
+//!
+//! Minimise `c^T x` subject to `A_ub x <= b_ub`, `A_eq x = b_eq` and `lb <= x <= ub`, with
+//! `milp` for problems in which some of the variables are integer or semi-continuous.
+
+mod dual_simplex;
+mod interior_point;
+mod milp;
+
+pub use milp::{milp, Integrality, MilpOptions, MilpResult, MilpStatus};
+
+use crate::constraints::Bounds;
+use crate::linalg::{dot, mat_vec};
+use crate::SwoopErrors;
+
+/// Algorithm used by `linprog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LinprogMethod {
+    /// Revised dual simplex method with bounded variables, returns a vertex of the feasible
+    /// region
+    #[default]
+    DualSimplex,
+    /// Homogeneous self-dual primal-dual interior point method with Mehrotra predictor-corrector
+    /// steps. A problem whose primal and dual are both infeasible may be reported as unbounded
+    InteriorPoint,
+}
+
+/// Options for the `linprog` solver
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LinprogOptions {
+    /// Algorithm to use
+    pub method: LinprogMethod,
+    /// Maximum iterations, defaults to `max(1000, 10 * (m + n))` for `DualSimplex` and `1000`
+    /// for `InteriorPoint`
+    pub maxiter: Option<usize>,
+    /// Tolerance for feasibility and optimality, defaults to `1e-9` for `DualSimplex` and
+    /// `1e-8` for `InteriorPoint`
+    pub tol: Option<f64>,
+}
+
+/// Reason the `linprog` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinprogStatus {
+    /// An optimal solution was found
+    Optimal,
+    /// The maximum number of iterations was exceeded
+    IterationLimit,
+    /// The problem has no feasible solution
+    Infeasible,
+    /// The objective is unbounded below on the feasible region
+    Unbounded,
+    /// The solver ran into numerical difficulties
+    NumericalDifficulties,
+}
+
+/// Struct to represent the result of a `linprog` optimisation
+#[derive(Debug, Clone)]
+pub struct LinprogResult {
+    /// The solution of the optimisation
+    pub x: Vec<f64>,
+    /// Value of the objective `c^T x`
+    pub fun: f64,
+    /// Slack of the inequality constraints `b_ub - A_ub x`
+    pub slack: Vec<f64>,
+    /// Residuals of the equality constraints `b_eq - A_eq x`
+    pub con: Vec<f64>,
+    /// Sensitivity of the objective to `b_ub`, non-positive at the optimum
+    pub ineqlin_marginals: Vec<f64>,
+    /// Sensitivity of the objective to `b_eq`
+    pub eqlin_marginals: Vec<f64>,
+    /// Sensitivity of the objective to the lower bounds, non-negative at the optimum
+    pub lower_marginals: Vec<f64>,
+    /// Sensitivity of the objective to the upper bounds, non-positive at the optimum
+    pub upper_marginals: Vec<f64>,
+    /// Reason the solver terminated
+    pub status: LinprogStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// Number of iterations
+    pub nit: usize,
+}
+
+/// Linear program `min c^T x` subject to `A x <= b` for the first `n_ub` rows, `A x = b` for the
+/// remaining rows and `lb <= x <= ub`
+struct LinearProgram {
+    c: Vec<f64>,
+    a: Vec<Vec<f64>>,
+    b: Vec<f64>,
+    n_ub: usize,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+/// Solution found by one of the methods
+struct Solution {
+    x: Vec<f64>,
+    row_duals: Vec<f64>,
+    lower_marginals: Vec<f64>,
+    upper_marginals: Vec<f64>,
+    status: LinprogStatus,
+    nit: usize,
+    /// Basic variables of the final basis, the logical of row `i` has index `n + i`. Empty for
+    /// the interior point method
+    basis: Vec<usize>,
+}
+
+/// Check that a constraint matrix and its right hand side have consistent sizes and are finite
+pub(crate) fn check_constraints(a: &[Vec<f64>], b: &[f64], n: usize) -> Result<(), SwoopErrors> {
+    if a.len() != b.len() || a.iter().any(|row| row.len() != n) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Constraint matrices must have one column per variable and one row per entry of the \
+             right hand side",
+        )));
+    }
+    if a.iter().flatten().chain(b).any(|v| !v.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Constraints must be finite",
+        )));
+    }
+    Ok(())
+}
+
+/// Linear programming
+///
+/// Minimise `c^T x` subject to `A_ub x <= b_ub`, `A_eq x = b_eq` and `lb <= x <= ub`.
+///
+/// # Parameters
+/// * `c` - coefficients of the objective
+/// * `a_ub` - inequality constraint matrix, one row per constraint
+/// * `b_ub` - upper bounds of the inequality constraints
+/// * `a_eq` - equality constraint matrix, one row per constraint
+/// * `b_eq` - right hand side of the equality constraints
+/// * `bounds` - optional bounds on the variables, defaults to `x >= 0`
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `c` is empty, the constraint matrices or bounds
+/// have the wrong size, the coefficients are not finite or the tolerance is not positive
+pub async fn linprog(
+    c: &[f64],
+    a_ub: &[Vec<f64>],
+    b_ub: &[f64],
+    a_eq: &[Vec<f64>],
+    b_eq: &[f64],
+    bounds: Option<&Bounds>,
+    options: LinprogOptions,
+) -> Result<LinprogResult, SwoopErrors> {
+    let n = c.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must have at least one variable",
+        )));
+    }
+    if c.iter().any(|ci| !ci.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must be finite",
+        )));
+    }
+    check_constraints(a_ub, b_ub, n)?;
+    check_constraints(a_eq, b_eq, n)?;
+    let (lb, ub) = match bounds {
+        Some(bounds) => {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            (bounds.lb().to_vec(), bounds.ub().to_vec())
+        }
+        None => (vec![0f64; n], vec![f64::INFINITY; n]),
+    };
+    if options.tol.is_some_and(|tol| tol <= 0f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance must be positive",
+        )));
+    }
+
+    let lp = LinearProgram {
+        c: c.to_vec(),
+        a: a_ub.iter().chain(a_eq).cloned().collect(),
+        b: b_ub.iter().chain(b_eq).copied().collect(),
+        n_ub: a_ub.len(),
+        lb,
+        ub,
+    };
+    let solution = match options.method {
+        LinprogMethod::DualSimplex => dual_simplex::dual_simplex(
+            &lp,
+            options.tol.unwrap_or(1e-9),
+            options
+                .maxiter
+                .unwrap_or_else(|| usize::max(1000, 10 * (lp.a.len() + n))),
+        ),
+        LinprogMethod::InteriorPoint => interior_point::interior_point(
+            &lp,
+            options.tol.unwrap_or(1e-8),
+            options.maxiter.unwrap_or(1000),
+        ),
+    };
+
+    let residuals: Vec<f64> =
+        lp.b.iter()
+            .zip(mat_vec(&lp.a, &solution.x))
+            .map(|(bi, ax)| bi - ax)
+            .collect();
+    let (slack, con) = residuals.split_at(lp.n_ub);
+    let (ineqlin_marginals, eqlin_marginals) = solution.row_duals.split_at(lp.n_ub);
+    Ok(LinprogResult {
+        fun: dot(c, &solution.x),
+        slack: slack.to_vec(),
+        con: con.to_vec(),
+        ineqlin_marginals: ineqlin_marginals.to_vec(),
+        eqlin_marginals: eqlin_marginals.to_vec(),
+        lower_marginals: solution.lower_marginals,
+        upper_marginals: solution.upper_marginals,
+        status: solution.status,
+        success: solution.status == LinprogStatus::Optimal,
+        nit: solution.nit,
+        x: solution.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    const METHODS: [LinprogMethod; 2] = [LinprogMethod::DualSimplex, LinprogMethod::InteriorPoint];
+
+    fn options(method: LinprogMethod) -> LinprogOptions {
+        LinprogOptions {
+            method,
+            ..LinprogOptions::default()
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                relative_eq!(a, e, epsilon = 1e-6),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_chvatal() -> Result<(), SwoopErrors> {
+        // Chvátal, Linear Programming, chapter 2: maximise 5 x1 + 4 x2 + 3 x3
+        let a_ub = vec![
+            vec![2f64, 3f64, 1f64],
+            vec![4f64, 1f64, 2f64],
+            vec![3f64, 4f64, 2f64],
+        ];
+        for method in METHODS {
+            let result = linprog(
+                &[-5f64, -4f64, -3f64],
+                &a_ub,
+                &[5f64, 11f64, 8f64],
+                &[],
+                &[],
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -13f64, epsilon = 1e-6));
+            assert_close(&result.x, &[2f64, 0f64, 1f64]);
+            assert_close(&result.slack, &[0f64, 1f64, 0f64]);
+            assert_close(&result.ineqlin_marginals, &[-1f64, 0f64, -1f64]);
+            assert_close(&result.lower_marginals, &[0f64, 3f64, 0f64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wyndor_glass() -> Result<(), SwoopErrors> {
+        // Hillier and Lieberman, Introduction to Operations Research, section 3.1
+        let a_ub = vec![vec![1f64, 0f64], vec![0f64, 2f64], vec![3f64, 2f64]];
+        for method in METHODS {
+            let result = linprog(
+                &[-3f64, -5f64],
+                &a_ub,
+                &[4f64, 12f64, 18f64],
+                &[],
+                &[],
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -36f64, epsilon = 1e-6));
+            assert_close(&result.x, &[2f64, 6f64]);
+            assert_close(&result.ineqlin_marginals, &[0f64, -1.5, -1f64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_free_and_bounded_variables() -> Result<(), SwoopErrors> {
+        // example of the scipy documentation, with a free variable
+        let bounds = Bounds::new(vec![f64::NEG_INFINITY, -3f64], vec![f64::INFINITY; 2])?;
+        for method in METHODS {
+            let result = linprog(
+                &[-1f64, 4f64],
+                &[vec![-3f64, 1f64], vec![1f64, 2f64]],
+                &[6f64, 4f64],
+                &[],
+                &[],
+                Some(&bounds),
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -22f64, epsilon = 1e-6));
+            assert_close(&result.x, &[10f64, -3f64]);
+            assert_close(&result.ineqlin_marginals, &[0f64, -1f64]);
+            assert_close(&result.lower_marginals, &[0f64, 6f64]);
+            assert_close(&result.upper_marginals, &[0f64, 0f64]);
+        }
+
+        // upper bounds and a variable bounded only above
+        let bounds = Bounds::new(vec![0f64, f64::NEG_INFINITY], vec![3f64, 1f64])?;
+        for method in METHODS {
+            let result = linprog(
+                &[-1f64, -1f64],
+                &[vec![1f64, -1f64]],
+                &[10f64],
+                &[],
+                &[],
+                Some(&bounds),
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -4f64, epsilon = 1e-6));
+            assert_close(&result.x, &[3f64, 1f64]);
+            assert_close(&result.upper_marginals, &[-1f64, -1f64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transportation() -> Result<(), SwoopErrors> {
+        // two plants with supplies 20 and 30 and three markets with demands 10, 25 and 15
+        let cost = [8f64, 6f64, 10f64, 9f64, 12f64, 13f64];
+        let a_eq = vec![
+            vec![1f64, 1f64, 1f64, 0f64, 0f64, 0f64],
+            vec![0f64, 0f64, 0f64, 1f64, 1f64, 1f64],
+            vec![1f64, 0f64, 0f64, 1f64, 0f64, 0f64],
+            vec![0f64, 1f64, 0f64, 0f64, 1f64, 0f64],
+            vec![0f64, 0f64, 1f64, 0f64, 0f64, 1f64],
+        ];
+        let b_eq = [20f64, 30f64, 10f64, 25f64, 15f64];
+        for method in METHODS {
+            let result = linprog(&cost, &[], &[], &a_eq, &b_eq, None, options(method)).await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, 465f64, epsilon = 1e-6));
+            assert_close(&result.x, &[0f64, 20f64, 0f64, 10f64, 5f64, 15f64]);
+            assert_close(&result.con, &[0f64; 5]);
+            // the duals of the redundant balance constraint are not unique
+            let y = &result.eqlin_marginals;
+            assert!(relative_eq!(y[0] + y[3], 6f64, epsilon = 1e-6));
+            assert!(relative_eq!(y[1] + y[2], 9f64, epsilon = 1e-6));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_infeasible_and_unbounded() -> Result<(), SwoopErrors> {
+        for method in METHODS {
+            let result = linprog(
+                &[1f64, 1f64],
+                &[vec![1f64, 1f64]],
+                &[-1f64],
+                &[],
+                &[],
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(!result.success);
+            assert_eq!(result.status, LinprogStatus::Infeasible);
+
+            let result = linprog(
+                &[-1f64, 0f64],
+                &[vec![1f64, -1f64]],
+                &[1f64],
+                &[],
+                &[],
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(!result.success);
+            assert_eq!(result.status, LinprogStatus::Unbounded);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let result = linprog(&[], &[], &[], &[], &[], None, LinprogOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = linprog(
+            &[1f64, 1f64],
+            &[vec![1f64]],
+            &[1f64],
+            &[],
+            &[],
+            None,
+            LinprogOptions::default(),
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,650 @@
+//! Basin-hopping
+//!
+//! Each iteration perturbs the current minimum with a random step, minimises locally from the
+//! perturbed point and accepts the new local minimum with the Metropolis criterion: downhill
+//! moves are always accepted and uphill moves with probability `exp(-(f_new - f_old) / T)`. The
+//! step size adapts to reach a target acceptance rate.
+//!
+//! # References
+//! * Wales and Doye, "Global Optimization by Basin-Hopping and the Lowest Energy Structures of
+//!   Lennard-Jones Clusters Containing up to 110 Atoms", Journal of Physical Chemistry A 101.28
+//!   (1997)
+
+use super::Shifted;
+use crate::constraints::Bounds;
+use crate::minimise_multivariate::{
+    minimise, MinimiseOptions, MultivariateCallback, MultivariateMethod,
+    MultivariateObjectiveFunction, MultivariateOptimisationResult,
+};
+use crate::minimise_scalar::{bounded, brent, ScalarOptimisationResult};
+use crate::random::Rng;
+use crate::SwoopErrors;
+
+/// Maximum iterations of the scalar local minimisers
+const SCALAR_MAXITER: usize = 500;
+
+/// Trait to implement for a custom step of `basinhopping`
+pub trait TakeStep {
+    /// Method to implement the random displacement of the current minimum `x`, returning the
+    /// starting point of the next local minimisation
+    fn take_step(&mut self, x: &[f64]) -> Vec<f64>;
+
+    /// Method to implement the current step size. If `Some` is returned the step size is
+    /// adapted to the target acceptance rate through `set_stepsize`
+    fn stepsize(&self) -> Option<f64> {
+        None
+    }
+
+    /// Method to implement the update of the step size
+    fn set_stepsize(&mut self, _stepsize: f64) {}
+}
+
+/// Decision of a custom acceptance test of `basinhopping`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Acceptance {
+    /// Reject the new minimum
+    Reject,
+    /// Accept the new minimum if the Metropolis criterion accepts it as well
+    Accept,
+    /// Accept the new minimum regardless of the Metropolis criterion, for example to escape a
+    /// region where the search is trapped
+    ForceAccept,
+}
+
+/// Trait to implement for a custom acceptance test of `basinhopping`
+pub trait AcceptTest {
+    /// Method to implement the test of the new local minimum `x_new` with value `f_new` against
+    /// the current minimum `x_old` with value `f_old`
+    fn accept(&mut self, x_new: &[f64], f_new: f64, x_old: &[f64], f_old: f64) -> Acceptance;
+}
+
+/// Local minimiser used by `basinhopping`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LocalMinimiser {
+    /// Brent's method for univariate functions, started at the perturbed point
+    Brent,
+    /// Bounded univariate minimisation within `[lb, ub]`, searching the interval of the current
+    /// step size around the perturbed point
+    Bounded {
+        /// Lower bound
+        lb: f64,
+        /// Upper bound
+        ub: f64,
+    },
+    /// Multivariate method of `minimise`
+    Multivariate(MultivariateMethod),
+}
+
+impl Default for LocalMinimiser {
+    fn default() -> Self {
+        Self::Multivariate(MultivariateMethod::TrustConstr)
+    }
+}
+
+/// Options for the `basinhopping` solver
+#[derive(Debug, Clone)]
+pub struct BasinhoppingOptions {
+    /// Number of basin-hopping iterations, each one a step and a local minimisation
+    pub niter: usize,
+    /// Temperature `T` of the Metropolis criterion, of the order of the difference between
+    /// local minima. Zero only accepts downhill moves
+    pub temperature: f64,
+    /// Initial maximum displacement of each variable by the default random step
+    pub stepsize: f64,
+    /// Number of iterations between updates of the step size
+    pub interval: usize,
+    /// Acceptance rate the step size is adapted to
+    pub target_accept_rate: f64,
+    /// Factor in `(0, 1)` that the step size is multiplied or divided by at each update
+    pub stepwise_factor: f64,
+    /// Stop when the lowest minimum has not improved for this many iterations
+    pub niter_success: Option<usize>,
+    /// Seed of the random number generator, results are reproducible for a given seed
+    pub seed: Option<u64>,
+    /// Local minimiser
+    pub minimiser: LocalMinimiser,
+    /// Bounds passed to a multivariate local minimiser
+    pub bounds: Option<Bounds>,
+}
+
+impl Default for BasinhoppingOptions {
+    fn default() -> Self {
+        Self {
+            niter: 100,
+            temperature: 1f64,
+            stepsize: 0.5,
+            interval: 50,
+            target_accept_rate: 0.5,
+            stepwise_factor: 0.9,
+            niter_success: None,
+            seed: None,
+            minimiser: LocalMinimiser::default(),
+            bounds: None,
+        }
+    }
+}
+
+/// Reason the `basinhopping` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasinhoppingStatus {
+    /// All iterations were performed
+    IterationsCompleted,
+    /// The lowest minimum did not improve for `niter_success` iterations
+    NoImprovement,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+}
+
+/// Local minimum found by `basinhopping`
+#[derive(Debug, Clone)]
+pub struct LocalMinimum {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Whether the minimum was accepted as the new current minimum
+    pub accepted: bool,
+    /// Location of the minimum
+    pub x: Vec<f64>,
+}
+
+/// Struct to represent the result of a `basinhopping` optimisation
+#[derive(Debug, Clone)]
+pub struct BasinhoppingResult {
+    /// Value of the objective function at the lowest minimum
+    pub fun: f64,
+    /// Result of the local minimisation that found the lowest minimum
+    pub lowest_optimisation_result: MultivariateOptimisationResult,
+    /// Every local minimum in the order they were found, starting with the minimum from the
+    /// initial guess
+    pub minima: Vec<LocalMinimum>,
+    /// Number of local minimisations that did not succeed
+    pub minimisation_failures: usize,
+    /// Number of evaluations of the objective function
+    pub nfev: usize,
+    /// Number of basin-hopping iterations
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: BasinhoppingStatus,
+    /// Whether the local minimisation of the lowest minimum was successful or not
+    pub success: bool,
+    /// The lowest minimum
+    pub x: Vec<f64>,
+}
+
+/// Default step, a uniform random displacement of every variable by at most the step size
+struct RandomDisplacement<'a> {
+    rng: &'a mut Rng,
+    stepsize: f64,
+}
+
+impl TakeStep for RandomDisplacement<'_> {
+    fn take_step(&mut self, x: &[f64]) -> Vec<f64> {
+        x.iter()
+            .map(|xi| xi + self.rng.uniform_in(-self.stepsize, self.stepsize))
+            .collect()
+    }
+
+    fn stepsize(&self) -> Option<f64> {
+        Some(self.stepsize)
+    }
+
+    fn set_stepsize(&mut self, stepsize: f64) {
+        self.stepsize = stepsize;
+    }
+}
+
+/// Result of a univariate local minimisation as a multivariate result
+fn from_scalar(result: &ScalarOptimisationResult, x: f64) -> MultivariateOptimisationResult {
+    MultivariateOptimisationResult {
+        fun: result.fun,
+        jac: None,
+        nfev: result.nfev,
+        njev: 0,
+        nhev: 0,
+        nit: 0,
+        success: result.success,
+        message: String::new(),
+        warnings: Vec::new(),
+        x: vec![x],
+    }
+}
+
+/// Minimise locally from `x`, `half_width` is the half width of the search interval of the
+/// bounded univariate minimiser
+async fn local_minimise<T: MultivariateObjectiveFunction>(
+    objective_function: &T,
+    x: &[f64],
+    options: &BasinhoppingOptions,
+    half_width: f64,
+) -> Result<MultivariateOptimisationResult, SwoopErrors> {
+    match options.minimiser {
+        LocalMinimiser::Brent => {
+            let shifted = Shifted {
+                objective: objective_function,
+                origin: x[0],
+            };
+            let result = brent(shifted, None, SCALAR_MAXITER).await?;
+            Ok(from_scalar(&result, x[0] + result.x))
+        }
+        LocalMinimiser::Bounded { lb, ub } => {
+            let centre = x[0].clamp(lb, ub);
+            let interval = Bounds::new(
+                vec![(centre - half_width).max(lb)],
+                vec![(centre + half_width).min(ub)],
+            )?;
+            let scalar = Shifted {
+                objective: objective_function,
+                origin: 0f64,
+            };
+            let result = bounded(scalar, &interval, SCALAR_MAXITER).await?;
+            Ok(from_scalar(&result, result.x))
+        }
+        LocalMinimiser::Multivariate(method) => {
+            minimise(
+                objective_function,
+                x,
+                method,
+                MinimiseOptions {
+                    bounds: options.bounds.as_ref(),
+                    jac: objective_function.gradient(x).is_some(),
+                    ..MinimiseOptions::default()
+                },
+            )
+            .await
+        }
+    }
+}
+
+/// Check the options of `basinhopping`
+fn check_options(options: &BasinhoppingOptions, n: usize) -> Result<(), SwoopErrors> {
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    if options.temperature < 0f64 || options.temperature.is_nan() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The temperature cannot be negative",
+        )));
+    }
+    if options.stepsize <= 0f64 || options.interval == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The step size and the update interval must be positive",
+        )));
+    }
+    if options.stepwise_factor <= 0f64 || options.stepwise_factor >= 1f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The stepwise factor must be in (0, 1)",
+        )));
+    }
+    match options.minimiser {
+        LocalMinimiser::Brent | LocalMinimiser::Bounded { .. } if n != 1 => {
+            Err(SwoopErrors::ArgumentError(String::from(
+                "Univariate local minimisers require a single variable",
+            )))
+        }
+        LocalMinimiser::Bounded { lb, ub } if lb > ub => Err(SwoopErrors::ArgumentError(
+            String::from("The lower bound exceeds the upper bound"),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Basin-hopping global optimisation
+///
+/// Find the global minimum of a function by repeated random perturbations of the current
+/// minimum, each followed by a local minimisation. Suited to functions whose local minima are
+/// separated by barriers, such as the energy landscapes of molecular clusters.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `x0` - initial guess
+/// * `options` - solver options
+/// * `take_step` - optional custom step replacing the uniform random displacement, its step
+///   size is adapted if it reports one
+/// * `accept_test` - optional acceptance test in addition to the Metropolis criterion
+/// * `callback` - optional callback called with every new local minimum, returning `true`
+///   stops the solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the temperature is negative, the
+/// step size, interval or stepwise factor are out of range, a univariate local minimiser is
+/// used with several variables, or the local minimiser rejects its arguments
+#[allow(clippy::too_many_lines)]
+#[allow(clippy::cast_precision_loss)]
+pub async fn basinhopping<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    x0: &[f64],
+    options: BasinhoppingOptions,
+    take_step: Option<&mut dyn TakeStep>,
+    mut accept_test: Option<&mut dyn AcceptTest>,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<BasinhoppingResult, SwoopErrors> {
+    check_options(&options, x0.len())?;
+    let mut rng = Rng::new(options.seed);
+    let mut step_rng = Rng::new(Some(rng.next_u64()));
+    let mut displacement = RandomDisplacement {
+        rng: &mut step_rng,
+        stepsize: options.stepsize,
+    };
+    let step: &mut dyn TakeStep = match take_step {
+        Some(step) => step,
+        None => &mut displacement,
+    };
+
+    let half_width = |step: &dyn TakeStep| step.stepsize().unwrap_or(options.stepsize);
+    let first = local_minimise(&objective_function, x0, &options, half_width(step)).await?;
+    let mut nfev = first.nfev;
+    let mut minimisation_failures = usize::from(!first.success);
+    let mut minima = vec![LocalMinimum {
+        fun: first.fun,
+        accepted: true,
+        x: first.x.clone(),
+    }];
+    let mut x = first.x.clone();
+    let mut fun = first.fun;
+    let mut lowest = first;
+
+    let mut nit = 0;
+    let mut naccept = 0usize;
+    let mut without_improvement = 0;
+    let mut status = BasinhoppingStatus::IterationsCompleted;
+    while nit < options.niter {
+        nit += 1;
+        let x_trial = step.take_step(&x);
+        let result =
+            local_minimise(&objective_function, &x_trial, &options, half_width(step)).await?;
+        nfev += result.nfev;
+        if !result.success {
+            minimisation_failures += 1;
+        }
+
+        let acceptance = match accept_test.as_mut() {
+            Some(test) => test.accept(&result.x, result.fun, &x, fun),
+            None => Acceptance::Accept,
+        };
+        let accepted = match acceptance {
+            Acceptance::Reject => false,
+            // Metropolis criterion
+            Acceptance::Accept => {
+                result.fun <= fun
+                    || (options.temperature > 0f64
+                        && rng.uniform() < (-(result.fun - fun) / options.temperature).exp())
+            }
+            Acceptance::ForceAccept => true,
+        };
+
+        let mut improved = false;
+        if accepted {
+            naccept += 1;
+            x.clone_from(&result.x);
+            fun = result.fun;
+            if fun < lowest.fun {
+                improved = true;
+            }
+        }
+        minima.push(LocalMinimum {
+            fun: result.fun,
+            accepted,
+            x: result.x.clone(),
+        });
+        if improved {
+            lowest = result;
+        }
+
+        // adapt the step size to the target acceptance rate
+        if nit % options.interval == 0 {
+            if let Some(stepsize) = step.stepsize() {
+                let accept_rate = naccept as f64 / nit as f64;
+                step.set_stepsize(if accept_rate > options.target_accept_rate {
+                    stepsize / options.stepwise_factor
+                } else {
+                    stepsize * options.stepwise_factor
+                });
+            }
+        }
+
+        if let Some(callback) = callback.as_mut() {
+            let newest = &minima[minima.len() - 1];
+            if callback(&newest.x, newest.fun) {
+                status = BasinhoppingStatus::CallbackTerminated;
+                break;
+            }
+        }
+        if let Some(niter_success) = options.niter_success {
+            without_improvement = if improved { 0 } else { without_improvement + 1 };
+            if without_improvement >= niter_success {
+                status = BasinhoppingStatus::NoImprovement;
+                break;
+            }
+        }
+    }
+
+    Ok(BasinhoppingResult {
+        fun: lowest.fun,
+        x: lowest.x.clone(),
+        success: lowest.success,
+        lowest_optimisation_result: lowest,
+        minima,
+        minimisation_failures,
+        nfev,
+        nit,
+        status,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Example of the scipy documentation, many local minima along the first variable and the
+    /// global minimum near `x = (-0.195, -0.1, ...)`
+    struct Wavy {}
+
+    impl MultivariateObjectiveFunction for Wavy {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            (14.5 * x[0] - 0.3).cos() + x.iter().map(|xi| (xi + 0.2) * xi).sum::<f64>()
+        }
+    }
+
+    fn options(minimiser: LocalMinimiser) -> BasinhoppingOptions {
+        BasinhoppingOptions {
+            niter: 200,
+            seed: Some(1),
+            minimiser,
+            ..BasinhoppingOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_univariate() -> Result<(), SwoopErrors> {
+        for minimiser in [
+            LocalMinimiser::Brent,
+            LocalMinimiser::Bounded {
+                lb: -2f64,
+                ub: 2f64,
+            },
+            LocalMinimiser::Multivariate(MultivariateMethod::NelderMead),
+        ] {
+            let result =
+                basinhopping(Wavy {}, &[1f64], options(minimiser), None, None, None).await?;
+            assert!(relative_eq!(result.x[0], -0.195_068, epsilon = 1e-4));
+            assert!(relative_eq!(result.fun, -1.000_876_2, epsilon = 1e-6));
+            assert_eq!(result.minima.len(), 201);
+            assert_eq!(result.status, BasinhoppingStatus::IterationsCompleted);
+            assert!(result.success);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_multivariate() -> Result<(), SwoopErrors> {
+        let result = basinhopping(
+            Wavy {},
+            &[1f64, 1f64],
+            options(LocalMinimiser::default()),
+            None,
+            None,
+            None,
+        )
+        .await?;
+        assert!(relative_eq!(result.x[0], -0.195_068, epsilon = 1e-4));
+        assert!(relative_eq!(result.x[1], -0.1, epsilon = 1e-4));
+        assert!(relative_eq!(result.fun, -1.010_876_2, epsilon = 1e-6));
+        assert!(result.minima.iter().any(|m| !m.accepted));
+        assert!(result.minima.iter().all(|m| m.fun >= result.fun));
+
+        // the same seed gives the same minima
+        let seeded = || BasinhoppingOptions {
+            niter: 20,
+            ..options(LocalMinimiser::Multivariate(MultivariateMethod::NelderMead))
+        };
+        let first = basinhopping(Wavy {}, &[1f64, 1f64], seeded(), None, None, None).await?;
+        let second = basinhopping(Wavy {}, &[1f64, 1f64], seeded(), None, None, None).await?;
+        let values = |r: &BasinhoppingResult| r.minima.iter().map(|m| m.fun).collect::<Vec<f64>>();
+        assert_eq!(values(&first), values(&second));
+        assert_eq!(first.nfev, second.nfev);
+        Ok(())
+    }
+
+    /// Step that moves the first variable by a fixed amount and records the step sizes it is
+    /// given
+    struct Stepper {
+        stepsize: f64,
+        history: Vec<f64>,
+    }
+
+    impl TakeStep for Stepper {
+        fn take_step(&mut self, x: &[f64]) -> Vec<f64> {
+            vec![x[0] + self.stepsize]
+        }
+
+        fn stepsize(&self) -> Option<f64> {
+            Some(self.stepsize)
+        }
+
+        fn set_stepsize(&mut self, stepsize: f64) {
+            self.history.push(stepsize);
+            self.stepsize = stepsize;
+        }
+    }
+
+    /// Rejects minima outside `[lb, ub]`
+    struct Within {
+        lb: f64,
+        ub: f64,
+    }
+
+    impl AcceptTest for Within {
+        fn accept(&mut self, x_new: &[f64], _: f64, _: &[f64], _: f64) -> Acceptance {
+            if x_new.iter().all(|x| *x >= self.lb && *x <= self.ub) {
+                Acceptance::Accept
+            } else {
+                Acceptance::Reject
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_step_and_accept() -> Result<(), SwoopErrors> {
+        let mut stepper = Stepper {
+            stepsize: 0.2,
+            history: Vec::new(),
+        };
+        let mut within = Within { lb: 0f64, ub: 3f64 };
+        let result = basinhopping(
+            Wavy {},
+            &[1f64],
+            BasinhoppingOptions {
+                niter: 40,
+                interval: 10,
+                temperature: 0f64,
+                ..options(LocalMinimiser::Brent)
+            },
+            Some(&mut stepper),
+            Some(&mut within),
+            None,
+        )
+        .await?;
+        assert_eq!(stepper.history.len(), 4);
+        assert!(result.x[0] >= 0f64 && result.x[0] <= 3f64);
+        // without uphill moves the accepted minima only decrease
+        let accepted: Vec<f64> = result
+            .minima
+            .iter()
+            .filter(|m| m.accepted)
+            .map(|m| m.fun)
+            .collect();
+        assert!(accepted.windows(2).all(|w| w[1] <= w[0]));
+
+        let mut calls = 0;
+        let mut callback = |_: &[f64], _: f64| {
+            calls += 1;
+            calls == 5
+        };
+        let result = basinhopping(
+            Wavy {},
+            &[1f64],
+            options(LocalMinimiser::Brent),
+            None,
+            None,
+            Some(&mut callback),
+        )
+        .await?;
+        assert_eq!(result.status, BasinhoppingStatus::CallbackTerminated);
+        assert_eq!(result.nit, 5);
+
+        let result = basinhopping(
+            Wavy {},
+            &[1f64],
+            BasinhoppingOptions {
+                niter_success: Some(10),
+                ..options(LocalMinimiser::Brent)
+            },
+            None,
+            None,
+            None,
+        )
+        .await?;
+        assert_eq!(result.status, BasinhoppingStatus::NoImprovement);
+        assert!(result.nit < 200);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let invalid = [
+            BasinhoppingOptions {
+                temperature: -1f64,
+                ..BasinhoppingOptions::default()
+            },
+            BasinhoppingOptions {
+                stepsize: 0f64,
+                ..BasinhoppingOptions::default()
+            },
+            BasinhoppingOptions {
+                stepwise_factor: 1f64,
+                ..BasinhoppingOptions::default()
+            },
+            BasinhoppingOptions {
+                minimiser: LocalMinimiser::Brent,
+                ..BasinhoppingOptions::default()
+            },
+        ];
+        for options in invalid {
+            let result = basinhopping(Wavy {}, &[1f64, 1f64], options, None, None, None).await;
+            assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        }
+        let result = basinhopping(
+            Wavy {},
+            &[1f64],
+            options(LocalMinimiser::Bounded { lb: 1f64, ub: 0f64 }),
+            None,
+            None,
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,303 @@
+//! Brute force minimisation over a grid
+//!
+//! The objective function is evaluated at every point of a regular grid, the Cartesian product of
+//! one range of values per variable. The best grid point can then be polished by a local
+//! minimiser, `bounded` between the neighbouring grid points for a single variable and
+//! `nelder_mead` within the grid otherwise. Evaluating the whole grid is a useful check of the
+//! basin found by a local method, at a cost that grows exponentially with the dimension.
+
+use super::Shifted;
+use crate::constraints::Bounds;
+use crate::minimise_multivariate::{nelder_mead, MultivariateObjectiveFunction, NelderMeadOptions};
+use crate::minimise_scalar::bounded;
+use crate::SwoopErrors;
+
+/// Maximum iterations of the univariate polish
+const SCALAR_MAXITER: usize = 500;
+
+/// Values taken by one variable of the grid
+#[derive(Debug, Clone, Copy)]
+pub enum BruteRange {
+    /// `start`, `start + step`, ... up to but excluding `stop`
+    Slice {
+        /// First value
+        start: f64,
+        /// Values stop before this one
+        stop: f64,
+        /// Spacing of the values, may be negative
+        step: f64,
+    },
+    /// `ns` evenly spaced values from `lo` to `hi` inclusive
+    Points {
+        /// First value
+        lo: f64,
+        /// Last value
+        hi: f64,
+        /// Number of values
+        ns: usize,
+    },
+}
+
+impl BruteRange {
+    /// Values of the range
+    #[allow(clippy::cast_precision_loss)]
+    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_possible_truncation)]
+    fn values(&self) -> Result<Vec<f64>, SwoopErrors> {
+        match *self {
+            Self::Slice { start, stop, step } => {
+                let count = ((stop - start) / step).ceil();
+                if step == 0f64 || !count.is_finite() || count < 1f64 {
+                    return Err(SwoopErrors::ArgumentError(String::from(
+                        "A slice must have a nonzero step and contain at least one value",
+                    )));
+                }
+                Ok((0..count as usize)
+                    .map(|k| start + k as f64 * step)
+                    .collect())
+            }
+            Self::Points { lo, hi, ns } => {
+                if ns == 0 || !lo.is_finite() || !hi.is_finite() {
+                    return Err(SwoopErrors::ArgumentError(String::from(
+                        "A range must have finite limits and at least one value",
+                    )));
+                }
+                if ns == 1 {
+                    return Ok(vec![lo]);
+                }
+                let spacing = (hi - lo) / (ns - 1) as f64;
+                Ok((0..ns).map(|k| lo + k as f64 * spacing).collect())
+            }
+        }
+    }
+}
+
+/// Struct to represent the result of a `brute` optimisation
+#[derive(Debug, Clone)]
+pub struct BruteResult {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Values of each variable on the grid
+    pub axes: Vec<Vec<f64>>,
+    /// Objective function on the grid, in row-major order where the last variable varies
+    /// fastest
+    pub values: Vec<f64>,
+    /// Number of evaluations of the objective function, including the polish
+    pub nfev: usize,
+    /// Whether the polish improved on the best grid point
+    pub polished: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Brute force minimisation over a grid
+///
+/// Evaluate the objective function on the grid of the ranges and return the best point, polished
+/// by a local minimiser if `finish` is set. The polished point is kept only if it improves on the
+/// best grid point.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `ranges` - values taken by each variable
+/// * `finish` - polish the best grid point with `bounded` for a single variable and
+///   `nelder_mead` otherwise
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if there are no ranges or a range has no values
+pub async fn brute<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    ranges: &[BruteRange],
+    finish: bool,
+) -> Result<BruteResult, SwoopErrors> {
+    if ranges.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "At least one range is required",
+        )));
+    }
+    let axes = ranges
+        .iter()
+        .map(BruteRange::values)
+        .collect::<Result<Vec<Vec<f64>>, SwoopErrors>>()?;
+    let count: usize = axes.iter().map(Vec::len).product();
+    let grid_point = |mut k: usize| -> Vec<usize> {
+        let mut index = vec![0; axes.len()];
+        for (i, axis) in index.iter_mut().zip(&axes).rev() {
+            *i = k % axis.len();
+            k /= axis.len();
+        }
+        index
+    };
+    let point = |index: &[usize]| -> Vec<f64> {
+        index.iter().zip(&axes).map(|(i, axis)| axis[*i]).collect()
+    };
+
+    let points: Vec<Vec<f64>> = (0..count).map(|k| point(&grid_point(k))).collect();
+    let values = objective_function.evaluate_batch(&points);
+    let mut best = 0;
+    for (k, value) in values.iter().enumerate() {
+        if *value < values[best] || values[best].is_nan() {
+            best = k;
+        }
+    }
+    let index = grid_point(best);
+    let mut x = point(&index);
+    let mut fun = values[best];
+    let mut nfev = count;
+    let mut polished = false;
+
+    if finish {
+        let (lb, ub): (Vec<f64>, Vec<f64>) = axes
+            .iter()
+            .map(|axis| {
+                let (first, last) = (axis[0], axis[axis.len() - 1]);
+                (first.min(last), first.max(last))
+            })
+            .unzip();
+        let (x_local, fun_local, nfev_local) = if let [axis] = axes.as_slice() {
+            // the basin of the best point lies between its neighbours
+            let i = index[0];
+            let (a, b) = (axis[i.saturating_sub(1)], axis[(i + 1).min(axis.len() - 1)]);
+            let scalar = Shifted {
+                objective: &objective_function,
+                origin: 0f64,
+            };
+            let interval = Bounds::new(vec![a.min(b)], vec![a.max(b)])?;
+            let result = bounded(scalar, &interval, SCALAR_MAXITER).await?;
+            (vec![result.x], result.fun, result.nfev)
+        } else {
+            let bounds = Bounds::new(lb, ub)?;
+            let result = nelder_mead(
+                &objective_function,
+                &x,
+                Some(&bounds),
+                NelderMeadOptions::default(),
+                None,
+            )
+            .await?;
+            (result.x, result.fun, result.nfev)
+        };
+        nfev += nfev_local;
+        if fun_local < fun || fun.is_nan() {
+            x = x_local;
+            fun = fun_local;
+            polished = true;
+        }
+    }
+
+    Ok(BruteResult {
+        fun,
+        axes,
+        values,
+        nfev,
+        polished,
+        x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::minimise_scalar::{brent, ScalarObjectiveFunction};
+    use approx::relative_eq;
+
+    /// Double well with the global minimum at `x = -2.0305466` and a local one near `x = 2`
+    struct DoubleWell {}
+
+    impl MultivariateObjectiveFunction for DoubleWell {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            (x[0].powi(2) - 4f64).powi(2) + x[0]
+        }
+    }
+
+    impl ScalarObjectiveFunction for DoubleWell {
+        fn evaluate(&self, x: f64) -> f64 {
+            (x.powi(2) - 4f64).powi(2) + x
+        }
+    }
+
+    /// Six-hump camel function, with global minima at `(0.0898, -0.7126)` and `(-0.0898, 0.7126)`
+    struct Camel {}
+
+    impl MultivariateObjectiveFunction for Camel {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            (4f64 - 2.1 * x[0].powi(2) + x[0].powi(4) / 3f64) * x[0].powi(2)
+                + x[0] * x[1]
+                + (-4f64 + 4f64 * x[1].powi(2)) * x[1].powi(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_double_well() -> Result<(), SwoopErrors> {
+        // brent brackets from 0 and 1 and goes downhill into the local minimum
+        let local = brent(DoubleWell {}, None, 500).await?;
+        assert!(local.x > 0f64);
+
+        let ranges = [BruteRange::Points {
+            lo: -3f64,
+            hi: 3f64,
+            ns: 25,
+        }];
+        let result = brute(DoubleWell {}, &ranges, false).await?;
+        assert_eq!(result.values.len(), 25);
+        assert_eq!(result.nfev, 25);
+        assert!(!result.polished);
+        assert!(relative_eq!(result.x[0], -2f64));
+        assert!(relative_eq!(result.fun, -2f64));
+
+        let result = brute(DoubleWell {}, &ranges, true).await?;
+        assert!(result.polished);
+        assert!(result.nfev > 25);
+        assert!(relative_eq!(result.x[0], -2.030_546_6, epsilon = 1e-5));
+        assert!(relative_eq!(result.fun, -2.015_388_2, epsilon = 1e-8));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_camel() -> Result<(), SwoopErrors> {
+        let range = BruteRange::Slice {
+            start: -2f64,
+            stop: 2f64,
+            step: 0.25,
+        };
+        let result = brute(Camel {}, &[range, range], false).await?;
+        assert_eq!(result.axes[0].len(), 16);
+        assert_eq!(result.values.len(), 256);
+        // row-major order, the second variable varies fastest
+        assert!(relative_eq!(
+            result.values[1],
+            Camel {}.evaluate(&[-2f64, -1.75])
+        ));
+        // of the two symmetric minima on the grid the first is returned
+        assert_eq!(result.x, vec![0f64, -0.75]);
+
+        let result = brute(Camel {}, &[range, range], true).await?;
+        assert!(result.polished);
+        assert!(relative_eq!(result.x[0], 0.089_842, epsilon = 1e-3));
+        assert!(relative_eq!(result.x[1], -0.712_656, epsilon = 1e-3));
+        assert!(relative_eq!(result.fun, -1.031_628_4, epsilon = 1e-6));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let result = brute(Camel {}, &[], true).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let empty = BruteRange::Slice {
+            start: 1f64,
+            stop: 0f64,
+            step: 0.5,
+        };
+        let result = brute(DoubleWell {}, &[empty], true).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let no_points = BruteRange::Points {
+            lo: 0f64,
+            hi: 1f64,
+            ns: 0,
+        };
+        let result = brute(DoubleWell {}, &[no_points], true).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,1072 @@
+//! Differential evolution
+//!
+//! A population of candidate solutions evolves by mutation and crossover: for each member a
+//! mutant vector is formed from scaled differences of other members, crossed over with the
+//! member, and the resulting trial vector replaces the member if it is better. The population
+//! is kept in the unit hypercube and scaled to the bounds for evaluation. Constraints are
+//! handled with the feasibility rules of Lampinen or with a penalty on the violation.
+//!
+//! # References
+//! * Storn and Price, "Differential Evolution - a simple and efficient heuristic for global
+//!   optimization over continuous spaces", Journal of Global Optimization 11 (1997)
+//! * Lampinen, "A constraint handling approach for the differential evolution algorithm",
+//!   Proceedings of the 2002 Congress on Evolutionary Computation (2002)
+
+use super::{sampling, FEASIBILITY_TOL};
+use crate::constraints::{Bounds, Constraint};
+use crate::minimise_multivariate::{
+    minimise, MinimiseOptions, MultivariateCallback, MultivariateMethod,
+    MultivariateObjectiveFunction,
+};
+use crate::random::Rng;
+use crate::SwoopErrors;
+
+/// Strategy used by `differential_evolution` to create trial vectors
+///
+/// The first part of the name is the mutant vector, for example `Best1` adds one scaled
+/// difference of two random members to the best member and `CurrentToBest1` moves the member
+/// towards the best member. The mutant is crossed over with the member by binomial (`Bin`) or
+/// exponential (`Exp`) crossover.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DifferentialEvolutionStrategy {
+    /// `best + F (r0 - r1)` with binomial crossover
+    #[default]
+    Best1Bin,
+    /// `best + F (r0 - r1)` with exponential crossover
+    Best1Exp,
+    /// `r0 + F (r1 - r2)` with binomial crossover
+    Rand1Bin,
+    /// `r0 + F (r1 - r2)` with exponential crossover
+    Rand1Exp,
+    /// `r0 + F (r1 + r2 - r3 - r4)` with binomial crossover
+    Rand2Bin,
+    /// `r0 + F (r1 + r2 - r3 - r4)` with exponential crossover
+    Rand2Exp,
+    /// `r0 + F (best - r0) + F (r1 - r2)` with binomial crossover
+    RandToBest1Bin,
+    /// `r0 + F (best - r0) + F (r1 - r2)` with exponential crossover
+    RandToBest1Exp,
+    /// `x + F (best - x + r0 - r1)` with binomial crossover
+    CurrentToBest1Bin,
+    /// `x + F (best - x + r0 - r1)` with exponential crossover
+    CurrentToBest1Exp,
+    /// `best + F (r0 + r1 - r2 - r3)` with binomial crossover
+    Best2Bin,
+    /// `best + F (r0 + r1 - r2 - r3)` with exponential crossover
+    Best2Exp,
+}
+
+impl DifferentialEvolutionStrategy {
+    /// Whether the strategy uses binomial crossover, otherwise it uses exponential crossover
+    fn binomial(self) -> bool {
+        matches!(
+            self,
+            Self::Best1Bin
+                | Self::Rand1Bin
+                | Self::Rand2Bin
+                | Self::RandToBest1Bin
+                | Self::CurrentToBest1Bin
+                | Self::Best2Bin
+        )
+    }
+
+    /// Number of random members used by the mutation
+    fn samples(self) -> usize {
+        match self {
+            Self::Best1Bin | Self::Best1Exp | Self::CurrentToBest1Bin | Self::CurrentToBest1Exp => {
+                2
+            }
+            Self::Rand1Bin | Self::Rand1Exp | Self::RandToBest1Bin | Self::RandToBest1Exp => 3,
+            Self::Best2Bin | Self::Best2Exp => 4,
+            Self::Rand2Bin | Self::Rand2Exp => 5,
+        }
+    }
+
+    /// Mutant vector for the member `candidate`, where `r` are distinct random members other
+    /// than the candidate and the best member is the first of the population
+    fn mutant(self, population: &[Vec<f64>], candidate: usize, r: &[usize], f: f64) -> Vec<f64> {
+        let best = &population[0];
+        let current = &population[candidate];
+        let p = |k: usize, j: usize| population[r[k]][j];
+        (0..current.len())
+            .map(|j| match self {
+                Self::Best1Bin | Self::Best1Exp => best[j] + f * (p(0, j) - p(1, j)),
+                Self::Rand1Bin | Self::Rand1Exp => p(0, j) + f * (p(1, j) - p(2, j)),
+                Self::Rand2Bin | Self::Rand2Exp => {
+                    p(0, j) + f * (p(1, j) + p(2, j) - p(3, j) - p(4, j))
+                }
+                Self::RandToBest1Bin | Self::RandToBest1Exp => {
+                    p(0, j) + f * (best[j] - p(0, j)) + f * (p(1, j) - p(2, j))
+                }
+                Self::CurrentToBest1Bin | Self::CurrentToBest1Exp => {
+                    current[j] + f * (best[j] - current[j] + p(0, j) - p(1, j))
+                }
+                Self::Best2Bin | Self::Best2Exp => {
+                    best[j] + f * (p(0, j) + p(1, j) - p(2, j) - p(3, j))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Mutation constant `F` of `differential_evolution`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mutation {
+    /// The same mutation constant in every generation
+    Constant(f64),
+    /// Dithering, the mutation constant is drawn uniformly from `[low, high)` in every
+    /// generation, which can speed up convergence significantly
+    Dithering(f64, f64),
+}
+
+impl Default for Mutation {
+    fn default() -> Self {
+        Self::Dithering(0.5, 1f64)
+    }
+}
+
+/// Initialisation of the population of `differential_evolution`
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum PopulationInit {
+    /// Latin hypercube sampling, which covers the whole parameter space
+    #[default]
+    LatinHypercube,
+    /// Sobol sequence, the population size is rounded up to the next power of two. Supports at
+    /// most 21 variables
+    Sobol,
+    /// Halton sequence
+    Halton,
+    /// Uniform random sampling
+    Random,
+    /// Population given by the user, one member per row with at least five members and more
+    /// members than the strategy samples. Members outside the bounds are clipped
+    Population(Vec<Vec<f64>>),
+}
+
+/// When `differential_evolution` updates the best member
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Updating {
+    /// The best member is updated as soon as a better trial vector is found, within the
+    /// generation
+    #[default]
+    Immediate,
+    /// The population is updated once per generation, after all trial vectors are evaluated
+    /// as one batch, which `Parallel` objective functions evaluate in parallel
+    Deferred,
+}
+
+/// Handling of the constraints by `differential_evolution`
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ConstraintHandling {
+    /// Feasible members are preferred over infeasible ones, feasible members are compared by
+    /// the objective function and infeasible members by their constraint violations. The
+    /// objective function is only evaluated at feasible points
+    #[default]
+    FeasibilityRules,
+    /// The sum of the constraint violations multiplied by the given weight is added to the
+    /// objective function
+    Penalty(f64),
+}
+
+/// Options for the `differential_evolution` solver
+#[derive(Debug, Clone)]
+pub struct DifferentialEvolutionOptions {
+    /// Strategy to create trial vectors
+    pub strategy: DifferentialEvolutionStrategy,
+    /// Maximum number of generations
+    pub maxiter: usize,
+    /// Multiplier of the population size, the population has `max(5, popsize * n)` members, and
+    /// at least six for the `Rand2` strategies that sample five members besides the candidate
+    pub popsize: usize,
+    /// Relative tolerance for convergence, the population has converged when the standard
+    /// deviation of the objective values is at most `atol + tol * |mean|`
+    pub tol: f64,
+    /// Absolute tolerance for convergence
+    pub atol: f64,
+    /// Mutation constant, in `[0, 2)`
+    pub mutation: Mutation,
+    /// Crossover probability, in `[0, 1]`
+    pub recombination: f64,
+    /// Seed of the random number generator, results are reproducible for a given seed
+    pub seed: Option<u64>,
+    /// Local method that polishes the best member at the end, `None` to skip polishing
+    pub polish: Option<MultivariateMethod>,
+    /// Initialisation of the population
+    pub init: PopulationInit,
+    /// Initial guess that replaces the first member of the population
+    pub x0: Option<Vec<f64>>,
+    /// When the best member is updated
+    pub updating: Updating,
+    /// Handling of the constraints
+    pub constraint_handling: ConstraintHandling,
+    /// Maximum evaluations of the objective function, excluding polishing
+    pub maxfun: Option<usize>,
+}
+
+impl Default for DifferentialEvolutionOptions {
+    fn default() -> Self {
+        Self {
+            strategy: DifferentialEvolutionStrategy::default(),
+            maxiter: 1000,
+            popsize: 15,
+            tol: 0.01,
+            atol: 0f64,
+            mutation: Mutation::default(),
+            recombination: 0.7,
+            seed: None,
+            polish: Some(MultivariateMethod::TrustConstr),
+            init: PopulationInit::default(),
+            x0: None,
+            updating: Updating::default(),
+            constraint_handling: ConstraintHandling::default(),
+            maxfun: None,
+        }
+    }
+}
+
+/// Reason the `differential_evolution` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifferentialEvolutionStatus {
+    /// The objective values of the population converged
+    Converged,
+    /// The maximum number of generations was reached
+    MaxIterExceeded,
+    /// The maximum number of evaluations of the objective function was reached
+    MaxFunExceeded,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+    /// No member of the population satisfies the constraints
+    Infeasible,
+}
+
+/// Struct to represent the result of a `differential_evolution` optimisation
+#[derive(Debug, Clone)]
+pub struct DifferentialEvolutionResult {
+    /// Value of the objective function, infinite if no feasible solution was found with the
+    /// feasibility rules
+    pub fun: f64,
+    /// Maximum constraint violation at the solution
+    pub constr_violation: f64,
+    /// Final population, one member per row
+    pub population: Vec<Vec<f64>>,
+    /// Objective value of each member of the population, including the penalty of the
+    /// constraint violation if any
+    pub population_energies: Vec<f64>,
+    /// Number of evaluations of the objective function, including polishing
+    pub nfev: usize,
+    /// Number of generations
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: DifferentialEvolutionStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Member of the population, in the unit hypercube
+#[derive(Debug, Clone)]
+struct Member {
+    x: Vec<f64>,
+    /// Objective function, infinite if it was not evaluated
+    fun: f64,
+    /// Value compared between members, the objective function plus any penalty
+    energy: f64,
+    /// Violation of each constraint
+    violation: Vec<f64>,
+    /// Whether the member is compared as a feasible member
+    feasible: bool,
+}
+
+impl Member {
+    /// Whether the member should replace `other`
+    fn better_than(&self, other: &Self) -> bool {
+        match (self.feasible, other.feasible) {
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => self.energy <= other.energy,
+            (false, false) => self
+                .violation
+                .iter()
+                .zip(&other.violation)
+                .all(|(a, b)| a <= b),
+        }
+    }
+
+    /// Maximum constraint violation
+    fn max_violation(&self) -> f64 {
+        self.violation.iter().copied().fold(0f64, f64::max)
+    }
+}
+
+/// The objective function and constraints in the scaled variables, counting evaluations
+struct Problem<'a, T: MultivariateObjectiveFunction> {
+    fun: &'a T,
+    lb: &'a [f64],
+    ub: &'a [f64],
+    constraints: &'a [Constraint],
+    handling: ConstraintHandling,
+    nfev: usize,
+}
+
+impl<T: MultivariateObjectiveFunction> Problem<'_, T> {
+    /// Variables in the bounds of the point `x` of the unit hypercube
+    fn scale(&self, x: &[f64]) -> Vec<f64> {
+        x.iter()
+            .zip(self.lb.iter().zip(self.ub))
+            .map(|(xi, (l, u))| l + xi * (u - l))
+            .collect()
+    }
+
+    /// Evaluate the point `x` of the unit hypercube
+    fn member(&mut self, x: Vec<f64>) -> Member {
+        self.members(&[x], usize::MAX).remove(0)
+    }
+
+    /// Evaluate the points `xs` of the unit hypercube in order while the evaluations of the
+    /// objective function are within `maxfun`, evaluating the objective function as one batch
+    fn members(&mut self, xs: &[Vec<f64>], maxfun: usize) -> Vec<Member> {
+        let mut pending: Vec<Pending> = Vec::with_capacity(xs.len());
+        for x in xs {
+            if self.nfev >= maxfun {
+                break;
+            }
+            let params = self.scale(x);
+            let violation: Vec<f64> = self
+                .constraints
+                .iter()
+                .flat_map(|c| c.violation(&params))
+                .collect();
+            let evaluate = matches!(self.handling, ConstraintHandling::Penalty(_))
+                || violation.iter().all(|v| *v <= 0f64);
+            if evaluate {
+                self.nfev += 1;
+            }
+            pending.push(Pending {
+                params,
+                violation,
+                evaluate,
+            });
+        }
+        let batch: Vec<Vec<f64>> = pending
+            .iter()
+            .filter(|p| p.evaluate)
+            .map(|p| p.params.clone())
+            .collect();
+        let mut values = self.fun.evaluate_batch(&batch).into_iter();
+
+        let handling = self.handling;
+        pending
+            .into_iter()
+            .zip(xs)
+            .map(|(p, x)| {
+                let fun = if p.evaluate {
+                    values.next().unwrap_or(f64::NAN)
+                } else {
+                    f64::INFINITY
+                };
+                let energy = match handling {
+                    ConstraintHandling::Penalty(weight) => {
+                        fun + weight * p.violation.iter().sum::<f64>()
+                    }
+                    ConstraintHandling::FeasibilityRules => fun,
+                };
+                Member {
+                    x: x.clone(),
+                    fun,
+                    // NaN objective values never win a comparison
+                    energy: if energy.is_nan() {
+                        f64::INFINITY
+                    } else {
+                        energy
+                    },
+                    violation: p.violation,
+                    feasible: p.evaluate,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Point of the unit hypercube waiting for the evaluation of the objective function
+struct Pending {
+    params: Vec<f64>,
+    violation: Vec<f64>,
+    evaluate: bool,
+}
+
+/// Move the best member to the front of the population: the feasible member with the lowest
+/// energy, or the member with the lowest total constraint violation if none is feasible
+fn promote_best(population: &mut [Member]) {
+    let best = if population.iter().any(|m| m.feasible) {
+        population
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| m.feasible)
+            .min_by(|a, b| a.1.energy.total_cmp(&b.1.energy))
+            .map(|(i, _)| i)
+    } else {
+        population
+            .iter()
+            .enumerate()
+            .min_by(|a, b| {
+                let a_sum: f64 = a.1.violation.iter().sum();
+                a_sum.total_cmp(&b.1.violation.iter().sum())
+            })
+            .map(|(i, _)| i)
+    };
+    population.swap(0, best.unwrap_or(0));
+}
+
+/// Trial vector for the member `candidate`
+fn trial(
+    rng: &mut Rng,
+    population: &[Vec<f64>],
+    candidate: usize,
+    strategy: DifferentialEvolutionStrategy,
+    f: f64,
+    recombination: f64,
+) -> Vec<f64> {
+    let n = population[candidate].len();
+    let mut indices: Vec<usize> = (0..population.len()).filter(|i| *i != candidate).collect();
+    rng.shuffle(&mut indices);
+    let mutant = strategy.mutant(population, candidate, &indices[..strategy.samples()], f);
+
+    let mut trial = population[candidate].clone();
+    let mut fill_point = rng.below(n);
+    if strategy.binomial() {
+        for (j, (t, m)) in trial.iter_mut().zip(&mutant).enumerate() {
+            if j == fill_point || rng.uniform() < recombination {
+                *t = *m;
+            }
+        }
+    } else {
+        // copy a run of consecutive variables, at least one
+        for i in 0..n {
+            if i > 0 && rng.uniform() >= recombination {
+                break;
+            }
+            trial[fill_point] = mutant[fill_point];
+            fill_point = (fill_point + 1) % n;
+        }
+    }
+    // variables outside the unit hypercube are replaced at random
+    for t in &mut trial {
+        if !(0f64..=1f64).contains(t) {
+            *t = rng.uniform();
+        }
+    }
+    trial
+}
+
+/// Whether the objective values of the population have converged
+#[allow(clippy::cast_precision_loss)]
+fn converged(population: &[Member], tol: f64, atol: f64) -> bool {
+    if population.iter().any(|m| !m.energy.is_finite()) {
+        return false;
+    }
+    let count = population.len() as f64;
+    let mean = population.iter().map(|m| m.energy).sum::<f64>() / count;
+    let variance = population
+        .iter()
+        .map(|m| (m.energy - mean).powi(2))
+        .sum::<f64>()
+        / count;
+    variance.sqrt() <= atol + tol * mean.abs()
+}
+
+/// Check the options of `differential_evolution`
+fn check_options(
+    options: &DifferentialEvolutionOptions,
+    n: usize,
+    lb: &[f64],
+    ub: &[f64],
+    constrained: bool,
+) -> Result<(), SwoopErrors> {
+    let valid_mutation = |f: f64| (0f64..2f64).contains(&f);
+    let mutation_ok = match options.mutation {
+        Mutation::Constant(f) => valid_mutation(f),
+        Mutation::Dithering(low, high) => {
+            valid_mutation(low) && valid_mutation(high) && low <= high
+        }
+    };
+    if !mutation_ok {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The mutation constant must be in [0, 2), with the lower dithering bound first",
+        )));
+    }
+    if !(0f64..=1f64).contains(&options.recombination) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The recombination must be in [0, 1]",
+        )));
+    }
+    if options.tol < 0f64 || options.atol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    if let ConstraintHandling::Penalty(weight) = options.constraint_handling {
+        if weight <= 0f64 || !weight.is_finite() {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The penalty weight must be positive and finite",
+            )));
+        }
+    }
+    if constrained && options.polish == Some(MultivariateMethod::NelderMead) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method NelderMead cannot polish a constrained problem",
+        )));
+    }
+    if let PopulationInit::Population(population) = &options.init {
+        if population.len() < 5
+            || population.len() <= options.strategy.samples()
+            || population.iter().any(|member| member.len() != n)
+        {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The initial population must have at least five members, more than the strategy \
+                 samples, with one entry per variable",
+            )));
+        }
+    }
+    if let Some(x0) = &options.x0 {
+        let inside = x0
+            .iter()
+            .zip(lb.iter().zip(ub))
+            .all(|(x, (l, u))| x >= l && x <= u);
+        if x0.len() != n || !inside {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Initial guess must have one entry per variable and be within the bounds",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Differential evolution global optimisation
+///
+/// Minimise a function over a bounded domain, optionally subject to constraints. The method
+/// does not use gradients and can find the global minimum of multimodal functions, at the cost
+/// of many more evaluations than local methods. The best member is polished by a local method
+/// at the end.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `bounds` - finite bounds on the variables
+/// * `constraints` - linear and nonlinear constraints
+/// * `options` - solver options
+/// * `callback` - optional callback called after each generation with the best member,
+///   returning `true` stops the solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if there are no variables, a bound is infinite, the
+/// mutation constant, recombination, tolerances or penalty weight are out of range, the
+/// initial population or guess has the wrong dimensions, `NelderMead` polishes a constrained
+/// problem, Sobol initialisation is used with more than 21 variables, or the polishing method
+/// rejects its arguments
+#[allow(clippy::too_many_lines)]
+pub async fn differential_evolution<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    bounds: &Bounds,
+    constraints: &[Constraint],
+    options: DifferentialEvolutionOptions,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<DifferentialEvolutionResult, SwoopErrors> {
+    let (lb, ub) = (bounds.lb(), bounds.ub());
+    let n = lb.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must have at least one variable",
+        )));
+    }
+    if lb.iter().chain(ub).any(|b| !b.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must be finite",
+        )));
+    }
+    check_options(&options, n, lb, ub, !constraints.is_empty())?;
+
+    let mut rng = Rng::new(options.seed);
+    let size = (options.popsize * n)
+        .max(5)
+        .max(options.strategy.samples() + 1);
+    let unit = |x: &[f64]| -> Vec<f64> {
+        x.iter()
+            .zip(lb.iter().zip(ub))
+            .map(|(xi, (l, u))| {
+                if u > l {
+                    ((xi - l) / (u - l)).clamp(0f64, 1f64)
+                } else {
+                    0.5
+                }
+            })
+            .collect()
+    };
+    let mut initial = match &options.init {
+        PopulationInit::LatinHypercube => sampling::latin_hypercube(&mut rng, size, n),
+        PopulationInit::Sobol => sampling::sobol(Some(&mut rng), size.next_power_of_two(), n)?,
+        PopulationInit::Halton => sampling::halton(&mut rng, size, n),
+        PopulationInit::Random => sampling::random(&mut rng, size, n),
+        PopulationInit::Population(population) => population.iter().map(|x| unit(x)).collect(),
+    };
+    if let Some(x0) = &options.x0 {
+        initial[0] = unit(x0);
+    }
+
+    let mut problem = Problem {
+        fun: &objective_function,
+        lb,
+        ub,
+        constraints,
+        handling: options.constraint_handling,
+        nfev: 0,
+    };
+    let maxfun = options.maxfun.unwrap_or(usize::MAX);
+    let mut population = problem.members(&initial, maxfun);
+    // members beyond the evaluation budget are never selected
+    population.extend(initial[population.len()..].iter().map(|x| Member {
+        x: x.clone(),
+        fun: f64::INFINITY,
+        energy: f64::INFINITY,
+        violation: vec![f64::INFINITY; constraints.iter().map(|c| c.lb().len()).sum()],
+        feasible: false,
+    }));
+    promote_best(&mut population);
+
+    let mut nit = 0;
+    let mut status = if problem.nfev >= maxfun {
+        DifferentialEvolutionStatus::MaxFunExceeded
+    } else {
+        DifferentialEvolutionStatus::MaxIterExceeded
+    };
+    while status != DifferentialEvolutionStatus::MaxFunExceeded && nit < options.maxiter {
+        nit += 1;
+        let f = match options.mutation {
+            Mutation::Constant(f) => f,
+            Mutation::Dithering(low, high) => rng.uniform_in(low, high),
+        };
+        match options.updating {
+            Updating::Immediate => {
+                for candidate in 0..population.len() {
+                    if problem.nfev >= maxfun {
+                        status = DifferentialEvolutionStatus::MaxFunExceeded;
+                        break;
+                    }
+                    let xs: Vec<Vec<f64>> = population.iter().map(|m| m.x.clone()).collect();
+                    let x = trial(
+                        &mut rng,
+                        &xs,
+                        candidate,
+                        options.strategy,
+                        f,
+                        options.recombination,
+                    );
+                    let member = problem.member(x);
+                    if member.better_than(&population[candidate]) {
+                        population[candidate] = member;
+                        if population[candidate].better_than(&population[0]) {
+                            population.swap(0, candidate);
+                        }
+                    }
+                }
+            }
+            Updating::Deferred => {
+                let xs: Vec<Vec<f64>> = population.iter().map(|m| m.x.clone()).collect();
+                let trials: Vec<Vec<f64>> = (0..population.len())
+                    .map(|candidate| {
+                        trial(
+                            &mut rng,
+                            &xs,
+                            candidate,
+                            options.strategy,
+                            f,
+                            options.recombination,
+                        )
+                    })
+                    .collect();
+                let members = problem.members(&trials, maxfun);
+                if members.len() < trials.len() {
+                    status = DifferentialEvolutionStatus::MaxFunExceeded;
+                }
+                for (candidate, member) in members.into_iter().enumerate() {
+                    if member.better_than(&population[candidate]) {
+                        population[candidate] = member;
+                    }
+                }
+                promote_best(&mut population);
+            }
+        }
+
+        if let Some(callback) = callback.as_mut() {
+            if callback(&problem.scale(&population[0].x), population[0].fun) {
+                status = DifferentialEvolutionStatus::CallbackTerminated;
+                break;
+            }
+        }
+        if converged(&population, options.tol, options.atol) {
+            status = DifferentialEvolutionStatus::Converged;
+            break;
+        }
+    }
+
+    let mut x = problem.scale(&population[0].x);
+    let mut fun = population[0].fun;
+    let mut constr_violation = population[0].max_violation();
+    let mut nfev = problem.nfev;
+
+    if let Some(method) = options.polish {
+        let polished = minimise(
+            &objective_function,
+            &x,
+            method,
+            MinimiseOptions {
+                bounds: Some(bounds),
+                constraints,
+                jac: objective_function.gradient(&x).is_some(),
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        nfev += polished.nfev;
+        let inside = polished
+            .x
+            .iter()
+            .zip(lb.iter().zip(ub))
+            .all(|(xi, (l, u))| xi >= l && xi <= u);
+        let violation = constraints
+            .iter()
+            .flat_map(|c| c.violation(&polished.x))
+            .fold(0f64, f64::max);
+        if inside && polished.fun < fun && violation <= constr_violation.max(FEASIBILITY_TOL) {
+            x = polished.x;
+            fun = polished.fun;
+            constr_violation = violation;
+        }
+    }
+
+    if constr_violation > FEASIBILITY_TOL {
+        status = DifferentialEvolutionStatus::Infeasible;
+    }
+    Ok(DifferentialEvolutionResult {
+        fun,
+        constr_violation,
+        population: population.iter().map(|m| problem.scale(&m.x)).collect(),
+        population_energies: population.iter().map(|m| m.energy).collect(),
+        nfev,
+        nit,
+        success: status == DifferentialEvolutionStatus::Converged,
+        status,
+        x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::LinearConstraint;
+    use approx::relative_eq;
+
+    struct Rosenbrock {}
+
+    impl MultivariateObjectiveFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            x.windows(2)
+                .map(|w| 100f64 * (w[1] - w[0].powi(2)).powi(2) + (1f64 - w[0]).powi(2))
+                .sum()
+        }
+    }
+
+    /// Ackley function, many local minima and the global minimum at the origin
+    struct Ackley {}
+
+    impl MultivariateObjectiveFunction for Ackley {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            let tau = 2f64 * std::f64::consts::PI;
+            let (squares, cosines) = x.iter().fold((0f64, 0f64), |(s, c), xi| {
+                (s + xi * xi, c + (tau * xi).cos())
+            });
+            -20f64 * (-0.2 * (0.5 * squares).sqrt()).exp() - (0.5 * cosines).exp()
+                + std::f64::consts::E
+                + 20f64
+        }
+    }
+
+    fn options(seed: u64) -> DifferentialEvolutionOptions {
+        DifferentialEvolutionOptions {
+            seed: Some(seed),
+            ..DifferentialEvolutionOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rosenbrock() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![0f64; 5], vec![2f64; 5])?;
+        let result = differential_evolution(Rosenbrock {}, &bounds, &[], options(1), None).await?;
+        assert!(result.success);
+        assert_eq!(result.status, DifferentialEvolutionStatus::Converged);
+        for xi in &result.x {
+            assert!(relative_eq!(*xi, 1f64, epsilon = 1e-4));
+        }
+        assert!(result.fun < 1e-8);
+        assert_eq!(result.population.len(), 75);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_small_population() -> Result<(), SwoopErrors> {
+        struct Parabola {}
+
+        impl MultivariateObjectiveFunction for Parabola {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                (x[0] - 0.5).powi(2)
+            }
+        }
+
+        // Rand2 samples five members besides the candidate
+        let bounds = Bounds::new(vec![-2f64], vec![2f64])?;
+        let result = differential_evolution(
+            Parabola {},
+            &bounds,
+            &[],
+            DifferentialEvolutionOptions {
+                strategy: DifferentialEvolutionStrategy::Rand2Bin,
+                popsize: 1,
+                ..options(3)
+            },
+            None,
+        )
+        .await?;
+        assert_eq!(result.population.len(), 6);
+        assert!(relative_eq!(result.x[0], 0.5, epsilon = 1e-6));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_strategies_and_init() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-5f64; 2], vec![5f64; 2])?;
+        let strategies = [
+            DifferentialEvolutionStrategy::Best1Bin,
+            DifferentialEvolutionStrategy::Best1Exp,
+            DifferentialEvolutionStrategy::Rand1Bin,
+            DifferentialEvolutionStrategy::Rand1Exp,
+            DifferentialEvolutionStrategy::Rand2Bin,
+            DifferentialEvolutionStrategy::Rand2Exp,
+            DifferentialEvolutionStrategy::RandToBest1Bin,
+            DifferentialEvolutionStrategy::RandToBest1Exp,
+            DifferentialEvolutionStrategy::CurrentToBest1Bin,
+            DifferentialEvolutionStrategy::CurrentToBest1Exp,
+            DifferentialEvolutionStrategy::Best2Bin,
+            DifferentialEvolutionStrategy::Best2Exp,
+        ];
+        let inits = [
+            PopulationInit::LatinHypercube,
+            PopulationInit::Sobol,
+            PopulationInit::Halton,
+            PopulationInit::Random,
+        ];
+        for (k, strategy) in strategies.into_iter().enumerate() {
+            for init in &inits {
+                for updating in [Updating::Immediate, Updating::Deferred] {
+                    let result = differential_evolution(
+                        Ackley {},
+                        &bounds,
+                        &[],
+                        DifferentialEvolutionOptions {
+                            strategy,
+                            init: init.clone(),
+                            updating,
+                            // the minimum is zero, where the relative tolerance vanishes
+                            atol: 1e-12,
+                            ..options(k as u64)
+                        },
+                        None,
+                    )
+                    .await?;
+                    assert!(result.success, "{strategy:?} {init:?} {updating:?}");
+                    assert!(result.fun < 1e-6, "{strategy:?} {init:?} {updating:?}");
+                }
+            }
+        }
+
+        let sobol = differential_evolution(
+            Ackley {},
+            &bounds,
+            &[],
+            DifferentialEvolutionOptions {
+                init: PopulationInit::Sobol,
+                maxiter: 0,
+                polish: None,
+                ..options(0)
+            },
+            None,
+        )
+        .await?;
+        assert_eq!(sobol.population.len(), 32);
+        assert_eq!(sobol.nfev, 32);
+        assert_eq!(sobol.status, DifferentialEvolutionStatus::MaxIterExceeded);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seed() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-5f64; 3], vec![5f64; 3])?;
+        let no_polish = || DifferentialEvolutionOptions {
+            polish: None,
+            mutation: Mutation::Constant(0.6),
+            ..options(7)
+        };
+        let first = differential_evolution(Ackley {}, &bounds, &[], no_polish(), None).await?;
+        let second = differential_evolution(Ackley {}, &bounds, &[], no_polish(), None).await?;
+        assert_eq!(first.x, second.x);
+        assert_eq!(first.nfev, second.nfev);
+        assert_eq!(first.population, second.population);
+
+        let mut generations = 0;
+        let mut callback = |_: &[f64], _: f64| {
+            generations += 1;
+            generations == 3
+        };
+        let result =
+            differential_evolution(Ackley {}, &bounds, &[], no_polish(), Some(&mut callback))
+                .await?;
+        assert_eq!(
+            result.status,
+            DifferentialEvolutionStatus::CallbackTerminated
+        );
+        assert_eq!(result.nit, 3);
+
+        let result = differential_evolution(
+            Ackley {},
+            &bounds,
+            &[],
+            DifferentialEvolutionOptions {
+                maxfun: Some(100),
+                ..no_polish()
+            },
+            None,
+        )
+        .await?;
+        assert_eq!(result.status, DifferentialEvolutionStatus::MaxFunExceeded);
+        assert_eq!(result.nfev, 100);
+        assert!(!result.success);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_constraints() -> Result<(), SwoopErrors> {
+        // example of the scipy documentation, x0 + x1 <= 1.9
+        let bounds = Bounds::new(vec![0f64; 2], vec![2f64; 2])?;
+        let constraints = [Constraint::from(LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![f64::NEG_INFINITY],
+            vec![1.9],
+        )?)];
+        for constraint_handling in [
+            ConstraintHandling::FeasibilityRules,
+            ConstraintHandling::Penalty(1e3),
+        ] {
+            let result = differential_evolution(
+                Rosenbrock {},
+                &bounds,
+                &constraints,
+                DifferentialEvolutionOptions {
+                    constraint_handling,
+                    ..options(1)
+                },
+                None,
+            )
+            .await?;
+            assert!(result.success, "{constraint_handling:?}");
+            assert!(relative_eq!(result.x[0], 0.966_326_22, epsilon = 1e-5));
+            assert!(relative_eq!(result.x[1], 0.933_671_55, epsilon = 1e-5));
+            assert!(result.constr_violation <= FEASIBILITY_TOL);
+        }
+
+        // no point of the box satisfies x0 + x1 <= -1
+        let infeasible = [Constraint::from(LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![f64::NEG_INFINITY],
+            vec![-1f64],
+        )?)];
+        let result = differential_evolution(
+            Rosenbrock {},
+            &bounds,
+            &infeasible,
+            DifferentialEvolutionOptions {
+                maxiter: 20,
+                polish: None,
+                ..options(1)
+            },
+            None,
+        )
+        .await?;
+        assert_eq!(result.status, DifferentialEvolutionStatus::Infeasible);
+        assert!(relative_eq!(result.constr_violation, 1f64, epsilon = 1e-2));
+        assert!(!result.success);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let bounds = Bounds::new(vec![0f64; 2], vec![2f64; 2]).unwrap();
+        let invalid = [
+            DifferentialEvolutionOptions {
+                mutation: Mutation::Constant(2f64),
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                mutation: Mutation::Dithering(1f64, 0.5),
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                recombination: 1.5,
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                tol: -1f64,
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                x0: Some(vec![3f64, 0f64]),
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                init: PopulationInit::Population(vec![vec![0f64, 0f64]; 4]),
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                strategy: DifferentialEvolutionStrategy::Rand2Bin,
+                init: PopulationInit::Population(vec![vec![0f64, 0f64]; 5]),
+                ..options(0)
+            },
+            DifferentialEvolutionOptions {
+                constraint_handling: ConstraintHandling::Penalty(0f64),
+                ..options(0)
+            },
+        ];
+        for options in invalid {
+            let result = differential_evolution(Rosenbrock {}, &bounds, &[], options, None).await;
+            assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        }
+
+        let unbounded = Bounds::new(vec![0f64; 2], vec![f64::INFINITY; 2]).unwrap();
+        let result = differential_evolution(Rosenbrock {}, &unbounded, &[], options(0), None).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let constraints = [Constraint::from(
+            LinearConstraint::new(vec![vec![1f64, 1f64]], vec![0f64], vec![1f64]).unwrap(),
+        )];
+        let result = differential_evolution(
+            Rosenbrock {},
+            &bounds,
+            &constraints,
+            DifferentialEvolutionOptions {
+                polish: Some(MultivariateMethod::NelderMead),
+                ..options(0)
+            },
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,514 @@
+//! DIRECT, dividing rectangles
+//!
+//! The bounds are scaled to the unit hypercube, which is divided into hyperrectangles with a
+//! sampled centre. Every iteration divides the potentially optimal hyperrectangles: those with
+//! the lowest value for some Lipschitz constant, on the lower right convex hull of the centre
+//! values against the hyperrectangle sizes. A hyperrectangle is trisected along its longest
+//! sides, the side with the best samples first so that they get the largest hyperrectangles.
+//! The locally biased variant DIRECT-L measures sizes by the longest side and divides only one
+//! hyperrectangle of each size, which suits functions with few local minima.
+//!
+//! The method is deterministic, a given problem always gives the same result.
+//!
+//! # References
+//! * Jones, Perttunen and Stuckman, "Lipschitzian optimization without the Lipschitz constant",
+//!   Journal of Optimization Theory and Applications 79.1 (1993)
+//! * Gablonsky and Kelley, "A locally-biased form of the DIRECT algorithm", Journal of Global
+//!   Optimization 21.1 (2001)
+
+use crate::constraints::Bounds;
+use crate::minimise_multivariate::{MultivariateCallback, MultivariateObjectiveFunction};
+use crate::SwoopErrors;
+
+/// Options for the `direct` solver
+#[derive(Debug, Clone)]
+pub struct DirectOptions {
+    /// Minimum relative improvement over the best value that a divided hyperrectangle must be
+    /// able to achieve, larger values favour global over local search
+    pub eps: f64,
+    /// Maximum evaluations of the objective function, `1000 * n` if not given
+    pub maxfun: Option<usize>,
+    /// Maximum iterations
+    pub maxiter: usize,
+    /// Use the locally biased variant DIRECT-L
+    pub locally_biased: bool,
+    /// Known global minimum, the solver stops when it is reached within `f_min_rtol`
+    pub f_min: Option<f64>,
+    /// Relative tolerance for reaching `f_min`
+    pub f_min_rtol: f64,
+    /// Stop when the volume of the hyperrectangle with the best value is at most this fraction
+    /// of the volume of the bounds
+    pub vol_tol: f64,
+    /// Stop when half the size of the hyperrectangle with the best value is at most this
+    /// fraction of the bounds, the longest side with `locally_biased` and the diagonal otherwise
+    pub len_tol: f64,
+}
+
+impl Default for DirectOptions {
+    fn default() -> Self {
+        Self {
+            eps: 1e-4,
+            maxfun: None,
+            maxiter: 1000,
+            locally_biased: true,
+            f_min: None,
+            f_min_rtol: 1e-4,
+            vol_tol: 1e-16,
+            len_tol: 1e-6,
+        }
+    }
+}
+
+/// Reason the `direct` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectStatus {
+    /// The maximum number of evaluations of the objective function was reached
+    MaxFunExceeded,
+    /// The maximum number of iterations was reached
+    MaxIterExceeded,
+    /// The known global minimum `f_min` was reached
+    FMinReached,
+    /// The volume of the hyperrectangle with the best value fell below `vol_tol`
+    VolumeTolerance,
+    /// The size of the hyperrectangle with the best value fell below `len_tol`
+    LengthTolerance,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+}
+
+/// Struct to represent the result of a `direct` optimisation
+#[derive(Debug, Clone)]
+pub struct DirectResult {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Number of evaluations of the objective function
+    pub nfev: usize,
+    /// Number of iterations
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: DirectStatus,
+    /// Whether the solver stopped on `f_min`, `vol_tol` or `len_tol`
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Hyperrectangle of the unit hypercube, each side of length `3^-level`
+#[derive(Debug, Clone)]
+struct Rectangle {
+    centre: Vec<f64>,
+    levels: Vec<i32>,
+    fun: f64,
+}
+
+impl Rectangle {
+    /// Size of the hyperrectangle, half its longest side if `locally_biased` and half its
+    /// diagonal otherwise
+    fn size(&self, locally_biased: bool) -> f64 {
+        if locally_biased {
+            let level = self.levels.iter().copied().min().unwrap_or(0);
+            0.5 * 3f64.powi(-level)
+        } else {
+            0.5 * self
+                .levels
+                .iter()
+                .map(|k| 9f64.powi(-k))
+                .sum::<f64>()
+                .sqrt()
+        }
+    }
+
+    fn volume(&self) -> f64 {
+        3f64.powi(-self.levels.iter().sum::<i32>())
+    }
+}
+
+/// Indices of the potentially optimal hyperrectangles
+fn potentially_optimal(
+    rectangles: &[Rectangle],
+    fun_min: f64,
+    options: &DirectOptions,
+) -> Vec<usize> {
+    // points where the objective function is not finite count as the worst point found
+    let worst = rectangles
+        .iter()
+        .map(|r| r.fun)
+        .filter(|f| f.is_finite())
+        .fold(fun_min, f64::max);
+    let value = |r: &Rectangle| {
+        if r.fun.is_finite() {
+            r.fun
+        } else {
+            worst + 1f64
+        }
+    };
+
+    // the lowest hyperrectangles of each size, by increasing size, where equal levels give
+    // bitwise equal sizes
+    let mut groups: Vec<(f64, f64, Vec<usize>)> = Vec::new();
+    for (i, rectangle) in rectangles.iter().enumerate() {
+        let (size, fun) = (rectangle.size(options.locally_biased), value(rectangle));
+        match groups
+            .iter_mut()
+            .find(|(s, _, _)| s.to_bits() == size.to_bits())
+        {
+            Some((_, f, members)) if fun < *f => {
+                *f = fun;
+                *members = vec![i];
+            }
+            Some((_, f, members)) if fun <= *f && !options.locally_biased => members.push(i),
+            Some(_) => {}
+            None => groups.push((size, fun, vec![i])),
+        }
+    }
+    groups.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let threshold = fun_min - options.eps * fun_min.abs();
+    let mut selected = Vec::new();
+    for (j, (size, fun, members)) in groups.iter().enumerate() {
+        // range of Lipschitz constants for which this group has the lowest lower bound
+        let k_low = groups[..j]
+            .iter()
+            .map(|(s, f, _)| (fun - f) / (size - s))
+            .fold(f64::NEG_INFINITY, f64::max);
+        let k_high = groups[j + 1..]
+            .iter()
+            .map(|(s, f, _)| (f - fun) / (s - size))
+            .fold(f64::INFINITY, f64::min);
+        let lower_bound = if k_high.is_finite() {
+            fun - k_high * size
+        } else {
+            f64::NEG_INFINITY
+        };
+        if k_high > 0f64 && k_low <= k_high && lower_bound <= threshold {
+            selected.extend(members);
+        }
+    }
+    selected
+}
+
+/// Check the options of `direct`
+fn check_options(options: &DirectOptions, lb: &[f64], ub: &[f64]) -> Result<(), SwoopErrors> {
+    if lb.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must have at least one variable",
+        )));
+    }
+    if lb
+        .iter()
+        .zip(ub)
+        .any(|(l, u)| !l.is_finite() || !u.is_finite() || l >= u)
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must be finite with each lower bound below its upper bound",
+        )));
+    }
+    let tolerances = [
+        options.eps,
+        options.f_min_rtol,
+        options.vol_tol,
+        options.len_tol,
+    ];
+    if tolerances.iter().any(|tol| *tol < 0f64 || tol.is_nan()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    Ok(())
+}
+
+/// DIRECT global optimisation
+///
+/// Minimise a function over a bounded domain by dividing it into hyperrectangles, without
+/// gradients or random numbers. Suited to expensive functions of few variables.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `bounds` - finite bounds on the variables
+/// * `options` - solver options
+/// * `callback` - optional callback called after each iteration with the best point, returning
+///   `true` stops the solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if a bound is infinite or not below its upper bound,
+/// or a tolerance is negative
+#[allow(clippy::too_many_lines)]
+pub async fn direct<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    bounds: &Bounds,
+    options: DirectOptions,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<DirectResult, SwoopErrors> {
+    let (lb, ub) = (bounds.lb(), bounds.ub());
+    check_options(&options, lb, ub)?;
+    let n = lb.len();
+    let maxfun = options.maxfun.unwrap_or(1000 * n);
+
+    let scale = |c: &[f64]| -> Vec<f64> {
+        c.iter()
+            .zip(lb.iter().zip(ub))
+            .map(|(ci, (l, u))| l + ci * (u - l))
+            .collect()
+    };
+    let mut nfev = 0;
+    let evaluate = |c: &[f64], nfev: &mut usize| {
+        *nfev += 1;
+        let fun = objective_function.evaluate(&scale(c));
+        if fun.is_nan() {
+            f64::INFINITY
+        } else {
+            fun
+        }
+    };
+
+    let centre = vec![0.5; n];
+    let mut rectangles = vec![Rectangle {
+        fun: evaluate(&centre, &mut nfev),
+        centre,
+        levels: vec![0; n],
+    }];
+    let mut best = 0;
+    let mut nit = 0;
+    let status = 'search: loop {
+        if nit >= options.maxiter {
+            break DirectStatus::MaxIterExceeded;
+        }
+        nit += 1;
+
+        for index in potentially_optimal(&rectangles, rectangles[best].fun, &options) {
+            if nfev >= maxfun {
+                break 'search DirectStatus::MaxFunExceeded;
+            }
+            let parent = rectangles[index].clone();
+            let level = parent.levels.iter().copied().min().unwrap_or(0);
+            let delta = 3f64.powi(-(level + 1));
+
+            // sample along the longest sides, then divide along the best side first
+            let mut samples: Vec<(usize, Rectangle, Rectangle)> = Vec::new();
+            for i in (0..n).filter(|&i| parent.levels[i] == level) {
+                let mut lower = parent.centre.clone();
+                lower[i] -= delta;
+                let mut upper = parent.centre.clone();
+                upper[i] += delta;
+                samples.push((
+                    i,
+                    Rectangle {
+                        fun: evaluate(&lower, &mut nfev),
+                        centre: lower,
+                        levels: Vec::new(),
+                    },
+                    Rectangle {
+                        fun: evaluate(&upper, &mut nfev),
+                        centre: upper,
+                        levels: Vec::new(),
+                    },
+                ));
+            }
+            samples.sort_by(|a, b| a.1.fun.min(a.2.fun).total_cmp(&b.1.fun.min(b.2.fun)));
+
+            let mut levels = parent.levels.clone();
+            for (i, mut lower, mut upper) in samples {
+                levels[i] += 1;
+                lower.levels.clone_from(&levels);
+                upper.levels.clone_from(&levels);
+                rectangles.push(lower);
+                rectangles.push(upper);
+            }
+            rectangles[index].levels = levels;
+        }
+
+        best = (0..rectangles.len())
+            .min_by(|a, b| rectangles[*a].fun.total_cmp(&rectangles[*b].fun))
+            .unwrap_or(0);
+        let incumbent = &rectangles[best];
+        if let Some(callback) = callback.as_mut() {
+            if callback(&scale(&incumbent.centre), incumbent.fun) {
+                break DirectStatus::CallbackTerminated;
+            }
+        }
+        if let Some(f_min) = options.f_min {
+            let error = if f_min == 0f64 {
+                incumbent.fun
+            } else {
+                (incumbent.fun - f_min) / f_min.abs()
+            };
+            if error <= options.f_min_rtol {
+                break DirectStatus::FMinReached;
+            }
+        }
+        if incumbent.volume() <= options.vol_tol {
+            break DirectStatus::VolumeTolerance;
+        }
+        if incumbent.size(options.locally_biased) <= options.len_tol {
+            break DirectStatus::LengthTolerance;
+        }
+        if nfev >= maxfun {
+            break DirectStatus::MaxFunExceeded;
+        }
+    };
+
+    let incumbent = &rectangles[best];
+    Ok(DirectResult {
+        fun: incumbent.fun,
+        nfev,
+        nit,
+        success: matches!(
+            status,
+            DirectStatus::FMinReached
+                | DirectStatus::VolumeTolerance
+                | DirectStatus::LengthTolerance
+        ),
+        status,
+        x: scale(&incumbent.centre),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Styblinski-Tang function, the global minimum near `x = -2.9035` in every variable
+    struct StyblinskiTang {}
+
+    impl MultivariateObjectiveFunction for StyblinskiTang {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            0.5 * x
+                .iter()
+                .map(|xi| xi.powi(4) - 16f64 * xi.powi(2) + 5f64 * xi)
+                .sum::<f64>()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_styblinski_tang() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-4f64; 2], vec![4f64; 2])?;
+        for locally_biased in [true, false] {
+            let result = direct(
+                StyblinskiTang {},
+                &bounds,
+                DirectOptions {
+                    locally_biased,
+                    ..DirectOptions::default()
+                },
+                None,
+            )
+            .await?;
+            assert!(relative_eq!(result.fun, -78.332_331, epsilon = 1e-4));
+            for xi in &result.x {
+                assert!(relative_eq!(*xi, -2.903_534, epsilon = 1e-3));
+            }
+            assert!(result.nfev >= 2000);
+            assert_eq!(result.status, DirectStatus::MaxFunExceeded);
+            assert!(!result.success);
+
+            // the result is reproducible
+            let again = direct(
+                StyblinskiTang {},
+                &bounds,
+                DirectOptions {
+                    locally_biased,
+                    ..DirectOptions::default()
+                },
+                None,
+            )
+            .await?;
+            assert_eq!(result.x, again.x);
+            assert_eq!(result.nfev, again.nfev);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stopping() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-4f64; 3], vec![4f64; 3])?;
+        let run = |options: DirectOptions| direct(StyblinskiTang {}, &bounds, options, None);
+
+        // the first iteration samples the centre and two points along each side
+        let result = run(DirectOptions {
+            maxiter: 1,
+            ..DirectOptions::default()
+        })
+        .await?;
+        assert_eq!(result.nfev, 7);
+        assert_eq!(result.status, DirectStatus::MaxIterExceeded);
+
+        let result = run(DirectOptions {
+            f_min: Some(-3f64 * 39.166_165_7),
+            f_min_rtol: 1e-3,
+            ..DirectOptions::default()
+        })
+        .await?;
+        assert_eq!(result.status, DirectStatus::FMinReached);
+        assert!(result.success);
+        assert!(result.fun <= -3f64 * 39.166_165_7 * (1f64 - 1e-3));
+
+        let result = run(DirectOptions {
+            vol_tol: 1e-6,
+            ..DirectOptions::default()
+        })
+        .await?;
+        assert_eq!(result.status, DirectStatus::VolumeTolerance);
+
+        for locally_biased in [true, false] {
+            let result = run(DirectOptions {
+                len_tol: 1e-3,
+                locally_biased,
+                ..DirectOptions::default()
+            })
+            .await?;
+            assert_eq!(result.status, DirectStatus::LengthTolerance);
+            assert!(relative_eq!(result.fun, -117.498_497, epsilon = 1e-2));
+        }
+
+        let result = run(DirectOptions {
+            maxfun: Some(100),
+            ..DirectOptions::default()
+        })
+        .await?;
+        assert_eq!(result.status, DirectStatus::MaxFunExceeded);
+
+        let mut iterations = 0;
+        let mut callback = |_: &[f64], _: f64| {
+            iterations += 1;
+            iterations == 4
+        };
+        let result = direct(
+            StyblinskiTang {},
+            &bounds,
+            DirectOptions::default(),
+            Some(&mut callback),
+        )
+        .await?;
+        assert_eq!(result.status, DirectStatus::CallbackTerminated);
+        assert_eq!(result.nit, 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let bounds = Bounds::new(vec![-1f64; 2], vec![1f64; 2]).unwrap();
+        let result = direct(
+            StyblinskiTang {},
+            &bounds,
+            DirectOptions {
+                eps: -1f64,
+                ..DirectOptions::default()
+            },
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let unbounded = Bounds::new(vec![-1f64; 2], vec![f64::INFINITY; 2]).unwrap();
+        let result = direct(
+            StyblinskiTang {},
+            &unbounded,
+            DirectOptions::default(),
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
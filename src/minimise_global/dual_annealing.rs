@@ -0,0 +1,722 @@
+//! Dual annealing
+//!
+//! Generalized simulated annealing: new points are drawn from the heavy tailed Tsallis-Stariolo
+//! visiting distribution, whose spread shrinks as the temperature decreases, and accepted with
+//! the generalized Metropolis criterion. The annealing alternates with a local search from the
+//! best point found, and restarts from a random point when the temperature falls below
+//! `initial_temp * restart_temp_ratio`.
+//!
+//! # References
+//! * Tsallis and Stariolo, "Generalized Simulated Annealing", Physica A 233 (1996)
+//! * Xiang, Sun, Fan and Gong, "Generalized Simulated Annealing Algorithm and Its Application to
+//!   the Thomson Model", Physics Letters A 233 (1997)
+
+use crate::constraints::Bounds;
+use crate::minimise_multivariate::{
+    minimise, MinimiseOptions, MultivariateCallback, MultivariateMethod,
+    MultivariateObjectiveFunction,
+};
+use crate::random::Rng;
+use crate::SwoopErrors;
+
+/// Magnitude beyond which visits are redrawn within the limit
+const TAIL_LIMIT: f64 = 1e8;
+
+/// Minimum distance of a visited point from its lower bound
+const MIN_VISIT_BOUND: f64 = 1e-10;
+
+/// Number of attempts to find a random starting point with a finite objective value
+const MAX_REINIT_COUNT: usize = 1000;
+
+/// Options for the `dual_annealing` solver
+#[derive(Debug, Clone)]
+pub struct DualAnnealingOptions {
+    /// Maximum number of global search iterations
+    pub maxiter: usize,
+    /// Initial temperature, in `(0.01, 5e4]`. Higher temperatures spread the search further
+    pub initial_temp: f64,
+    /// Ratio of the initial temperature below which the annealing restarts, in `(0, 1)`
+    pub restart_temp_ratio: f64,
+    /// Parameter `q_v` of the visiting distribution, in `(1, 3)`. Higher values give heavier
+    /// tails and longer jumps
+    pub visit: f64,
+    /// Parameter `q_a` of the acceptance probability, in `(-1e4, -5]`. Lower values make
+    /// uphill moves less likely
+    pub accept: f64,
+    /// Soft limit on the evaluations of the objective function, checked during the annealing
+    /// and after each local search
+    pub maxfun: usize,
+    /// Seed of the random number generator, results are reproducible for a given seed
+    pub seed: Option<u64>,
+    /// Local method of the local search phase, `None` for classical generalized simulated
+    /// annealing without local search
+    pub local_search: Option<MultivariateMethod>,
+    /// Initial guess, a random point within the bounds if not given
+    pub x0: Option<Vec<f64>>,
+}
+
+impl Default for DualAnnealingOptions {
+    fn default() -> Self {
+        Self {
+            maxiter: 1000,
+            initial_temp: 5230f64,
+            restart_temp_ratio: 2e-5,
+            visit: 2.62,
+            accept: -5f64,
+            maxfun: 10_000_000,
+            seed: None,
+            local_search: Some(MultivariateMethod::TrustConstr),
+            x0: None,
+        }
+    }
+}
+
+/// Reason the `dual_annealing` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DualAnnealingStatus {
+    /// The maximum number of iterations was reached
+    MaxIterReached,
+    /// The maximum number of evaluations of the objective function was reached
+    MaxFunReached,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+}
+
+/// Struct to represent the result of a `dual_annealing` optimisation
+#[derive(Debug, Clone)]
+pub struct DualAnnealingResult {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Number of evaluations of the objective function, including the local searches
+    pub nfev: usize,
+    /// Number of global search iterations
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: DualAnnealingStatus,
+    /// Whether the solver performed all its iterations
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// Natural logarithm of the absolute value of the gamma function, by the Lanczos approximation
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+    if x < 0.5 {
+        // reflection formula
+        let pi = std::f64::consts::PI;
+        return (pi / (pi * x).sin().abs()).ln() - ln_gamma(1f64 - x);
+    }
+    let x = x - 1f64;
+    let t = x + 7.5;
+    let series = COEFFICIENTS[1..]
+        .iter()
+        .zip(1u32..)
+        .fold(COEFFICIENTS[0], |acc, (c, k)| acc + c / (x + f64::from(k)));
+    0.5 * (2f64 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// Tsallis-Stariolo visiting distribution
+struct VisitingDistribution {
+    qv: f64,
+    factor4_p: f64,
+    factor6: f64,
+}
+
+impl VisitingDistribution {
+    fn new(qv: f64) -> Self {
+        let pi = std::f64::consts::PI;
+        let factor2 = ((4f64 - qv) * (qv - 1f64).ln()).exp();
+        let factor3 = ((2f64 - qv) * 2f64.ln() / (qv - 1f64)).exp();
+        let factor5 = 1f64 / (qv - 1f64) - 0.5;
+        let d1 = 2f64 - factor5;
+        Self {
+            qv,
+            factor4_p: pi.sqrt() * factor2 / (factor3 * (3f64 - qv)),
+            factor6: pi * (1f64 - factor5) / (pi * (1f64 - factor5)).sin() / ln_gamma(d1).exp(),
+        }
+    }
+
+    /// Random visit at the given temperature
+    fn visit(&self, rng: &mut Rng, temperature: f64) -> f64 {
+        let (x, y) = (rng.normal(), rng.normal());
+        let factor1 = (temperature.ln() / (self.qv - 1f64)).exp();
+        let factor4 = self.factor4_p * factor1;
+        let sigma = (-(self.qv - 1f64) * (self.factor6 / factor4).ln() / (3f64 - self.qv)).exp();
+        let den = ((self.qv - 1f64) * y.abs().ln() / (3f64 - self.qv)).exp();
+        x * sigma / den
+    }
+}
+
+/// State of the annealing, the strategy chain of the current and best points
+struct Annealing<'a, T: MultivariateObjectiveFunction> {
+    fun: &'a T,
+    lb: &'a [f64],
+    ub: &'a [f64],
+    bounds: &'a Bounds,
+    options: &'a DualAnnealingOptions,
+    visiting: VisitingDistribution,
+    rng: Rng,
+    nfev: usize,
+    current_x: Vec<f64>,
+    current_e: f64,
+    best_x: Vec<f64>,
+    best_e: f64,
+    /// Best point of the strategy chain, started from when the search stagnates
+    xmin: Vec<f64>,
+    emin: f64,
+    not_improved_idx: usize,
+    not_improved_max_idx: usize,
+    temperature_step: f64,
+    improved: bool,
+}
+
+impl<T: MultivariateObjectiveFunction> Annealing<'_, T> {
+    fn evaluate(&mut self, x: &[f64]) -> f64 {
+        self.nfev += 1;
+        self.fun.evaluate(x)
+    }
+
+    /// Move the current point to `x0`, or to a random point with a finite objective value
+    fn reset(&mut self, x0: Option<Vec<f64>>) -> Result<(), SwoopErrors> {
+        let mut x = x0.unwrap_or_else(|| self.random_point());
+        for _ in 0..MAX_REINIT_COUNT {
+            let e = self.evaluate(&x);
+            if e.is_finite() {
+                self.current_x = x;
+                self.current_e = e;
+                return Ok(());
+            }
+            x = self.random_point();
+        }
+        Err(SwoopErrors::Other(anyhow::anyhow!(
+            "The objective function is not finite at {MAX_REINIT_COUNT} random points"
+        )))
+    }
+
+    fn random_point(&mut self) -> Vec<f64> {
+        self.lb
+            .iter()
+            .zip(self.ub)
+            .map(|(l, u)| self.rng.uniform_in(*l, *u))
+            .collect()
+    }
+
+    /// Wrap a coordinate back into its bounds, periodically
+    fn wrap(&self, value: f64, j: usize) -> f64 {
+        let range = self.ub[j] - self.lb[j];
+        let wrapped = ((value - self.lb[j]) % range + range) % range + self.lb[j];
+        if (wrapped - self.lb[j]).abs() < MIN_VISIT_BOUND {
+            wrapped + MIN_VISIT_BOUND
+        } else {
+            wrapped
+        }
+    }
+
+    /// Visit a new point from the current point, changing all coordinates in the first `n`
+    /// steps of the chain and one coordinate at a time in the next `n` steps
+    fn visit(&mut self, step: usize, temperature: f64) -> Vec<f64> {
+        let n = self.current_x.len();
+        let mut x = self.current_x.clone();
+        if step < n {
+            let mut visits: Vec<f64> = (0..n)
+                .map(|_| self.visiting.visit(&mut self.rng, temperature))
+                .collect();
+            let (upper_sample, lower_sample) = (self.rng.uniform(), self.rng.uniform());
+            for v in &mut visits {
+                if *v > TAIL_LIMIT {
+                    *v = TAIL_LIMIT * upper_sample;
+                } else if *v < -TAIL_LIMIT {
+                    *v = -TAIL_LIMIT * lower_sample;
+                }
+            }
+            for (j, v) in visits.iter().enumerate() {
+                x[j] = self.wrap(x[j] + v, j);
+            }
+        } else {
+            let mut v = self.visiting.visit(&mut self.rng, temperature);
+            if v > TAIL_LIMIT {
+                v = TAIL_LIMIT * self.rng.uniform();
+            } else if v < -TAIL_LIMIT {
+                v = -TAIL_LIMIT * self.rng.uniform();
+            }
+            let j = step - n;
+            x[j] = self.wrap(x[j] + v, j);
+        }
+        x
+    }
+
+    /// Record a new best point, returning whether the callback requested to stop
+    fn update_best(
+        &mut self,
+        e: f64,
+        x: &[f64],
+        callback: &mut Option<MultivariateCallback<'_>>,
+    ) -> bool {
+        self.best_e = e;
+        self.best_x = x.to_vec();
+        callback.as_mut().is_some_and(|callback| callback(x, e))
+    }
+
+    /// Generalized Metropolis criterion for a point that does not improve the current one
+    fn accept_reject(&mut self, step: usize, e: f64, x: Vec<f64>) {
+        let r = self.rng.uniform();
+        let accept = self.options.accept;
+        let pqv_temp = 1f64 - (1f64 - accept) * (e - self.current_e) / self.temperature_step;
+        let pqv = if pqv_temp <= 0f64 {
+            0f64
+        } else {
+            (pqv_temp.ln() / (1f64 - accept)).exp()
+        };
+        if r <= pqv {
+            self.current_x = x;
+            self.current_e = e;
+            self.xmin.clone_from(&self.current_x);
+        }
+        // no improvement for a long time
+        if self.not_improved_idx >= self.not_improved_max_idx
+            && (step == 0 || self.current_e < self.emin)
+        {
+            self.emin = self.current_e;
+            self.xmin.clone_from(&self.current_x);
+        }
+    }
+
+    /// Run the strategy chain at the given temperature
+    #[allow(clippy::cast_precision_loss)]
+    fn run(
+        &mut self,
+        iteration: usize,
+        temperature: f64,
+        callback: &mut Option<MultivariateCallback<'_>>,
+    ) -> Option<DualAnnealingStatus> {
+        self.temperature_step = temperature / (iteration + 1) as f64;
+        self.not_improved_idx += 1;
+        self.improved = iteration == 0;
+        for step in 0..2 * self.current_x.len() {
+            let x = self.visit(step, temperature);
+            let e = self.evaluate(&x);
+            if e < self.current_e {
+                self.current_x.clone_from(&x);
+                self.current_e = e;
+                if e < self.best_e {
+                    if self.update_best(e, &x, callback) {
+                        return Some(DualAnnealingStatus::CallbackTerminated);
+                    }
+                    self.improved = true;
+                    self.not_improved_idx = 0;
+                }
+            } else {
+                self.accept_reject(step, e, x);
+            }
+            if self.nfev >= self.options.maxfun {
+                return Some(DualAnnealingStatus::MaxFunReached);
+            }
+        }
+        None
+    }
+
+    /// Minimise locally from `x`, returning the local minimum if it is valid and improves `e`
+    async fn minimise_locally(
+        &mut self,
+        method: MultivariateMethod,
+        x: Vec<f64>,
+        e: f64,
+    ) -> Result<(f64, Vec<f64>), SwoopErrors> {
+        let n = x.len();
+        let result = minimise(
+            self.fun,
+            &x,
+            method,
+            MinimiseOptions {
+                bounds: Some(self.bounds),
+                jac: self.fun.gradient(&x).is_some(),
+                maxiter: Some((6 * n).clamp(100, 1000)),
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        self.nfev += result.nfev;
+        let valid = result.fun.is_finite()
+            && result
+                .x
+                .iter()
+                .zip(self.lb.iter().zip(self.ub))
+                .all(|(xi, (l, u))| xi >= l && xi <= u);
+        if valid && result.fun < e {
+            Ok((result.fun, result.x))
+        } else {
+            Ok((e, x))
+        }
+    }
+
+    /// Local search from the best point if the annealing improved it, and from the best point
+    /// of the chain if the search stagnates
+    async fn local_search(
+        &mut self,
+        method: MultivariateMethod,
+        callback: &mut Option<MultivariateCallback<'_>>,
+    ) -> Result<Option<DualAnnealingStatus>, SwoopErrors> {
+        if self.improved {
+            let (e, x) = self
+                .minimise_locally(method, self.best_x.clone(), self.best_e)
+                .await?;
+            if e < self.best_e {
+                self.not_improved_idx = 0;
+                if self.update_best(e, &x, callback) {
+                    return Ok(Some(DualAnnealingStatus::CallbackTerminated));
+                }
+                self.current_x = x;
+                self.current_e = e;
+            }
+            if self.nfev >= self.options.maxfun {
+                return Ok(Some(DualAnnealingStatus::MaxFunReached));
+            }
+        }
+        if self.not_improved_idx >= self.not_improved_max_idx {
+            let (e, x) = self
+                .minimise_locally(method, self.xmin.clone(), self.emin)
+                .await?;
+            self.xmin.clone_from(&x);
+            self.emin = e;
+            self.not_improved_idx = 0;
+            self.not_improved_max_idx = x.len();
+            if e < self.best_e {
+                if self.update_best(e, &x, callback) {
+                    return Ok(Some(DualAnnealingStatus::CallbackTerminated));
+                }
+                self.current_x = x;
+                self.current_e = e;
+            }
+            if self.nfev >= self.options.maxfun {
+                return Ok(Some(DualAnnealingStatus::MaxFunReached));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Check the options of `dual_annealing`
+fn check_options(
+    options: &DualAnnealingOptions,
+    lb: &[f64],
+    ub: &[f64],
+) -> Result<(), SwoopErrors> {
+    if lb.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must have at least one variable",
+        )));
+    }
+    if lb
+        .iter()
+        .zip(ub)
+        .any(|(l, u)| !l.is_finite() || !u.is_finite() || l >= u)
+    {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must be finite with each lower bound below its upper bound",
+        )));
+    }
+    if !(options.initial_temp > 0.01 && options.initial_temp <= 5e4) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The initial temperature must be in (0.01, 5e4]",
+        )));
+    }
+    if !(options.restart_temp_ratio > 0f64 && options.restart_temp_ratio < 1f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The restart temperature ratio must be in (0, 1)",
+        )));
+    }
+    if !(options.visit > 1f64 && options.visit < 3f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The visiting parameter must be in (1, 3)",
+        )));
+    }
+    if !(options.accept > -1e4 && options.accept <= -5f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The acceptance parameter must be in (-1e4, -5]",
+        )));
+    }
+    if let Some(x0) = &options.x0 {
+        let inside = x0
+            .iter()
+            .zip(lb.iter().zip(ub))
+            .all(|(x, (l, u))| x >= l && x <= u);
+        if x0.len() != lb.len() || !inside {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Initial guess must have one entry per variable and be within the bounds",
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Dual annealing global optimisation
+///
+/// Minimise a function over a bounded domain by generalized simulated annealing combined with
+/// a local search. Its long jumps early in the annealing suit functions with many local minima
+/// over a wide domain.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `bounds` - finite bounds on the variables
+/// * `options` - solver options
+/// * `callback` - optional callback called with every new best point, returning `true` stops
+///   the solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if a bound is infinite or not below its upper bound,
+/// a parameter is out of range, the initial guess has the wrong dimensions or is outside the
+/// bounds, or the local method rejects its arguments. Will return `SwoopErrors::Other` if no
+/// random starting point with a finite objective value is found
+#[allow(clippy::cast_precision_loss)]
+pub async fn dual_annealing<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    bounds: &Bounds,
+    options: DualAnnealingOptions,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<DualAnnealingResult, SwoopErrors> {
+    let (lb, ub) = (bounds.lb(), bounds.ub());
+    check_options(&options, lb, ub)?;
+
+    let mut annealing = Annealing {
+        fun: &objective_function,
+        lb,
+        ub,
+        bounds,
+        options: &options,
+        visiting: VisitingDistribution::new(options.visit),
+        rng: Rng::new(options.seed),
+        nfev: 0,
+        current_x: Vec::new(),
+        current_e: f64::INFINITY,
+        best_x: Vec::new(),
+        best_e: f64::INFINITY,
+        xmin: Vec::new(),
+        emin: f64::INFINITY,
+        not_improved_idx: 0,
+        not_improved_max_idx: 1000,
+        temperature_step: 0f64,
+        improved: false,
+    };
+    annealing.reset(options.x0.clone())?;
+    annealing.best_x.clone_from(&annealing.current_x);
+    annealing.best_e = annealing.current_e;
+    annealing.xmin.clone_from(&annealing.current_x);
+    annealing.emin = annealing.current_e;
+
+    let temperature_restart = options.initial_temp * options.restart_temp_ratio;
+    let t1 = ((options.visit - 1f64) * 2f64.ln()).exp() - 1f64;
+    let mut nit = 0;
+    let status = 'annealing: loop {
+        for i in 0.. {
+            if nit >= options.maxiter {
+                break 'annealing DualAnnealingStatus::MaxIterReached;
+            }
+            let t2 = ((options.visit - 1f64) * (i as f64 + 2f64).ln()).exp() - 1f64;
+            let temperature = options.initial_temp * t1 / t2;
+            if temperature < temperature_restart {
+                annealing.reset(None)?;
+                continue 'annealing;
+            }
+            if let Some(status) = annealing.run(i, temperature, &mut callback) {
+                break 'annealing status;
+            }
+            if let Some(method) = options.local_search {
+                if let Some(status) = annealing.local_search(method, &mut callback).await? {
+                    break 'annealing status;
+                }
+            }
+            nit += 1;
+        }
+    };
+
+    Ok(DualAnnealingResult {
+        fun: annealing.best_e,
+        nfev: annealing.nfev,
+        nit,
+        success: status == DualAnnealingStatus::MaxIterReached,
+        status,
+        x: annealing.best_x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Rastrigin function, a regular grid of local minima and the global minimum at the origin
+    struct Rastrigin {}
+
+    impl MultivariateObjectiveFunction for Rastrigin {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            let tau = 2f64 * std::f64::consts::PI;
+            x.iter()
+                .map(|xi| 10f64 + xi * xi - 10f64 * (tau * xi).cos())
+                .sum()
+        }
+
+        fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+            let tau = 2f64 * std::f64::consts::PI;
+            Some(
+                x.iter()
+                    .map(|xi| 2f64 * xi + 10f64 * tau * (tau * xi).sin())
+                    .collect(),
+            )
+        }
+    }
+
+    /// Objective function that is nowhere defined
+    struct Undefined {}
+
+    impl MultivariateObjectiveFunction for Undefined {
+        fn evaluate(&self, _: &[f64]) -> f64 {
+            f64::NAN
+        }
+    }
+
+    fn options(seed: u64) -> DualAnnealingOptions {
+        DualAnnealingOptions {
+            seed: Some(seed),
+            ..DualAnnealingOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_ln_gamma() {
+        assert!(relative_eq!(ln_gamma(1f64), 0f64, epsilon = 1e-12));
+        assert!(relative_eq!(ln_gamma(5f64), 24f64.ln(), epsilon = 1e-12));
+        assert!(relative_eq!(
+            ln_gamma(0.5),
+            std::f64::consts::PI.sqrt().ln(),
+            epsilon = 1e-12
+        ));
+        // |gamma(-0.5)| = 2 sqrt(pi)
+        assert!(relative_eq!(
+            ln_gamma(-0.5),
+            (2f64 * std::f64::consts::PI.sqrt()).ln(),
+            epsilon = 1e-12
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rastrigin() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-5.12; 4], vec![5.12; 4])?;
+        // Nelder-Mead stops at its default tolerance of 1e-4 on the variables
+        for (method, tol) in [
+            (MultivariateMethod::TrustConstr, 1e-8),
+            (MultivariateMethod::NelderMead, 1e-4),
+        ] {
+            let result = dual_annealing(
+                Rastrigin {},
+                &bounds,
+                DualAnnealingOptions {
+                    maxiter: 200,
+                    local_search: Some(method),
+                    ..options(3)
+                },
+                None,
+            )
+            .await?;
+            assert!(result.success);
+            assert_eq!(result.status, DualAnnealingStatus::MaxIterReached);
+            assert_eq!(result.nit, 200);
+            assert!(result.fun < tol, "{method:?}");
+            for xi in &result.x {
+                assert!(relative_eq!(*xi, 0f64, epsilon = tol.sqrt()));
+            }
+        }
+
+        // without local search the annealing only gets close to the minimum
+        let bounds = Bounds::new(vec![-5.12; 2], vec![5.12; 2])?;
+        let result = dual_annealing(
+            Rastrigin {},
+            &bounds,
+            DualAnnealingOptions {
+                local_search: None,
+                x0: Some(vec![4f64, -4f64]),
+                ..options(3)
+            },
+            None,
+        )
+        .await?;
+        assert!(result.fun < 0.1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_budget_and_seed() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-5.12; 3], vec![5.12; 3])?;
+        let limited = || DualAnnealingOptions {
+            maxfun: 500,
+            local_search: None,
+            ..options(11)
+        };
+        let first = dual_annealing(Rastrigin {}, &bounds, limited(), None).await?;
+        assert_eq!(first.status, DualAnnealingStatus::MaxFunReached);
+        assert_eq!(first.nfev, 500);
+        assert!(!first.success);
+        let second = dual_annealing(Rastrigin {}, &bounds, limited(), None).await?;
+        assert_eq!(first.x, second.x);
+        assert_eq!(first.nit, second.nit);
+
+        let mut improvements = 0;
+        let mut callback = |_: &[f64], _: f64| {
+            improvements += 1;
+            improvements == 3
+        };
+        let result = dual_annealing(Rastrigin {}, &bounds, limited(), Some(&mut callback)).await?;
+        assert_eq!(result.status, DualAnnealingStatus::CallbackTerminated);
+        assert_eq!(improvements, 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let bounds = Bounds::new(vec![-1f64; 2], vec![1f64; 2]).unwrap();
+        let invalid = [
+            DualAnnealingOptions {
+                initial_temp: 0f64,
+                ..options(0)
+            },
+            DualAnnealingOptions {
+                restart_temp_ratio: 1f64,
+                ..options(0)
+            },
+            DualAnnealingOptions {
+                visit: 3f64,
+                ..options(0)
+            },
+            DualAnnealingOptions {
+                accept: 0f64,
+                ..options(0)
+            },
+            DualAnnealingOptions {
+                x0: Some(vec![2f64, 0f64]),
+                ..options(0)
+            },
+        ];
+        for options in invalid {
+            let result = dual_annealing(Rastrigin {}, &bounds, options, None).await;
+            assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        }
+
+        let unbounded = Bounds::new(vec![-1f64; 2], vec![f64::INFINITY; 2]).unwrap();
+        let result = dual_annealing(Rastrigin {}, &unbounded, options(0), None).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = dual_annealing(Undefined {}, &bounds, options(0), None).await;
+        assert!(matches!(result, Err(SwoopErrors::Other(_))));
+    }
+}
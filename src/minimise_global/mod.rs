@@ -0,0 +1,51 @@
+//! Algorithms for global optimisation
+//!
+//! Search a bounded domain for the global minimum of functions with many local minima.
+
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use crate::minimise_scalar::ScalarObjectiveFunction;
+
+mod basinhopping;
+pub use basinhopping::{
+    basinhopping, AcceptTest, Acceptance, BasinhoppingOptions, BasinhoppingResult,
+    BasinhoppingStatus, LocalMinimiser, LocalMinimum, TakeStep,
+};
+
+mod brute;
+pub use brute::{brute, BruteRange, BruteResult};
+
+mod differential_evolution;
+pub use differential_evolution::{
+    differential_evolution, ConstraintHandling, DifferentialEvolutionOptions,
+    DifferentialEvolutionResult, DifferentialEvolutionStatus, DifferentialEvolutionStrategy,
+    Mutation, PopulationInit, Updating,
+};
+
+mod direct;
+pub use direct::{direct, DirectOptions, DirectResult, DirectStatus};
+
+mod dual_annealing;
+pub use dual_annealing::{
+    dual_annealing, DualAnnealingOptions, DualAnnealingResult, DualAnnealingStatus,
+};
+
+mod sampling;
+
+mod shgo;
+pub use shgo::{shgo, ShgoOptions, ShgoResult, ShgoSampling};
+
+/// Maximum constraint violation of a solution that is reported as feasible, and that a local
+/// solution may have
+const FEASIBILITY_TOL: f64 = 1e-8;
+
+/// Univariate view of the objective function, shifted such that `t = 0` is the point `origin`
+pub(crate) struct Shifted<'a, T: MultivariateObjectiveFunction> {
+    pub(crate) objective: &'a T,
+    pub(crate) origin: f64,
+}
+
+impl<T: MultivariateObjectiveFunction> ScalarObjectiveFunction for Shifted<'_, T> {
+    fn evaluate(&self, t: f64) -> f64 {
+        self.objective.evaluate(&[self.origin + t])
+    }
+}
@@ -0,0 +1,226 @@
+//! Sampling of the unit hypercube for initial populations and sampling points
+//!
+//! # References
+//! * Joe and Kuo, "Constructing Sobol sequences with better two-dimensional projections", SIAM
+//!   Journal on Scientific Computing 30.5 (2008)
+//! * Halton, "On the efficiency of certain quasi-random sequences of points in evaluating
+//!   multi-dimensional integrals", Numerische Mathematik 2 (1960)
+
+use crate::random::Rng;
+use crate::SwoopErrors;
+
+/// Degree, coefficients and initial direction numbers of the primitive polynomials of the
+/// Sobol sequence in dimensions `2..=21`, from the `new-joe-kuo-6.21201` table
+const SOBOL_DIRECTIONS: [(u32, u32, &[u32]); 20] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+/// Number of bits of the Sobol points
+const SOBOL_BITS: u32 = 32;
+
+/// Maximum dimension of the Sobol sequence
+pub(super) const SOBOL_MAX_DIM: usize = SOBOL_DIRECTIONS.len() + 1;
+
+/// Latin hypercube sample of `n` points: every dimension is divided into `n` strata that each
+/// contain exactly one point, at a random position within the stratum
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn latin_hypercube(rng: &mut Rng, n: usize, dim: usize) -> Vec<Vec<f64>> {
+    let mut points = vec![vec![0f64; dim]; n];
+    for j in 0..dim {
+        let mut strata: Vec<usize> = (0..n).collect();
+        rng.shuffle(&mut strata);
+        for (point, stratum) in points.iter_mut().zip(strata) {
+            point[j] = (stratum as f64 + rng.uniform()) / n as f64;
+        }
+    }
+    points
+}
+
+/// Uniform random sample of `n` points
+pub(super) fn random(rng: &mut Rng, n: usize, dim: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|_| (0..dim).map(|_| rng.uniform()).collect())
+        .collect()
+}
+
+/// Direction numbers of the Sobol sequence in each dimension, scaled to `SOBOL_BITS` bits
+fn sobol_directions(dim: usize) -> Vec<Vec<u32>> {
+    let bits = SOBOL_BITS as usize;
+    let mut directions = vec![(0..SOBOL_BITS)
+        .map(|k| 1u32 << (31 - k))
+        .collect::<Vec<u32>>()];
+    for &(s, a, m) in SOBOL_DIRECTIONS.iter().take(dim.saturating_sub(1)) {
+        let s_usize = s as usize;
+        let mut v = vec![0u32; bits];
+        for (k, mk) in m.iter().enumerate() {
+            v[k] = mk << (31 - k);
+        }
+        for k in s_usize..bits {
+            v[k] = v[k - s_usize] ^ (v[k - s_usize] >> s);
+            for i in 1..s_usize {
+                if (a >> (s_usize - 1 - i)) & 1 == 1 {
+                    v[k] ^= v[k - i];
+                }
+            }
+        }
+        directions.push(v);
+    }
+    directions
+}
+
+/// Sobol sample of the first `n` points of the sequence in Gray code order, scrambled with a
+/// random digital shift if `rng` is given. The balance properties of the sequence hold when `n`
+/// is a power of two
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `dim` exceeds `SOBOL_MAX_DIM`
+pub(super) fn sobol(
+    rng: Option<&mut Rng>,
+    n: usize,
+    dim: usize,
+) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+    if dim > SOBOL_MAX_DIM {
+        return Err(SwoopErrors::ArgumentError(format!(
+            "Sobol sampling supports at most {SOBOL_MAX_DIM} dimensions"
+        )));
+    }
+    let directions = sobol_directions(dim);
+    let shift: Vec<u32> = match rng {
+        Some(rng) => (0..dim)
+            .map(|_| u32::try_from(rng.next_u64() >> SOBOL_BITS).unwrap_or(0))
+            .collect(),
+        None => vec![0; dim],
+    };
+    let scale = f64::from(u32::MAX) + 1f64;
+    let mut state = vec![0u32; dim];
+    let mut points = Vec::with_capacity(n);
+    for i in 0..n {
+        points.push(
+            state
+                .iter()
+                .zip(&shift)
+                .map(|(s, t)| f64::from(s ^ t) / scale)
+                .collect(),
+        );
+        // the next point differs from this one in the direction of the lowest zero bit of `i`
+        let c = (!i).trailing_zeros() as usize;
+        if c < SOBOL_BITS as usize {
+            for (s, v) in state.iter_mut().zip(&directions) {
+                *s ^= v[c];
+            }
+        }
+    }
+    Ok(points)
+}
+
+/// First `count` prime numbers
+fn primes(count: usize) -> Vec<u64> {
+    let mut primes: Vec<u64> = Vec::with_capacity(count);
+    let mut candidate = 2;
+    while primes.len() < count {
+        if primes
+            .iter()
+            .take_while(|p| *p * *p <= candidate)
+            .all(|p| candidate % p != 0)
+        {
+            primes.push(candidate);
+        }
+        candidate += 1;
+    }
+    primes
+}
+
+/// Halton sample of `n` points, the radical inverses of `1..=n` in the first `dim` prime
+/// bases, randomised by a random shift modulo one in every dimension
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn halton(rng: &mut Rng, n: usize, dim: usize) -> Vec<Vec<f64>> {
+    let bases = primes(dim);
+    let shift: Vec<f64> = (0..dim).map(|_| rng.uniform()).collect();
+    (1..=n as u64)
+        .map(|i| {
+            bases
+                .iter()
+                .zip(&shift)
+                .map(|(&base, s)| {
+                    let mut inverse = 0f64;
+                    let mut factor = 1f64 / base as f64;
+                    let mut k = i;
+                    while k > 0 {
+                        inverse += (k % base) as f64 * factor;
+                        k /= base;
+                        factor /= base as f64;
+                    }
+                    (inverse + s).fract()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every one of `n` equal intervals of each dimension contains exactly one point
+    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_sign_loss)]
+    #[allow(clippy::cast_precision_loss)]
+    fn stratified(points: &[Vec<f64>], dim: usize) -> bool {
+        let n = points.len();
+        (0..dim).all(|j| {
+            let mut counts = vec![0; n];
+            for point in points {
+                counts[(point[j] * n as f64) as usize] += 1;
+            }
+            counts.iter().all(|c| *c == 1)
+        })
+    }
+
+    #[test]
+    fn test_sampling() -> Result<(), SwoopErrors> {
+        let mut rng = Rng::new(Some(0));
+        assert!(stratified(&latin_hypercube(&mut rng, 50, 4), 4));
+        assert!(stratified(
+            &sobol(Some(&mut rng), 64, SOBOL_MAX_DIM)?,
+            SOBOL_MAX_DIM
+        ));
+        assert!(random(&mut rng, 20, 3)
+            .iter()
+            .flatten()
+            .all(|u| (0f64..1f64).contains(u)));
+        assert!(sobol(None, 4, SOBOL_MAX_DIM + 1).is_err());
+
+        // without the shift the Sobol points are the binary fractions in Gray code order
+        let directions = sobol_directions(2);
+        assert_eq!(directions[1][0], 1 << 31);
+        assert_eq!(directions[1][1], 3 << 30);
+        let points = sobol(None, 4, 2)?;
+        assert_eq!(points[0], vec![0f64, 0f64]);
+        assert_eq!(points[1], vec![0.5, 0.5]);
+        assert_eq!(points[2], vec![0.75, 0.25]);
+
+        let points = halton(&mut rng, 30, 3);
+        assert!(points.iter().flatten().all(|u| (0f64..1f64).contains(u)));
+        assert_eq!(primes(5), vec![2, 3, 5, 7, 11]);
+        Ok(())
+    }
+}
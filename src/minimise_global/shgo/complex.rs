@@ -0,0 +1,232 @@
+//! Triangulations of the sampling points of the unit hypercube
+//!
+//! Only the edges of the triangulation are needed, to compare each vertex with its neighbours.
+//! The simplicial sampling uses the Freudenthal triangulation of a regular grid, where two grid
+//! vertices are neighbours if their difference is a nonzero vector of zeros and ones or of zeros
+//! and minus ones. Other samplings use the Delaunay triangulation, built incrementally with the
+//! Bowyer-Watson algorithm.
+//!
+//! # References
+//! * Freudenthal, "Simplizialzerlegungen von beschränkter Flachheit", Annals of Mathematics 43.3
+//!   (1942)
+//! * Watson, "Computing the n-dimensional Delaunay tessellation with application to Voronoi
+//!   polytopes", The Computer Journal 24.2 (1981)
+
+use crate::linalg::{dot, inverse, mat_vec};
+use std::collections::HashMap;
+
+/// Distance of the vertices of the enclosing simplex from the unit hypercube, large enough that
+/// few edges of the convex hull of the points are lost
+const SUPER_SIMPLEX_MARGIN: f64 = 100f64;
+
+/// Grid of `divisions + 1` points per dimension of the unit hypercube and the neighbours of
+/// each point in its Freudenthal triangulation
+#[allow(clippy::cast_precision_loss)]
+pub(super) fn freudenthal(dim: usize, divisions: usize) -> (Vec<Vec<f64>>, Vec<Vec<usize>>) {
+    let side = divisions + 1;
+    let count = side.pow(u32::try_from(dim).unwrap_or(u32::MAX));
+    let index = |multi: &[usize]| multi.iter().rev().fold(0, |acc, i| acc * side + i);
+    let multi_index = |mut k: usize| -> Vec<usize> {
+        (0..dim)
+            .map(|_| {
+                let i = k % side;
+                k /= side;
+                i
+            })
+            .collect()
+    };
+
+    let points = (0..count)
+        .map(|k| {
+            multi_index(k)
+                .iter()
+                .map(|i| *i as f64 / divisions as f64)
+                .collect()
+        })
+        .collect();
+    let neighbours = (0..count)
+        .map(|k| {
+            let vertex = multi_index(k);
+            let mut neighbours = Vec::new();
+            for mask in 1..1usize << dim {
+                for forward in [true, false] {
+                    let neighbour: Option<Vec<usize>> = vertex
+                        .iter()
+                        .enumerate()
+                        .map(|(j, i)| match (mask >> j & 1 == 1, forward) {
+                            (false, _) => Some(*i),
+                            (true, true) => (*i < divisions).then_some(i + 1),
+                            (true, false) => i.checked_sub(1),
+                        })
+                        .collect();
+                    if let Some(neighbour) = neighbour {
+                        neighbours.push(index(&neighbour));
+                    }
+                }
+            }
+            neighbours
+        })
+        .collect();
+    (points, neighbours)
+}
+
+/// Simplex of the Delaunay triangulation with its circumscribed sphere
+struct Simplex {
+    vertices: Vec<usize>,
+    centre: Vec<f64>,
+    radius2: f64,
+}
+
+impl Simplex {
+    fn new(vertices: Vec<usize>, points: &[Vec<f64>]) -> Self {
+        // the centre is equidistant from all vertices, 2 (v_i - v_0) c = |v_i|^2 - |v_0|^2
+        let v0 = &points[vertices[0]];
+        let (a, b): (Vec<Vec<f64>>, Vec<f64>) = vertices[1..]
+            .iter()
+            .map(|&i| {
+                let vi = &points[i];
+                let row = vi.iter().zip(v0).map(|(p, q)| 2f64 * (p - q)).collect();
+                (row, dot(vi, vi) - dot(v0, v0))
+            })
+            .unzip();
+        match inverse(&a) {
+            Some(inv) => {
+                let centre = mat_vec(&inv, &b);
+                let radius2 = squared_distance(&centre, v0);
+                Self {
+                    vertices,
+                    centre,
+                    radius2,
+                }
+            }
+            // a flat simplex is replaced by the next insertion
+            None => Self {
+                vertices,
+                centre: v0.clone(),
+                radius2: f64::INFINITY,
+            },
+        }
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(p, q)| (p - q).powi(2)).sum()
+}
+
+/// Neighbours of each point of the unit hypercube in the Delaunay triangulation of `points`
+pub(super) fn delaunay(points: &[Vec<f64>], dim: usize) -> Vec<Vec<usize>> {
+    // the points follow the vertices of a simplex that encloses the unit hypercube
+    let corner = -SUPER_SIMPLEX_MARGIN;
+    #[allow(clippy::cast_precision_loss)]
+    let edge = (dim as f64 + 1f64) * (1f64 + 2f64 * SUPER_SIMPLEX_MARGIN);
+    let mut all: Vec<Vec<f64>> = (0..=dim)
+        .map(|k| {
+            (0..dim)
+                .map(|j| if k == j + 1 { corner + edge } else { corner })
+                .collect()
+        })
+        .collect();
+    all.extend(points.iter().cloned());
+    let mut simplices = vec![Simplex::new((0..=dim).collect(), &all)];
+
+    for p in dim + 1..all.len() {
+        let (bad, good): (Vec<Simplex>, Vec<Simplex>) = simplices
+            .into_iter()
+            .partition(|s| squared_distance(&s.centre, &all[p]) < s.radius2);
+        simplices = good;
+
+        // the facets of the cavity that are not shared by two removed simplices
+        let mut facets: HashMap<Vec<usize>, usize> = HashMap::new();
+        for simplex in &bad {
+            for skip in 0..simplex.vertices.len() {
+                let mut facet: Vec<usize> = simplex
+                    .vertices
+                    .iter()
+                    .enumerate()
+                    .filter(|(k, _)| *k != skip)
+                    .map(|(_, v)| *v)
+                    .collect();
+                facet.sort_unstable();
+                *facets.entry(facet).or_insert(0) += 1;
+            }
+        }
+        let mut boundary: Vec<Vec<usize>> = facets
+            .into_iter()
+            .filter(|(_, count)| *count == 1)
+            .map(|(facet, _)| facet)
+            .collect();
+        // the order of the hash map is not deterministic
+        boundary.sort_unstable();
+        for mut facet in boundary {
+            facet.push(p);
+            simplices.push(Simplex::new(facet, &all));
+        }
+    }
+
+    let mut neighbours = vec![Vec::new(); points.len()];
+    for simplex in &simplices {
+        for &a in &simplex.vertices {
+            for &b in &simplex.vertices {
+                if a != b && a > dim && b > dim {
+                    neighbours[a - dim - 1].push(b - dim - 1);
+                }
+            }
+        }
+    }
+    for list in &mut neighbours {
+        list.sort_unstable();
+        list.dedup();
+    }
+    neighbours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_triangulations() {
+        // a square is split into two triangles along the main diagonal
+        let (points, neighbours) = freudenthal(2, 1);
+        assert_eq!(
+            points,
+            vec![
+                vec![0f64, 0f64],
+                vec![1f64, 0f64],
+                vec![0f64, 1f64],
+                vec![1f64, 1f64]
+            ]
+        );
+        assert_eq!(neighbours[0], vec![1, 2, 3]);
+        assert_eq!(neighbours[1], vec![0, 3]);
+        let (points, neighbours) = freudenthal(3, 2);
+        assert_eq!(points.len(), 27);
+        // the centre of the cube is a vertex of 24 tetrahedra with 14 neighbours
+        assert_eq!(neighbours[13].len(), 14);
+
+        // the Delaunay triangulation of a square and its centre connects every corner to the
+        // centre and to its two adjacent corners
+        let points = vec![
+            vec![0f64, 0f64],
+            vec![1f64, 0f64],
+            vec![0f64, 1f64],
+            vec![1f64, 1f64],
+            vec![0.5, 0.5],
+        ];
+        let neighbours = delaunay(&points, 2);
+        assert_eq!(neighbours[4], vec![0, 1, 2, 3]);
+        assert_eq!(neighbours[0], vec![1, 2, 4]);
+        assert_eq!(neighbours[3], vec![1, 2, 4]);
+
+        let points = vec![
+            vec![0.1, 0.2, 0.3],
+            vec![0.9, 0.1, 0.2],
+            vec![0.2, 0.8, 0.1],
+            vec![0.3, 0.3, 0.9],
+            vec![0.35, 0.35, 0.35],
+        ];
+        let neighbours = delaunay(&points, 3);
+        // the interior point connects to every vertex of the enclosing tetrahedron
+        assert_eq!(neighbours[4], vec![0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,440 @@
+//! Simplicial homology global optimisation
+//!
+//! The bounded domain is sampled and the sampling points are triangulated. A vertex whose
+//! objective value is lower than that of all its neighbours in the triangulation approximates
+//! a local minimum, and these vertices form the minimiser pool. A local minimisation is started
+//! from every point of the pool, which gives all the local minima the sampling resolves rather
+//! than only the global one. Sampling points that violate the constraints are not evaluated.
+//!
+//! # References
+//! * Endres, Sandrock and Focke, "A simplicial homology algorithm for Lipschitz optimisation",
+//!   Journal of Global Optimization 72.2 (2018)
+
+mod complex;
+
+use super::{sampling, FEASIBILITY_TOL};
+use crate::constraints::{Bounds, Constraint};
+use crate::minimise_multivariate::{
+    minimise, MinimiseOptions, MultivariateMethod, MultivariateObjectiveFunction,
+};
+use crate::SwoopErrors;
+
+/// Relative distance, as a fraction of the bounds, below which two local minima are the same
+const DUPLICATE_TOL: f64 = 1e-4;
+
+/// Sampling method of `shgo`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShgoSampling {
+    /// Regular grid with its Freudenthal triangulation, refined by bisection in every
+    /// iteration. The grid has `(2^iters + 1)^n` points
+    #[default]
+    Simplicial,
+    /// Sobol sequence with its Delaunay triangulation, `n` more points in every iteration.
+    /// Supports at most 21 variables
+    Sobol,
+}
+
+/// Options for the `shgo` solver
+#[derive(Debug, Clone)]
+pub struct ShgoOptions {
+    /// Number of sampling points added in every iteration of the Sobol sampling
+    pub n: usize,
+    /// Number of iterations refining the sampling
+    pub iters: usize,
+    /// Sampling method
+    pub sampling_method: ShgoSampling,
+    /// Local method started from every point of the minimiser pool
+    pub local_method: MultivariateMethod,
+}
+
+impl Default for ShgoOptions {
+    fn default() -> Self {
+        Self {
+            n: 128,
+            iters: 1,
+            sampling_method: ShgoSampling::default(),
+            local_method: MultivariateMethod::TrustConstr,
+        }
+    }
+}
+
+/// Struct to represent the result of a `shgo` optimisation
+#[derive(Debug, Clone)]
+pub struct ShgoResult {
+    /// Value of the objective function at the global minimum
+    pub fun: f64,
+    /// Value of the objective function at each local minimum, in increasing order
+    pub funl: Vec<f64>,
+    /// Number of evaluations of the objective function, including the local minimisations
+    pub nfev: usize,
+    /// Number of evaluations of the objective function by the local minimisations
+    pub nlfev: usize,
+    /// Number of iterations refining the sampling
+    pub nit: usize,
+    /// Number of local minimisations
+    pub nlmin: usize,
+    /// Whether a feasible minimum was found
+    pub success: bool,
+    /// The global minimum
+    pub x: Vec<f64>,
+    /// Local minima, in the order of `funl`
+    pub xl: Vec<Vec<f64>>,
+}
+
+/// Objective value of a sampling point, infinite if it violates the constraints
+struct Sampler<'a, T: MultivariateObjectiveFunction> {
+    fun: &'a T,
+    lb: &'a [f64],
+    ub: &'a [f64],
+    constraints: &'a [Constraint],
+    nfev: usize,
+}
+
+impl<T: MultivariateObjectiveFunction> Sampler<'_, T> {
+    /// Variables in the bounds of the point `x` of the unit hypercube
+    fn scale(&self, x: &[f64]) -> Vec<f64> {
+        x.iter()
+            .zip(self.lb.iter().zip(self.ub))
+            .map(|(xi, (l, u))| l + xi * (u - l))
+            .collect()
+    }
+
+    /// Objective values of the points `xs` of the unit hypercube, evaluating the feasible
+    /// points as one batch
+    fn values(&mut self, xs: &[Vec<f64>]) -> Vec<f64> {
+        let params: Vec<Vec<f64>> = xs.iter().map(|x| self.scale(x)).collect();
+        let feasible: Vec<bool> = params
+            .iter()
+            .map(|p| {
+                self.constraints
+                    .iter()
+                    .flat_map(|c| c.violation(p))
+                    .all(|v| v <= 0f64)
+            })
+            .collect();
+        let batch: Vec<Vec<f64>> = params
+            .into_iter()
+            .zip(&feasible)
+            .filter_map(|(p, f)| f.then_some(p))
+            .collect();
+        self.nfev += batch.len();
+        let mut evaluated = self.fun.evaluate_batch(&batch).into_iter();
+        feasible
+            .iter()
+            .map(|f| match f.then(|| evaluated.next()).flatten() {
+                Some(value) if !value.is_nan() => value,
+                _ => f64::INFINITY,
+            })
+            .collect()
+    }
+}
+
+/// Check the options of `shgo`
+fn check_options(
+    options: &ShgoOptions,
+    lb: &[f64],
+    ub: &[f64],
+    constrained: bool,
+) -> Result<(), SwoopErrors> {
+    if lb.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must have at least one variable",
+        )));
+    }
+    if lb.iter().chain(ub).any(|b| !b.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Bounds must be finite",
+        )));
+    }
+    if options.iters == 0 || options.n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The number of iterations and of sampling points must be positive",
+        )));
+    }
+    if options.sampling_method == ShgoSampling::Sobol && lb.len() > sampling::SOBOL_MAX_DIM {
+        return Err(SwoopErrors::ArgumentError(format!(
+            "Sobol sampling supports at most {} variables",
+            sampling::SOBOL_MAX_DIM
+        )));
+    }
+    if constrained && options.local_method == MultivariateMethod::NelderMead {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Method NelderMead cannot handle constraints",
+        )));
+    }
+    Ok(())
+}
+
+/// Simplicial homology global optimisation
+///
+/// Find all the local minima of a function over a bounded domain that the sampling resolves,
+/// optionally subject to constraints. Refining the sampling with more iterations or points
+/// resolves minima that are closer together.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `bounds` - finite bounds on the variables
+/// * `constraints` - linear and nonlinear constraints
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if a bound is infinite, the number of iterations or
+/// points is zero, Sobol sampling is used with more than 21 variables, `NelderMead` is used with
+/// constraints, or the local method rejects its arguments
+#[allow(clippy::too_many_lines)]
+pub async fn shgo<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    bounds: &Bounds,
+    constraints: &[Constraint],
+    options: ShgoOptions,
+) -> Result<ShgoResult, SwoopErrors> {
+    let (lb, ub) = (bounds.lb(), bounds.ub());
+    let dim = lb.len();
+    check_options(&options, lb, ub, !constraints.is_empty())?;
+
+    let mut sampler = Sampler {
+        fun: &objective_function,
+        lb,
+        ub,
+        constraints,
+        nfev: 0,
+    };
+    let (points, neighbours) = match options.sampling_method {
+        ShgoSampling::Simplicial => {
+            let divisions = 1usize << options.iters.min(usize::BITS as usize - 1);
+            complex::freudenthal(dim, divisions)
+        }
+        ShgoSampling::Sobol => {
+            let points = sampling::sobol(None, options.n * options.iters, dim)?;
+            let neighbours = complex::delaunay(&points, dim);
+            (points, neighbours)
+        }
+    };
+    let values = sampler.values(&points);
+
+    // vertices lower than all their neighbours, lowest first. Of neighbours with equal values
+    // only the first is in the pool, such that a flat region still gives a minimiser
+    let lower = |k: usize, j: usize| values[k] < values[j] || (values[k] <= values[j] && k < j);
+    let mut pool: Vec<usize> = (0..points.len())
+        .filter(|&k| values[k].is_finite() && neighbours[k].iter().all(|&j| lower(k, j)))
+        .collect();
+    pool.sort_by(|a, b| values[*a].total_cmp(&values[*b]));
+
+    let mut minima: Vec<(Vec<f64>, f64)> = Vec::new();
+    let mut nlfev = 0;
+    let jac = objective_function
+        .gradient(&sampler.scale(&points[0]))
+        .is_some();
+    for &k in &pool {
+        let start = sampler.scale(&points[k]);
+        let result = minimise(
+            &objective_function,
+            &start,
+            options.local_method,
+            MinimiseOptions {
+                bounds: Some(bounds),
+                constraints,
+                jac,
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        nlfev += result.nfev;
+        let inside = result
+            .x
+            .iter()
+            .zip(lb.iter().zip(ub))
+            .all(|(xi, (l, u))| xi >= l && xi <= u);
+        let violation = constraints
+            .iter()
+            .flat_map(|c| c.violation(&result.x))
+            .fold(0f64, f64::max);
+        // the sampling point itself if the local minimisation did not improve it feasibly
+        let (x, fun) = if inside && violation <= FEASIBILITY_TOL && result.fun <= values[k] {
+            (result.x, result.fun)
+        } else {
+            (start, values[k])
+        };
+
+        let duplicate = minima.iter().position(|(y, _)| {
+            x.iter()
+                .zip(y)
+                .zip(lb.iter().zip(ub))
+                .all(|((a, b), (l, u))| (a - b).abs() <= DUPLICATE_TOL * (u - l))
+        });
+        match duplicate {
+            Some(i) if fun < minima[i].1 => minima[i] = (x, fun),
+            Some(_) => {}
+            None => minima.push((x, fun)),
+        }
+    }
+    minima.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    let (xl, funl): (Vec<Vec<f64>>, Vec<f64>) = minima.into_iter().unzip();
+    Ok(ShgoResult {
+        fun: funl.first().copied().unwrap_or(f64::INFINITY),
+        nfev: sampler.nfev + nlfev,
+        nlfev,
+        nit: options.iters,
+        nlmin: pool.len(),
+        success: !xl.is_empty(),
+        x: xl.first().cloned().unwrap_or_default(),
+        funl,
+        xl,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::LinearConstraint;
+    use approx::relative_eq;
+
+    /// Two minima at `(-1, 0)` and `(1, 0)`, the first one lower
+    struct DoubleWell {}
+
+    impl MultivariateObjectiveFunction for DoubleWell {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            (x[0].powi(2) - 1f64).powi(2) + 0.1 * x[0] + x[1].powi(2)
+        }
+    }
+
+    /// Six-hump camel function, six local minima and two global ones
+    struct Camel {}
+
+    impl MultivariateObjectiveFunction for Camel {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            let (a, b) = (x[0], x[1]);
+            (4f64 - 2.1 * a.powi(2) + a.powi(4) / 3f64) * a.powi(2)
+                + a * b
+                + (-4f64 + 4f64 * b.powi(2)) * b.powi(2)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_all_minima() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![-2f64, -1f64], vec![2f64, 1f64])?;
+        for sampling_method in [ShgoSampling::Simplicial, ShgoSampling::Sobol] {
+            let result = shgo(
+                DoubleWell {},
+                &bounds,
+                &[],
+                ShgoOptions {
+                    sampling_method,
+                    iters: 3,
+                    n: 32,
+                    ..ShgoOptions::default()
+                },
+            )
+            .await?;
+            assert!(result.success);
+            assert_eq!(result.xl.len(), 2, "{sampling_method:?}");
+            assert!(result.funl[0] < result.funl[1]);
+            assert!(relative_eq!(result.x[0], -1.012_273, epsilon = 1e-4));
+            assert!(relative_eq!(result.xl[1][0], 0.987_257, epsilon = 1e-4));
+            assert!(relative_eq!(result.xl[1][1], 0f64, epsilon = 1e-5));
+        }
+
+        let bounds = Bounds::new(vec![-3f64, -2f64], vec![3f64, 2f64])?;
+        let result = shgo(
+            Camel {},
+            &bounds,
+            &[],
+            ShgoOptions {
+                sampling_method: ShgoSampling::Sobol,
+                n: 512,
+                // trust-constr can step out of the shallow basins from its initial trust radius
+                local_method: MultivariateMethod::NelderMead,
+                ..ShgoOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(result.xl.len(), 6);
+        assert!(relative_eq!(result.funl[5], 2.104_250_3, epsilon = 1e-6));
+        assert!(relative_eq!(result.funl[0], -1.031_628_4, epsilon = 1e-6));
+        assert!(relative_eq!(result.funl[1], -1.031_628_4, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[0].abs(), 0.089_842, epsilon = 1e-4));
+        assert!(relative_eq!(result.x[1].abs(), 0.712_656, epsilon = 1e-4));
+        assert!(result.nfev > result.nlfev);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_constraints() -> Result<(), SwoopErrors> {
+        // x0 >= 0.2 removes the lower minimum
+        let bounds = Bounds::new(vec![-2f64, -1f64], vec![2f64, 1f64])?;
+        let constraints = [Constraint::from(LinearConstraint::new(
+            vec![vec![1f64, 0f64]],
+            vec![0.2],
+            vec![f64::INFINITY],
+        )?)];
+        let result = shgo(
+            DoubleWell {},
+            &bounds,
+            &constraints,
+            ShgoOptions {
+                iters: 3,
+                ..ShgoOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(result.xl.len(), 1);
+        assert!(relative_eq!(result.x[0], 0.987_257, epsilon = 1e-4));
+
+        // x0 + x1 >= 1.5 moves the minimum onto the constraint
+        let constraints = [Constraint::from(LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![1.5],
+            vec![f64::INFINITY],
+        )?)];
+        let result = shgo(
+            DoubleWell {},
+            &bounds,
+            &constraints,
+            ShgoOptions {
+                iters: 3,
+                ..ShgoOptions::default()
+            },
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0] + result.x[1], 1.5, epsilon = 1e-6));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let bounds = Bounds::new(vec![-1f64; 2], vec![1f64; 2]).unwrap();
+        let result = shgo(
+            DoubleWell {},
+            &bounds,
+            &[],
+            ShgoOptions {
+                iters: 0,
+                ..ShgoOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let unbounded = Bounds::new(vec![-1f64; 2], vec![f64::INFINITY; 2]).unwrap();
+        let result = shgo(DoubleWell {}, &unbounded, &[], ShgoOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let constraints = [Constraint::from(
+            LinearConstraint::new(vec![vec![1f64, 1f64]], vec![0f64], vec![1f64]).unwrap(),
+        )];
+        let result = shgo(
+            DoubleWell {},
+            &bounds,
+            &constraints,
+            ShgoOptions {
+                local_method: MultivariateMethod::NelderMead,
+                ..ShgoOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
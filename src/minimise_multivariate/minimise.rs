@@ -0,0 +1,345 @@
+use crate::constraints::{Bounds, Constraint};
+use crate::minimise_multivariate::{
+    nelder_mead, trust_constr, MultivariateCallback, MultivariateObjectiveFunction,
+    MultivariateOptimisationResult, NelderMeadOptions, TrustConstrOptions,
+};
+use crate::SwoopErrors;
+use std::str::FromStr;
+
+/// Multivariate optimisation method used by `minimise`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultivariateMethod {
+    /// Nelder-Mead downhill simplex, supports bounds but no constraints or derivatives
+    NelderMead,
+    /// Trust region constrained algorithm, supports bounds, constraints and derivatives
+    TrustConstr,
+}
+
+impl FromStr for MultivariateMethod {
+    type Err = SwoopErrors;
+
+    /// Parse a method from its name, e.g. `"nelder-mead"` or `"trust-constr"`. Case, `-` and
+    /// `_` are ignored.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| *c != '-' && *c != '_')
+            .collect::<String>()
+            .to_lowercase();
+        match name.as_str() {
+            "neldermead" => Ok(Self::NelderMead),
+            "trustconstr" => Ok(Self::TrustConstr),
+            _ => Err(SwoopErrors::ArgumentError(format!(
+                "Unknown multivariate method {s}"
+            ))),
+        }
+    }
+}
+
+/// Options for `minimise`, shared by all methods
+#[derive(Default)]
+pub struct MinimiseOptions<'a> {
+    /// Optional bounds on the variables
+    pub bounds: Option<&'a Bounds>,
+    /// Linear and nonlinear constraints
+    pub constraints: &'a [Constraint],
+    /// Use the gradient implemented by the objective function, otherwise it is approximated
+    pub jac: bool,
+    /// Use the Hessian implemented by the objective function, otherwise it is approximated
+    pub hess: bool,
+    /// Tolerance for termination, mapped to the tolerances of the method
+    pub tol: Option<f64>,
+    /// Maximum iterations, if not given the default of the method is used
+    pub maxiter: Option<usize>,
+    /// Optional callback called after each iteration, returning `true` stops the solver
+    pub callback: Option<MultivariateCallback<'a>>,
+}
+
+/// Objective function that only exposes the derivatives that were requested
+struct Capabilities<T: MultivariateObjectiveFunction> {
+    objective: T,
+    jac: bool,
+    hess: bool,
+}
+
+impl<T: MultivariateObjectiveFunction> MultivariateObjectiveFunction for Capabilities<T> {
+    fn evaluate(&self, x: &[f64]) -> f64 {
+        self.objective.evaluate(x)
+    }
+
+    fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+        if self.jac {
+            self.objective.gradient(x)
+        } else {
+            None
+        }
+    }
+
+    fn hessian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        if self.hess {
+            self.objective.hessian(x)
+        } else {
+            None
+        }
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        self.objective.evaluate_batch(points)
+    }
+}
+
+/// Clip the initial guess to the bounds, with a warning if it was outside them
+fn clip_initial_guess(x0: &[f64], bounds: Option<&Bounds>, warnings: &mut Vec<String>) -> Vec<f64> {
+    let mut x0 = x0.to_vec();
+    if let Some(bounds) = bounds {
+        let outside = x0
+            .iter()
+            .zip(bounds.lb().iter().zip(bounds.ub()))
+            .any(|(x, (l, u))| x < l || x > u);
+        if outside {
+            warnings.push(String::from(
+                "Initial guess is not within the bounds, it has been clipped",
+            ));
+            for (x, (l, u)) in x0.iter_mut().zip(bounds.lb().iter().zip(bounds.ub())) {
+                *x = x.max(*l).min(*u);
+            }
+        }
+    }
+    x0
+}
+
+/// Minimise a multivariate objective function with the given method
+///
+/// The combination of method and options is validated first. Options the method cannot use
+/// are reported in the `warnings` of the result rather than silently dropped.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `x0` - initial guess
+/// * `method` - optimisation method
+/// * `options` - bounds, constraints, derivatives, tolerance and callback
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the method cannot handle the constraints, `jac`
+/// or `hess` is set but not implemented by the objective function, the tolerance is negative
+/// or the method itself rejects its arguments
+pub async fn minimise<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    x0: &[f64],
+    method: MultivariateMethod,
+    options: MinimiseOptions<'_>,
+) -> Result<MultivariateOptimisationResult, SwoopErrors> {
+    if options.tol.is_some_and(|tol| tol < 0f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    if options.jac && objective_function.gradient(x0).is_none() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "`jac` is set but the objective function does not implement the gradient",
+        )));
+    }
+    if options.hess && objective_function.hessian(x0).is_none() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "`hess` is set but the objective function does not implement the Hessian",
+        )));
+    }
+
+    let mut warnings = Vec::new();
+    let objective = Capabilities {
+        objective: objective_function,
+        jac: options.jac,
+        hess: options.hess,
+    };
+
+    let mut result = match method {
+        MultivariateMethod::NelderMead => {
+            if !options.constraints.is_empty() {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Method NelderMead cannot handle constraints",
+                )));
+            }
+            if options.jac {
+                warnings.push(String::from(
+                    "Method NelderMead does not use the gradient, `jac` is ignored",
+                ));
+            }
+            if options.hess {
+                warnings.push(String::from(
+                    "Method NelderMead does not use the Hessian, `hess` is ignored",
+                ));
+            }
+            let x0 = clip_initial_guess(x0, options.bounds, &mut warnings);
+            let defaults = NelderMeadOptions::default();
+            let nelder_mead_options = NelderMeadOptions {
+                xatol: options.tol.unwrap_or(defaults.xatol),
+                fatol: options.tol.unwrap_or(defaults.fatol),
+                maxiter: options.maxiter,
+                ..defaults
+            };
+            nelder_mead(
+                objective,
+                &x0,
+                options.bounds,
+                nelder_mead_options,
+                options.callback,
+            )
+            .await?
+        }
+        MultivariateMethod::TrustConstr => {
+            let has_nonlinear = options
+                .constraints
+                .iter()
+                .any(|c| matches!(c, Constraint::Nonlinear(_)));
+            if options.hess && has_nonlinear {
+                warnings.push(String::from(
+                    "Method TrustConstr approximates the Hessian of the Lagrangian with \
+                     nonlinear constraints, `hess` is ignored",
+                ));
+            }
+            let defaults = TrustConstrOptions::default();
+            let trust_constr_options = TrustConstrOptions {
+                gtol: options.tol.unwrap_or(defaults.gtol),
+                xtol: options.tol.unwrap_or(defaults.xtol),
+                barrier_tol: options.tol.unwrap_or(defaults.barrier_tol),
+                maxiter: options.maxiter.unwrap_or(defaults.maxiter),
+                ..defaults
+            };
+            trust_constr(
+                objective,
+                x0,
+                options.bounds,
+                options.constraints,
+                trust_constr_options,
+                options.callback,
+            )
+            .await?
+            .into()
+        }
+    };
+
+    warnings.append(&mut result.warnings);
+    result.warnings = warnings;
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::LinearConstraint;
+    use approx::relative_eq;
+
+    struct Quadratic {}
+
+    impl MultivariateObjectiveFunction for Quadratic {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            (x[0] - 1f64).powi(2) + (x[1] + 2f64).powi(2)
+        }
+
+        fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+            Some(vec![2f64 * (x[0] - 1f64), 2f64 * (x[1] + 2f64)])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() -> Result<(), SwoopErrors> {
+        for name in ["nelder-mead", "trust_constr"] {
+            let method: MultivariateMethod = name.parse()?;
+            let result = minimise(
+                Quadratic {},
+                &[0f64, 0f64],
+                method,
+                MinimiseOptions {
+                    tol: Some(1e-8),
+                    ..MinimiseOptions::default()
+                },
+            )
+            .await?;
+            assert!(result.success);
+            assert!(result.warnings.is_empty());
+            assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-4));
+            assert!(relative_eq!(result.x[1], -2f64, epsilon = 1e-4));
+        }
+        assert!(matches!(
+            "bfgs".parse::<MultivariateMethod>(),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() -> Result<(), SwoopErrors> {
+        let constraints = vec![Constraint::Linear(LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![0f64],
+            vec![0f64],
+        )?)];
+        let constrained = minimise(
+            Quadratic {},
+            &[0f64, 0f64],
+            MultivariateMethod::NelderMead,
+            MinimiseOptions {
+                constraints: &constraints,
+                ..MinimiseOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(constrained, Err(SwoopErrors::ArgumentError(_))));
+
+        let missing_hessian = minimise(
+            Quadratic {},
+            &[0f64, 0f64],
+            MultivariateMethod::TrustConstr,
+            MinimiseOptions {
+                hess: true,
+                ..MinimiseOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(
+            missing_hessian,
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+
+        let bounds = Bounds::new(vec![2f64, f64::NEG_INFINITY], vec![3f64, f64::INFINITY])?;
+        let result = minimise(
+            Quadratic {},
+            &[0f64, 0f64],
+            MultivariateMethod::NelderMead,
+            MinimiseOptions {
+                bounds: Some(&bounds),
+                jac: true,
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(result.warnings.len(), 2);
+        assert!(relative_eq!(result.x[0], 2f64, epsilon = 1e-4));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_callback() -> Result<(), SwoopErrors> {
+        let mut history = Vec::new();
+        let mut callback = |x: &[f64], _f: f64| {
+            history.push(x.to_vec());
+            history.len() == 2
+        };
+        let result = minimise(
+            Quadratic {},
+            &[0f64, 0f64],
+            MultivariateMethod::TrustConstr,
+            MinimiseOptions {
+                jac: true,
+                callback: Some(&mut callback),
+                ..MinimiseOptions::default()
+            },
+        )
+        .await?;
+        assert!(!result.success);
+        assert_eq!(result.nit, 2);
+        assert_eq!(history.len(), 2);
+        Ok(())
+    }
+}
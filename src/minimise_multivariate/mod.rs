@@ -1,4 +1,109 @@
 //! Algorithms for multivariate function optimisation
 
+mod minimise;
+pub use minimise::{minimise, MinimiseOptions, MultivariateMethod};
+
 mod nelder_mead;
+pub use nelder_mead::{nelder_mead, NelderMeadOptions};
+
 mod slsqp;
+
+mod trust_constr;
+pub use trust_constr::{trust_constr, TrustConstrOptions, TrustConstrResult, TrustConstrStatus};
+
+/// Callback called by the multivariate solvers after each iteration with the current best
+/// solution and its objective value. Returning `true` stops the solver.
+pub type MultivariateCallback<'a> = &'a mut dyn FnMut(&[f64], f64) -> bool;
+
+/// Struct to represent the result of a multivariate function optimisation
+#[derive(Debug, Clone)]
+pub struct MultivariateOptimisationResult {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Gradient of the objective function at the solution, if the method computes it
+    pub jac: Option<Vec<f64>>,
+    /// Number of evaluations of the objective function
+    pub nfev: usize,
+    /// Number of evaluations of the gradient of the objective function
+    pub njev: usize,
+    /// Number of evaluations of the Hessian of the objective function
+    pub nhev: usize,
+    /// Number of iterations
+    pub nit: usize,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// Description of the reason the solver terminated
+    pub message: String,
+    /// Warnings about options that were ignored or adjusted
+    pub warnings: Vec<String>,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+impl From<TrustConstrResult> for MultivariateOptimisationResult {
+    fn from(result: TrustConstrResult) -> Self {
+        let message = match result.status {
+            TrustConstrStatus::MaxIterExceeded => "Maximum number of iterations has been exceeded",
+            TrustConstrStatus::GradientTolerance => "`gtol` termination condition is satisfied",
+            TrustConstrStatus::StepTolerance => "`xtol` termination condition is satisfied",
+            TrustConstrStatus::CallbackTerminated => "Stopped by the callback",
+        };
+        Self {
+            fun: result.fun,
+            jac: Some(result.grad),
+            nfev: result.nfev,
+            njev: result.njev,
+            nhev: result.nhev,
+            nit: result.nit,
+            success: result.success,
+            message: String::from(message),
+            warnings: Vec::new(),
+            x: result.x,
+        }
+    }
+}
+
+/// Trait to implement for a multivariate objective function
+pub trait MultivariateObjectiveFunction {
+    /// Method to implement the objective function that will be used for evaluation when
+    /// optimising
+    fn evaluate(&self, x: &[f64]) -> f64;
+
+    /// Method to implement the gradient of the objective function. If `None` is returned the
+    /// gradient is approximated using finite differences
+    fn gradient(&self, _x: &[f64]) -> Option<Vec<f64>> {
+        None
+    }
+
+    /// Method to implement the Hessian of the objective function. If `None` is returned the
+    /// Hessian is approximated with quasi-Newton updates
+    fn hessian(&self, _x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        None
+    }
+
+    /// Method to evaluate the objective function at a batch of independent points, such as a
+    /// population, a grid or the steps of a finite difference gradient. The points are
+    /// evaluated one after another unless this is implemented, with vectorised code or by
+    /// wrapping the objective function in `Parallel`
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        points.iter().map(|x| self.evaluate(x)).collect()
+    }
+}
+
+impl<T: MultivariateObjectiveFunction + ?Sized> MultivariateObjectiveFunction for &T {
+    fn evaluate(&self, x: &[f64]) -> f64 {
+        (**self).evaluate(x)
+    }
+
+    fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+        (**self).gradient(x)
+    }
+
+    fn hessian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        (**self).hessian(x)
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        (**self).evaluate_batch(points)
+    }
+}
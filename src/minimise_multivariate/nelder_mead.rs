@@ -1 +1,360 @@
-// todo
+use crate::constraints::Bounds;
+use crate::minimise_multivariate::{
+    MultivariateCallback, MultivariateObjectiveFunction, MultivariateOptimisationResult,
+};
+use crate::SwoopErrors;
+use std::cell::Cell;
+
+/// Options for the `nelder_mead` solver
+#[derive(Debug, Clone)]
+pub struct NelderMeadOptions {
+    /// Absolute error in the solution between iterations that is acceptable for convergence
+    pub xatol: f64,
+    /// Absolute error in the objective function between iterations that is acceptable for
+    /// convergence
+    pub fatol: f64,
+    /// Maximum iterations, defaults to `200 * n`
+    pub maxiter: Option<usize>,
+    /// Maximum evaluations of the objective function, defaults to `200 * n`
+    pub maxfev: Option<usize>,
+    /// Adapt the algorithm parameters to the dimension of the problem
+    pub adaptive: bool,
+    /// Initial simplex of `n + 1` vertices, if not given it is constructed around `x0`
+    pub initial_simplex: Option<Vec<Vec<f64>>>,
+}
+
+impl Default for NelderMeadOptions {
+    fn default() -> Self {
+        Self {
+            xatol: 1e-4,
+            fatol: 1e-4,
+            maxiter: None,
+            maxfev: None,
+            adaptive: false,
+            initial_simplex: None,
+        }
+    }
+}
+
+/// Clip `x` to the bounds, if any
+fn clip(x: &mut [f64], bounds: Option<&Bounds>) {
+    if let Some(bounds) = bounds {
+        for (xi, (l, u)) in x.iter_mut().zip(bounds.lb().iter().zip(bounds.ub())) {
+            *xi = xi.max(*l).min(*u);
+        }
+    }
+}
+
+/// `(1 + a) * xbar - a * x`
+fn extrapolate(xbar: &[f64], x: &[f64], a: f64) -> Vec<f64> {
+    xbar.iter()
+        .zip(x)
+        .map(|(xb, xi)| (1f64 + a) * xb - a * xi)
+        .collect()
+}
+
+/// Sort the vertices of the simplex by their objective value
+fn sort_simplex(sim: &mut Vec<Vec<f64>>, fsim: &mut Vec<f64>) {
+    let mut order: Vec<usize> = (0..fsim.len()).collect();
+    order.sort_by(|a, b| fsim[*a].total_cmp(&fsim[*b]));
+    *sim = order.iter().map(|i| sim[*i].clone()).collect();
+    *fsim = order.iter().map(|i| fsim[*i]).collect();
+}
+
+/// Nelder-Mead downhill simplex multivariate optimisation
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `x0` - initial guess
+/// * `bounds` - optional bounds on the variables, points outside the bounds are clipped
+/// * `options` - solver options
+/// * `callback` - optional callback called after each iteration, returning `true` stops the
+///   solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the bounds or initial simplex
+/// have the wrong dimensions or a tolerance is negative
+#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::too_many_lines)]
+pub async fn nelder_mead<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    x0: &[f64],
+    bounds: Option<&Bounds>,
+    options: NelderMeadOptions,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<MultivariateOptimisationResult, SwoopErrors> {
+    let n = x0.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    if options.xatol < 0f64 || options.fatol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    if let Some(bounds) = bounds {
+        if bounds.lb().len() != n {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Bounds must have one entry per variable",
+            )));
+        }
+    }
+
+    let (rho, chi, psi, sigma) = if options.adaptive {
+        let dim = n as f64;
+        (
+            1f64,
+            1f64 + 2f64 / dim,
+            0.75 - 1f64 / (2f64 * dim),
+            1f64 - 1f64 / dim,
+        )
+    } else {
+        (1f64, 2f64, 0.5, 0.5)
+    };
+    let maxiter = options.maxiter.unwrap_or(200 * n);
+    let maxfev = options.maxfev.unwrap_or(200 * n);
+
+    let mut sim: Vec<Vec<f64>> = if let Some(initial_simplex) = options.initial_simplex {
+        if initial_simplex.len() != n + 1 || initial_simplex.iter().any(|v| v.len() != n) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Initial simplex must have n + 1 vertices of dimension n",
+            )));
+        }
+        initial_simplex
+    } else {
+        let nonzdelt = 0.05;
+        let zdelt = 0.000_25;
+        let mut sim = vec![x0.to_vec()];
+        for k in 0..n {
+            let mut y = x0.to_vec();
+            if y[k] == 0f64 {
+                y[k] = zdelt;
+            } else {
+                y[k] *= 1f64 + nonzdelt;
+            }
+            sim.push(y);
+        }
+        sim
+    };
+
+    if let Some(bounds) = bounds {
+        // reflect vertices beyond the upper bound into the interior so that clipping does not
+        // make the simplex degenerate
+        for vertex in &mut sim {
+            for (xi, u) in vertex.iter_mut().zip(bounds.ub()) {
+                if *xi > *u {
+                    *xi = 2f64 * u - *xi;
+                }
+            }
+            clip(vertex, Some(bounds));
+        }
+    }
+
+    let fcalls = Cell::new(0usize);
+    let evaluate = |x: &[f64]| -> f64 {
+        fcalls.set(fcalls.get() + 1);
+        objective_function.evaluate(x)
+    };
+    let evaluate_batch = |xs: &[Vec<f64>]| -> Vec<f64> {
+        fcalls.set(fcalls.get() + xs.len());
+        objective_function.evaluate_batch(xs)
+    };
+
+    let mut fsim = evaluate_batch(&sim);
+    sort_simplex(&mut sim, &mut fsim);
+
+    let mut iterations = 1usize;
+    let mut stopped_by_callback = false;
+
+    while fcalls.get() < maxfev && iterations < maxiter {
+        let x_spread = sim[1..]
+            .iter()
+            .flat_map(|vertex| vertex.iter().zip(&sim[0]).map(|(a, b)| (a - b).abs()))
+            .fold(0f64, f64::max);
+        let f_spread = fsim[1..]
+            .iter()
+            .fold(0f64, |acc, f| acc.max((f - fsim[0]).abs()));
+        if x_spread <= options.xatol && f_spread <= options.fatol {
+            break;
+        }
+
+        // centroid of all vertices except the worst
+        let mut xbar = vec![0f64; n];
+        for vertex in &sim[..n] {
+            for (xb, xi) in xbar.iter_mut().zip(vertex) {
+                *xb += xi / n as f64;
+            }
+        }
+
+        let mut xr = extrapolate(&xbar, &sim[n], rho);
+        clip(&mut xr, bounds);
+        let fxr = evaluate(&xr);
+        let mut shrink = false;
+
+        if fxr < fsim[0] {
+            let mut xe = extrapolate(&xbar, &sim[n], rho * chi);
+            clip(&mut xe, bounds);
+            let fxe = evaluate(&xe);
+            if fxe < fxr {
+                sim[n] = xe;
+                fsim[n] = fxe;
+            } else {
+                sim[n] = xr;
+                fsim[n] = fxr;
+            }
+        } else if fxr < fsim[n - 1] {
+            sim[n] = xr;
+            fsim[n] = fxr;
+        } else if fxr < fsim[n] {
+            // outside contraction
+            let mut xc = extrapolate(&xbar, &sim[n], psi * rho);
+            clip(&mut xc, bounds);
+            let fxc = evaluate(&xc);
+            if fxc <= fxr {
+                sim[n] = xc;
+                fsim[n] = fxc;
+            } else {
+                shrink = true;
+            }
+        } else {
+            // inside contraction
+            let mut xcc = extrapolate(&xbar, &sim[n], -psi);
+            clip(&mut xcc, bounds);
+            let fxcc = evaluate(&xcc);
+            if fxcc < fsim[n] {
+                sim[n] = xcc;
+                fsim[n] = fxcc;
+            } else {
+                shrink = true;
+            }
+        }
+
+        if shrink {
+            let best = sim[0].clone();
+            for vertex in &mut sim[1..] {
+                for (xj, x0) in vertex.iter_mut().zip(&best) {
+                    *xj = x0 + sigma * (*xj - x0);
+                }
+                clip(vertex, bounds);
+            }
+            let values = evaluate_batch(&sim[1..]);
+            fsim[1..].copy_from_slice(&values);
+        }
+
+        iterations += 1;
+        sort_simplex(&mut sim, &mut fsim);
+
+        if let Some(callback) = callback.as_mut() {
+            if callback(&sim[0], fsim[0]) {
+                stopped_by_callback = true;
+                break;
+            }
+        }
+    }
+
+    let nfev = fcalls.get();
+    let (success, message) = if stopped_by_callback {
+        (false, "Stopped by the callback")
+    } else if nfev >= maxfev {
+        (
+            false,
+            "Maximum number of function evaluations has been exceeded",
+        )
+    } else if iterations >= maxiter {
+        (false, "Maximum number of iterations has been exceeded")
+    } else {
+        (true, "Optimisation terminated successfully")
+    };
+
+    Ok(MultivariateOptimisationResult {
+        fun: fsim[0],
+        jac: None,
+        nfev,
+        njev: 0,
+        nhev: 0,
+        nit: iterations - 1,
+        success,
+        message: String::from(message),
+        warnings: Vec::new(),
+        x: sim.swap_remove(0),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    struct Rosenbrock {}
+
+    impl MultivariateObjectiveFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            x.windows(2)
+                .map(|w| 100f64 * (w[1] - w[0].powi(2)).powi(2) + (1f64 - w[0]).powi(2))
+                .sum()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rosenbrock() -> Result<(), SwoopErrors> {
+        let options = NelderMeadOptions {
+            xatol: 1e-8,
+            fatol: 1e-8,
+            ..NelderMeadOptions::default()
+        };
+        let result = nelder_mead(
+            Rosenbrock {},
+            &[1.3f64, 0.7f64, 0.8f64, 1.9f64, 1.2f64],
+            None,
+            NelderMeadOptions {
+                maxiter: Some(5000),
+                maxfev: Some(5000),
+                ..options
+            },
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        for xi in result.x {
+            assert!(relative_eq!(xi, 1f64, epsilon = 1e-5));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bounds_and_callback() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![f64::NEG_INFINITY, 1.5f64], vec![0.5f64, f64::INFINITY])?;
+        let result = nelder_mead(
+            Rosenbrock {},
+            &[0f64, 2f64],
+            Some(&bounds),
+            NelderMeadOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(result.x[0] <= 0.5f64 && result.x[1] >= 1.5f64);
+        assert!(relative_eq!(result.x[0], 0.5f64, epsilon = 1e-3));
+        assert!(relative_eq!(result.x[1], 1.5f64, epsilon = 1e-3));
+
+        let mut calls = 0usize;
+        let mut callback = |_x: &[f64], _f: f64| {
+            calls += 1;
+            calls == 3
+        };
+        let stopped = nelder_mead(
+            Rosenbrock {},
+            &[0f64, 2f64],
+            None,
+            NelderMeadOptions::default(),
+            Some(&mut callback),
+        )
+        .await?;
+        assert!(!stopped.success);
+        assert_eq!(stopped.nit, 3);
+        Ok(())
+    }
+}
@@ -1 +1 @@
-// todo
+// todo: SLSQP, taking `constraints::Bounds` and `constraints::Constraint`
@@ -0,0 +1,267 @@
+use crate::constraints::{Bounds, Constraint};
+use crate::differentiate::{approx_derivative_batch, DifferenceMethod, DifferentiateOptions};
+use crate::SwoopErrors;
+
+/// Jacobians of the canonical constraints, `(J_eq, J_ineq)`
+type CanonicalJacobian = (Vec<Vec<f64>>, Vec<Vec<f64>>);
+
+/// A row of the canonical form, `sign * (c_source[index] - offset)`
+struct Row {
+    source: usize,
+    index: usize,
+    sign: f64,
+    offset: f64,
+    keep_feasible: bool,
+}
+
+/// Constraints converted to the canonical form `c_eq(x) = 0`, `c_ineq(x) <= 0`
+pub(super) struct CanonicalConstraint<'a> {
+    constraints: &'a [Constraint],
+    bounds: Option<&'a Bounds>,
+    eq: Vec<Row>,
+    ineq: Vec<Row>,
+    n: usize,
+}
+
+/// Split `lb <= c <= ub` into canonical rows
+fn push_rows(
+    source: usize,
+    lb: &[f64],
+    ub: &[f64],
+    keep_feasible: &[bool],
+    eq: &mut Vec<Row>,
+    ineq: &mut Vec<Row>,
+) {
+    for (index, ((&l, &u), &keep_feasible)) in lb.iter().zip(ub).zip(keep_feasible).enumerate() {
+        if (l - u).abs() < f64::EPSILON {
+            eq.push(Row {
+                source,
+                index,
+                sign: 1f64,
+                offset: l,
+                keep_feasible: false,
+            });
+            continue;
+        }
+        if u.is_finite() {
+            ineq.push(Row {
+                source,
+                index,
+                sign: 1f64,
+                offset: u,
+                keep_feasible,
+            });
+        }
+        if l.is_finite() {
+            ineq.push(Row {
+                source,
+                index,
+                sign: -1f64,
+                offset: l,
+                keep_feasible,
+            });
+        }
+    }
+}
+
+impl<'a> CanonicalConstraint<'a> {
+    /// Convert the bounds and constraints of a problem to canonical form
+    ///
+    /// # Parameters
+    /// * `bounds` - optional bounds on the variables
+    /// * `constraints` - linear and nonlinear constraints
+    /// * `x0` - initial guess, used to check the dimensions of the constraints
+    pub(super) fn new(
+        bounds: Option<&'a Bounds>,
+        constraints: &'a [Constraint],
+        x0: &[f64],
+    ) -> Result<Self, SwoopErrors> {
+        let n = x0.len();
+        let mut eq = Vec::new();
+        let mut ineq = Vec::new();
+        for (source, constraint) in constraints.iter().enumerate() {
+            if let Constraint::Linear(linear) = constraint {
+                if linear.a().iter().any(|row| row.len() != n) {
+                    return Err(SwoopErrors::ArgumentError(String::from(
+                        "Linear constraint matrix must have one column per variable",
+                    )));
+                }
+            }
+            if constraint.evaluate(x0).len() != constraint.lb().len() {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Constraint function must return one value per bound",
+                )));
+            }
+            let keep_feasible = vec![false; constraint.lb().len()];
+            push_rows(
+                source,
+                constraint.lb(),
+                constraint.ub(),
+                &keep_feasible,
+                &mut eq,
+                &mut ineq,
+            );
+        }
+        if let Some(bounds) = bounds {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            let infeasible = x0
+                .iter()
+                .zip(bounds.lb().iter().zip(bounds.ub()))
+                .zip(bounds.keep_feasible())
+                .any(|((x, (l, u)), keep_feasible)| *keep_feasible && (x <= l || x >= u));
+            if infeasible {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Initial guess must lie strictly within the bounds with keep_feasible set",
+                )));
+            }
+            push_rows(
+                constraints.len(),
+                bounds.lb(),
+                bounds.ub(),
+                bounds.keep_feasible(),
+                &mut eq,
+                &mut ineq,
+            );
+        }
+        Ok(Self {
+            constraints,
+            bounds,
+            eq,
+            ineq,
+            n,
+        })
+    }
+
+    /// Number of equality constraints
+    pub(super) fn n_eq(&self) -> usize {
+        self.eq.len()
+    }
+
+    /// Number of inequality constraints
+    pub(super) fn n_ineq(&self) -> usize {
+        self.ineq.len()
+    }
+
+    /// Whether each inequality constraint must be kept feasible
+    pub(super) fn enforce_feasibility(&self) -> Vec<bool> {
+        self.ineq.iter().map(|row| row.keep_feasible).collect()
+    }
+
+    /// Whether any of the constraints are nonlinear
+    pub(super) fn has_nonlinear(&self) -> bool {
+        self.constraints
+            .iter()
+            .any(|c| matches!(c, Constraint::Nonlinear(_)))
+    }
+
+    /// Values of each source constraint, with the bounds last
+    fn source_values(&self, x: &[f64]) -> Vec<Vec<f64>> {
+        let mut values: Vec<Vec<f64>> = self
+            .constraints
+            .iter()
+            .map(|constraint| constraint.evaluate(x))
+            .collect();
+        if self.bounds.is_some() {
+            values.push(x.to_vec());
+        }
+        values
+    }
+
+    /// Forward differences at `x`, with steps within the bounds when `x` is within them
+    pub(super) fn difference_options(&self, x: &[f64]) -> DifferentiateOptions {
+        let bounds = self.bounds.filter(|bounds| {
+            x.iter()
+                .zip(bounds.lb().iter().zip(bounds.ub()))
+                .all(|(xi, (l, u))| xi >= l && xi <= u)
+        });
+        DifferentiateOptions {
+            method: DifferenceMethod::Forward,
+            bounds: bounds.cloned(),
+            ..DifferentiateOptions::default()
+        }
+    }
+
+    /// Jacobians of each source constraint, with the bounds last
+    fn source_jacobians(&self, x: &[f64]) -> Result<Vec<Vec<Vec<f64>>>, SwoopErrors> {
+        let mut jacobians = self
+            .constraints
+            .iter()
+            .map(|constraint| match constraint {
+                Constraint::Linear(linear) => Ok(linear.a().to_vec()),
+                Constraint::Nonlinear(nonlinear) => match nonlinear.fun().jacobian(x) {
+                    Some(jacobian) => Ok(jacobian),
+                    None => approx_derivative_batch(
+                        |points| points.iter().map(|x| nonlinear.fun().evaluate(x)).collect(),
+                        x,
+                        None,
+                        &self.difference_options(x),
+                    ),
+                },
+            })
+            .collect::<Result<Vec<Vec<Vec<f64>>>, SwoopErrors>>()?;
+        if self.bounds.is_some() {
+            jacobians.push(
+                (0..self.n)
+                    .map(|i| {
+                        let mut row = vec![0f64; self.n];
+                        row[i] = 1f64;
+                        row
+                    })
+                    .collect(),
+            );
+        }
+        Ok(jacobians)
+    }
+
+    /// Evaluate the canonical constraints, returns `(c_eq, c_ineq)`
+    pub(super) fn evaluate(&self, x: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let values = self.source_values(x);
+        let canonical = |rows: &[Row]| -> Vec<f64> {
+            rows.iter()
+                .map(|row| row.sign * (values[row.source][row.index] - row.offset))
+                .collect()
+        };
+        (canonical(&self.eq), canonical(&self.ineq))
+    }
+
+    /// Jacobians of the canonical constraints, returns `(J_eq, J_ineq)`
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if the number of constraint values changes between
+    /// the steps of the finite differences
+    pub(super) fn jacobian(&self, x: &[f64]) -> Result<CanonicalJacobian, SwoopErrors> {
+        let jacobians = self.source_jacobians(x)?;
+        let canonical = |rows: &[Row]| -> Vec<Vec<f64>> {
+            rows.iter()
+                .map(|row| {
+                    jacobians[row.source][row.index]
+                        .iter()
+                        .map(|j| row.sign * j)
+                        .collect()
+                })
+                .collect()
+        };
+        Ok((canonical(&self.eq), canonical(&self.ineq)))
+    }
+
+    /// Map the multipliers of the canonical constraints back to the source constraints.
+    ///
+    /// Returns the multipliers of each constraint and the multipliers of the bounds.
+    pub(super) fn multipliers(&self, v_eq: &[f64], v_ineq: &[f64]) -> (Vec<Vec<f64>>, Vec<f64>) {
+        let mut multipliers: Vec<Vec<f64>> = self
+            .constraints
+            .iter()
+            .map(|constraint| vec![0f64; constraint.lb().len()])
+            .collect();
+        multipliers.push(vec![0f64; if self.bounds.is_some() { self.n } else { 0 }]);
+        for (row, v) in self.eq.iter().zip(v_eq).chain(self.ineq.iter().zip(v_ineq)) {
+            multipliers[row.source][row.index] += row.sign * v;
+        }
+        let bound_multipliers = multipliers.pop().unwrap_or_default();
+        (multipliers, bound_multipliers)
+    }
+}
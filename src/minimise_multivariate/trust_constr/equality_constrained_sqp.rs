@@ -0,0 +1,205 @@
+use super::projections::Projections;
+use super::qp_subproblem::{box_intersections, modified_dogleg, projected_cg, CgInfo};
+use super::tr_interior_point::BarrierSubproblem;
+use crate::linalg::{dot, mat_t_vec, mat_vec, norm, norm_inf};
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use crate::SwoopErrors;
+
+/// Rho from formula (3.51) of Byrd, Hribar and Nocedal
+const PENALTY_FACTOR: f64 = 0.3;
+const LARGE_REDUCTION_RATIO: f64 = 0.9;
+const INTERMEDIARY_REDUCTION_RATIO: f64 = 0.3;
+/// Eta from Byrd, Hribar and Nocedal
+const SUFFICIENT_REDUCTION_RATIO: f64 = 1e-8;
+const TRUST_ENLARGEMENT_FACTOR_L: f64 = 7.0;
+const TRUST_ENLARGEMENT_FACTOR_S: f64 = 2.0;
+const MAX_TRUST_REDUCTION: f64 = 0.5;
+const MIN_TRUST_REDUCTION: f64 = 0.1;
+const SOC_THRESHOLD: f64 = 0.1;
+/// Zeta from formula (3.21) of Byrd, Hribar and Nocedal
+const TR_FACTOR: f64 = 0.8;
+const BOX_FACTOR: f64 = 0.5;
+
+/// Point at which the subproblem has been evaluated
+pub(super) struct Iterate {
+    pub(super) z: Vec<f64>,
+    pub(super) fun: f64,
+    pub(super) constr: Vec<f64>,
+    pub(super) grad: Vec<f64>,
+    pub(super) jac: Vec<Vec<f64>>,
+}
+
+/// Solve an equality constrained subproblem using the Byrd-Omojokun trust region SQP method,
+/// the trust region is scaled by the subproblem and may be further restricted to the box
+/// `trust_lb <= d <= trust_ub`.
+///
+/// Returns the final iterate and trust radius.
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of constraint values changes between
+/// the steps of the finite differences
+#[allow(clippy::too_many_lines)]
+pub(super) fn equality_constrained_sqp<T: MultivariateObjectiveFunction>(
+    problem: &mut BarrierSubproblem<'_, '_, T>,
+    initial: Iterate,
+    initial_penalty: f64,
+    initial_trust_radius: f64,
+    trust_lb: &[f64],
+    trust_ub: &[f64],
+) -> Result<(Iterate, f64), SwoopErrors> {
+    let Iterate {
+        mut z,
+        fun: mut f,
+        constr: mut b,
+        grad: mut c,
+        jac: mut a,
+    } = initial;
+    let n = z.len();
+    let mut trust_radius = initial_trust_radius;
+    let mut penalty = initial_penalty;
+    let mut scaling = problem.scaling(&z);
+
+    let mut projections = Projections::new(&a, n);
+    let mut v: Vec<f64> = projections.least_squares(&c).iter().map(|vi| -vi).collect();
+    let mut hessian = problem.lagrangian_hessian(&z, &v);
+
+    let lagrangian_grad = |c: &[f64], a: &[Vec<f64>], v: &[f64]| -> Vec<f64> {
+        c.iter()
+            .zip(mat_t_vec(a, v, n))
+            .map(|(ci, ai)| ci + ai)
+            .collect()
+    };
+    let mut optimality = norm_inf(&lagrangian_grad(&c, &a, &v));
+    let mut constr_violation = norm_inf(&b);
+    let mut cg_info = CgInfo::default();
+
+    let box_lb: Vec<f64> = trust_lb.iter().map(|l| BOX_FACTOR * l).collect();
+    let box_ub: Vec<f64> = trust_ub.iter().map(|u| BOX_FACTOR * u).collect();
+
+    while !problem.stop_criteria(
+        &v,
+        optimality,
+        constr_violation,
+        trust_radius,
+        penalty,
+        cg_info,
+    ) {
+        // normal step, reduce the constraint violation
+        let dn = modified_dogleg(
+            &a,
+            &projections,
+            &b,
+            TR_FACTOR * trust_radius,
+            &box_lb,
+            &box_ub,
+        );
+
+        // tangential step, reduce the objective while keeping the linearised constraint
+        // violation constant
+        let h_dn = hessian.dot(&dn);
+        let c_t: Vec<f64> = h_dn.iter().zip(&c).map(|(h, ci)| h + ci).collect();
+        let trust_radius_t = (trust_radius.powi(2) - dot(&dn, &dn)).max(0f64).sqrt();
+        let lb_t: Vec<f64> = trust_lb.iter().zip(&dn).map(|(l, d)| l - d).collect();
+        let ub_t: Vec<f64> = trust_ub.iter().zip(&dn).map(|(u, d)| u - d).collect();
+        let (dt, info) = projected_cg(&hessian, &c_t, &projections, trust_radius_t, &lb_t, &ub_t);
+        cg_info = info;
+
+        let d: Vec<f64> = dn.iter().zip(&dt).map(|(x, y)| x + y).collect();
+
+        // update the penalty parameter and compute the predicted reduction of the merit
+        // function
+        let quadratic_model = 0.5 * dot(&hessian.dot(&d), &d) + dot(&c, &d);
+        let linearised_constr: Vec<f64> = mat_vec(&a, &d)
+            .iter()
+            .zip(&b)
+            .map(|(ad, bi)| ad + bi)
+            .collect();
+        let vpred = (norm(&b) - norm(&linearised_constr)).max(1e-16);
+        let previous_penalty = penalty;
+        if quadratic_model > 0f64 {
+            let new_penalty = quadratic_model / ((1f64 - PENALTY_FACTOR) * vpred);
+            penalty = penalty.max(new_penalty);
+        }
+        let predicted_reduction = -quadratic_model + penalty * vpred;
+
+        // actual reduction of the merit function
+        let merit_function = f + penalty * norm(&b);
+        let mut z_next: Vec<f64> = z
+            .iter()
+            .zip(scaling.iter().zip(&d))
+            .map(|(zi, (si, di))| zi + si * di)
+            .collect();
+        let (mut f_next, mut b_next) = problem.function_and_constraints(&mut z_next);
+        let merit_function_next = f_next + penalty * norm(&b_next);
+        let actual_reduction = merit_function - merit_function_next;
+        let mut reduction_ratio = actual_reduction / predicted_reduction;
+        if reduction_ratio.is_nan() {
+            reduction_ratio = f64::NEG_INFINITY;
+        }
+
+        // second order correction
+        if reduction_ratio < SUFFICIENT_REDUCTION_RATIO && norm(&dn) <= SOC_THRESHOLD * norm(&dt) {
+            let neg_b_next: Vec<f64> = b_next.iter().map(|bi| -bi).collect();
+            let y = projections.least_norm(&neg_b_next);
+            let (_, t, intersect) = box_intersections(&d, &y, trust_lb, trust_ub, false);
+            let mut z_soc: Vec<f64> = z
+                .iter()
+                .zip(scaling.iter().zip(d.iter().zip(&y)))
+                .map(|(zi, (si, (di, yi)))| zi + si * (di + t * yi))
+                .collect();
+            let (f_soc, b_soc) = problem.function_and_constraints(&mut z_soc);
+            let merit_function_soc = f_soc + penalty * norm(&b_soc);
+            let reduction_ratio_soc = (merit_function - merit_function_soc) / predicted_reduction;
+            if intersect && reduction_ratio_soc >= SUFFICIENT_REDUCTION_RATIO {
+                z_next = z_soc;
+                f_next = f_soc;
+                b_next = b_soc;
+                reduction_ratio = reduction_ratio_soc;
+            }
+        }
+
+        // update the trust radius
+        let d_norm = norm(&d);
+        if reduction_ratio >= LARGE_REDUCTION_RATIO {
+            trust_radius = trust_radius.max(TRUST_ENLARGEMENT_FACTOR_L * d_norm);
+        } else if reduction_ratio >= INTERMEDIARY_REDUCTION_RATIO {
+            trust_radius = trust_radius.max(TRUST_ENLARGEMENT_FACTOR_S * d_norm);
+        } else if reduction_ratio < SUFFICIENT_REDUCTION_RATIO {
+            let trust_reduction = (1f64 - SUFFICIENT_REDUCTION_RATIO) / (1f64 - reduction_ratio);
+            let new_trust_radius = trust_reduction * d_norm;
+            if new_trust_radius >= MAX_TRUST_REDUCTION * trust_radius {
+                trust_radius *= MAX_TRUST_REDUCTION;
+            } else if new_trust_radius >= MIN_TRUST_REDUCTION * trust_radius {
+                trust_radius = new_trust_radius;
+            } else {
+                trust_radius *= MIN_TRUST_REDUCTION;
+            }
+        }
+
+        if reduction_ratio >= SUFFICIENT_REDUCTION_RATIO {
+            z = z_next;
+            f = f_next;
+            b = b_next;
+            (c, a) = problem.gradient_and_jacobian(&z)?;
+            scaling = problem.scaling(&z);
+            projections = Projections::new(&a, n);
+            v = projections.least_squares(&c).iter().map(|vi| -vi).collect();
+            hessian = problem.lagrangian_hessian(&z, &v);
+            optimality = norm_inf(&lagrangian_grad(&c, &a, &v));
+            constr_violation = norm_inf(&b);
+        } else {
+            penalty = previous_penalty;
+        }
+    }
+
+    Ok((
+        Iterate {
+            z,
+            fun: f,
+            constr: b,
+            grad: c,
+            jac: a,
+        },
+        trust_radius,
+    ))
+}
@@ -0,0 +1,428 @@
+//! Trust region algorithm for constrained optimisation
+//!
+//! Equality constrained problems are solved with the Byrd-Omojokun trust region SQP method,
+//! inequality constraints are handled with a barrier interior point method that solves a
+//! sequence of equality constrained barrier subproblems.
+//!
+//! # References
+//! * Byrd, Hribar and Nocedal, "An interior point algorithm for large-scale nonlinear
+//!   programming", SIAM Journal on Optimization 9.4 (1999)
+//! * Lalee, Nocedal and Plantenga, "On the implementation of an algorithm for large-scale
+//!   equality constrained optimization", SIAM Journal on Optimization 8.3 (1998)
+
+mod canonical_constraint;
+mod equality_constrained_sqp;
+mod projections;
+mod qp_subproblem;
+mod tr_interior_point;
+
+use crate::constraints::{Bounds, Constraint};
+use crate::minimise_multivariate::{MultivariateCallback, MultivariateObjectiveFunction};
+use crate::SwoopErrors;
+use canonical_constraint::CanonicalConstraint;
+
+/// Options for the `trust_constr` solver
+#[derive(Debug, Clone)]
+pub struct TrustConstrOptions {
+    /// Tolerance for the norm of the Lagrangian gradient and the constraint violation
+    pub gtol: f64,
+    /// Tolerance for the trust region radius
+    pub xtol: f64,
+    /// Tolerance for the barrier parameter, only used when there are inequality constraints
+    pub barrier_tol: f64,
+    /// Initial trust region radius
+    pub initial_tr_radius: f64,
+    /// Initial penalty of the constraint violation in the merit function
+    pub initial_constr_penalty: f64,
+    /// Initial barrier parameter
+    pub initial_barrier_parameter: f64,
+    /// Initial tolerance for the barrier subproblems
+    pub initial_barrier_tolerance: f64,
+    /// Maximum iterations
+    pub maxiter: usize,
+}
+
+impl Default for TrustConstrOptions {
+    fn default() -> Self {
+        Self {
+            gtol: 1e-8,
+            xtol: 1e-8,
+            barrier_tol: 1e-8,
+            initial_tr_radius: 1.0,
+            initial_constr_penalty: 1.0,
+            initial_barrier_parameter: 0.1,
+            initial_barrier_tolerance: 0.1,
+            maxiter: 1000,
+        }
+    }
+}
+
+/// Reason the `trust_constr` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrustConstrStatus {
+    /// The maximum number of iterations was exceeded
+    #[default]
+    MaxIterExceeded,
+    /// The Lagrangian gradient and constraint violation are below `gtol`
+    GradientTolerance,
+    /// The trust region radius is below `xtol`
+    StepTolerance,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+}
+
+/// Struct to represent the result of a `trust_constr` optimisation
+#[derive(Debug, Clone)]
+pub struct TrustConstrResult {
+    /// Value of the objective function
+    pub fun: f64,
+    /// Gradient of the objective function at the solution
+    pub grad: Vec<f64>,
+    /// Gradient of the Lagrangian at the solution
+    pub lagrangian_grad: Vec<f64>,
+    /// Lagrange multipliers of each constraint, in the order the constraints were given
+    pub constraint_multipliers: Vec<Vec<f64>>,
+    /// Lagrange multipliers of the bounds, empty if no bounds were given
+    pub bound_multipliers: Vec<f64>,
+    /// Barrier parameter at termination
+    pub barrier_parameter: f64,
+    /// Tolerance of the barrier subproblem at termination
+    pub barrier_tolerance: f64,
+    /// Infinity norm of the Lagrangian gradient
+    pub optimality: f64,
+    /// Maximum constraint violation
+    pub constr_violation: f64,
+    /// Optimality at each iteration
+    pub optimality_history: Vec<f64>,
+    /// Constraint violation at each iteration
+    pub constr_violation_history: Vec<f64>,
+    /// Trust region radius at termination
+    pub tr_radius: f64,
+    /// Penalty of the constraint violation in the merit function at termination
+    pub constr_penalty: f64,
+    /// Number of iterations
+    pub nit: usize,
+    /// Number of evaluations of the objective function
+    pub nfev: usize,
+    /// Number of evaluations of the gradient of the objective function
+    pub njev: usize,
+    /// Number of evaluations of the Hessian of the objective function
+    pub nhev: usize,
+    /// Total number of conjugate gradient iterations
+    pub cg_niter: usize,
+    /// Reason the solver terminated
+    pub status: TrustConstrStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// The solution of the optimization
+    pub x: Vec<f64>,
+}
+
+/// State of the solver, updated at every iteration
+#[derive(Debug, Default)]
+struct State {
+    x: Vec<f64>,
+    fun: f64,
+    grad: Vec<f64>,
+    lagrangian_grad: Vec<f64>,
+    v: Vec<f64>,
+    optimality: f64,
+    constr_violation: f64,
+    optimality_history: Vec<f64>,
+    constr_violation_history: Vec<f64>,
+    tr_radius: f64,
+    constr_penalty: f64,
+    barrier_parameter: f64,
+    barrier_tolerance: f64,
+    nit: usize,
+    nfev: usize,
+    njev: usize,
+    nhev: usize,
+    cg_niter: usize,
+    status: TrustConstrStatus,
+}
+
+/// Trust region constrained multivariate optimisation
+///
+/// Minimise `f(x)` subject to the bounds and constraints, see the module documentation for
+/// details of the algorithm.
+///
+/// # Parameters
+/// * `objective_function` - objective struct that implements the trait
+///   `MultivariateObjectiveFunction`
+/// * `x0` - initial guess
+/// * `bounds` - optional bounds on the variables
+/// * `constraints` - linear and nonlinear constraints
+/// * `options` - solver options
+/// * `callback` - optional callback called after each iteration, returning `true` stops the
+///   solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the dimensions of `x0`, the bounds and the
+/// constraints are inconsistent, there are more equality constraints than variables, a
+/// tolerance is negative or the number of constraint values changes between the steps of the
+/// finite differences
+pub async fn trust_constr<T: MultivariateObjectiveFunction>(
+    objective_function: T,
+    x0: &[f64],
+    bounds: Option<&Bounds>,
+    constraints: &[Constraint],
+    options: TrustConstrOptions,
+    callback: Option<MultivariateCallback<'_>>,
+) -> Result<TrustConstrResult, SwoopErrors> {
+    if x0.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    if options.gtol < 0f64 || options.xtol < 0f64 || options.barrier_tol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    let canonical = CanonicalConstraint::new(bounds, constraints, x0)?;
+    if canonical.n_eq() > x0.len() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "There are more equality constraints than variables",
+        )));
+    }
+
+    let state = tr_interior_point::tr_interior_point(
+        &objective_function,
+        &canonical,
+        x0,
+        &options,
+        callback,
+    )?;
+
+    let (v_eq, v_ineq) = state.v.split_at(canonical.n_eq());
+    let (constraint_multipliers, bound_multipliers) = canonical.multipliers(v_eq, v_ineq);
+
+    Ok(TrustConstrResult {
+        fun: state.fun,
+        grad: state.grad,
+        lagrangian_grad: state.lagrangian_grad,
+        constraint_multipliers,
+        bound_multipliers,
+        barrier_parameter: state.barrier_parameter,
+        barrier_tolerance: state.barrier_tolerance,
+        optimality: state.optimality,
+        constr_violation: state.constr_violation,
+        optimality_history: state.optimality_history,
+        constr_violation_history: state.constr_violation_history,
+        tr_radius: state.tr_radius,
+        constr_penalty: state.constr_penalty,
+        nit: state.nit,
+        nfev: state.nfev,
+        njev: state.njev,
+        nhev: state.nhev,
+        cg_niter: state.cg_niter,
+        status: state.status,
+        success: matches!(
+            state.status,
+            TrustConstrStatus::GradientTolerance | TrustConstrStatus::StepTolerance
+        ),
+        x: state.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::{ConstraintFunction, LinearConstraint, NonlinearConstraint};
+    use approx::relative_eq;
+    use std::cell::Cell;
+
+    struct Rosenbrock {}
+
+    impl MultivariateObjectiveFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            100f64 * (x[1] - x[0].powi(2)).powi(2) + (1f64 - x[0]).powi(2)
+        }
+
+        fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+            Some(vec![
+                -400f64 * x[0] * (x[1] - x[0].powi(2)) - 2f64 * (1f64 - x[0]),
+                200f64 * (x[1] - x[0].powi(2)),
+            ])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rosenbrock_unconstrained() -> Result<(), SwoopErrors> {
+        let result = trust_constr(
+            Rosenbrock {},
+            &[-1.2f64, 1f64],
+            None,
+            &[],
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 1f64, epsilon = 1e-6));
+        assert!(relative_eq!(result.fun, 0f64, epsilon = 1e-10));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_equality_constrained() -> Result<(), SwoopErrors> {
+        struct SumOfSquares {}
+
+        impl MultivariateObjectiveFunction for SumOfSquares {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                x.iter().map(|xi| xi.powi(2)).sum()
+            }
+        }
+
+        let constraints = vec![Constraint::Linear(LinearConstraint::new(
+            vec![vec![1f64, 1f64]],
+            vec![1f64],
+            vec![1f64],
+        )?)];
+        let result = trust_constr(
+            SumOfSquares {},
+            &[2f64, 0f64],
+            None,
+            &constraints,
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 0.5f64, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 0.5f64, epsilon = 1e-6));
+        assert!(relative_eq!(
+            result.constraint_multipliers[0][0],
+            -1f64,
+            epsilon = 1e-5
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bounds() -> Result<(), SwoopErrors> {
+        struct Quadratic {}
+
+        impl MultivariateObjectiveFunction for Quadratic {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                (x[0] - 2f64).powi(2) + (x[1] + 1f64).powi(2)
+            }
+        }
+
+        let bounds = Bounds::new(vec![f64::NEG_INFINITY, 0f64], vec![1f64, f64::INFINITY])?;
+        let result = trust_constr(
+            Quadratic {},
+            &[0f64, 1f64],
+            Some(&bounds),
+            &[],
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 0f64, epsilon = 1e-6));
+        assert!(relative_eq!(
+            result.bound_multipliers[0],
+            2f64,
+            epsilon = 1e-4
+        ));
+        assert!(relative_eq!(
+            result.bound_multipliers[1],
+            -2f64,
+            epsilon = 1e-4
+        ));
+        assert!(result.barrier_parameter < 1e-8);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rosenbrock_constrained() -> Result<(), SwoopErrors> {
+        struct Cons {}
+
+        impl ConstraintFunction for Cons {
+            fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                vec![x[0].powi(2) + x[1], x[0].powi(2) - x[1]]
+            }
+
+            fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+                Some(vec![vec![2f64 * x[0], 1f64], vec![2f64 * x[0], -1f64]])
+            }
+        }
+
+        let bounds = Bounds::new(vec![0f64, -0.5f64], vec![1f64, 2f64])?;
+        let constraints = vec![
+            Constraint::Linear(LinearConstraint::new(
+                vec![vec![1f64, 2f64], vec![2f64, 1f64]],
+                vec![f64::NEG_INFINITY, 1f64],
+                vec![1f64, 1f64],
+            )?),
+            Constraint::Nonlinear(NonlinearConstraint::new(
+                Cons {},
+                vec![f64::NEG_INFINITY, f64::NEG_INFINITY],
+                vec![1f64, 1f64],
+            )?),
+        ];
+        let result = trust_constr(
+            Rosenbrock {},
+            &[0.5f64, 0f64],
+            Some(&bounds),
+            &constraints,
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 0.414_944_315, epsilon = 1e-6));
+        assert!(relative_eq!(result.x[1], 0.170_111_369, epsilon = 1e-6));
+        assert!(result.constr_violation < 1e-8);
+        assert_eq!(result.optimality_history.len(), result.nit);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_keep_feasible() -> Result<(), SwoopErrors> {
+        struct Recorder {
+            min_x0: Cell<f64>,
+        }
+
+        impl MultivariateObjectiveFunction for Recorder {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                self.min_x0.set(self.min_x0.get().min(x[0]));
+                (x[0] - 0.5f64).powi(2) + x[1].powi(2)
+            }
+        }
+
+        let recorder = Recorder {
+            min_x0: Cell::new(f64::INFINITY),
+        };
+        let bounds = Bounds::new(vec![1f64, f64::NEG_INFINITY], vec![f64::INFINITY; 2])?
+            .with_keep_feasible(vec![true, false])?;
+        let result = trust_constr(
+            &recorder,
+            &[4f64, 1f64],
+            Some(&bounds),
+            &[],
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 1f64, epsilon = 1e-6));
+        assert!(recorder.min_x0.get() > 1f64);
+
+        let infeasible = trust_constr(
+            &recorder,
+            &[0f64, 1f64],
+            Some(&bounds),
+            &[],
+            TrustConstrOptions::default(),
+            None,
+        )
+        .await;
+        assert!(matches!(infeasible, Err(SwoopErrors::ArgumentError(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use crate::linalg;
+
+/// Projections associated with a constraint Jacobian `A`, computed from a QR factorisation of
+/// `A^T`
+pub(super) struct Projections {
+    /// Orthonormal basis of the range of `A^T`, one row per variable
+    q: Vec<Vec<f64>>,
+    /// Upper triangular factor
+    r: Vec<Vec<f64>>,
+    /// Number of constraints
+    m: usize,
+}
+
+impl Projections {
+    /// Factorise the constraint Jacobian
+    ///
+    /// # Parameters
+    /// * `a` - constraint Jacobian, one row per constraint
+    /// * `n` - number of variables
+    pub(super) fn new(a: &[Vec<f64>], n: usize) -> Self {
+        let m = a.len();
+        let (q, r) = linalg::qr(&linalg::transpose(a, n), m);
+        Self { q, r, m }
+    }
+
+    /// `Q^T x`
+    fn q_t(&self, x: &[f64]) -> Vec<f64> {
+        linalg::mat_t_vec(&self.q, x, self.m)
+    }
+
+    /// Projection of `x` onto the null space of `A`, `Z x = x - A^T (A A^T)^-1 A x`
+    pub(super) fn null_space(&self, x: &[f64]) -> Vec<f64> {
+        let qx = linalg::mat_vec(&self.q, &self.q_t(x));
+        x.iter().zip(qx).map(|(xi, qi)| xi - qi).collect()
+    }
+
+    /// Least squares solution of `A^T v = x`, `v = (A A^T)^-1 A x`
+    pub(super) fn least_squares(&self, x: &[f64]) -> Vec<f64> {
+        linalg::solve_upper_triangular(&self.r, &self.q_t(x))
+    }
+
+    /// Least norm solution of `A x = b`, `x = A^T (A A^T)^-1 b`
+    pub(super) fn least_norm(&self, b: &[f64]) -> Vec<f64> {
+        linalg::mat_vec(
+            &self.q,
+            &linalg::solve_upper_triangular_transpose(&self.r, b),
+        )
+    }
+}
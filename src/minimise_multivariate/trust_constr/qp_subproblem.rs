@@ -0,0 +1,320 @@
+use super::projections::Projections;
+use crate::linalg::{dot, mat_vec, norm};
+
+/// Lagrangian Hessian of the barrier subproblem, a dense block for the variables and a diagonal
+/// block for the slack variables
+pub(super) struct Hessian {
+    pub(super) xx: Vec<Vec<f64>>,
+    pub(super) ss: Vec<f64>,
+}
+
+impl Hessian {
+    /// Hessian vector product
+    pub(super) fn dot(&self, p: &[f64]) -> Vec<f64> {
+        let n = self.xx.len();
+        let mut out = mat_vec(&self.xx, &p[..n]);
+        out.extend(self.ss.iter().zip(&p[n..]).map(|(h, pi)| h * pi));
+        out
+    }
+}
+
+/// Information about the projected conjugate gradient iterations
+#[derive(Debug, Clone, Copy, Default)]
+pub(super) struct CgInfo {
+    /// Number of iterations performed
+    pub(super) niter: usize,
+}
+
+/// Find the interval `ta <= t <= tb` for which `z + t d` lies inside the ball of radius
+/// `trust_radius`, restricted to `0 <= t <= 1` unless `entire_line` is set.
+///
+/// Returns `(ta, tb, intersect)`.
+pub(super) fn sphere_intersections(
+    z: &[f64],
+    d: &[f64],
+    trust_radius: f64,
+    entire_line: bool,
+) -> (f64, f64, bool) {
+    if norm(d) == 0f64 {
+        return (0f64, 0f64, false);
+    }
+    if trust_radius.is_infinite() {
+        return if entire_line {
+            (f64::NEG_INFINITY, f64::INFINITY, true)
+        } else {
+            (0f64, 1f64, true)
+        };
+    }
+    let a = dot(d, d);
+    let b = 2f64 * dot(z, d);
+    let c = dot(z, z) - trust_radius.powi(2);
+    let discriminant = b * b - 4f64 * a * c;
+    if discriminant < 0f64 {
+        return (0f64, 0f64, false);
+    }
+    let aux = b + discriminant.sqrt().copysign(b);
+    if aux == 0f64 {
+        return (0f64, 0f64, false);
+    }
+    let (ta, tb) = {
+        let t1 = -aux / (2f64 * a);
+        let t2 = -2f64 * c / aux;
+        (t1.min(t2), t1.max(t2))
+    };
+    if entire_line {
+        (ta, tb, true)
+    } else if tb < 0f64 || ta > 1f64 {
+        (0f64, 0f64, false)
+    } else {
+        (ta.max(0f64), tb.min(1f64), true)
+    }
+}
+
+/// Find the interval `ta <= t <= tb` for which `z + t d` lies inside the box `lb <= x <= ub`,
+/// restricted to `0 <= t <= 1` unless `entire_line` is set.
+///
+/// Returns `(ta, tb, intersect)`.
+pub(super) fn box_intersections(
+    z: &[f64],
+    d: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+    entire_line: bool,
+) -> (f64, f64, bool) {
+    if norm(d) == 0f64 {
+        return (0f64, 0f64, false);
+    }
+    let mut ta = f64::NEG_INFINITY;
+    let mut tb = f64::INFINITY;
+    for i in 0..z.len() {
+        if d[i] == 0f64 {
+            if z[i] < lb[i] || z[i] > ub[i] {
+                return (0f64, 0f64, false);
+            }
+            continue;
+        }
+        let t_lb = (lb[i] - z[i]) / d[i];
+        let t_ub = (ub[i] - z[i]) / d[i];
+        ta = ta.max(t_lb.min(t_ub));
+        tb = tb.min(t_lb.max(t_ub));
+    }
+    let intersect = ta <= tb;
+    if entire_line {
+        (ta, tb, intersect)
+    } else if tb < 0f64 || ta > 1f64 {
+        (0f64, 0f64, false)
+    } else {
+        (ta.max(0f64), tb.min(1f64), intersect)
+    }
+}
+
+/// Intersection of the segment (or line) `z + t d` with both the box and the ball.
+///
+/// Returns `(ta, tb, intersect)`.
+pub(super) fn box_sphere_intersections(
+    z: &[f64],
+    d: &[f64],
+    lb: &[f64],
+    ub: &[f64],
+    trust_radius: f64,
+    entire_line: bool,
+) -> (f64, f64, bool) {
+    let (ta_b, tb_b, intersect_b) = box_intersections(z, d, lb, ub, entire_line);
+    let (ta_s, tb_s, intersect_s) = sphere_intersections(z, d, trust_radius, entire_line);
+    let ta = ta_b.max(ta_s);
+    let tb = tb_b.min(tb_s);
+    (ta, tb, intersect_b && intersect_s && ta <= tb)
+}
+
+/// Check if `x` lies inside the box `lb <= x <= ub`
+pub(super) fn inside_box_boundaries(x: &[f64], lb: &[f64], ub: &[f64]) -> bool {
+    x.iter()
+        .zip(lb.iter().zip(ub))
+        .all(|(xi, (l, u))| l <= xi && xi <= u)
+}
+
+/// Clip `x` to the box `lb <= x <= ub`, guards against roundoff errors
+fn reinforce_box_boundaries(x: &mut [f64], lb: &[f64], ub: &[f64]) {
+    for (xi, (l, u)) in x.iter_mut().zip(lb.iter().zip(ub)) {
+        *xi = xi.max(*l).min(*u);
+    }
+}
+
+/// `z + t d`
+fn along(z: &[f64], d: &[f64], t: f64) -> Vec<f64> {
+    z.iter().zip(d).map(|(zi, di)| zi + t * di).collect()
+}
+
+/// Approximately minimise `||A x + b||` subject to `||x|| <= trust_radius` and `lb <= x <= ub`
+/// using a dogleg path between the Cauchy point and the least norm (Newton) point.
+pub(super) fn modified_dogleg(
+    a: &[Vec<f64>],
+    projections: &Projections,
+    b: &[f64],
+    trust_radius: f64,
+    lb: &[f64],
+    ub: &[f64],
+) -> Vec<f64> {
+    let neg_b: Vec<f64> = b.iter().map(|bi| -bi).collect();
+    let newton_point = projections.least_norm(&neg_b);
+    if inside_box_boundaries(&newton_point, lb, ub) && norm(&newton_point) <= trust_radius {
+        return newton_point;
+    }
+
+    let n = newton_point.len();
+    let g = crate::linalg::mat_t_vec(a, b, n);
+    let a_g = mat_vec(a, &g);
+    let a_g_norm2 = dot(&a_g, &a_g);
+    let cauchy_point: Vec<f64> = if a_g_norm2 > 0f64 {
+        let scale = -dot(&g, &g) / a_g_norm2;
+        g.iter().map(|gi| scale * gi).collect()
+    } else {
+        vec![0f64; n]
+    };
+    let origin_point = vec![0f64; n];
+
+    // the segment between the Cauchy point and the Newton point
+    let p: Vec<f64> = newton_point
+        .iter()
+        .zip(&cauchy_point)
+        .map(|(x, c)| x - c)
+        .collect();
+    let (_, alpha, intersect) =
+        box_sphere_intersections(&cauchy_point, &p, lb, ub, trust_radius, false);
+    let x1 = if intersect {
+        along(&cauchy_point, &p, alpha)
+    } else {
+        let (_, alpha, _) =
+            box_sphere_intersections(&origin_point, &cauchy_point, lb, ub, trust_radius, false);
+        along(&origin_point, &cauchy_point, alpha)
+    };
+
+    // the segment between the origin and the Newton point
+    let (_, alpha, _) =
+        box_sphere_intersections(&origin_point, &newton_point, lb, ub, trust_radius, false);
+    let x2 = along(&origin_point, &newton_point, alpha);
+
+    let residual = |x: &[f64]| -> f64 {
+        let ax = mat_vec(a, x);
+        norm(
+            &ax.iter()
+                .zip(b)
+                .map(|(axi, bi)| axi + bi)
+                .collect::<Vec<f64>>(),
+        )
+    };
+    if residual(&x1) < residual(&x2) {
+        x1
+    } else {
+        x2
+    }
+}
+
+/// Solve the equality constrained quadratic problem
+///
+/// `min 1/2 x^T H x + c^T x` subject to `A x = 0`, `||x|| <= trust_radius` and `lb <= x <= ub`
+///
+/// using the projected conjugate gradient method.
+pub(super) fn projected_cg(
+    hessian: &Hessian,
+    c: &[f64],
+    projections: &Projections,
+    trust_radius: f64,
+    lb: &[f64],
+    ub: &[f64],
+) -> (Vec<f64>, CgInfo) {
+    let close_to_zero = 1e-25;
+    let n = c.len();
+    let max_iter = n;
+    let max_infeasible_iter = n;
+
+    let mut x = vec![0f64; n];
+    let mut r = projections.null_space(c);
+    let mut g = projections.null_space(&r);
+    let mut p: Vec<f64> = g.iter().map(|gi| -gi).collect();
+    let mut h_p = hessian.dot(&p);
+    let mut rt_g = dot(&g, &g);
+
+    if trust_radius < close_to_zero {
+        return (x, CgInfo { niter: 0 });
+    }
+
+    let tol = (0.01 * rt_g.sqrt()).min(0.1 * rt_g).max(close_to_zero);
+    let mut counter = 0usize;
+    let mut last_feasible_x = vec![0f64; n];
+    let mut k = 0usize;
+
+    for _ in 0..max_iter {
+        if rt_g < tol {
+            break;
+        }
+        k += 1;
+        let pt_h_p = dot(&h_p, &p);
+
+        // negative curvature, move along the direction to the trust region boundary
+        if pt_h_p <= 0f64 {
+            let (_, alpha, intersect) =
+                box_sphere_intersections(&x, &p, lb, ub, trust_radius, true);
+            if intersect {
+                x = along(&x, &p, alpha);
+            }
+            reinforce_box_boundaries(&mut x, lb, ub);
+            break;
+        }
+
+        let alpha = rt_g / pt_h_p;
+        let x_next = along(&x, &p, alpha);
+
+        if norm(&x_next) >= trust_radius {
+            let step: Vec<f64> = p.iter().map(|pi| alpha * pi).collect();
+            let (_, theta, intersect) =
+                box_sphere_intersections(&x, &step, lb, ub, trust_radius, false);
+            if intersect {
+                x = along(&x, &step, theta);
+            }
+            reinforce_box_boundaries(&mut x, lb, ub);
+            break;
+        }
+
+        if inside_box_boundaries(&x_next, lb, ub) {
+            counter = 0;
+        } else {
+            counter += 1;
+        }
+        if counter > 0 {
+            let step: Vec<f64> = p.iter().map(|pi| alpha * pi).collect();
+            let (_, theta, intersect) =
+                box_sphere_intersections(&x, &step, lb, ub, trust_radius, false);
+            if intersect {
+                last_feasible_x = along(&x, &step, theta);
+                reinforce_box_boundaries(&mut last_feasible_x, lb, ub);
+                counter = 0;
+            }
+        }
+        if counter > max_infeasible_iter {
+            break;
+        }
+
+        let r_next = along(&r, &h_p, alpha);
+        let g_next = projections.null_space(&r_next);
+        let rt_g_next = dot(&g_next, &g_next);
+        let beta = rt_g_next / rt_g;
+        p = g_next
+            .iter()
+            .zip(&p)
+            .map(|(gi, pi)| -gi + beta * pi)
+            .collect();
+
+        x = x_next;
+        g = g_next;
+        r.clone_from(&g);
+        rt_g = dot(&g, &g);
+        h_p = hessian.dot(&p);
+    }
+
+    if !inside_box_boundaries(&x, lb, ub) {
+        x = last_feasible_x;
+    }
+
+    (x, CgInfo { niter: k })
+}
@@ -0,0 +1,448 @@
+use super::canonical_constraint::CanonicalConstraint;
+use super::equality_constrained_sqp::{equality_constrained_sqp, Iterate};
+use super::qp_subproblem::{CgInfo, Hessian};
+use super::{State, TrustConstrOptions, TrustConstrStatus};
+use crate::differentiate::approx_derivative_batch;
+use crate::linalg::{dot, mat_t_vec, norm_inf};
+use crate::minimise_multivariate::{MultivariateCallback, MultivariateObjectiveFunction};
+use crate::SwoopErrors;
+
+/// Fraction of the distance to the boundary that the slack variables may move per step
+const BOUNDARY_PARAMETER: f64 = 0.995;
+const BARRIER_DECAY_RATIO: f64 = 0.2;
+const TRUST_ENLARGEMENT: f64 = 5.0;
+
+/// Evaluation of the objective and canonical constraints, `(x, f, c_eq, c_ineq)`
+type Evaluation = (Vec<f64>, f64, Vec<f64>, Vec<f64>);
+
+/// Variables, objective gradient and canonical Jacobian at the previous point
+type PreviousPoint = (Vec<f64>, Vec<f64>, Vec<Vec<f64>>);
+
+/// Approximation of the Hessian of the Lagrangian with respect to the variables
+enum HessianApproximation {
+    /// Hessian supplied by the objective function, only used when all constraints are linear
+    Exact(Vec<Vec<f64>>),
+    /// Damped BFGS approximation
+    Bfgs {
+        b: Vec<Vec<f64>>,
+        initialised: bool,
+        previous: Option<PreviousPoint>,
+    },
+}
+
+/// Barrier subproblem
+///
+/// `min f(x) - mu * sum(ln(s))` subject to `c_eq(x) = 0` and `c_ineq(x) + s = 0`
+///
+/// in the variables `z = (x, s)`, where steps in the slack variables are scaled by `s`.
+pub(super) struct BarrierSubproblem<'a, 'c, T: MultivariateObjectiveFunction> {
+    objective: &'a T,
+    constraints: &'a CanonicalConstraint<'a>,
+    options: &'a TrustConstrOptions,
+    n_vars: usize,
+    n_eq: usize,
+    n_ineq: usize,
+    barrier_parameter: f64,
+    tolerance: f64,
+    enforce_feasibility: Vec<bool>,
+    hessian: HessianApproximation,
+    /// Most recent evaluation of the objective and canonical constraints
+    trial: Evaluation,
+    /// Evaluation of the objective and canonical constraints at the current point
+    current: Evaluation,
+    /// Gradient and canonical Jacobian at the current point
+    grad: Vec<f64>,
+    jac: Vec<Vec<f64>>,
+    callback: Option<MultivariateCallback<'c>>,
+    pub(super) state: State,
+    terminate: bool,
+}
+
+impl<T: MultivariateObjectiveFunction> BarrierSubproblem<'_, '_, T> {
+    fn x<'z>(&self, z: &'z [f64]) -> &'z [f64] {
+        &z[..self.n_vars]
+    }
+
+    fn s<'z>(&self, z: &'z [f64]) -> &'z [f64] {
+        &z[self.n_vars..]
+    }
+
+    /// Evaluate the objective and canonical constraints at `x`
+    fn evaluate(&mut self, x: &[f64]) -> (f64, Vec<f64>, Vec<f64>) {
+        if self.trial.0 != x {
+            self.state.nfev += 1;
+            let f = self.objective.evaluate(x);
+            let (c_eq, c_ineq) = self.constraints.evaluate(x);
+            self.trial = (x.to_vec(), f, c_eq, c_ineq);
+        }
+        (self.trial.1, self.trial.2.clone(), self.trial.3.clone())
+    }
+
+    /// Barrier function and constraints of the subproblem. The slack variables of the
+    /// inequality constraints that are kept feasible are set to `-c_ineq(x)` so that the
+    /// barrier function is infinite outside the feasible region.
+    pub(super) fn function_and_constraints(&mut self, z: &mut [f64]) -> (f64, Vec<f64>) {
+        let n_vars = self.n_vars;
+        let (f, c_eq, c_ineq) = self.evaluate(&z[..n_vars]);
+        let s = &mut z[n_vars..];
+        for ((si, ci), enforce) in s.iter_mut().zip(&c_ineq).zip(&self.enforce_feasibility) {
+            if *enforce {
+                *si = -ci;
+            }
+        }
+        let log_s: f64 = s
+            .iter()
+            .map(|si| {
+                if *si > 0f64 {
+                    si.ln()
+                } else {
+                    f64::NEG_INFINITY
+                }
+            })
+            .sum();
+        let mut constr = c_eq;
+        constr.extend(c_ineq.iter().zip(s.iter()).map(|(c, si)| c + si));
+        (f - self.barrier_parameter * log_s, constr)
+    }
+
+    /// Scaled gradient and Jacobian of the subproblem
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if the number of constraint values changes
+    /// between the steps of the finite differences
+    pub(super) fn gradient_and_jacobian(
+        &mut self,
+        z: &[f64],
+    ) -> Result<(Vec<f64>, Vec<Vec<f64>>), SwoopErrors> {
+        let x = self.x(z).to_vec();
+        let s = self.s(z).to_vec();
+        let (f, c_eq, c_ineq) = self.evaluate(&x);
+        self.current = (x.clone(), f, c_eq, c_ineq);
+        self.state.njev += 1;
+        self.grad = if let Some(grad) = self.objective.gradient(&x) {
+            grad
+        } else {
+            self.state.nfev += x.len();
+            let objective = self.objective;
+            let mut jacobian = approx_derivative_batch(
+                |points| {
+                    objective
+                        .evaluate_batch(points)
+                        .into_iter()
+                        .map(|f| vec![f])
+                        .collect()
+                },
+                &x,
+                Some(&[f]),
+                &self.constraints.difference_options(&x),
+            )?;
+            jacobian.swap_remove(0)
+        };
+        let (j_eq, j_ineq) = self.constraints.jacobian(&x)?;
+
+        let mut grad = self.grad.clone();
+        grad.resize(self.n_vars + self.n_ineq, -self.barrier_parameter);
+
+        let mut jac: Vec<Vec<f64>> = Vec::with_capacity(self.n_eq + self.n_ineq);
+        for row in &j_eq {
+            let mut row = row.clone();
+            row.resize(self.n_vars + self.n_ineq, 0f64);
+            jac.push(row);
+        }
+        for (i, row) in j_ineq.iter().enumerate() {
+            let mut row = row.clone();
+            row.resize(self.n_vars + self.n_ineq, 0f64);
+            row[self.n_vars + i] = s[i];
+            jac.push(row);
+        }
+        self.jac = j_eq;
+        self.jac.extend(j_ineq);
+        Ok((grad, jac))
+    }
+
+    /// Gradient of the Lagrangian of the original problem with respect to the variables at the
+    /// current point
+    fn lagrangian_grad(&self, v: &[f64]) -> Vec<f64> {
+        self.grad
+            .iter()
+            .zip(mat_t_vec(&self.jac, v, self.n_vars))
+            .map(|(g, jv)| g + jv)
+            .collect()
+    }
+
+    /// Scaled Lagrangian Hessian of the subproblem
+    pub(super) fn lagrangian_hessian(&mut self, z: &[f64], v: &[f64]) -> Hessian {
+        let x = self.x(z).to_vec();
+        let lagrangian_grad = self.lagrangian_grad(v);
+        let xx = match &mut self.hessian {
+            HessianApproximation::Exact(h) => {
+                if let Some(hessian) = self.objective.hessian(&x) {
+                    self.state.nhev += 1;
+                    *h = hessian;
+                }
+                h.clone()
+            }
+            HessianApproximation::Bfgs {
+                b,
+                initialised,
+                previous,
+            } => {
+                if let Some((x_prev, grad_prev, jac_prev)) = previous.take() {
+                    let step: Vec<f64> = x.iter().zip(&x_prev).map(|(a, b)| a - b).collect();
+                    let lagrangian_grad_prev: Vec<f64> = grad_prev
+                        .iter()
+                        .zip(mat_t_vec(&jac_prev, v, self.n_vars))
+                        .map(|(g, jv)| g + jv)
+                        .collect();
+                    let y: Vec<f64> = lagrangian_grad
+                        .iter()
+                        .zip(&lagrangian_grad_prev)
+                        .map(|(a, b)| a - b)
+                        .collect();
+                    damped_bfgs_update(b, initialised, &step, &y);
+                }
+                *previous = Some((x, self.grad.clone(), self.jac.clone()));
+                b.clone()
+            }
+        };
+
+        let s = self.s(z);
+        let ss = v[self.n_eq..]
+            .iter()
+            .zip(s)
+            .map(|(vi, si)| {
+                if *vi > 0f64 {
+                    vi * si
+                } else {
+                    self.barrier_parameter
+                }
+            })
+            .collect();
+        Hessian { xx, ss }
+    }
+
+    /// Diagonal scaling of the step, the slack variables are scaled by their value
+    pub(super) fn scaling(&self, z: &[f64]) -> Vec<f64> {
+        let mut scaling = vec![1f64; self.n_vars];
+        scaling.extend_from_slice(self.s(z));
+        scaling
+    }
+
+    /// Update the state of the solver and decide if the subproblem, or the whole problem, has
+    /// been solved
+    pub(super) fn stop_criteria(
+        &mut self,
+        v: &[f64],
+        optimality: f64,
+        constr_violation: f64,
+        trust_radius: f64,
+        penalty: f64,
+        cg_info: CgInfo,
+    ) -> bool {
+        let lagrangian_grad = self.lagrangian_grad(v);
+        let (x, fun, c_eq, c_ineq) = &self.current;
+        let state = &mut self.state;
+        state.nit += 1;
+        state.x.clone_from(x);
+        state.fun = *fun;
+        state.grad = self.grad.clone();
+        state.optimality = norm_inf(&lagrangian_grad);
+        state.constr_violation = c_ineq.iter().fold(norm_inf(c_eq), |acc, c| acc.max(*c));
+        state.optimality_history.push(state.optimality);
+        state.constr_violation_history.push(state.constr_violation);
+        state.lagrangian_grad = lagrangian_grad;
+        state.v = v.to_vec();
+        state.tr_radius = trust_radius;
+        state.constr_penalty = penalty;
+        state.barrier_parameter = self.barrier_parameter;
+        state.barrier_tolerance = self.tolerance;
+        state.cg_niter += cg_info.niter;
+
+        let barrier_converged =
+            self.n_ineq == 0 || self.barrier_parameter < self.options.barrier_tol;
+        let stop_requested = match self.callback.as_mut() {
+            Some(callback) => callback(&state.x, state.fun),
+            None => false,
+        };
+        let status = if stop_requested {
+            Some(TrustConstrStatus::CallbackTerminated)
+        } else if state.optimality < self.options.gtol
+            && state.constr_violation < self.options.gtol
+            && barrier_converged
+        {
+            Some(TrustConstrStatus::GradientTolerance)
+        } else if trust_radius < self.options.xtol && barrier_converged {
+            Some(TrustConstrStatus::StepTolerance)
+        } else if state.nit >= self.options.maxiter {
+            Some(TrustConstrStatus::MaxIterExceeded)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            state.status = status;
+            self.terminate = true;
+            return true;
+        }
+
+        let g_cond = optimality < self.tolerance && constr_violation < self.tolerance;
+        let x_cond = trust_radius < self.options.xtol;
+        g_cond || x_cond
+    }
+}
+
+/// Damped BFGS update of `b` given the step `s` and the change in gradient `y`
+fn damped_bfgs_update(b: &mut [Vec<f64>], initialised: &mut bool, s: &[f64], y: &[f64]) {
+    let n = s.len();
+    if !*initialised {
+        let sy = dot(s, y);
+        let yy = dot(y, y);
+        if sy > 0f64 && yy > 0f64 {
+            let scale = yy / sy;
+            for (i, row) in b.iter_mut().enumerate() {
+                *row = vec![0f64; n];
+                row[i] = scale;
+            }
+            *initialised = true;
+        }
+    }
+    let bs = crate::linalg::mat_vec(b, s);
+    let sbs = dot(s, &bs);
+    if sbs <= 0f64 {
+        return;
+    }
+    let sy = dot(s, y);
+    let theta = if sy < 0.2 * sbs {
+        0.8 * sbs / (sbs - sy)
+    } else {
+        1f64
+    };
+    let r: Vec<f64> = y
+        .iter()
+        .zip(&bs)
+        .map(|(yi, bsi)| theta * yi + (1f64 - theta) * bsi)
+        .collect();
+    let sr = dot(s, &r);
+    if sr <= 0f64 {
+        return;
+    }
+    for i in 0..n {
+        for j in 0..n {
+            b[i][j] += r[i] * r[j] / sr - bs[i] * bs[j] / sbs;
+        }
+    }
+}
+
+/// Solve the problem with a sequence of barrier subproblems, with a decreasing barrier
+/// parameter, using a trust region interior point method.
+///
+/// # Parameters
+/// * `objective` - objective function
+/// * `constraints` - constraints in canonical form
+/// * `x0` - initial guess
+/// * `options` - solver options
+/// * `callback` - optional callback called after each iteration
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of constraint values changes between
+/// the steps of the finite differences
+pub(super) fn tr_interior_point<T: MultivariateObjectiveFunction>(
+    objective: &T,
+    constraints: &CanonicalConstraint<'_>,
+    x0: &[f64],
+    options: &TrustConstrOptions,
+    callback: Option<MultivariateCallback<'_>>,
+) -> Result<State, SwoopErrors> {
+    let n_vars = x0.len();
+    let n_eq = constraints.n_eq();
+    let n_ineq = constraints.n_ineq();
+
+    let hessian = match objective.hessian(x0) {
+        Some(h) if !constraints.has_nonlinear() => HessianApproximation::Exact(h),
+        _ => {
+            let mut b = vec![vec![0f64; n_vars]; n_vars];
+            for (i, row) in b.iter_mut().enumerate() {
+                row[i] = 1f64;
+            }
+            HessianApproximation::Bfgs {
+                b,
+                initialised: false,
+                previous: None,
+            }
+        }
+    };
+
+    let mut barrier_parameter = options.initial_barrier_parameter;
+    let mut tolerance = if n_ineq == 0 {
+        options.gtol
+    } else {
+        options.initial_barrier_tolerance
+    };
+
+    let (_, c_ineq0) = constraints.evaluate(x0);
+    let enforce_feasibility = constraints.enforce_feasibility();
+    let s0: Vec<f64> = c_ineq0
+        .iter()
+        .zip(&enforce_feasibility)
+        .map(|(c, enforce)| if *enforce { -c } else { (-1.5 * c).max(1f64) })
+        .collect();
+
+    let mut subproblem = BarrierSubproblem {
+        objective,
+        constraints,
+        options,
+        n_vars,
+        n_eq,
+        n_ineq,
+        barrier_parameter,
+        tolerance,
+        enforce_feasibility,
+        hessian,
+        trial: (Vec::new(), 0f64, Vec::new(), Vec::new()),
+        current: (Vec::new(), 0f64, Vec::new(), Vec::new()),
+        grad: Vec::new(),
+        jac: Vec::new(),
+        callback,
+        state: State::default(),
+        terminate: false,
+    };
+
+    let mut trust_lb = vec![f64::NEG_INFINITY; n_vars];
+    trust_lb.resize(n_vars + n_ineq, -BOUNDARY_PARAMETER);
+    let trust_ub = vec![f64::INFINITY; n_vars + n_ineq];
+
+    let mut z = x0.to_vec();
+    z.extend(s0);
+    let mut trust_radius = options.initial_tr_radius;
+
+    loop {
+        let (fun, constr) = subproblem.function_and_constraints(&mut z);
+        let (grad, jac) = subproblem.gradient_and_jacobian(&z)?;
+        let (iterate, final_trust_radius) = equality_constrained_sqp(
+            &mut subproblem,
+            Iterate {
+                z,
+                fun,
+                constr,
+                grad,
+                jac,
+            },
+            options.initial_constr_penalty,
+            trust_radius,
+            &trust_lb,
+            &trust_ub,
+        )?;
+        z = iterate.z;
+        if subproblem.terminate {
+            break;
+        }
+        trust_radius = options
+            .initial_tr_radius
+            .max(TRUST_ENLARGEMENT * final_trust_radius);
+        barrier_parameter *= BARRIER_DECAY_RATIO;
+        tolerance *= BARRIER_DECAY_RATIO;
+        subproblem.barrier_parameter = barrier_parameter;
+        subproblem.tolerance = tolerance;
+    }
+
+    Ok(subproblem.state)
+}
@@ -1,3 +1,4 @@
+use crate::constraints::Bounds;
 use crate::minimise_scalar::{ScalarObjectiveFunction, ScalarOptimisationResult};
 use crate::SwoopErrors;
 
@@ -49,28 +50,30 @@ fn arg_max(a: f64, b: f64) -> f64 {
 ///
 /// # Parameters
 /// * `objective_function` - objective struct that implements the trait `ScalarObjectiveFunction`
-/// * `bounds` - bounds for the optimisation
+/// * `bounds` - finite bounds on the single variable
 /// * `maxiter` - maximum iterations
 ///
 /// # Errors
-/// Will return `SwoopErrors::ArgumentError` is passed tolerance `xtol` is negative
+/// Will return `SwoopErrors::ArgumentError` if `bounds` are not finite bounds on one variable
 #[allow(clippy::cast_sign_loss)]
 #[allow(clippy::cast_possible_truncation)]
 #[allow(clippy::cast_precision_loss)]
 #[allow(clippy::too_many_lines)]
 pub async fn bounded<T: ScalarObjectiveFunction>(
     objective_function: T,
-    bounds: (f64, f64),
+    bounds: &Bounds,
     maxiter: usize,
 ) -> Result<ScalarOptimisationResult, SwoopErrors> {
     let error_margin = f64::EPSILON;
     let xatol = 1e-5f64;
-    let (x1, x2) = bounds;
-    if x2 < x1 {
-        return Err(SwoopErrors::ArgumentError(String::from(
-            "The lower bound exceeds the upper bound",
-        )));
-    }
+    let (x1, x2) = match (bounds.lb(), bounds.ub()) {
+        ([x1], [x2]) if x1.is_finite() && x2.is_finite() => (*x1, *x2),
+        _ => {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The bounds must be finite bounds on one variable",
+            )))
+        }
+    };
     let sqrt_eps = f64::EPSILON.sqrt();
     let golden_mean = 0.5f64 * (3.0f64 - (5.0f64.sqrt()));
     let (mut a, mut b) = (x1, x2);
@@ -221,7 +224,8 @@ mod tests {
         }
 
         let objective_function = QuadraticFunction::new(3f64, 4f64, 50f64);
-        let result = bounded(objective_function, (-10f64, 10f64), 500usize).await?;
+        let bounds = Bounds::new(vec![-10f64], vec![10f64])?;
+        let result = bounded(objective_function, &bounds, 500usize).await?;
         println!("{:?}", result);
         assert_eq!(
             relative_eq!(result.fun, 48.666666666666664, epsilon = 1e-6),
@@ -233,4 +237,27 @@ mod tests {
         );
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_bounds() -> Result<(), SwoopErrors> {
+        struct Square {}
+
+        impl ScalarObjectiveFunction for Square {
+            fn evaluate(&self, x: f64) -> f64 {
+                x.powi(2)
+            }
+        }
+
+        // the bounds must be finite and on a single variable
+        for bounds in [
+            Bounds::new(vec![-1f64, -1f64], vec![1f64, 1f64])?,
+            Bounds::new(vec![f64::NEG_INFINITY], vec![1f64])?,
+        ] {
+            assert!(matches!(
+                bounded(Square {}, &bounds, 500usize).await,
+                Err(SwoopErrors::ArgumentError(_))
+            ));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,615 @@
+//! Parallel evaluation of objective and residual functions
+//!
+//! `Parallel` wraps an objective or residual function and evaluates the batches of independent
+//! points of the solvers on several threads:
+//! * the populations of `differential_evolution` with `Updating::Deferred`, the grids of
+//!   `brute` and the samples of `shgo`
+//! * the steps of the finite difference gradients of `trust_constr` and of the finite
+//!   difference Jacobians of `least_squares` and `root`, and `approx_derivative_batch` given
+//!   the `evaluate_batch` method
+//! * the initial simplex and the shrink steps of `nelder_mead`, and with these two methods the
+//!   local searches of `minimise`, `basinhopping` and `dual_annealing`
+//!
+//! Single evaluations are unchanged, as are the evaluations of constraint functions. Each batch
+//! is split into one contiguous chunk per worker, evaluated by the `evaluate_batch` method of the
+//! wrapped function on scoped threads, or on a `rayon` thread pool with the `rayon` feature.
+//! Parallel evaluation pays off when the function is expensive, as each batch has the overhead of
+//! starting or waking the threads.
+//!
+//! `Concurrent` wraps an async objective or residual function, implementing
+//! `AsyncObjectiveFunction` or `AsyncResidualFunction`, for the same solvers. The points of each
+//! batch are evaluated as concurrent futures, at most `limit` at a time, and the values are
+//! returned in the order of the points. The futures are polled on the thread of the solver, which
+//! waits for the whole batch, so futures relying on the I/O or timers of a Tokio runtime need the
+//! multi-threaded runtime.
+
+use crate::least_squares::ResidualFunction;
+use crate::minimise_multivariate::MultivariateObjectiveFunction;
+use crate::SwoopErrors;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::pin::{pin, Pin};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+/// Adapter evaluating the batches of points of an objective or residual function in parallel
+#[derive(Debug)]
+pub struct Parallel<F> {
+    function: F,
+    workers: usize,
+    #[cfg(feature = "rayon")]
+    pool: rayon::ThreadPool,
+}
+
+impl<F> Parallel<F> {
+    /// Wrap a function, with as many workers as the available parallelism
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::Other` if the `rayon` thread pool cannot be built
+    pub fn new(function: F) -> Result<Self, SwoopErrors> {
+        let workers = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        Self::with_workers(function, workers)
+    }
+
+    /// Wrap a function, evaluating each batch on at most `workers` threads
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `workers` is zero and `SwoopErrors::Other` if
+    /// the `rayon` thread pool cannot be built
+    pub fn with_workers(function: F, workers: usize) -> Result<Self, SwoopErrors> {
+        if workers == 0 {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "There must be at least one worker",
+            )));
+        }
+        Ok(Self {
+            function,
+            workers,
+            #[cfg(feature = "rayon")]
+            pool: rayon::ThreadPoolBuilder::new()
+                .num_threads(workers)
+                .build()
+                .map_err(anyhow::Error::from)?,
+        })
+    }
+
+    /// Number of workers
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Unwrap the function
+    pub fn into_inner(self) -> F {
+        self.function
+    }
+}
+
+impl<F: Sync> Parallel<F> {
+    /// Evaluate contiguous chunks of the points, one batch per worker, on the rayon thread pool
+    #[cfg(feature = "rayon")]
+    fn map<R, E>(&self, points: &[Vec<f64>], evaluate: E) -> Vec<R>
+    where
+        R: Send,
+        E: Fn(&F, &[Vec<f64>]) -> Vec<R> + Sync,
+    {
+        use rayon::prelude::*;
+        let chunk = points.len().div_ceil(self.workers);
+        self.pool.install(|| {
+            points
+                .par_chunks(chunk)
+                .flat_map_iter(|chunk| evaluate(&self.function, chunk))
+                .collect()
+        })
+    }
+
+    /// Evaluate contiguous chunks of the points, one batch per worker, on scoped threads
+    #[cfg(not(feature = "rayon"))]
+    fn map<R, E>(&self, points: &[Vec<f64>], evaluate: E) -> Vec<R>
+    where
+        R: Send,
+        E: Fn(&F, &[Vec<f64>]) -> Vec<R> + Sync,
+    {
+        let chunk = points.len().div_ceil(self.workers);
+        let evaluate = &evaluate;
+        thread::scope(|scope| {
+            let handles: Vec<_> = points
+                .chunks(chunk)
+                .map(|chunk| scope.spawn(move || evaluate(&self.function, chunk)))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| match handle.join() {
+                    Ok(values) => values,
+                    Err(panic) => std::panic::resume_unwind(panic),
+                })
+                .collect()
+        })
+    }
+}
+
+impl<F: MultivariateObjectiveFunction + Sync> MultivariateObjectiveFunction for Parallel<F> {
+    fn evaluate(&self, x: &[f64]) -> f64 {
+        self.function.evaluate(x)
+    }
+
+    fn gradient(&self, x: &[f64]) -> Option<Vec<f64>> {
+        self.function.gradient(x)
+    }
+
+    fn hessian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        self.function.hessian(x)
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        if self.workers == 1 || points.len() < 2 {
+            return self.function.evaluate_batch(points);
+        }
+        self.map(points, MultivariateObjectiveFunction::evaluate_batch)
+    }
+}
+
+impl<F: ResidualFunction + Sync> ResidualFunction for Parallel<F> {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        self.function.evaluate(x)
+    }
+
+    fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+        self.function.jacobian(x)
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        if self.workers == 1 || points.len() < 2 {
+            return self.function.evaluate_batch(points);
+        }
+        self.map(points, ResidualFunction::evaluate_batch)
+    }
+}
+
+/// Async objective function, evaluated by the solvers through `Concurrent`
+pub trait AsyncObjectiveFunction {
+    /// Evaluate the objective function at `x`
+    fn evaluate(&self, x: &[f64]) -> impl Future<Output = f64>;
+}
+
+/// Async residual function, evaluated by the solvers through `Concurrent`
+pub trait AsyncResidualFunction {
+    /// Evaluate the residuals at `x`
+    fn evaluate(&self, x: &[f64]) -> impl Future<Output = Vec<f64>>;
+}
+
+/// Adapter evaluating the batches of points of an async objective or residual function as
+/// concurrent futures
+#[derive(Debug)]
+pub struct Concurrent<F> {
+    function: F,
+    limit: usize,
+}
+
+impl<F> Concurrent<F> {
+    /// Wrap an async function, running at most `limit` evaluations of each batch at once
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `limit` is zero
+    pub fn new(function: F, limit: usize) -> Result<Self, SwoopErrors> {
+        if limit == 0 {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The concurrency limit must be at least one",
+            )));
+        }
+        Ok(Self { function, limit })
+    }
+
+    /// Maximum number of concurrent evaluations
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Unwrap the function
+    pub fn into_inner(self) -> F {
+        self.function
+    }
+}
+
+impl<F: AsyncObjectiveFunction> MultivariateObjectiveFunction for Concurrent<F> {
+    fn evaluate(&self, x: &[f64]) -> f64 {
+        block_on(self.function.evaluate(x))
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+        buffered(points.iter().map(|x| self.function.evaluate(x)), self.limit)
+    }
+}
+
+impl<F: AsyncResidualFunction> ResidualFunction for Concurrent<F> {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        block_on(self.function.evaluate(x))
+    }
+
+    fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<Vec<f64>> {
+        buffered(points.iter().map(|x| self.function.evaluate(x)), self.limit)
+    }
+}
+
+/// Waker unparking the thread polling the futures
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Poll a future to completion on the current thread
+fn block_on<T>(future: impl Future<Output = T>) -> T {
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(value) => return value,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Poll the futures on the current thread, at most `limit` at once, returning their outputs in
+/// order
+fn buffered<T, U>(futures: impl Iterator<Item = U>, limit: usize) -> Vec<T>
+where
+    U: Future<Output = T>,
+{
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut context = Context::from_waker(&waker);
+    let mut futures = futures.enumerate();
+    let mut running: Vec<(usize, Pin<Box<U>>)> = Vec::with_capacity(limit);
+    let mut outputs: Vec<Option<T>> = Vec::new();
+    loop {
+        while running.len() < limit {
+            let Some((index, future)) = futures.next() else {
+                break;
+            };
+            running.push((index, Box::pin(future)));
+            outputs.push(None);
+        }
+        if running.is_empty() {
+            return outputs.into_iter().flatten().collect();
+        }
+        let mut ready = false;
+        running.retain_mut(|(index, future)| match future.as_mut().poll(&mut context) {
+            Poll::Ready(value) => {
+                outputs[*index] = Some(value);
+                ready = true;
+                false
+            }
+            Poll::Pending => true,
+        });
+        // a wake during the polls makes the park return immediately
+        if !ready {
+            thread::park();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constraints::Bounds;
+    use crate::least_squares::{least_squares, LeastSquaresMethod, LeastSquaresOptions};
+    use crate::minimise_global::{
+        brute, differential_evolution, BruteRange, DifferentialEvolutionOptions, Updating,
+    };
+    use crate::minimise_multivariate::{nelder_mead, NelderMeadOptions};
+    use approx::relative_eq;
+    use std::cell::Cell;
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// Sphere function recording the threads it is evaluated on
+    struct Sphere {
+        threads: Mutex<HashSet<thread::ThreadId>>,
+    }
+
+    impl MultivariateObjectiveFunction for Sphere {
+        fn evaluate(&self, x: &[f64]) -> f64 {
+            if let Ok(mut threads) = self.threads.lock() {
+                threads.insert(thread::current().id());
+            }
+            x.iter().map(|xi| (xi - 0.5).powi(2)).sum()
+        }
+    }
+
+    fn sphere() -> Sphere {
+        Sphere {
+            threads: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn threads(objective: Parallel<Sphere>) -> HashSet<thread::ThreadId> {
+        objective
+            .into_inner()
+            .threads
+            .into_inner()
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_batch() -> Result<(), SwoopErrors> {
+        let points: Vec<Vec<f64>> = (0..100).map(|i| vec![f64::from(i) / 10f64]).collect();
+        let serial = sphere().evaluate_batch(&points);
+        let caller = HashSet::from([thread::current().id()]);
+        let objective = Parallel::with_workers(sphere(), 4)?;
+        // the values are in the order of the points, evaluated by the workers
+        assert_eq!(objective.evaluate_batch(&points), serial);
+        assert!(threads(objective).is_disjoint(&caller));
+
+        let objective = Parallel::with_workers(sphere(), 1)?;
+        assert_eq!(objective.evaluate_batch(&points), serial);
+        assert_eq!(threads(objective), caller);
+
+        assert!(Parallel::new(sphere())?.workers() >= 1);
+        assert!(matches!(
+            Parallel::with_workers(sphere(), 0),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_inner_batch() -> Result<(), SwoopErrors> {
+        /// Objective recording the sizes of the batches it evaluates
+        struct Batches {
+            sizes: Mutex<Vec<usize>>,
+        }
+
+        impl MultivariateObjectiveFunction for Batches {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                self.evaluate_batch(&[x.to_vec()])[0]
+            }
+
+            fn evaluate_batch(&self, points: &[Vec<f64>]) -> Vec<f64> {
+                if let Ok(mut sizes) = self.sizes.lock() {
+                    sizes.push(points.len());
+                }
+                points.iter().map(|x| x[0]).collect()
+            }
+        }
+
+        let points: Vec<Vec<f64>> = (0..10).map(|i| vec![f64::from(i)]).collect();
+        let objective = Parallel::with_workers(
+            Batches {
+                sizes: Mutex::new(Vec::new()),
+            },
+            4,
+        )?;
+        assert_eq!(
+            objective.evaluate_batch(&points),
+            (0..10).map(f64::from).collect::<Vec<f64>>()
+        );
+        // each worker evaluates its chunk with the batch method of the wrapped function
+        let mut sizes = objective
+            .into_inner()
+            .sizes
+            .into_inner()
+            .unwrap_or_default();
+        sizes.sort_unstable();
+        assert_eq!(sizes, vec![1, 3, 3, 3]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_solvers() -> Result<(), SwoopErrors> {
+        let range = BruteRange::Slice {
+            start: -2f64,
+            stop: 2f64,
+            step: 0.25,
+        };
+        let result = brute(Parallel::with_workers(sphere(), 4)?, &[range, range], false).await?;
+        assert_eq!(result.x, vec![0.5, 0.5]);
+
+        let bounds = Bounds::new(vec![-2f64; 3], vec![2f64; 3])?;
+        let options = DifferentialEvolutionOptions {
+            seed: Some(7),
+            updating: Updating::Deferred,
+            polish: None,
+            ..DifferentialEvolutionOptions::default()
+        };
+        let serial = differential_evolution(sphere(), &bounds, &[], options.clone(), None).await?;
+        let parallel = differential_evolution(
+            Parallel::with_workers(sphere(), 4)?,
+            &bounds,
+            &[],
+            options,
+            None,
+        )
+        .await?;
+        // parallel evaluation does not change the result
+        assert_eq!(parallel.x, serial.x);
+        assert_eq!(parallel.nfev, serial.nfev);
+        assert!(relative_eq!(parallel.fun, 0f64, epsilon = 1e-3));
+
+        let options = NelderMeadOptions::default();
+        let serial = nelder_mead(sphere(), &[1f64; 3], None, options.clone(), None).await?;
+        let objective = Parallel::with_workers(sphere(), 4)?;
+        let parallel = nelder_mead(&objective, &[1f64; 3], None, options, None).await?;
+        assert_eq!(parallel.x, serial.x);
+        assert_eq!(parallel.nfev, serial.nfev);
+        // the initial simplex is evaluated by the workers
+        assert!(threads(objective).len() > 1);
+        Ok(())
+    }
+
+    /// Future returning pending once, waking itself, so that other futures are polled before it
+    /// completes
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            context.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    /// Async sphere function recording the largest number of evaluations running at once
+    #[derive(Default)]
+    struct AsyncSphere {
+        running: Cell<usize>,
+        most: Cell<usize>,
+    }
+
+    impl AsyncObjectiveFunction for AsyncSphere {
+        async fn evaluate(&self, x: &[f64]) -> f64 {
+            self.running.set(self.running.get() + 1);
+            self.most.set(self.most.get().max(self.running.get()));
+            YieldOnce(false).await;
+            self.running.set(self.running.get() - 1);
+            x.iter().map(|xi| (xi - 0.5).powi(2)).sum()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent() -> Result<(), SwoopErrors> {
+        let points: Vec<Vec<f64>> = (0..10).map(|i| vec![f64::from(i) / 10f64]).collect();
+        let objective = Concurrent::new(AsyncSphere::default(), 3)?;
+        // the values are in the order of the points, at most three evaluated at once
+        assert_eq!(
+            objective.evaluate_batch(&points),
+            sphere().evaluate_batch(&points)
+        );
+        assert_eq!(objective.limit(), 3);
+        assert_eq!(objective.into_inner().most.get(), 3);
+        assert!(matches!(
+            Concurrent::new(AsyncSphere::default(), 0),
+            Err(SwoopErrors::ArgumentError(_))
+        ));
+
+        let range = BruteRange::Slice {
+            start: -2f64,
+            stop: 2f64,
+            step: 0.25,
+        };
+        let objective = Concurrent::new(AsyncSphere::default(), 4)?;
+        let result = brute(&objective, &[range, range], false).await?;
+        assert_eq!(result.x, vec![0.5, 0.5]);
+        assert_eq!(objective.into_inner().most.get(), 4);
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_concurrent_runtime() -> Result<(), SwoopErrors> {
+        /// Async residuals of the Rosenbrock function, waiting on a Tokio timer
+        #[derive(Default)]
+        struct Delayed {
+            running: AtomicUsize,
+            most: AtomicUsize,
+        }
+
+        impl AsyncResidualFunction for Delayed {
+            async fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+                let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+                self.most.fetch_max(running, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                self.running.fetch_sub(1, Ordering::SeqCst);
+                vec![10f64 * (x[1] - x[0].powi(2)), 1f64 - x[0]]
+            }
+        }
+
+        let residuals = Concurrent::new(Delayed::default(), 2)?;
+        let options = LeastSquaresOptions {
+            method: LeastSquaresMethod::Trf,
+            ..LeastSquaresOptions::default()
+        };
+        let serial = least_squares(Rosenbrock {}, &[2f64, 2f64], None, options.clone()).await?;
+        let concurrent = least_squares(&residuals, &[2f64, 2f64], None, options).await?;
+        assert_eq!(concurrent.x, serial.x);
+        assert_eq!(concurrent.nfev, serial.nfev);
+        // the two steps of each finite difference Jacobian wait on their timers together
+        assert_eq!(residuals.into_inner().most.into_inner(), 2);
+        Ok(())
+    }
+
+    /// Residuals of the Rosenbrock function
+    struct Rosenbrock {}
+
+    impl ResidualFunction for Rosenbrock {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![10f64 * (x[1] - x[0].powi(2)), 1f64 - x[0]]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_residuals() -> Result<(), SwoopErrors> {
+        let points = vec![vec![0f64, 1f64], vec![2f64, 3f64], vec![-1f64, 0.5]];
+        let residuals = Parallel::with_workers(Rosenbrock {}, 2)?;
+        assert_eq!(
+            ResidualFunction::evaluate_batch(&residuals, &points),
+            Rosenbrock {}.evaluate_batch(&points)
+        );
+
+        // the finite difference Jacobians are evaluated as batches
+        for method in [LeastSquaresMethod::Lm, LeastSquaresMethod::Trf] {
+            let options = LeastSquaresOptions {
+                method,
+                ..LeastSquaresOptions::default()
+            };
+            let serial = least_squares(Rosenbrock {}, &[2f64, 2f64], None, options.clone()).await?;
+            let parallel = least_squares(&residuals, &[2f64, 2f64], None, options).await?;
+            assert_eq!(parallel.x, serial.x);
+            assert_eq!(parallel.nfev, serial.nfev);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_rayon_pool() -> Result<(), SwoopErrors> {
+        /// Objective recording the index of the pool thread it is evaluated on
+        struct Indices {
+            indices: Mutex<HashSet<Option<usize>>>,
+        }
+
+        impl MultivariateObjectiveFunction for Indices {
+            fn evaluate(&self, x: &[f64]) -> f64 {
+                if let Ok(mut indices) = self.indices.lock() {
+                    indices.insert(rayon::current_thread_index());
+                }
+                x[0]
+            }
+        }
+
+        let points: Vec<Vec<f64>> = (0..100).map(|i| vec![f64::from(i)]).collect();
+        let objective = Parallel::with_workers(
+            Indices {
+                indices: Mutex::new(HashSet::new()),
+            },
+            3,
+        )?;
+        assert_eq!(
+            objective.evaluate_batch(&points),
+            (0..100).map(f64::from).collect::<Vec<f64>>()
+        );
+        // every point is evaluated on the pool of three threads
+        let indices = objective
+            .into_inner()
+            .indices
+            .into_inner()
+            .unwrap_or_default();
+        assert!(!indices.is_empty());
+        assert!(indices
+            .iter()
+            .all(|index| index.is_some_and(|index| index < 3)));
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+//! Primal active set method
+//!
+//! Starting from a feasible point, every iteration minimises the objective subject to the
+//! equality constraints and the inequalities in the working set by solving their KKT system. The
+//! step is shortened at the first blocking inequality, which joins the working set, and once the
+//! step vanishes the inequality with the most negative multiplier leaves it. The KKT system is
+//! solved in the least squares sense such that `P` may be singular: when the system is
+//! inconsistent the objective has zero curvature along a descent direction, which is followed
+//! until an inequality blocks it.
+//!
+//! A feasible starting point is found with the dual simplex method, unless the warm start is
+//! feasible, in which case its active inequalities with non-zero marginals form the initial
+//! working set.
+//!
+//! # References
+//! * Nocedal and Wright, "Numerical optimization", 2nd edition, Springer (2006), section 16.5
+
+use super::{QuadprogResult, QuadprogStatus, QuadraticProgram, Solution};
+use crate::constraints::Bounds;
+use crate::linalg::{dot, lstsq, mat_vec, norm, norm_inf, transpose};
+use crate::linprog::{linprog, LinprogOptions, LinprogStatus};
+use crate::SwoopErrors;
+
+/// Tolerance for the residual of a consistent KKT system, relative to the gradient
+const KKT_TOL: f64 = 1e-8;
+
+/// Inequality `a x <= b` of the problem
+#[derive(Debug, Clone, Copy)]
+enum Inequality {
+    /// Row of `A_ub`
+    Row(usize),
+    /// Lower bound of a variable
+    Lower(usize),
+    /// Upper bound of a variable
+    Upper(usize),
+}
+
+/// Whether `a` is linearly independent of `rows`
+fn independent(rows: &[Vec<f64>], a: &[f64]) -> bool {
+    let scale = norm(a);
+    if rows.is_empty() {
+        return scale > 0f64;
+    }
+    let rows_t = transpose(rows, a.len());
+    let projection = mat_vec(&rows_t, &lstsq(&rows_t, a, rows.len()));
+    let residual: Vec<f64> = a.iter().zip(&projection).map(|(ai, pi)| ai - pi).collect();
+    norm(&residual) > KKT_TOL * scale
+}
+
+/// Marginals from the multipliers of the equality constraints followed by those of the working
+/// set
+fn solution(
+    qp: &QuadraticProgram,
+    inequalities: &[Inequality],
+    working: &[usize],
+    multipliers: &[f64],
+    x: Vec<f64>,
+    status: QuadprogStatus,
+    nit: usize,
+) -> Solution {
+    let n = qp.q.len();
+    let n_eq = qp.b.len() - qp.n_ub;
+    let mut row_marginals = vec![0f64; qp.b.len()];
+    let mut lower_marginals = vec![0f64; n];
+    let mut upper_marginals = vec![0f64; n];
+    for (marginal, multiplier) in row_marginals[qp.n_ub..].iter_mut().zip(multipliers) {
+        *marginal = -multiplier;
+    }
+    for (&k, multiplier) in working.iter().zip(multipliers.iter().skip(n_eq)) {
+        match inequalities[k] {
+            Inequality::Row(i) => row_marginals[i] = -multiplier,
+            Inequality::Lower(j) => lower_marginals[j] = *multiplier,
+            Inequality::Upper(j) => upper_marginals[j] = -multiplier,
+        }
+    }
+    Solution {
+        x,
+        row_marginals,
+        lower_marginals,
+        upper_marginals,
+        status,
+        nit,
+    }
+}
+
+/// Solve a quadratic program with the primal active set method
+///
+/// # Parameters
+/// * `qp` - quadratic program
+/// * `warm_start` - optional previous result to start from
+/// * `tol` - tolerance for feasibility, the step and the multipliers
+/// * `maxiter` - maximum iterations
+///
+/// # Errors
+/// Propagates errors of the linear program that finds a feasible starting point
+#[allow(clippy::too_many_lines)]
+pub(super) async fn active_set(
+    qp: &QuadraticProgram,
+    warm_start: Option<&QuadprogResult>,
+    tol: f64,
+    maxiter: usize,
+) -> Result<Solution, SwoopErrors> {
+    let n = qp.q.len();
+    let mut inequalities = Vec::new();
+    let mut ineq_a = Vec::new();
+    let mut ineq_b = Vec::new();
+    for i in 0..qp.n_ub {
+        inequalities.push(Inequality::Row(i));
+        ineq_a.push(qp.a[i].clone());
+        ineq_b.push(qp.b[i]);
+    }
+    for j in 0..n {
+        let mut unit = vec![0f64; n];
+        if qp.lb[j].is_finite() {
+            unit[j] = -1f64;
+            inequalities.push(Inequality::Lower(j));
+            ineq_a.push(unit.clone());
+            ineq_b.push(-qp.lb[j]);
+        }
+        if qp.ub[j].is_finite() {
+            unit[j] = 1f64;
+            inequalities.push(Inequality::Upper(j));
+            ineq_a.push(unit);
+            ineq_b.push(qp.ub[j]);
+        }
+    }
+    let eq_a = &qp.a[qp.n_ub..];
+    let eq_b = &qp.b[qp.n_ub..];
+    let slack = |k: usize, x: &[f64]| ineq_b[k] - dot(&ineq_a[k], x);
+    let feasible = |x: &[f64]| {
+        (0..ineq_a.len()).all(|k| slack(k, x) >= -tol * (1f64 + ineq_b[k].abs()))
+            && eq_a
+                .iter()
+                .zip(eq_b)
+                .all(|(a, b)| (dot(a, x) - b).abs() <= tol * (1f64 + b.abs()))
+    };
+
+    let mut working: Vec<usize> = Vec::new();
+    let mut x = if let Some(warm_start) = warm_start.filter(|w| feasible(&w.x)) {
+        let mut rows = eq_a.to_vec();
+        for (k, inequality) in inequalities.iter().enumerate() {
+            let marginal = match *inequality {
+                Inequality::Row(i) => warm_start.ineqlin_marginals[i],
+                Inequality::Lower(j) => warm_start.lower_marginals[j],
+                Inequality::Upper(j) => warm_start.upper_marginals[j],
+            };
+            if marginal.abs() > 0f64
+                && slack(k, &warm_start.x).abs() <= tol * (1f64 + ineq_b[k].abs())
+                && independent(&rows, &ineq_a[k])
+            {
+                working.push(k);
+                rows.push(ineq_a[k].clone());
+            }
+        }
+        warm_start.x.clone()
+    } else {
+        let result = linprog(
+            &vec![0f64; n],
+            &qp.a[..qp.n_ub],
+            &qp.b[..qp.n_ub],
+            eq_a,
+            eq_b,
+            Some(&Bounds::new(qp.lb.clone(), qp.ub.clone())?),
+            LinprogOptions::default(),
+        )
+        .await?;
+        let status = match result.status {
+            LinprogStatus::Optimal => None,
+            LinprogStatus::Infeasible => Some(QuadprogStatus::Infeasible),
+            LinprogStatus::IterationLimit => Some(QuadprogStatus::IterationLimit),
+            LinprogStatus::Unbounded | LinprogStatus::NumericalDifficulties => {
+                Some(QuadprogStatus::NumericalDifficulties)
+            }
+        };
+        if let Some(status) = status {
+            return Ok(solution(qp, &inequalities, &[], &[], result.x, status, 0));
+        }
+        result.x
+    };
+
+    let mut nit = 0;
+    loop {
+        let g: Vec<f64> = mat_vec(&qp.p, &x)
+            .iter()
+            .zip(&qp.q)
+            .map(|(px, qi)| px + qi)
+            .collect();
+        let rows: Vec<Vec<f64>> = eq_a
+            .iter()
+            .chain(working.iter().map(|&k| &ineq_a[k]))
+            .cloned()
+            .collect();
+        let mut kkt = vec![vec![0f64; n + rows.len()]; n + rows.len()];
+        for (kkt_row, p_row) in kkt.iter_mut().zip(&qp.p) {
+            kkt_row[..n].copy_from_slice(p_row);
+        }
+        for (r, row) in rows.iter().enumerate() {
+            for (i, aij) in row.iter().enumerate() {
+                kkt[i][n + r] = *aij;
+                kkt[n + r][i] = *aij;
+            }
+        }
+        let mut rhs: Vec<f64> = g.iter().map(|gi| -gi).collect();
+        rhs.resize(n + rows.len(), 0f64);
+        let z = lstsq(&kkt, &rhs, n + rows.len());
+        let scale = 1f64 + norm_inf(&g);
+        let residual: Vec<f64> = mat_vec(&kkt, &z)
+            .iter()
+            .zip(&rhs)
+            .map(|(kz, r)| kz - r)
+            .collect();
+
+        let (step, zero_curvature) = if norm_inf(&residual) <= KKT_TOL * scale {
+            (z[..n].to_vec(), false)
+        } else {
+            // descent direction in the null space of P and the working set
+            let mut stacked = qp.p.clone();
+            stacked.extend(rows.iter().cloned());
+            let stacked_t = transpose(&stacked, n);
+            let projection = mat_vec(&stacked_t, &lstsq(&stacked_t, &g, stacked.len()));
+            let direction: Vec<f64> = projection.iter().zip(&g).map(|(p, gi)| p - gi).collect();
+            if norm_inf(&direction) <= KKT_TOL * scale {
+                return Ok(solution(
+                    qp,
+                    &inequalities,
+                    &[],
+                    &[],
+                    x,
+                    QuadprogStatus::NumericalDifficulties,
+                    nit,
+                ));
+            }
+            (direction, true)
+        };
+
+        if !zero_curvature && norm_inf(&step) <= tol * (1f64 + norm_inf(&x)) {
+            let multipliers = &z[n..];
+            let most_negative = multipliers[eq_a.len()..]
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.total_cmp(b.1));
+            match most_negative {
+                Some((r, multiplier)) if *multiplier < -tol * scale => {
+                    working.remove(r);
+                }
+                _ => {
+                    return Ok(solution(
+                        qp,
+                        &inequalities,
+                        &working,
+                        multipliers,
+                        x,
+                        QuadprogStatus::Optimal,
+                        nit,
+                    ))
+                }
+            }
+        } else {
+            let mut alpha = if zero_curvature { f64::INFINITY } else { 1f64 };
+            let mut blocking = None;
+            let step_norm = norm(&step);
+            for (k, a) in ineq_a.iter().enumerate() {
+                if working.contains(&k) {
+                    continue;
+                }
+                let a_step = dot(a, &step);
+                if a_step > KKT_TOL * norm(a) * step_norm {
+                    let length = (slack(k, &x) / a_step).max(0f64);
+                    if length < alpha {
+                        alpha = length;
+                        blocking = Some(k);
+                    }
+                }
+            }
+            if alpha.is_infinite() {
+                return Ok(solution(
+                    qp,
+                    &inequalities,
+                    &[],
+                    &[],
+                    x,
+                    QuadprogStatus::Unbounded,
+                    nit,
+                ));
+            }
+            for (xi, si) in x.iter_mut().zip(&step) {
+                *xi += alpha * si;
+            }
+            if let Some(k) = blocking {
+                working.push(k);
+            }
+        }
+
+        nit += 1;
+        if nit >= maxiter {
+            return Ok(solution(
+                qp,
+                &inequalities,
+                &[],
+                &[],
+                x,
+                QuadprogStatus::IterationLimit,
+                nit,
+            ));
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! Alternating direction method of multipliers
+//!
+//! The constraints are stacked as `l <= A x <= u`, with a row for every variable with a finite
+//! bound, and the problem is split as `min 0.5 x^T P x + q^T x` subject to `A x = z` and
+//! `l <= z <= u`. Every iteration solves a linear system with the sparse quasi-definite KKT
+//! matrix `[[P + sigma I, A^T], [A, -diag(1 / rho)]]`, whose `L D L^T` factorisation is only
+//! computed again when the step size `rho` is adapted to balance the primal and dual residuals,
+//! projects onto the bounds and updates the multipliers `y`. Infeasibility is detected from the
+//! differences of successive iterates, and the solution is polished by solving the regularised
+//! KKT system of the constraints found to be active with iterative refinement, which is also
+//! tried when the iteration limit is reached.
+//!
+//! # References
+//! * Stellato, Banjac, Goulart, Bemporad and Boyd, "OSQP: an operator splitting solver for
+//!   quadratic programs", Mathematical Programming Computation 12 (2020)
+
+use super::ldl::{ordering, Ldl};
+use super::{CscMatrix, QuadprogResult, QuadprogStatus, QuadraticProgram, Solution};
+use crate::linalg::{dot, norm_inf};
+
+/// Regularisation of the linear system
+const SIGMA: f64 = 1e-6;
+
+/// Over-relaxation parameter
+const ALPHA: f64 = 1.6;
+
+/// Initial step size
+const RHO: f64 = 0.1;
+
+/// Range of the step size
+const RHO_MIN: f64 = 1e-6;
+const RHO_MAX: f64 = 1e6;
+
+/// Factor of the step size of equality constraints
+const RHO_EQUALITY_SCALE: f64 = 1e3;
+
+/// Number of iterations between adaptations of the step size
+const ADAPTIVE_RHO_INTERVAL: usize = 25;
+
+/// Factor by which the step size has to change to be updated
+const ADAPTIVE_RHO_TOLERANCE: f64 = 5f64;
+
+/// Tolerance of the certificates of primal and dual infeasibility
+const INFEASIBILITY_TOL: f64 = 1e-5;
+
+/// Regularisation of the KKT system of the polishing step
+const POLISH_DELTA: f64 = 1e-6;
+
+/// Iterative refinement steps of the polished solution
+const POLISH_REFINE_ITER: usize = 3;
+
+/// Constraints `l <= A x <= u` of the splitting, with the variable of every bound row
+struct StackedConstraints {
+    a: CscMatrix,
+    l: Vec<f64>,
+    u: Vec<f64>,
+    bound_variables: Vec<usize>,
+}
+
+impl StackedConstraints {
+    fn new(qp: &QuadraticProgram<CscMatrix>) -> Self {
+        let n = qp.q.len();
+        let mut triplets: Vec<(usize, usize, f64)> = qp.a.triplets().collect();
+        let mut l: Vec<f64> = (0..qp.b.len())
+            .map(|i| {
+                if i < qp.n_ub {
+                    f64::NEG_INFINITY
+                } else {
+                    qp.b[i]
+                }
+            })
+            .collect();
+        let mut u = qp.b.clone();
+        let mut bound_variables = Vec::new();
+        for j in 0..n {
+            if qp.lb[j].is_finite() || qp.ub[j].is_finite() {
+                triplets.push((l.len(), j, 1f64));
+                l.push(qp.lb[j]);
+                u.push(qp.ub[j]);
+                bound_variables.push(j);
+            }
+        }
+        // the rows and columns of the triplets are in range by construction
+        let a = CscMatrix::from_triplets(l.len(), n, &triplets)
+            .unwrap_or_else(|_| CscMatrix::zeros(l.len(), n));
+        Self {
+            a,
+            l,
+            u,
+            bound_variables,
+        }
+    }
+
+    /// Projection onto `[l, u]`
+    fn project(&self, v: &[f64]) -> Vec<f64> {
+        v.iter()
+            .zip(&self.l)
+            .zip(&self.u)
+            .map(|((vi, li), ui)| vi.max(*li).min(*ui))
+            .collect()
+    }
+
+    /// Step size of every row, larger for equality constraints
+    fn rho(&self, rho: f64) -> Vec<f64> {
+        self.l
+            .iter()
+            .zip(&self.u)
+            .map(|(l, u)| {
+                if l >= u {
+                    rho * RHO_EQUALITY_SCALE
+                } else {
+                    rho
+                }
+            })
+            .collect()
+    }
+
+    /// Upper triangle of the KKT matrix `[[P + sigma I, A^T], [A, -diag(1 / rho)]]`
+    fn kkt(&self, p: &CscMatrix, rho: &[f64]) -> Vec<(usize, usize, f64)> {
+        let n = p.ncols();
+        let mut entries: Vec<(usize, usize, f64)> =
+            p.triplets().filter(|(i, j, _)| i <= j).collect();
+        entries.extend((0..n).map(|i| (i, i, SIGMA)));
+        entries.extend(self.a.triplets().map(|(i, j, v)| (j, n + i, v)));
+        entries.extend(
+            rho.iter()
+                .enumerate()
+                .map(|(i, rho_i)| (n + i, n + i, -1f64 / rho_i)),
+        );
+        entries
+    }
+}
+
+/// Residuals of the optimality conditions and the norms they are compared with
+struct Residuals {
+    primal: f64,
+    dual: f64,
+    primal_scale: f64,
+    dual_scale: f64,
+}
+
+impl Residuals {
+    fn new(
+        qp: &QuadraticProgram<CscMatrix>,
+        constraints: &StackedConstraints,
+        x: &[f64],
+        z: &[f64],
+        y: &[f64],
+    ) -> Self {
+        let ax = constraints.a.mat_vec(x);
+        let px = qp.p.mat_vec(x);
+        let aty = constraints.a.mat_t_vec(y);
+        let dual: Vec<f64> = px
+            .iter()
+            .zip(&qp.q)
+            .zip(&aty)
+            .map(|((pxi, qi), atyi)| pxi + qi + atyi)
+            .collect();
+        Self {
+            primal: ax
+                .iter()
+                .zip(z)
+                .fold(0f64, |acc, (axi, zi)| acc.max((axi - zi).abs())),
+            dual: norm_inf(&dual),
+            primal_scale: norm_inf(&ax).max(norm_inf(z)),
+            dual_scale: norm_inf(&px).max(norm_inf(&aty)).max(norm_inf(&qp.q)),
+        }
+    }
+
+    fn converged(&self, tol: f64) -> bool {
+        self.primal <= tol * (1f64 + self.primal_scale)
+            && self.dual <= tol * (1f64 + self.dual_scale)
+    }
+}
+
+/// Whether the change of the multipliers certifies that the constraints are inconsistent
+fn primal_infeasible(constraints: &StackedConstraints, delta_y: &[f64]) -> bool {
+    let scale = norm_inf(delta_y);
+    if scale <= INFEASIBILITY_TOL {
+        return false;
+    }
+    // project onto the polar cone of the recession cone of the bounds
+    let delta_y: Vec<f64> = delta_y
+        .iter()
+        .zip(&constraints.l)
+        .zip(&constraints.u)
+        .map(|((dy, l), u)| {
+            let mut dy = dy / scale;
+            if u.is_infinite() {
+                dy = dy.min(0f64);
+            }
+            if l.is_infinite() {
+                dy = dy.max(0f64);
+            }
+            dy
+        })
+        .collect();
+    let support: f64 = delta_y
+        .iter()
+        .zip(&constraints.l)
+        .zip(&constraints.u)
+        .map(|((dy, l), u)| {
+            if *dy > 0f64 {
+                u * dy
+            } else if *dy < 0f64 {
+                l * dy
+            } else {
+                0f64
+            }
+        })
+        .sum();
+    norm_inf(&constraints.a.mat_t_vec(&delta_y)) <= INFEASIBILITY_TOL
+        && support < -INFEASIBILITY_TOL
+}
+
+/// Whether the change of the solution is a direction along which the objective decreases
+/// without bound
+fn dual_infeasible(
+    qp: &QuadraticProgram<CscMatrix>,
+    constraints: &StackedConstraints,
+    delta_x: &[f64],
+) -> bool {
+    let scale = norm_inf(delta_x);
+    if scale <= INFEASIBILITY_TOL {
+        return false;
+    }
+    let delta_x: Vec<f64> = delta_x.iter().map(|dx| dx / scale).collect();
+    dot(&qp.q, &delta_x) < -INFEASIBILITY_TOL
+        && norm_inf(&qp.p.mat_vec(&delta_x)) <= INFEASIBILITY_TOL
+        && constraints
+            .a
+            .mat_vec(&delta_x)
+            .iter()
+            .zip(&constraints.l)
+            .zip(&constraints.u)
+            .all(|((adx, l), u)| {
+                (u.is_infinite() || *adx <= INFEASIBILITY_TOL)
+                    && (l.is_infinite() || *adx >= -INFEASIBILITY_TOL)
+            })
+}
+
+/// Side of an active constraint
+#[derive(Debug, Clone, Copy)]
+enum Side {
+    Equality,
+    Lower,
+    Upper,
+}
+
+/// Solve the KKT system of the constraints that are active at the solution, returning the
+/// refined solution and multipliers if they satisfy the optimality conditions at least as well
+/// as a converged solution, or within the tolerance otherwise
+#[allow(clippy::too_many_lines)]
+fn polish(
+    qp: &QuadraticProgram<CscMatrix>,
+    constraints: &StackedConstraints,
+    z: &[f64],
+    y: &[f64],
+    residuals: &Residuals,
+    tol: f64,
+    converged: bool,
+) -> Option<(Vec<f64>, Vec<f64>)> {
+    let n = qp.q.len();
+    let active: Vec<(usize, Side)> = z
+        .iter()
+        .zip(y)
+        .zip(constraints.l.iter().zip(&constraints.u))
+        .enumerate()
+        .filter_map(|(i, ((zi, yi), (li, ui)))| {
+            if li >= ui {
+                Some((i, Side::Equality))
+            } else if zi - li < -yi {
+                Some((i, Side::Lower))
+            } else if ui - zi < *yi {
+                Some((i, Side::Upper))
+            } else {
+                None
+            }
+        })
+        .collect();
+    let size = n + active.len();
+    let mut active_row = vec![None; z.len()];
+    let mut rhs: Vec<f64> = qp.q.iter().map(|qi| -qi).collect();
+    for (r, (i, side)) in active.iter().enumerate() {
+        active_row[*i] = Some(r);
+        rhs.push(match side {
+            Side::Lower => constraints.l[*i],
+            Side::Equality | Side::Upper => constraints.u[*i],
+        });
+    }
+    let rows: Vec<(usize, usize, f64)> = constraints
+        .a
+        .triplets()
+        .filter_map(|(i, j, v)| active_row[i].map(|r| (r, j, v)))
+        .collect();
+    // regularised KKT matrix [[P + delta I, A^T], [A, -delta I]] of the active rows
+    let mut entries: Vec<(usize, usize, f64)> =
+        qp.p.triplets().filter(|(i, j, _)| i <= j).collect();
+    entries.extend((0..n).map(|i| (i, i, POLISH_DELTA)));
+    entries.extend(rows.iter().map(|&(r, j, v)| (j, n + r, v)));
+    entries.extend((n..size).map(|i| (i, i, -POLISH_DELTA)));
+    let factor = Ldl::factorise(size, &entries, &ordering(size, &entries))?;
+    // product with the KKT matrix without the regularisation
+    let kkt = |v: &[f64]| -> Vec<f64> {
+        let mut product = qp.p.mat_vec(&v[..n]);
+        product.resize(size, 0f64);
+        for &(r, j, a) in &rows {
+            product[j] += a * v[n + r];
+            product[n + r] += a * v[j];
+        }
+        product
+    };
+    let mut solution = factor.solve(&rhs);
+    for _ in 0..POLISH_REFINE_ITER {
+        let residual: Vec<f64> = rhs.iter().zip(kkt(&solution)).map(|(b, k)| b - k).collect();
+        for (s, c) in solution.iter_mut().zip(factor.solve(&residual)) {
+            *s += c;
+        }
+    }
+    let x = solution[..n].to_vec();
+    let mut y_polished = vec![0f64; y.len()];
+    for ((i, side), multiplier) in active.iter().zip(&solution[n..]) {
+        // the multiplier of an active lower bound is non-positive, of an upper bound
+        // non-negative
+        match side {
+            Side::Lower if *multiplier > tol => return None,
+            Side::Upper if *multiplier < -tol => return None,
+            _ => {}
+        }
+        y_polished[*i] = *multiplier;
+    }
+    let ax = constraints.a.mat_vec(&x);
+    let violation = ax
+        .iter()
+        .zip(&constraints.l)
+        .zip(&constraints.u)
+        .fold(0f64, |acc, ((axi, li), ui)| acc.max(li - axi).max(axi - ui));
+    let z_polished = constraints.project(&ax);
+    let polished = Residuals::new(qp, constraints, &x, &z_polished, &y_polished);
+    let mut primal_limit = tol * (1f64 + residuals.primal_scale);
+    let mut dual_limit = tol * (1f64 + residuals.dual_scale);
+    if converged {
+        primal_limit = primal_limit.max(residuals.primal);
+        dual_limit = dual_limit.max(residuals.dual);
+    }
+    (violation <= primal_limit && polished.dual <= dual_limit).then_some((x, y_polished))
+}
+
+/// Solve a quadratic program with the alternating direction method of multipliers
+///
+/// # Parameters
+/// * `qp` - quadratic program
+/// * `warm_start` - optional previous result to start from
+/// * `tol` - absolute and relative tolerance of the residuals
+/// * `maxiter` - maximum iterations
+/// * `polish_solution` - whether to polish the solution
+#[allow(clippy::too_many_lines)]
+pub(super) fn admm(
+    qp: &QuadraticProgram<CscMatrix>,
+    warm_start: Option<&QuadprogResult>,
+    tol: f64,
+    maxiter: usize,
+    polish_solution: bool,
+) -> Solution {
+    let n = qp.q.len();
+    let n_rows = qp.b.len();
+    let constraints = StackedConstraints::new(qp);
+    let m = constraints.a.nrows();
+
+    let (mut x, mut y) = match warm_start {
+        Some(warm_start) => {
+            let mut y: Vec<f64> = warm_start
+                .ineqlin_marginals
+                .iter()
+                .chain(&warm_start.eqlin_marginals)
+                .map(|marginal| -marginal)
+                .collect();
+            y.extend(
+                constraints
+                    .bound_variables
+                    .iter()
+                    .map(|&j| -(warm_start.lower_marginals[j] + warm_start.upper_marginals[j])),
+            );
+            (warm_start.x.clone(), y)
+        }
+        None => (vec![0f64; n], vec![0f64; m]),
+    };
+    let mut z = constraints.project(&constraints.a.mat_vec(&x));
+
+    let mut rho = RHO;
+    let mut rho_vec = constraints.rho(rho);
+    // the pattern of the KKT matrix does not depend on the step size
+    let kkt = constraints.kkt(&qp.p, &rho_vec);
+    let perm = ordering(n + m, &kkt);
+    let mut factor = Ldl::factorise(n + m, &kkt, &perm);
+    let mut status = QuadprogStatus::IterationLimit;
+    let mut residuals = Residuals::new(qp, &constraints, &x, &z, &y);
+    let mut nit = 0;
+    while nit < maxiter {
+        let Some(ldl) = &factor else {
+            status = QuadprogStatus::NumericalDifficulties;
+            break;
+        };
+        nit += 1;
+
+        let rhs: Vec<f64> = x
+            .iter()
+            .zip(&qp.q)
+            .map(|(xi, qi)| SIGMA * xi - qi)
+            .chain(
+                z.iter()
+                    .zip(&y)
+                    .zip(&rho_vec)
+                    .map(|((zi, yi), rho_i)| zi - yi / rho_i),
+            )
+            .collect();
+        let solution = ldl.solve(&rhs);
+        let (x_tilde, nu) = solution.split_at(n);
+        let z_tilde: Vec<f64> = z
+            .iter()
+            .zip(nu)
+            .zip(y.iter().zip(&rho_vec))
+            .map(|((zi, nui), (yi, rho_i))| zi + (nui - yi) / rho_i)
+            .collect();
+
+        let x_new: Vec<f64> = x_tilde
+            .iter()
+            .zip(&x)
+            .map(|(xt, xi)| ALPHA * xt + (1f64 - ALPHA) * xi)
+            .collect();
+        let z_relaxed: Vec<f64> = z_tilde
+            .iter()
+            .zip(&z)
+            .map(|(zt, zi)| ALPHA * zt + (1f64 - ALPHA) * zi)
+            .collect();
+        let shifted: Vec<f64> = z_relaxed
+            .iter()
+            .zip(&y)
+            .zip(&rho_vec)
+            .map(|((zr, yi), rho_i)| zr + yi / rho_i)
+            .collect();
+        let z_new = constraints.project(&shifted);
+        let y_new: Vec<f64> = y
+            .iter()
+            .zip(&rho_vec)
+            .zip(z_relaxed.iter().zip(&z_new))
+            .map(|((yi, rho_i), (zr, zn))| yi + rho_i * (zr - zn))
+            .collect();
+
+        let delta_x: Vec<f64> = x_new.iter().zip(&x).map(|(a, b)| a - b).collect();
+        let delta_y: Vec<f64> = y_new.iter().zip(&y).map(|(a, b)| a - b).collect();
+        x = x_new;
+        z = z_new;
+        y = y_new;
+
+        residuals = Residuals::new(qp, &constraints, &x, &z, &y);
+        if residuals.converged(tol) {
+            status = QuadprogStatus::Optimal;
+            break;
+        }
+        if primal_infeasible(&constraints, &delta_y) {
+            status = QuadprogStatus::Infeasible;
+            break;
+        }
+        if dual_infeasible(qp, &constraints, &delta_x) {
+            status = QuadprogStatus::Unbounded;
+            break;
+        }
+
+        if nit % ADAPTIVE_RHO_INTERVAL == 0 {
+            let primal = residuals.primal / residuals.primal_scale.max(1e-10);
+            let dual = residuals.dual / residuals.dual_scale.max(1e-10);
+            let new_rho = (rho * (primal / (dual + 1e-10)).sqrt()).clamp(RHO_MIN, RHO_MAX);
+            if new_rho > ADAPTIVE_RHO_TOLERANCE * rho || new_rho < rho / ADAPTIVE_RHO_TOLERANCE {
+                rho = new_rho;
+                rho_vec = constraints.rho(rho);
+                factor = Ldl::factorise(n + m, &constraints.kkt(&qp.p, &rho_vec), &perm);
+            }
+        }
+    }
+
+    // a solution that is not converged yet may still identify the active constraints
+    if polish_solution
+        && matches!(
+            status,
+            QuadprogStatus::Optimal | QuadprogStatus::IterationLimit
+        )
+    {
+        let converged = status == QuadprogStatus::Optimal;
+        if let Some((x_polished, y_polished)) =
+            polish(qp, &constraints, &z, &y, &residuals, tol, converged)
+        {
+            x = x_polished;
+            y = y_polished;
+            status = QuadprogStatus::Optimal;
+        }
+    }
+
+    let mut row_marginals = vec![0f64; n_rows];
+    let mut lower_marginals = vec![0f64; n];
+    let mut upper_marginals = vec![0f64; n];
+    if status == QuadprogStatus::Optimal {
+        for (marginal, yi) in row_marginals.iter_mut().zip(&y) {
+            *marginal = -yi;
+        }
+        for (&j, yi) in constraints.bound_variables.iter().zip(&y[n_rows..]) {
+            if *yi < 0f64 {
+                lower_marginals[j] = -yi;
+            } else {
+                upper_marginals[j] = -yi;
+            }
+        }
+    }
+    Solution {
+        x,
+        row_marginals,
+        lower_marginals,
+        upper_marginals,
+        status,
+        nit,
+    }
+}
@@ -0,0 +1,255 @@
+//! Sparse LDL^T factorisation
+//!
+//! Symmetric quasi-definite matrices, such as the KKT systems of the ADMM method, have an
+//! `L D L^T` factorisation for every symmetric permutation, without pivoting. The rows and
+//! columns are ordered by minimum degree to limit the fill-in, the pattern of `L` is found from
+//! the elimination tree and `L` is computed one row at a time as in QDLDL.
+//!
+//! # References
+//! * Vanderbei, "Symmetric quasidefinite matrices", SIAM Journal on Optimization 5.1 (1995)
+//! * Davis, "Algorithm 849: A concise sparse Cholesky factorization package", ACM Transactions
+//!   on Mathematical Software 31.4 (2005)
+//! * Stellato, Banjac, Goulart, Bemporad and Boyd, "OSQP: an operator splitting solver for
+//!   quadratic programs", Mathematical Programming Computation 12 (2020)
+
+use super::CscMatrix;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap};
+
+/// Order of elimination of the variables of a symmetric pattern, eliminating a variable with
+/// the fewest remaining neighbours at every step
+fn minimum_degree(n: usize, edges: impl Iterator<Item = (usize, usize)>) -> Vec<usize> {
+    let mut adjacency: Vec<BTreeSet<usize>> = vec![BTreeSet::new(); n];
+    for (i, j) in edges.filter(|(i, j)| i != j) {
+        adjacency[i].insert(j);
+        adjacency[j].insert(i);
+    }
+    let mut heap: BinaryHeap<Reverse<(usize, usize)>> = adjacency
+        .iter()
+        .enumerate()
+        .map(|(i, neighbours)| Reverse((neighbours.len(), i)))
+        .collect();
+    let mut eliminated = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+    while let Some(Reverse((degree, v))) = heap.pop() {
+        // entries are pushed again when the degree changes, the outdated ones are skipped
+        if eliminated[v] || degree != adjacency[v].len() {
+            continue;
+        }
+        eliminated[v] = true;
+        order.push(v);
+        // the neighbours of an eliminated variable become a clique
+        let neighbours: Vec<usize> = std::mem::take(&mut adjacency[v]).into_iter().collect();
+        for &u in &neighbours {
+            adjacency[u].remove(&v);
+            adjacency[u].extend(neighbours.iter().filter(|&&w| w != u));
+        }
+        for &u in &neighbours {
+            heap.push(Reverse((adjacency[u].len(), u)));
+        }
+    }
+    order
+}
+
+/// Fill reducing ordering of a symmetric matrix of size `n` given by the entries of one of its
+/// triangles, such that variable `perm[k]` is eliminated `k`-th
+pub(super) fn ordering(n: usize, entries: &[(usize, usize, f64)]) -> Vec<usize> {
+    minimum_degree(n, entries.iter().map(|&(i, j, _)| (i, j)))
+}
+
+/// Factorisation `P A P^T = L D L^T` of a symmetric matrix, with unit lower triangular `L`
+#[derive(Debug, Clone)]
+pub(super) struct Ldl {
+    perm: Vec<usize>,
+    l_col_ptr: Vec<usize>,
+    l_row_idx: Vec<usize>,
+    l_values: Vec<f64>,
+    d: Vec<f64>,
+}
+
+impl Ldl {
+    /// Factorise the symmetric matrix of size `n` given by the entries of one of its triangles,
+    /// repeated entries are summed. Returns `None` if a pivot is zero or not finite
+    pub(super) fn factorise(
+        n: usize,
+        entries: &[(usize, usize, f64)],
+        perm: &[usize],
+    ) -> Option<Self> {
+        let mut inverse = vec![0usize; n];
+        for (k, &i) in perm.iter().enumerate() {
+            inverse[i] = k;
+        }
+        // upper triangle of the permuted matrix
+        let upper: Vec<(usize, usize, f64)> = entries
+            .iter()
+            .map(|&(i, j, v)| {
+                let (i, j) = (inverse[i], inverse[j]);
+                (i.min(j), i.max(j), v)
+            })
+            .collect();
+        let upper = CscMatrix::from_triplets(n, n, &upper).ok()?;
+
+        // elimination tree and the number of entries in every column of L
+        let mut parent: Vec<Option<usize>> = vec![None; n];
+        let mut counts = vec![0usize; n];
+        let mut visited = vec![usize::MAX; n];
+        for k in 0..n {
+            visited[k] = k;
+            for &i in upper.column(k).0 {
+                let mut i = i;
+                while visited[i] != k {
+                    if parent[i].is_none() {
+                        parent[i] = Some(k);
+                    }
+                    counts[i] += 1;
+                    visited[i] = k;
+                    match parent[i] {
+                        Some(p) => i = p,
+                        None => break,
+                    }
+                }
+            }
+        }
+        let mut l_col_ptr = vec![0usize; n + 1];
+        for i in 0..n {
+            l_col_ptr[i + 1] = l_col_ptr[i] + counts[i];
+        }
+        let mut l_row_idx = vec![0usize; l_col_ptr[n]];
+        let mut l_values = vec![0f64; l_col_ptr[n]];
+        let mut next = l_col_ptr[..n].to_vec();
+
+        // rows of L in turn, the pattern of row k is the set of ancestors of the entries of
+        // column k of the upper triangle in the elimination tree
+        let mut d = vec![0f64; n];
+        let mut y = vec![0f64; n];
+        let mut marked = vec![false; n];
+        let mut pattern: Vec<usize> = Vec::with_capacity(n);
+        let mut stack: Vec<usize> = Vec::with_capacity(n);
+        for k in 0..n {
+            pattern.clear();
+            let (rows, values) = upper.column(k);
+            for (&i, &v) in rows.iter().zip(values) {
+                if i == k {
+                    d[k] = v;
+                    continue;
+                }
+                y[i] = v;
+                let mut node = Some(i);
+                while let Some(j) = node.filter(|&j| j < k && !marked[j]) {
+                    marked[j] = true;
+                    stack.push(j);
+                    node = parent[j];
+                }
+                while let Some(j) = stack.pop() {
+                    pattern.push(j);
+                }
+            }
+            // the columns of the pattern in topological order
+            for &j in pattern.iter().rev() {
+                let y_j = y[j];
+                for p in l_col_ptr[j]..next[j] {
+                    y[l_row_idx[p]] -= l_values[p] * y_j;
+                }
+                let l_kj = y_j / d[j];
+                l_row_idx[next[j]] = k;
+                l_values[next[j]] = l_kj;
+                next[j] += 1;
+                d[k] -= y_j * l_kj;
+                y[j] = 0f64;
+                marked[j] = false;
+            }
+            if d[k] == 0f64 || !d[k].is_finite() {
+                return None;
+            }
+        }
+        Some(Self {
+            perm: perm.to_vec(),
+            l_col_ptr,
+            l_row_idx,
+            l_values,
+            d,
+        })
+    }
+
+    /// Diagonal `D` of the factorisation, in the permuted order
+    pub(super) fn d(&self) -> &[f64] {
+        &self.d
+    }
+
+    /// Solve `A x = b`
+    pub(super) fn solve(&self, b: &[f64]) -> Vec<f64> {
+        let mut y: Vec<f64> = self.perm.iter().map(|&i| b[i]).collect();
+        for j in 0..y.len() {
+            for p in self.l_col_ptr[j]..self.l_col_ptr[j + 1] {
+                y[self.l_row_idx[p]] -= self.l_values[p] * y[j];
+            }
+        }
+        for (yi, di) in y.iter_mut().zip(&self.d) {
+            *yi /= di;
+        }
+        for j in (0..y.len()).rev() {
+            for p in self.l_col_ptr[j]..self.l_col_ptr[j + 1] {
+                y[j] -= self.l_values[p] * y[self.l_row_idx[p]];
+            }
+        }
+        let mut x = vec![0f64; y.len()];
+        for (&i, yi) in self.perm.iter().zip(y) {
+            x[i] = yi;
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_ldl() {
+        // quasi-definite KKT matrix [[P, A^T], [A, -D]] with an arrow shaped P
+        let n = 6;
+        let entries = vec![
+            (0, 0, 10f64),
+            (0, 1, 1f64),
+            (0, 2, 1f64),
+            (0, 3, 1f64),
+            (1, 1, 3f64),
+            (2, 2, 4f64),
+            (3, 3, 5f64),
+            (0, 4, 1f64),
+            (2, 4, -1f64),
+            (3, 5, 2f64),
+            (4, 4, -0.5),
+            (5, 5, -0.25),
+        ];
+        let mut symmetric = vec![vec![0f64; n]; n];
+        for &(i, j, v) in &entries {
+            symmetric[i][j] = v;
+            symmetric[j][i] = v;
+        }
+        let b = [1f64, -2f64, 0.5, 3f64, -1f64, 2f64];
+
+        let perm = ordering(n, &entries);
+        // the hub of the arrow is eliminated after its leaves
+        assert!(perm.iter().position(|&i| i == 0) > perm.iter().position(|&i| i == 1));
+        let mut sorted = perm.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..n).collect::<Vec<usize>>());
+
+        for perm in [perm, (0..n).collect()] {
+            let Some(ldl) = Ldl::factorise(n, &entries, &perm) else {
+                panic!("the quasi-definite matrix has an LDL^T factorisation");
+            };
+            // four positive and two negative pivots
+            assert_eq!(ldl.d().iter().filter(|d| **d > 0f64).count(), 4);
+            let x = ldl.solve(&b);
+            for (row, bi) in symmetric.iter().zip(&b) {
+                let ax: f64 = row.iter().zip(&x).map(|(a, x)| a * x).sum();
+                assert!(relative_eq!(ax, bi, epsilon = 1e-12));
+            }
+        }
+        let singular = [(0, 0, 1f64), (0, 1, 1f64), (1, 1, 1f64)];
+        assert!(Ldl::factorise(2, &singular, &[0, 1]).is_none());
+    }
+}
@@ -0,0 +1,821 @@
+//! Convex quadratic programming
+//!
+//! Minimise `0.5 x^T P x + q^T x` subject to `A_ub x <= b_ub`, `A_eq x = b_eq` and
+//! `lb <= x <= ub`, where `P` is symmetric positive semidefinite.
+//!
+//! `quadprog` takes dense matrices and `quadprog_sparse` takes `CscMatrix`es. The active set
+//! method works on dense copies of the matrices, while the ADMM method keeps them sparse and
+//! factorises a sparse KKT system.
+
+mod active_set;
+mod admm;
+mod ldl;
+mod sparse;
+
+pub use sparse::CscMatrix;
+
+use crate::constraints::Bounds;
+use crate::linalg::{cholesky, dot};
+use crate::linprog::check_constraints;
+use crate::SwoopErrors;
+use ldl::{ordering, Ldl};
+
+/// Algorithm used by `quadprog`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QuadprogMethod {
+    /// Primal active set method solving the dense KKT system of the working set in every
+    /// iteration, accurate and suited to small problems
+    #[default]
+    ActiveSet,
+    /// Alternating direction method of multipliers as in OSQP, factorising a single sparse KKT
+    /// matrix until the step size is adapted, suited to larger and sparse problems that need
+    /// less accuracy
+    Admm,
+}
+
+/// Options for the `quadprog` solver
+#[derive(Debug, Clone, Copy)]
+pub struct QuadprogOptions {
+    /// Algorithm to use
+    pub method: QuadprogMethod,
+    /// Maximum iterations, defaults to `max(1000, 10 * (m + n))` for `ActiveSet`, where `m`
+    /// counts the constraints and finite bounds, and `10000` for `Admm`
+    pub maxiter: Option<usize>,
+    /// Tolerance for feasibility and optimality, defaults to `1e-9` for `ActiveSet` and `1e-6`
+    /// for `Admm`, which is used as both the absolute and relative tolerance
+    pub tol: Option<f64>,
+    /// Whether `Admm` refines its solution by solving the KKT system of the constraints it
+    /// finds active, which may also turn a solution at the iteration limit into an optimal one
+    pub polish: bool,
+}
+
+impl Default for QuadprogOptions {
+    fn default() -> Self {
+        Self {
+            method: QuadprogMethod::default(),
+            maxiter: None,
+            tol: None,
+            polish: true,
+        }
+    }
+}
+
+/// Reason the `quadprog` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuadprogStatus {
+    /// An optimal solution was found
+    Optimal,
+    /// The maximum number of iterations was exceeded
+    IterationLimit,
+    /// The problem has no feasible solution
+    Infeasible,
+    /// The objective is unbounded below on the feasible region
+    Unbounded,
+    /// The solver ran into numerical difficulties
+    NumericalDifficulties,
+}
+
+/// Struct to represent the result of a `quadprog` optimisation, which can be passed back to
+/// `quadprog` to warm start a related problem
+#[derive(Debug, Clone)]
+pub struct QuadprogResult {
+    /// The solution of the optimisation
+    pub x: Vec<f64>,
+    /// Value of the objective `0.5 x^T P x + q^T x`
+    pub fun: f64,
+    /// Slack of the inequality constraints `b_ub - A_ub x`
+    pub slack: Vec<f64>,
+    /// Residuals of the equality constraints `b_eq - A_eq x`
+    pub con: Vec<f64>,
+    /// Sensitivity of the objective to `b_ub`, non-positive at the optimum
+    pub ineqlin_marginals: Vec<f64>,
+    /// Sensitivity of the objective to `b_eq`
+    pub eqlin_marginals: Vec<f64>,
+    /// Sensitivity of the objective to the lower bounds, non-negative at the optimum
+    pub lower_marginals: Vec<f64>,
+    /// Sensitivity of the objective to the upper bounds, non-positive at the optimum
+    pub upper_marginals: Vec<f64>,
+    /// Reason the solver terminated
+    pub status: QuadprogStatus,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// Number of iterations
+    pub nit: usize,
+}
+
+/// Quadratic program `min 0.5 x^T P x + q^T x` subject to `A x <= b` for the first `n_ub` rows,
+/// `A x = b` for the remaining rows and `lb <= x <= ub`, with dense or sparse matrices
+struct QuadraticProgram<M = Vec<Vec<f64>>> {
+    p: M,
+    q: Vec<f64>,
+    a: M,
+    b: Vec<f64>,
+    n_ub: usize,
+    lb: Vec<f64>,
+    ub: Vec<f64>,
+}
+
+impl QuadraticProgram<CscMatrix> {
+    /// Objective `0.5 x^T P x + q^T x`
+    fn objective(&self, x: &[f64]) -> f64 {
+        0.5 * dot(x, &self.p.mat_vec(x)) + dot(&self.q, x)
+    }
+
+    /// Copy of the program with dense matrices
+    fn to_dense(&self) -> QuadraticProgram {
+        QuadraticProgram {
+            p: self.p.to_dense(),
+            q: self.q.clone(),
+            a: self.a.to_dense(),
+            b: self.b.clone(),
+            n_ub: self.n_ub,
+            lb: self.lb.clone(),
+            ub: self.ub.clone(),
+        }
+    }
+}
+
+/// Solution found by one of the methods, with the marginals of the rows of `a`
+struct Solution {
+    x: Vec<f64>,
+    row_marginals: Vec<f64>,
+    lower_marginals: Vec<f64>,
+    upper_marginals: Vec<f64>,
+    status: QuadprogStatus,
+    nit: usize,
+}
+
+/// Convex quadratic programming
+///
+/// Minimise `0.5 x^T P x + q^T x` subject to `A_ub x <= b_ub`, `A_eq x = b_eq` and
+/// `lb <= x <= ub`. Only the symmetric part of `P` is used, which must be positive
+/// semidefinite.
+///
+/// # Parameters
+/// * `p` - quadratic term of the objective, `n x n`
+/// * `q` - linear term of the objective
+/// * `a_ub` - inequality constraint matrix, one row per constraint
+/// * `b_ub` - upper bounds of the inequality constraints
+/// * `a_eq` - equality constraint matrix, one row per constraint
+/// * `b_eq` - right hand side of the equality constraints
+/// * `bounds` - optional bounds on the variables, the variables are free by default
+/// * `warm_start` - optional result of a previous solve of a problem of the same size, whose
+///   solution and marginals are used as the starting point
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `q` is empty, `p`, the constraint matrices,
+/// bounds or warm start have the wrong size, the coefficients are not finite, `p` is not
+/// positive semidefinite or the tolerance is not positive
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::too_many_lines)]
+pub async fn quadprog(
+    p: &[Vec<f64>],
+    q: &[f64],
+    a_ub: &[Vec<f64>],
+    b_ub: &[f64],
+    a_eq: &[Vec<f64>],
+    b_eq: &[f64],
+    bounds: Option<&Bounds>,
+    warm_start: Option<&QuadprogResult>,
+    options: QuadprogOptions,
+) -> Result<QuadprogResult, SwoopErrors> {
+    let n = q.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must have at least one variable",
+        )));
+    }
+    if q.iter().any(|qi| !qi.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must be finite",
+        )));
+    }
+    if p.len() != n || p.iter().any(|row| row.len() != n) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "P must be a square matrix with one row per variable",
+        )));
+    }
+    if p.iter().flatten().any(|v| !v.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from("P must be finite")));
+    }
+    let p: Vec<Vec<f64>> = (0..n)
+        .map(|i| (0..n).map(|j| 0.5 * (p[i][j] + p[j][i])).collect())
+        .collect();
+    // a small shift keeps semidefinite matrices positive definite but not indefinite ones
+    let shift = 1e-8 * p.iter().flatten().fold(1f64, |acc, v| acc.max(v.abs()));
+    let shifted: Vec<Vec<f64>> = p
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let mut row = row.clone();
+            row[i] += shift;
+            row
+        })
+        .collect();
+    if cholesky(&shifted).is_none() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "P must be positive semidefinite",
+        )));
+    }
+    check_constraints(a_ub, b_ub, n)?;
+    check_constraints(a_eq, b_eq, n)?;
+    let (lb, ub) = check_options(n, b_ub.len(), b_eq.len(), bounds, warm_start, options)?;
+
+    let qp = QuadraticProgram {
+        p: CscMatrix::from_dense(&p, n)?,
+        q: q.to_vec(),
+        a: CscMatrix::from_dense(&a_ub.iter().chain(a_eq).cloned().collect::<Vec<_>>(), n)?,
+        b: b_ub.iter().chain(b_eq).copied().collect(),
+        n_ub: a_ub.len(),
+        lb,
+        ub,
+    };
+    solve(&qp, warm_start, options).await
+}
+
+/// Convex quadratic programming with sparse matrices
+///
+/// Minimise `0.5 x^T P x + q^T x` subject to `A_ub x <= b_ub`, `A_eq x = b_eq` and
+/// `lb <= x <= ub` as `quadprog`, with the matrices in compressed sparse column format. Only the
+/// symmetric part of `P` is used, which must be positive semidefinite, so `P` may be given as
+/// either of its triangles with the off-diagonal entries doubled, or in full. Problems without
+/// inequality or equality constraints take a matrix without rows from `CscMatrix::zeros`.
+///
+/// # Parameters
+/// * `p` - quadratic term of the objective, `n x n`
+/// * `q` - linear term of the objective
+/// * `a_ub` - inequality constraint matrix, one row per constraint
+/// * `b_ub` - upper bounds of the inequality constraints
+/// * `a_eq` - equality constraint matrix, one row per constraint
+/// * `b_eq` - right hand side of the equality constraints
+/// * `bounds` - optional bounds on the variables, the variables are free by default
+/// * `warm_start` - optional result of a previous solve of a problem of the same size, whose
+///   solution and marginals are used as the starting point
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `q` is empty, `p`, the constraint matrices,
+/// bounds or warm start have the wrong size, the coefficients are not finite, `p` is not
+/// positive semidefinite or the tolerance is not positive
+#[allow(clippy::too_many_arguments)]
+pub async fn quadprog_sparse(
+    p: &CscMatrix,
+    q: &[f64],
+    a_ub: &CscMatrix,
+    b_ub: &[f64],
+    a_eq: &CscMatrix,
+    b_eq: &[f64],
+    bounds: Option<&Bounds>,
+    warm_start: Option<&QuadprogResult>,
+    options: QuadprogOptions,
+) -> Result<QuadprogResult, SwoopErrors> {
+    let n = q.len();
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must have at least one variable",
+        )));
+    }
+    if q.iter().any(|qi| !qi.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The objective must be finite",
+        )));
+    }
+    if p.nrows() != n || p.ncols() != n {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "P must be a square matrix with one row per variable",
+        )));
+    }
+    if p.values().iter().any(|v| !v.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from("P must be finite")));
+    }
+    let symmetric: Vec<(usize, usize, f64)> = p
+        .triplets()
+        .flat_map(|(i, j, v)| [(i, j, 0.5 * v), (j, i, 0.5 * v)])
+        .collect();
+    let p = CscMatrix::from_triplets(n, n, &symmetric)?;
+    // a small shift keeps semidefinite matrices positive definite but not indefinite ones
+    let shift = 1e-8 * p.values().iter().fold(1f64, |acc, v| acc.max(v.abs()));
+    let mut upper: Vec<(usize, usize, f64)> = p.triplets().filter(|(i, j, _)| i <= j).collect();
+    upper.extend((0..n).map(|i| (i, i, shift)));
+    let positive_definite = Ldl::factorise(n, &upper, &ordering(n, &upper))
+        .is_some_and(|ldl| ldl.d().iter().all(|d| *d > 0f64));
+    if !positive_definite {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "P must be positive semidefinite",
+        )));
+    }
+    check_sparse_constraints(a_ub, b_ub, n)?;
+    check_sparse_constraints(a_eq, b_eq, n)?;
+    let (lb, ub) = check_options(n, b_ub.len(), b_eq.len(), bounds, warm_start, options)?;
+
+    let rows: Vec<(usize, usize, f64)> = a_ub
+        .triplets()
+        .chain(a_eq.triplets().map(|(i, j, v)| (b_ub.len() + i, j, v)))
+        .collect();
+    let qp = QuadraticProgram {
+        p,
+        q: q.to_vec(),
+        a: CscMatrix::from_triplets(b_ub.len() + b_eq.len(), n, &rows)?,
+        b: b_ub.iter().chain(b_eq).copied().collect(),
+        n_ub: b_ub.len(),
+        lb,
+        ub,
+    };
+    solve(&qp, warm_start, options).await
+}
+
+/// Check that a sparse constraint matrix has one column per variable and one row per entry of
+/// the right hand side, and that both are finite
+fn check_sparse_constraints(a: &CscMatrix, b: &[f64], n: usize) -> Result<(), SwoopErrors> {
+    if a.nrows() != b.len() || a.ncols() != n {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Constraint matrices must have one column per variable and one row per entry of the \
+             right hand side",
+        )));
+    }
+    if a.values().iter().chain(b).any(|v| !v.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Constraints must be finite",
+        )));
+    }
+    Ok(())
+}
+
+/// Check the bounds, warm start and tolerance of a problem with `n` variables, `n_ub`
+/// inequality and `n_eq` equality constraints, returning the lower and upper bounds
+fn check_options(
+    n: usize,
+    n_ub: usize,
+    n_eq: usize,
+    bounds: Option<&Bounds>,
+    warm_start: Option<&QuadprogResult>,
+    options: QuadprogOptions,
+) -> Result<(Vec<f64>, Vec<f64>), SwoopErrors> {
+    let (lb, ub) = match bounds {
+        Some(bounds) => {
+            if bounds.lb().len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Bounds must have one entry per variable",
+                )));
+            }
+            (bounds.lb().to_vec(), bounds.ub().to_vec())
+        }
+        None => (vec![f64::NEG_INFINITY; n], vec![f64::INFINITY; n]),
+    };
+    if let Some(warm_start) = warm_start {
+        if warm_start.x.len() != n
+            || warm_start.ineqlin_marginals.len() != n_ub
+            || warm_start.eqlin_marginals.len() != n_eq
+            || warm_start.lower_marginals.len() != n
+            || warm_start.upper_marginals.len() != n
+        {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The warm start must come from a problem of the same size",
+            )));
+        }
+    }
+    if options.tol.is_some_and(|tol| tol <= 0f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance must be positive",
+        )));
+    }
+    Ok((lb, ub))
+}
+
+/// Solve a validated quadratic program with the method of the options
+///
+/// # Errors
+/// Propagates errors of the active set method
+async fn solve(
+    qp: &QuadraticProgram<CscMatrix>,
+    warm_start: Option<&QuadprogResult>,
+    options: QuadprogOptions,
+) -> Result<QuadprogResult, SwoopErrors> {
+    let n = qp.q.len();
+    let solution = match options.method {
+        QuadprogMethod::ActiveSet => {
+            let n_bounds = qp.lb.iter().chain(&qp.ub).filter(|v| v.is_finite()).count();
+            active_set::active_set(
+                &qp.to_dense(),
+                warm_start,
+                options.tol.unwrap_or(1e-9),
+                options
+                    .maxiter
+                    .unwrap_or_else(|| usize::max(1000, 10 * (qp.a.nrows() + n_bounds + n))),
+            )
+            .await?
+        }
+        QuadprogMethod::Admm => admm::admm(
+            qp,
+            warm_start,
+            options.tol.unwrap_or(1e-6),
+            options.maxiter.unwrap_or(10000),
+            options.polish,
+        ),
+    };
+
+    let residuals: Vec<f64> =
+        qp.b.iter()
+            .zip(qp.a.mat_vec(&solution.x))
+            .map(|(bi, ax)| bi - ax)
+            .collect();
+    let (slack, con) = residuals.split_at(qp.n_ub);
+    let (ineqlin_marginals, eqlin_marginals) = solution.row_marginals.split_at(qp.n_ub);
+    Ok(QuadprogResult {
+        fun: qp.objective(&solution.x),
+        slack: slack.to_vec(),
+        con: con.to_vec(),
+        ineqlin_marginals: ineqlin_marginals.to_vec(),
+        eqlin_marginals: eqlin_marginals.to_vec(),
+        lower_marginals: solution.lower_marginals,
+        upper_marginals: solution.upper_marginals,
+        status: solution.status,
+        success: solution.status == QuadprogStatus::Optimal,
+        nit: solution.nit,
+        x: solution.x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    const METHODS: [QuadprogMethod; 2] = [QuadprogMethod::ActiveSet, QuadprogMethod::Admm];
+
+    fn options(method: QuadprogMethod) -> QuadprogOptions {
+        QuadprogOptions {
+            method,
+            ..QuadprogOptions::default()
+        }
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                relative_eq!(a, e, epsilon = 1e-6),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    fn identity(n: usize) -> Vec<Vec<f64>> {
+        (0..n)
+            .map(|i| (0..n).map(|j| if i == j { 1f64 } else { 0f64 }).collect())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_quadprog_example() -> Result<(), SwoopErrors> {
+        // example of the documentation of the R package quadprog
+        let a_ub = vec![
+            vec![4f64, 3f64, 0f64],
+            vec![-2f64, -1f64, 0f64],
+            vec![0f64, 2f64, -1f64],
+        ];
+        for method in METHODS {
+            let result = quadprog(
+                &identity(3),
+                &[0f64, -5f64, 0f64],
+                &a_ub,
+                &[8f64, -2f64, 0f64],
+                &[],
+                &[],
+                None,
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -50f64 / 21f64, epsilon = 1e-6));
+            assert_close(&result.x, &[10f64 / 21f64, 22f64 / 21f64, 44f64 / 21f64]);
+            assert_close(
+                &result.ineqlin_marginals,
+                &[0f64, -5f64 / 21f64, -44f64 / 21f64],
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_simplex_projection() -> Result<(), SwoopErrors> {
+        // projection of (0.8, 0.6, -0.5) onto the probability simplex
+        let bounds = Bounds::new(vec![0f64; 3], vec![f64::INFINITY; 3])?;
+        for method in METHODS {
+            let result = quadprog(
+                &identity(3),
+                &[-0.8, -0.6, 0.5],
+                &[],
+                &[],
+                &[vec![1f64; 3]],
+                &[1f64],
+                Some(&bounds),
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert_close(&result.x, &[0.6, 0.4, 0f64]);
+            assert_close(&result.eqlin_marginals, &[-0.2]);
+            assert_close(&result.lower_marginals, &[0f64, 0f64, 0.7]);
+            assert_close(&result.con, &[0f64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_semidefinite() -> Result<(), SwoopErrors> {
+        // no curvature along x1, which is limited by x1 - x0 <= 1
+        let p = vec![vec![1f64, 0f64], vec![0f64, 0f64]];
+        for method in METHODS {
+            let result = quadprog(
+                &p,
+                &[0f64, -1f64],
+                &[vec![-1f64, 1f64]],
+                &[1f64],
+                &[],
+                &[],
+                None,
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert!(relative_eq!(result.fun, -1.5, epsilon = 1e-6));
+            assert_close(&result.x, &[1f64, 2f64]);
+            assert_close(&result.ineqlin_marginals, &[-1f64]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_portfolio_warm_start() -> Result<(), SwoopErrors> {
+        // minimum variance portfolio with a target return, then a higher target
+        let covariance = vec![
+            vec![0.04, 0.006, 0.002, 0f64],
+            vec![0.006, 0.09, 0.01, 0.004],
+            vec![0.002, 0.01, 0.0225, 0.001],
+            vec![0f64, 0.004, 0.001, 0.01],
+        ];
+        let returns = [-0.08, -0.12, -0.06, -0.03];
+        let bounds = Bounds::new(vec![0f64; 4], vec![0.6; 4])?;
+        let solve = |target: f64, method, warm_start: Option<QuadprogResult>| {
+            let covariance = covariance.clone();
+            let bounds = bounds.clone();
+            async move {
+                quadprog(
+                    &covariance,
+                    &[0f64; 4],
+                    &[returns.to_vec()],
+                    &[-target],
+                    &[vec![1f64; 4]],
+                    &[1f64],
+                    Some(&bounds),
+                    warm_start.as_ref(),
+                    options(method),
+                )
+                .await
+            }
+        };
+
+        let active_set = solve(0.07, QuadprogMethod::ActiveSet, None).await?;
+        let admm = solve(0.07, QuadprogMethod::Admm, None).await?;
+        assert!(active_set.success && admm.success);
+        assert_close(&admm.x, &active_set.x);
+        assert!(relative_eq!(active_set.x.iter().sum::<f64>(), 1f64));
+        assert!(active_set.slack[0] >= -1e-9);
+
+        // restarting from the solution needs no iterations
+        let restart = solve(0.07, QuadprogMethod::ActiveSet, Some(active_set.clone())).await?;
+        assert!(restart.success);
+        assert_eq!(restart.nit, 0);
+        assert_close(&restart.x, &active_set.x);
+
+        for method in METHODS {
+            let cold = solve(0.09, method, None).await?;
+            let warm = solve(0.09, method, Some(active_set.clone())).await?;
+            assert!(cold.success && warm.success);
+            assert_close(&warm.x, &cold.x);
+            assert!(warm.nit <= cold.nit);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_infeasible_and_unbounded() -> Result<(), SwoopErrors> {
+        let bounds = Bounds::new(vec![0f64], vec![f64::INFINITY])?;
+        let p = vec![vec![1f64, 0f64], vec![0f64, 0f64]];
+        for method in METHODS {
+            let result = quadprog(
+                &identity(1),
+                &[0f64],
+                &[vec![1f64]],
+                &[-1f64],
+                &[],
+                &[],
+                Some(&bounds),
+                None,
+                options(method),
+            )
+            .await?;
+            assert_eq!(result.status, QuadprogStatus::Infeasible);
+            assert!(!result.success);
+
+            let result = quadprog(
+                &p,
+                &[0f64, -1f64],
+                &[],
+                &[],
+                &[],
+                &[],
+                None,
+                None,
+                options(method),
+            )
+            .await?;
+            assert_eq!(result.status, QuadprogStatus::Unbounded);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sparse() -> Result<(), SwoopErrors> {
+        // the example of the R package quadprog, with P given as its upper triangle
+        let p = CscMatrix::from_triplets(3, 3, &[(0, 0, 1f64), (1, 1, 1f64), (2, 2, 1f64)])?;
+        let a_ub = CscMatrix::from_triplets(
+            3,
+            3,
+            &[
+                (0, 0, 4f64),
+                (0, 1, 3f64),
+                (1, 0, -2f64),
+                (1, 1, -1f64),
+                (2, 1, 2f64),
+                (2, 2, -1f64),
+            ],
+        )?;
+        for method in METHODS {
+            let result = quadprog_sparse(
+                &p,
+                &[0f64, -5f64, 0f64],
+                &a_ub,
+                &[8f64, -2f64, 0f64],
+                &CscMatrix::zeros(0, 3),
+                &[],
+                None,
+                None,
+                options(method),
+            )
+            .await?;
+            assert!(result.success);
+            assert_close(&result.x, &[10f64 / 21f64, 22f64 / 21f64, 44f64 / 21f64]);
+            assert_close(
+                &result.ineqlin_marginals,
+                &[0f64, -5f64 / 21f64, -44f64 / 21f64],
+            );
+        }
+
+        let options = QuadprogOptions::default();
+        let q = [1f64, 1f64];
+        let upper = CscMatrix::from_triplets(2, 2, &[(0, 0, 1f64), (0, 1, 4f64), (1, 1, 1f64)])?;
+        assert!(quadprog_sparse(
+            &upper,
+            &q,
+            &CscMatrix::zeros(0, 2),
+            &[],
+            &CscMatrix::zeros(0, 2),
+            &[],
+            None,
+            None,
+            options
+        )
+        .await
+        .is_err());
+        assert!(quadprog_sparse(
+            &CscMatrix::zeros(2, 2),
+            &q,
+            &CscMatrix::zeros(1, 2),
+            &[],
+            &CscMatrix::zeros(0, 2),
+            &[],
+            None,
+            None,
+            options
+        )
+        .await
+        .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sparse_chain() -> Result<(), SwoopErrors> {
+        // smoothing of a signal on the probability simplex, with a tridiagonal P and a dense
+        // equality constraint, whose dense KKT system would have 36 million entries
+        let q: Vec<f64> = (0..3000u32)
+            .map(|i| -(f64::from(i) / 100f64).sin() / 100f64)
+            .collect();
+        let n = q.len();
+        let mut triplets = Vec::new();
+        for i in 0..n {
+            triplets.push((i, i, 2.1));
+            if i + 1 < n {
+                triplets.push((i, i + 1, -1f64));
+                triplets.push((i + 1, i, -1f64));
+            }
+        }
+        let p = CscMatrix::from_triplets(n, n, &triplets)?;
+        let a_eq =
+            CscMatrix::from_triplets(1, n, &(0..n).map(|j| (0, j, 1f64)).collect::<Vec<_>>())?;
+        let bounds = Bounds::new(vec![0f64; n], vec![f64::INFINITY; n])?;
+        let result = quadprog_sparse(
+            &p,
+            &q,
+            &CscMatrix::zeros(0, n),
+            &[],
+            &a_eq,
+            &[1f64],
+            Some(&bounds),
+            None,
+            options(QuadprogMethod::Admm),
+        )
+        .await?;
+        assert!(result.success);
+        assert!(relative_eq!(
+            result.x.iter().sum::<f64>(),
+            1f64,
+            epsilon = 1e-6
+        ));
+        assert!(result.x.iter().all(|xi| *xi >= -1e-6));
+        assert!(result.x.iter().any(|xi| *xi < 1e-9));
+        // stationarity P x + q = A_eq^T eqlin_marginals + lower_marginals
+        let gradient: Vec<f64> = p
+            .mat_vec(&result.x)
+            .iter()
+            .zip(&q)
+            .map(|(px, qi)| px + qi)
+            .collect();
+        for (j, g) in gradient.iter().enumerate() {
+            let multipliers = result.eqlin_marginals[0] + result.lower_marginals[j];
+            assert!(relative_eq!(g, &multipliers, epsilon = 1e-6));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() -> Result<(), SwoopErrors> {
+        let options = QuadprogOptions::default();
+        let p = identity(2);
+        let q = [1f64, 1f64];
+        assert!(quadprog(&[], &[], &[], &[], &[], &[], None, None, options)
+            .await
+            .is_err());
+        assert!(
+            quadprog(&identity(1), &q, &[], &[], &[], &[], None, None, options)
+                .await
+                .is_err()
+        );
+        let indefinite = vec![vec![1f64, 0f64], vec![0f64, -1f64]];
+        assert!(
+            quadprog(&indefinite, &q, &[], &[], &[], &[], None, None, options)
+                .await
+                .is_err()
+        );
+        assert!(quadprog(
+            &p,
+            &q,
+            &[vec![1f64]],
+            &[1f64],
+            &[],
+            &[],
+            None,
+            None,
+            options
+        )
+        .await
+        .is_err());
+        let result = quadprog(&p, &q, &[], &[], &[], &[], None, None, options).await?;
+        assert!(quadprog(
+            &identity(3),
+            &[1f64; 3],
+            &[],
+            &[],
+            &[],
+            &[],
+            None,
+            Some(&result),
+            options
+        )
+        .await
+        .is_err());
+        let options = QuadprogOptions {
+            tol: Some(0f64),
+            ..QuadprogOptions::default()
+        };
+        assert!(quadprog(&p, &q, &[], &[], &[], &[], None, None, options)
+            .await
+            .is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//! Sparse matrices in compressed sparse column format
+//!
+//! The non-zero entries of every column are stored with their rows in increasing order, such
+//! that the row indices and values of column `j` are `row_idx[col_ptr[j]..col_ptr[j + 1]]` and
+//! `values[col_ptr[j]..col_ptr[j + 1]]`.
+
+use crate::SwoopErrors;
+
+/// Sparse matrix in compressed sparse column (CSC) format
+#[derive(Debug, Clone, PartialEq)]
+pub struct CscMatrix {
+    nrows: usize,
+    ncols: usize,
+    col_ptr: Vec<usize>,
+    row_idx: Vec<usize>,
+    values: Vec<f64>,
+}
+
+impl CscMatrix {
+    /// Create a matrix from its compressed columns
+    ///
+    /// # Parameters
+    /// * `nrows` - number of rows
+    /// * `ncols` - number of columns
+    /// * `col_ptr` - start of every column in `row_idx` and `values`, followed by the number of
+    ///   entries
+    /// * `row_idx` - row of every entry, strictly increasing within each column
+    /// * `values` - value of every entry
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if `col_ptr` does not have `ncols + 1`
+    /// non-decreasing entries from `0` to the number of entries, or the rows of a column are out
+    /// of range or not strictly increasing
+    pub fn new(
+        nrows: usize,
+        ncols: usize,
+        col_ptr: Vec<usize>,
+        row_idx: Vec<usize>,
+        values: Vec<f64>,
+    ) -> Result<Self, SwoopErrors> {
+        if col_ptr.len() != ncols + 1
+            || col_ptr[0] != 0
+            || col_ptr[ncols] != row_idx.len()
+            || row_idx.len() != values.len()
+            || col_ptr.windows(2).any(|w| w[0] > w[1])
+        {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The column pointers must run from zero to the number of entries, one per column",
+            )));
+        }
+        for column in col_ptr.windows(2) {
+            let rows = &row_idx[column[0]..column[1]];
+            if rows.iter().any(|&i| i >= nrows) || rows.windows(2).any(|w| w[0] >= w[1]) {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "The rows of each column must be in range and strictly increasing",
+                )));
+            }
+        }
+        Ok(Self {
+            nrows,
+            ncols,
+            col_ptr,
+            row_idx,
+            values,
+        })
+    }
+
+    /// Create a matrix from `(row, column, value)` triplets, summing the values of repeated
+    /// entries
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if a row or column is out of range
+    pub fn from_triplets(
+        nrows: usize,
+        ncols: usize,
+        triplets: &[(usize, usize, f64)],
+    ) -> Result<Self, SwoopErrors> {
+        if triplets.iter().any(|&(i, j, _)| i >= nrows || j >= ncols) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "The rows and columns of the triplets must be in range",
+            )));
+        }
+        let mut sorted = triplets.to_vec();
+        sorted.sort_by_key(|&(i, j, _)| (j, i));
+        let mut col_ptr = vec![0usize; ncols + 1];
+        let mut row_idx: Vec<usize> = Vec::with_capacity(sorted.len());
+        let mut values: Vec<f64> = Vec::with_capacity(sorted.len());
+        let mut last = None;
+        for (i, j, v) in sorted {
+            if last == Some((i, j)) {
+                if let Some(value) = values.last_mut() {
+                    *value += v;
+                }
+                continue;
+            }
+            last = Some((i, j));
+            col_ptr[j + 1] += 1;
+            row_idx.push(i);
+            values.push(v);
+        }
+        for j in 0..ncols {
+            col_ptr[j + 1] += col_ptr[j];
+        }
+        Ok(Self {
+            nrows,
+            ncols,
+            col_ptr,
+            row_idx,
+            values,
+        })
+    }
+
+    /// Create a matrix from the non-zero entries of dense rows
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if a row does not have `ncols` entries
+    pub fn from_dense(rows: &[Vec<f64>], ncols: usize) -> Result<Self, SwoopErrors> {
+        if rows.iter().any(|row| row.len() != ncols) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "Every row must have one entry per column",
+            )));
+        }
+        let triplets: Vec<(usize, usize, f64)> = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(i, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, v)| **v != 0f64)
+                    .map(move |(j, v)| (i, j, *v))
+            })
+            .collect();
+        Self::from_triplets(rows.len(), ncols, &triplets)
+    }
+
+    /// Matrix without entries
+    #[must_use]
+    pub fn zeros(nrows: usize, ncols: usize) -> Self {
+        Self {
+            nrows,
+            ncols,
+            col_ptr: vec![0; ncols + 1],
+            row_idx: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Number of rows
+    #[must_use]
+    pub fn nrows(&self) -> usize {
+        self.nrows
+    }
+
+    /// Number of columns
+    #[must_use]
+    pub fn ncols(&self) -> usize {
+        self.ncols
+    }
+
+    /// Number of stored entries
+    #[must_use]
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Stored entries as `(row, column, value)`, column by column
+    pub(super) fn triplets(&self) -> impl Iterator<Item = (usize, usize, f64)> + '_ {
+        self.col_ptr.windows(2).enumerate().flat_map(move |(j, w)| {
+            self.row_idx[w[0]..w[1]]
+                .iter()
+                .zip(&self.values[w[0]..w[1]])
+                .map(move |(&i, &v)| (i, j, v))
+        })
+    }
+
+    /// Row indices and values of column `j`
+    pub(super) fn column(&self, j: usize) -> (&[usize], &[f64]) {
+        let range = self.col_ptr[j]..self.col_ptr[j + 1];
+        (&self.row_idx[range.clone()], &self.values[range])
+    }
+
+    /// Stored values
+    pub(super) fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Product `A x`
+    pub(super) fn mat_vec(&self, x: &[f64]) -> Vec<f64> {
+        let mut y = vec![0f64; self.nrows];
+        for (i, j, v) in self.triplets() {
+            y[i] += v * x[j];
+        }
+        y
+    }
+
+    /// Product `A^T y`
+    pub(super) fn mat_t_vec(&self, y: &[f64]) -> Vec<f64> {
+        let mut x = vec![0f64; self.ncols];
+        for (i, j, v) in self.triplets() {
+            x[j] += v * y[i];
+        }
+        x
+    }
+
+    /// Dense rows of the matrix
+    pub(super) fn to_dense(&self) -> Vec<Vec<f64>> {
+        let mut rows = vec![vec![0f64; self.ncols]; self.nrows];
+        for (i, j, v) in self.triplets() {
+            rows[i][j] += v;
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csc_matrix() -> Result<(), SwoopErrors> {
+        let dense = vec![vec![1f64, 0f64, 2f64], vec![0f64, 0f64, 3f64]];
+        let a = CscMatrix::from_dense(&dense, 3)?;
+        assert_eq!(
+            a,
+            CscMatrix::new(
+                2,
+                3,
+                vec![0, 1, 1, 3],
+                vec![0, 0, 1],
+                vec![1f64, 2f64, 3f64]
+            )?
+        );
+        // repeated triplets are summed
+        let triplets = [(1, 2, 1f64), (0, 0, 1f64), (0, 2, 2f64), (1, 2, 2f64)];
+        assert_eq!(CscMatrix::from_triplets(2, 3, &triplets)?, a);
+        assert_eq!(a.nnz(), 3);
+        assert_eq!(a.to_dense(), dense);
+        assert_eq!(a.mat_vec(&[1f64, 1f64, 1f64]), vec![3f64, 3f64]);
+        assert_eq!(a.mat_t_vec(&[1f64, 1f64]), vec![1f64, 0f64, 5f64]);
+        assert_eq!(CscMatrix::zeros(0, 3).mat_t_vec(&[]), vec![0f64; 3]);
+
+        assert!(CscMatrix::new(2, 3, vec![0, 1, 3], vec![0, 0, 1], vec![1f64; 3]).is_err());
+        assert!(CscMatrix::new(2, 3, vec![0, 2, 1, 3], vec![0, 0, 1], vec![1f64; 3]).is_err());
+        assert!(CscMatrix::new(2, 3, vec![0, 1, 1, 3], vec![0, 1, 1], vec![1f64; 3]).is_err());
+        assert!(CscMatrix::new(2, 3, vec![0, 1, 1, 3], vec![0, 0, 2], vec![1f64; 3]).is_err());
+        assert!(CscMatrix::from_triplets(2, 3, &[(2, 0, 1f64)]).is_err());
+        assert!(CscMatrix::from_dense(&[vec![1f64]], 3).is_err());
+        Ok(())
+    }
+}
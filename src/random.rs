@@ -0,0 +1,138 @@
+//! Seeded pseudo random number generator for the stochastic solvers
+//!
+//! Implements xoshiro256** seeded through splitmix64, such that a seed gives the same
+//! sequence on every platform.
+//!
+//! # References
+//! * Blackman and Vigna, "Scrambled linear pseudorandom number generators", ACM Transactions on
+//!   Mathematical Software 47.4 (2021)
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Pseudo random number generator
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: [u64; 4],
+}
+
+/// Next output of the splitmix64 generator
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl Rng {
+    /// Create a generator from `seed`, or from the entropy of the process if `None`
+    pub(crate) fn new(seed: Option<u64>) -> Self {
+        let mut s = seed.unwrap_or_else(|| {
+            let mut hasher = RandomState::new().build_hasher();
+            hasher.write_u64(0);
+            hasher.finish()
+        });
+        Self {
+            state: [
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+                splitmix64(&mut s),
+            ],
+        }
+    }
+
+    /// Uniformly distributed 64 bit integer
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        let result = self.state[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = self.state[1] << 17;
+        self.state[2] ^= self.state[0];
+        self.state[3] ^= self.state[1];
+        self.state[1] ^= self.state[2];
+        self.state[0] ^= self.state[3];
+        self.state[2] ^= t;
+        self.state[3] = self.state[3].rotate_left(45);
+        result
+    }
+
+    /// Uniformly distributed number in `[0, 1)`
+    #[allow(clippy::cast_precision_loss)]
+    pub(crate) fn uniform(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed number in `[low, high)`
+    pub(crate) fn uniform_in(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.uniform()
+    }
+
+    /// Uniformly distributed integer in `0..n`, `n` must be positive
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        // reject the values that would bias the remainder
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return (value % n) as usize;
+            }
+        }
+    }
+
+    /// Standard normally distributed number, using the Box-Muller transform
+    pub(crate) fn normal(&mut self) -> f64 {
+        let u = 1f64 - self.uniform();
+        let v = self.uniform();
+        (-2f64 * u.ln()).sqrt() * (2f64 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Shuffle `values` in place with the Fisher-Yates algorithm
+    pub(crate) fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.below(i + 1);
+            values.swap(i, j);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_rng() {
+        let mut a = Rng::new(Some(42));
+        let mut b = Rng::new(Some(42));
+        let mut c = Rng::new(Some(43));
+        let first: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+        let third: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(first, third);
+
+        let samples: Vec<f64> = (0..10000).map(|_| a.uniform()).collect();
+        assert!(samples.iter().all(|u| (0f64..1f64).contains(u)));
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        assert!((mean - 0.5).abs() < 0.02);
+
+        let normals: Vec<f64> = (0..10000).map(|_| a.normal()).collect();
+        let mean = normals.iter().sum::<f64>() / normals.len() as f64;
+        let variance = normals.iter().map(|z| (z - mean).powi(2)).sum::<f64>() / 9999f64;
+        assert!(mean.abs() < 0.05);
+        assert!((variance - 1f64).abs() < 0.05);
+
+        let mut counts = [0usize; 3];
+        for _ in 0..3000 {
+            counts[a.below(3)] += 1;
+        }
+        assert!(counts.iter().all(|c| (900..1100).contains(c)));
+
+        let mut values: Vec<usize> = (0..10).collect();
+        a.shuffle(&mut values);
+        values.sort_unstable();
+        assert_eq!(values, (0..10).collect::<Vec<usize>>());
+    }
+}
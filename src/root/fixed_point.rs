@@ -0,0 +1,265 @@
+//! Fixed points of functions, `x = g(x)`
+//!
+//! The plain iteration `x <- g(x)` converges linearly when `g` is a contraction. Steffensen's
+//! method applies Aitken's delta squared extrapolation to every two steps of the iteration,
+//! `x <- x - (g(x) - x)^2 / (g(g(x)) - 2 g(x) + x)` element-wise, which converges quadratically
+//! near a fixed point where the derivative of `g` is not one.
+//!
+//! # References
+//! * Burden and Faires, "Numerical Analysis", 9th edition, section 2.5 (2011)
+
+use crate::least_squares::ResidualFunction;
+use crate::minimise_scalar::{ScalarObjectiveFunction, ScalarOptimisationResult};
+use crate::SwoopErrors;
+
+/// Algorithm used by `fixed_point`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FixedPointMethod {
+    /// Steffensen's method, the iteration accelerated by Aitken's delta squared process
+    #[default]
+    Del2,
+    /// The plain iteration `x <- g(x)`
+    Iteration,
+}
+
+/// Options for the `fixed_point` solvers
+#[derive(Debug, Clone, Copy)]
+pub struct FixedPointOptions {
+    /// Algorithm to use
+    pub method: FixedPointMethod,
+    /// Tolerance for termination by the relative change of each variable, or the absolute change
+    /// of variables that are zero
+    pub xtol: f64,
+    /// Maximum iterations, each of which takes two evaluations of the function for `Del2` and
+    /// one for `Iteration`
+    pub maxiter: usize,
+}
+
+impl Default for FixedPointOptions {
+    fn default() -> Self {
+        Self {
+            method: FixedPointMethod::default(),
+            xtol: 1e-8,
+            maxiter: 500,
+        }
+    }
+}
+
+/// Struct to represent the result of a `fixed_point_vector` solve
+#[derive(Debug, Clone)]
+pub struct FixedPointResult {
+    /// Value of the function at the solution
+    pub fun: Vec<f64>,
+    /// Number of evaluations of the function
+    pub nfev: usize,
+    /// Number of iterations
+    pub nit: usize,
+    /// Whether the optimisation was successful or not
+    pub success: bool,
+    /// The fixed point
+    pub x: Vec<f64>,
+}
+
+/// Scalar function as a function of a vector with one element
+struct Scalar<T> {
+    function: T,
+}
+
+impl<T: ScalarObjectiveFunction> ResidualFunction for Scalar<T> {
+    fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+        vec![self.function.evaluate(x[0])]
+    }
+}
+
+/// Find a fixed point of a scalar function
+///
+/// # Parameters
+/// * `function` - struct that implements the trait `ScalarObjectiveFunction` for `g`
+/// * `x0` - initial guess
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `xtol` is negative, and
+/// `SwoopErrors::MaxIterExceeded` if the tolerance is not met within `maxiter` iterations
+pub async fn fixed_point<T: ScalarObjectiveFunction>(
+    function: T,
+    x0: f64,
+    options: FixedPointOptions,
+) -> Result<ScalarOptimisationResult, SwoopErrors> {
+    let result = fixed_point_vector(Scalar { function }, &[x0], options).await?;
+    Ok(ScalarOptimisationResult {
+        fun: result.fun[0],
+        nfev: result.nfev,
+        success: result.success,
+        x: result.x[0],
+    })
+}
+
+/// Find a fixed point of a vector valued function
+///
+/// The variables are accelerated independently by `Del2`, and the iteration stops when every
+/// variable satisfies `xtol`.
+///
+/// # Parameters
+/// * `function` - struct that implements the trait `ResidualFunction` for `g`, with as many
+///   values as variables
+/// * `x0` - initial guess
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, `xtol` is negative or `g` does not
+/// return one value per variable, and `SwoopErrors::MaxIterExceeded` if the tolerance is not met
+/// within `maxiter` iterations
+pub async fn fixed_point_vector<T: ResidualFunction>(
+    function: T,
+    x0: &[f64],
+    options: FixedPointOptions,
+) -> Result<FixedPointResult, SwoopErrors> {
+    if x0.is_empty() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The initial guess cannot be empty",
+        )));
+    }
+    if options.xtol.is_nan() || options.xtol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    let mut nfev = 0;
+    let mut evaluate = |x: &[f64]| -> Result<Vec<f64>, SwoopErrors> {
+        nfev += 1;
+        let g = function.evaluate(x);
+        if g.len() == x.len() {
+            Ok(g)
+        } else {
+            Err(SwoopErrors::ArgumentError(String::from(
+                "The function must return one value per variable",
+            )))
+        }
+    };
+
+    let mut x0 = x0.to_vec();
+    for nit in 1..=options.maxiter {
+        let g1 = evaluate(&x0)?;
+        let x = match options.method {
+            FixedPointMethod::Iteration => g1,
+            FixedPointMethod::Del2 => {
+                let g2 = evaluate(&g1)?;
+                x0.iter()
+                    .zip(&g1)
+                    .zip(&g2)
+                    .map(|((p0, p1), p2)| {
+                        let d = p2 - 2f64 * p1 + p0;
+                        if d == 0f64 {
+                            *p2
+                        } else {
+                            p0 - (p1 - p0).powi(2) / d
+                        }
+                    })
+                    .collect()
+            }
+        };
+        let converged = x.iter().zip(&x0).all(|(p, p0)| {
+            let error = if *p0 == 0f64 { *p } else { (p - p0) / p0 };
+            error.abs() < options.xtol
+        });
+        if converged {
+            let fun = evaluate(&x)?;
+            return Ok(FixedPointResult {
+                fun,
+                nfev,
+                nit,
+                success: true,
+                x,
+            });
+        }
+        x0 = x;
+    }
+    Err(SwoopErrors::MaxIterExceeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// `x = cos(x)`, with the fixed point at the Dottie number
+    struct Cosine {}
+
+    impl ScalarObjectiveFunction for Cosine {
+        fn evaluate(&self, x: f64) -> f64 {
+            x.cos()
+        }
+    }
+
+    /// `x = sqrt(c / (x + a))` element-wise, the example of `scipy.optimize.fixed_point`
+    struct Coupled {}
+
+    impl ResidualFunction for Coupled {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            [(10f64, 3f64), (12f64, 5f64)]
+                .iter()
+                .zip(x)
+                .map(|((c, a), xi)| (c / (xi + a)).sqrt())
+                .collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scalar() -> Result<(), SwoopErrors> {
+        let dottie = 0.739_085_133_215_160_6;
+        let del2 = fixed_point(Cosine {}, 1f64, FixedPointOptions::default()).await?;
+        assert!(del2.success);
+        assert!(relative_eq!(del2.x, dottie, epsilon = 1e-12));
+        assert!(relative_eq!(del2.fun, dottie, epsilon = 1e-12));
+
+        let iteration = fixed_point(
+            Cosine {},
+            1f64,
+            FixedPointOptions {
+                method: FixedPointMethod::Iteration,
+                ..FixedPointOptions::default()
+            },
+        )
+        .await?;
+        assert!(relative_eq!(iteration.x, dottie, epsilon = 1e-7));
+        // the acceleration saves most of the evaluations
+        assert!(5 * del2.nfev < iteration.nfev);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vector() -> Result<(), SwoopErrors> {
+        let result =
+            fixed_point_vector(Coupled {}, &[1.2, 1.3], FixedPointOptions::default()).await?;
+        assert!(result.success);
+        assert!(relative_eq!(result.x[0], 1.492_033_301_2, epsilon = 1e-8));
+        assert!(relative_eq!(result.x[1], 1.372_281_323_3, epsilon = 1e-8));
+        for (x, g) in result.x.iter().zip(&result.fun) {
+            assert!(relative_eq!(x, g, epsilon = 1e-10));
+        }
+        assert_eq!(result.nfev, 2 * result.nit + 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_errors() {
+        let options = FixedPointOptions {
+            method: FixedPointMethod::Iteration,
+            maxiter: 5,
+            ..FixedPointOptions::default()
+        };
+        let result = fixed_point(Cosine {}, 1f64, options).await;
+        assert!(matches!(result, Err(SwoopErrors::MaxIterExceeded)));
+
+        let options = FixedPointOptions {
+            xtol: -1f64,
+            ..FixedPointOptions::default()
+        };
+        let result = fixed_point(Cosine {}, 1f64, options).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = fixed_point_vector(Coupled {}, &[1f64; 3], FixedPointOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,286 @@
+//! Powell's hybrid method, a port of `hybrd` and `hybrj` from MINPACK
+//!
+//! Each iteration takes a dogleg step between the Gauss-Newton and the scaled steepest descent
+//! directions within a trust region. The QR factorisation of the Jacobian is updated with a
+//! Broyden rank one update after every step, and the Jacobian is only evaluated again when the
+//! updates stop making progress.
+//!
+//! # References
+//! * Powell, "A hybrid method for nonlinear equations", Numerical Methods for Nonlinear
+//!   Algebraic Equations (1970)
+//! * Moré, Garbow and Hillstrom, "User guide for MINPACK-1", Argonne National Laboratory
+//!   ANL-80-74 (1980)
+
+use super::{ResidualFunction, RootOptions, RootStatus, System};
+use crate::linalg::{mat_t_vec, norm, qr};
+use crate::SwoopErrors;
+
+/// Solution found by `hybr`
+pub(super) struct Solution {
+    pub(super) x: Vec<f64>,
+    pub(super) fun: Vec<f64>,
+    pub(super) q: Vec<Vec<f64>>,
+    pub(super) r: Vec<Vec<f64>>,
+    pub(super) qtf: Vec<f64>,
+    pub(super) status: RootStatus,
+}
+
+/// Element-wise product of two vectors
+fn scaled(diag: &[f64], x: &[f64]) -> Vec<f64> {
+    diag.iter().zip(x).map(|(d, xi)| d * xi).collect()
+}
+
+/// Convex combination of the Gauss-Newton and scaled gradient directions that minimises
+/// `||R x - qtb||` within `||D x|| <= delta`, port of MINPACK `dogleg`
+#[allow(clippy::needless_range_loop)]
+fn dogleg(r: &[Vec<f64>], diag: &[f64], qtb: &[f64], delta: f64) -> Vec<f64> {
+    let n = qtb.len();
+    let eps = f64::EPSILON;
+
+    // Gauss-Newton direction, with zero diagonal elements replaced by small values
+    let mut x = vec![0f64; n];
+    for j in (0..n).rev() {
+        let sum: f64 = (j + 1..n).map(|i| r[j][i] * x[i]).sum();
+        let mut temp = r[j][j];
+        if temp == 0f64 {
+            temp = eps * (0..=j).fold(0f64, |acc, i| acc.max(r[i][j].abs()));
+            if temp == 0f64 {
+                temp = eps;
+            }
+        }
+        x[j] = (qtb[j] - sum) / temp;
+    }
+    let qnorm = norm(&scaled(diag, &x));
+    if qnorm <= delta {
+        return x;
+    }
+
+    // scaled gradient direction
+    let mut wa1: Vec<f64> = (0..n)
+        .map(|j| (0..=j).map(|i| r[i][j] * qtb[i]).sum::<f64>() / diag[j])
+        .collect();
+    let gnorm = norm(&wa1);
+    let mut sgnorm = 0f64;
+    let mut alpha = delta / qnorm;
+    if gnorm != 0f64 {
+        // point along the scaled gradient at which the quadratic is minimised
+        for (w, d) in wa1.iter_mut().zip(diag) {
+            *w = (*w / gnorm) / d;
+        }
+        let wa2: Vec<f64> = (0..n)
+            .map(|j| (j..n).map(|i| r[j][i] * wa1[i]).sum())
+            .collect();
+        let temp = norm(&wa2);
+        sgnorm = (gnorm / temp) / temp;
+        alpha = 0f64;
+        if sgnorm < delta {
+            // point along the dogleg at which the quadratic is minimised
+            let bnorm = norm(qtb);
+            let ratio = delta / qnorm;
+            let sratio = sgnorm / delta;
+            let temp = (bnorm / gnorm) * (bnorm / qnorm) * sratio;
+            let temp = temp - ratio * sratio.powi(2)
+                + ((temp - ratio).powi(2) + (1f64 - ratio.powi(2)) * (1f64 - sratio.powi(2)))
+                    .sqrt();
+            alpha = (ratio * (1f64 - sratio.powi(2))) / temp;
+        }
+    }
+    let temp = (1f64 - alpha) * sgnorm.min(delta);
+    wa1.iter()
+        .zip(&x)
+        .map(|(g, xi)| temp * g + alpha * xi)
+        .collect()
+}
+
+/// Solve the system with Powell's hybrid method
+///
+/// # Parameters
+/// * `system` - system of equations
+/// * `x0` - initial guess
+/// * `f0` - function values at `x0`
+/// * `options` - solver options
+/// * `maxfev` - maximum evaluations of the function
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if the number of function values changes between
+/// the steps of the finite differences
+#[allow(clippy::too_many_lines)]
+pub(super) fn hybr<T: ResidualFunction>(
+    system: &mut System<'_, T>,
+    x0: &[f64],
+    f0: Vec<f64>,
+    options: &RootOptions,
+    maxfev: usize,
+) -> Result<Solution, SwoopErrors> {
+    let n = x0.len();
+    let eps = f64::EPSILON;
+    let mut x = x0.to_vec();
+    let mut f = f0;
+    let mut fnorm = norm(&f);
+
+    let scale_with_jac = options.diag.is_none();
+    let mut diag = options.diag.clone().unwrap_or_else(|| vec![1f64; n]);
+    let mut delta = 0f64;
+    let mut xnorm = 0f64;
+    let mut iter = 1;
+    let (mut ncsuc, mut ncfail) = (0, 0);
+    let (mut nslow1, mut nslow2) = (0, 0);
+
+    loop {
+        let jac = system.jacobian(&x, &f)?;
+        let acnorm: Vec<f64> = (0..n)
+            .map(|j| jac.iter().map(|row| row[j].powi(2)).sum::<f64>().sqrt())
+            .collect();
+        let (mut q, mut r) = qr(&jac, n);
+
+        if iter == 1 {
+            if scale_with_jac {
+                diag = acnorm
+                    .iter()
+                    .map(|a| if *a == 0f64 { 1f64 } else { *a })
+                    .collect();
+            }
+            xnorm = norm(&scaled(&diag, &x));
+            delta = options.factor * xnorm;
+            if delta == 0f64 {
+                delta = options.factor;
+            }
+        }
+        let mut qtf = mat_t_vec(&q, &f, n);
+        if scale_with_jac {
+            for (d, a) in diag.iter_mut().zip(&acnorm) {
+                *d = d.max(*a);
+            }
+        }
+
+        let mut jeval = true;
+        loop {
+            let step: Vec<f64> = dogleg(&r, &diag, &qtf, delta).iter().map(|p| -p).collect();
+            let x_trial: Vec<f64> = x.iter().zip(&step).map(|(xi, pi)| xi + pi).collect();
+            let pnorm = norm(&scaled(&diag, &step));
+            if iter == 1 {
+                delta = delta.min(pnorm);
+            }
+
+            let f_trial = system.evaluate(&x_trial);
+            let fnorm_trial = norm(&f_trial);
+
+            // actual and predicted reductions, the prediction is Q^T f + R p in the basis of Q
+            let actred = if fnorm_trial < fnorm {
+                1f64 - (fnorm_trial / fnorm).powi(2)
+            } else {
+                -1f64
+            };
+            let predicted: Vec<f64> = (0..n)
+                .map(|i| qtf[i] + (i..n).map(|j| r[i][j] * step[j]).sum::<f64>())
+                .collect();
+            let temp = norm(&predicted);
+            let prered = if temp < fnorm {
+                1f64 - (temp / fnorm).powi(2)
+            } else {
+                0f64
+            };
+            let ratio = if prered > 0f64 { actred / prered } else { 0f64 };
+
+            // update the step bound
+            if ratio < 0.1 {
+                ncsuc = 0;
+                ncfail += 1;
+                delta *= 0.5;
+            } else {
+                ncfail = 0;
+                ncsuc += 1;
+                if ratio >= 0.5 || ncsuc > 1 {
+                    delta = delta.max(pnorm / 0.5);
+                }
+                if (ratio - 1f64).abs() <= 0.1 {
+                    delta = pnorm / 0.5;
+                }
+            }
+
+            let successful = ratio >= 1e-4;
+            if successful {
+                x = x_trial;
+                f.clone_from(&f_trial);
+                xnorm = norm(&scaled(&diag, &x));
+                fnorm = fnorm_trial;
+                iter += 1;
+            }
+
+            // progress of the iteration
+            nslow1 += 1;
+            if actred >= 0.001 {
+                nslow1 = 0;
+            }
+            if jeval {
+                nslow2 += 1;
+            }
+            if actred >= 0.1 {
+                nslow2 = 0;
+            }
+
+            let status = if delta <= options.xtol * xnorm || fnorm == 0f64 {
+                Some(RootStatus::StepTolerance)
+            } else if system.nfev >= maxfev {
+                Some(RootStatus::MaxFevExceeded)
+            } else if 0.1 * (0.1 * delta).max(pnorm) <= eps * xnorm {
+                Some(RootStatus::XtolTooSmall)
+            } else if nslow2 == 5 {
+                Some(RootStatus::NoProgressJacobian)
+            } else if nslow1 == 10 {
+                Some(RootStatus::NoProgressIterations)
+            } else {
+                None
+            };
+            if let Some(status) = status {
+                return Ok(Solution {
+                    qtf: mat_t_vec(&q, &f, n),
+                    x,
+                    fun: f,
+                    q,
+                    r,
+                    status,
+                });
+            }
+
+            // evaluate the Jacobian again after two failed steps
+            if ncfail == 2 {
+                break;
+            }
+            if !fnorm_trial.is_finite() {
+                jeval = false;
+                continue;
+            }
+
+            // Broyden rank one update Q R + (f_trial - f - J p) (D^2 p)^T / ||D p||^2, with the
+            // new factorisation of R + u v^T giving Q R = (Q Q') R'
+            let qtf_trial = mat_t_vec(&q, &f_trial, n);
+            let u: Vec<f64> = qtf_trial
+                .iter()
+                .zip(&predicted)
+                .map(|(a, b)| (a - b) / pnorm)
+                .collect();
+            let v: Vec<f64> = (0..n)
+                .map(|j| diag[j] * ((diag[j] * step[j]) / pnorm))
+                .collect();
+            if successful {
+                qtf = qtf_trial;
+            }
+            let updated: Vec<Vec<f64>> = (0..n)
+                .map(|i| (0..n).map(|j| r[i][j] + u[i] * v[j]).collect())
+                .collect();
+            let (q_update, r_update) = qr(&updated, n);
+            q = q
+                .iter()
+                .map(|row| {
+                    (0..n)
+                        .map(|j| (0..n).map(|k| row[k] * q_update[k][j]).sum())
+                        .collect()
+                })
+                .collect();
+            qtf = mat_t_vec(&q_update, &qtf, n);
+            r = r_update;
+            jeval = false;
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Krylov subspace solvers for `A x = b` that only use products with `A`
+//!
+//! The solvers start from `x = 0` and stop when the residual is at most `rtol * ||b||`, or after
+//! a fixed amount of work if the tolerance is not reached, as suits the inexact Newton steps of
+//! `nonlin_solve`.
+//!
+//! # References
+//! * Saad and Schultz, "GMRES: A generalized minimal residual algorithm for solving nonsymmetric
+//!   linear systems", SIAM Journal on Scientific and Statistical Computing 7.3 (1986)
+//! * van der Vorst, "Bi-CGSTAB: A fast and smoothly converging variant of Bi-CG for the solution
+//!   of nonsymmetric linear systems", SIAM Journal on Scientific and Statistical Computing 13.2
+//!   (1992)
+//! * Baker, Jessup and Manteuffel, "A technique for accelerating the convergence of restarted
+//!   GMRES", SIAM Journal on Matrix Analysis and Applications 26.4 (2005)
+
+use crate::linalg::{dot, norm, solve_upper_triangular};
+
+/// Product of the matrix with a vector
+pub(super) type MatVec<'a> = &'a mut dyn FnMut(&[f64]) -> Vec<f64>;
+
+/// Krylov subspace solver of the linear systems of `NewtonKrylov`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KrylovMethod {
+    /// GMRES with a Krylov subspace of `inner_maxiter` vectors
+    Gmres,
+    /// `BiCGStab` with at most `inner_maxiter` iterations
+    Bicgstab,
+    /// LGMRES, GMRES with a Krylov subspace of `inner_maxiter` vectors augmented with the
+    /// `outer_k` previous solutions
+    #[default]
+    Lgmres,
+}
+
+/// `y + a x`
+fn axpy(a: f64, x: &[f64], y: &mut [f64]) {
+    for (yi, xi) in y.iter_mut().zip(x) {
+        *yi += a * xi;
+    }
+}
+
+/// Flexible GMRES from `x = 0` over the Krylov subspace of `m` vectors, preceded by the search
+/// directions `outer_v`
+pub(super) fn fgmres(
+    matvec: MatVec<'_>,
+    b: &[f64],
+    m: usize,
+    rtol: f64,
+    outer_v: &[Vec<f64>],
+) -> Vec<f64> {
+    let beta = norm(b);
+    let mut x = vec![0f64; b.len()];
+    if beta == 0f64 {
+        return x;
+    }
+    let v0: Vec<f64> = b.iter().map(|bi| bi / beta).collect();
+    let mut vs = vec![v0];
+    let mut zs: Vec<Vec<f64>> = Vec::new();
+    // the Hessenberg matrix of the Arnoldi relation A Z = V H is reduced to the triangular R by
+    // Givens rotations, which also apply to beta e1 to give g
+    let mut columns: Vec<Vec<f64>> = Vec::new();
+    let mut rotations: Vec<(f64, f64)> = Vec::new();
+    let mut g = vec![beta];
+
+    for j in 0..m + outer_v.len() {
+        let z = match j.cmp(&outer_v.len()) {
+            std::cmp::Ordering::Less => outer_v[j].clone(),
+            std::cmp::Ordering::Equal => vs[0].clone(),
+            std::cmp::Ordering::Greater => vs[vs.len() - 1].clone(),
+        };
+        let mut w = matvec(&z);
+        let w_norm = norm(&w);
+        let mut column = Vec::with_capacity(j + 2);
+        for v in &vs {
+            let alpha = dot(v, &w);
+            axpy(-alpha, v, &mut w);
+            column.push(alpha);
+        }
+        let w_new_norm = norm(&w);
+        column.push(w_new_norm);
+        // w is in the span of the previous vectors, or not finite
+        let breakdown = w_new_norm <= f64::EPSILON * w_norm || !w_new_norm.is_finite();
+        if w_new_norm > 0f64 && w_new_norm.is_finite() {
+            for wi in &mut w {
+                *wi /= w_new_norm;
+            }
+        }
+        vs.push(w);
+        zs.push(z);
+
+        for (i, (c, s)) in rotations.iter().enumerate() {
+            let (a, b) = (column[i], column[i + 1]);
+            column[i] = c * a + s * b;
+            column[i + 1] = -s * a + c * b;
+        }
+        let (a, b) = (column[j], column[j + 1]);
+        let rho = a.hypot(b);
+        let (c, s) = if rho == 0f64 {
+            (1f64, 0f64)
+        } else {
+            (a / rho, b / rho)
+        };
+        column[j] = rho;
+        column.pop();
+        rotations.push((c, s));
+        g.push(-s * g[j]);
+        g[j] *= c;
+        columns.push(column);
+        if g[j + 1].abs() <= rtol * beta || breakdown {
+            break;
+        }
+    }
+
+    let k = columns.len();
+    let r: Vec<Vec<f64>> = (0..k)
+        .map(|i| {
+            (0..k)
+                .map(|j| columns[j].get(i).copied().unwrap_or(0f64))
+                .collect()
+        })
+        .collect();
+    let y = solve_upper_triangular(&r, &g[..k]);
+    for (z, yi) in zs.iter().zip(&y) {
+        axpy(*yi, z, &mut x);
+    }
+    x
+}
+
+/// `BiCGStab` from `x = 0` with at most `maxiter` iterations
+pub(super) fn bicgstab(matvec: MatVec<'_>, b: &[f64], maxiter: usize, rtol: f64) -> Vec<f64> {
+    let atol = rtol * norm(b);
+    let breakdown_tol = f64::EPSILON.powi(2);
+    let mut x = vec![0f64; b.len()];
+    let mut r = b.to_vec();
+    let r_tilde = r.clone();
+    let mut p = r.clone();
+    let mut v = vec![0f64; b.len()];
+    let (mut rho_previous, mut alpha, mut omega) = (1f64, 1f64, 1f64);
+
+    for iteration in 0..maxiter {
+        if norm(&r) <= atol {
+            break;
+        }
+        let rho = dot(&r_tilde, &r);
+        if rho.abs() < breakdown_tol {
+            break;
+        }
+        if iteration > 0 {
+            if omega.abs() < breakdown_tol {
+                break;
+            }
+            let beta = (rho / rho_previous) * (alpha / omega);
+            for ((pi, vi), ri) in p.iter_mut().zip(&v).zip(&r) {
+                *pi = ri + beta * (*pi - omega * vi);
+            }
+        }
+        v = matvec(&p);
+        let rv = dot(&r_tilde, &v);
+        if rv == 0f64 {
+            break;
+        }
+        alpha = rho / rv;
+        axpy(-alpha, &v, &mut r);
+        axpy(alpha, &p, &mut x);
+        if norm(&r) <= atol {
+            break;
+        }
+        let t = matvec(&r);
+        let tt = dot(&t, &t);
+        if tt == 0f64 {
+            break;
+        }
+        omega = dot(&t, &r) / tt;
+        axpy(omega, &r, &mut x);
+        axpy(-omega, &t, &mut r);
+        rho_previous = rho;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::mat_vec;
+    use approx::relative_eq;
+
+    #[test]
+    fn test_krylov() {
+        // nonsymmetric tridiagonal matrix
+        let n = 30usize;
+        let a: Vec<Vec<f64>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| match i.abs_diff(j) {
+                        0 => 4f64,
+                        1 if j > i => -1f64,
+                        1 => -2f64,
+                        _ => 0f64,
+                    })
+                    .collect()
+            })
+            .collect();
+        let expected: Vec<f64> = (0..30u8).map(|i| f64::from(i).sin()).collect();
+        let b = mat_vec(&a, &expected);
+        let mut matvec = |v: &[f64]| mat_vec(&a, v);
+
+        let x = fgmres(&mut matvec, &b, n, 1e-12, &[]);
+        let x_bicgstab = bicgstab(&mut matvec, &b, n, 1e-12);
+        // an outer vector along the solution solves the system in one step
+        let x_outer = fgmres(&mut matvec, &b, 1, 1e-12, std::slice::from_ref(&expected));
+        for i in 0..n {
+            assert!(relative_eq!(x[i], expected[i], epsilon = 1e-9));
+            assert!(relative_eq!(x_bicgstab[i], expected[i], epsilon = 1e-9));
+            assert!(relative_eq!(x_outer[i], expected[i], epsilon = 1e-9));
+        }
+
+        // a small subspace gives an approximate solution
+        let x = fgmres(&mut matvec, &b, 3, 1e-12, &[]);
+        assert!(x.iter().zip(&expected).any(|(a, b)| (a - b).abs() > 1e-6));
+    }
+}
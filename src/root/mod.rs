@@ -0,0 +1,476 @@
+//! Algorithms for finding the roots of systems of equations
+//!
+//! Solve `F(x) = 0` for a vector valued function `F` that implements `ResidualFunction`. `root`
+//! solves small systems with dense Jacobians, with as many equations as unknowns for `Hybr` and
+//! at least as many for `Lm`. `nonlin_solve` solves large square systems with quasi-Newton or
+//! Newton-Krylov methods, which only store a few vectors. `fixed_point` and `fixed_point_vector`
+//! solve `x = g(x)` by iteration, optionally accelerated by Steffensen's method.
+
+mod fixed_point;
+mod hybr;
+mod krylov;
+mod nonlin;
+
+pub use fixed_point::{
+    fixed_point, fixed_point_vector, FixedPointMethod, FixedPointOptions, FixedPointResult,
+};
+pub use krylov::KrylovMethod;
+pub use nonlin::{nonlin_solve, NonlinMethod, NonlinOptions, NonlinResult, NonlinStatus};
+
+use crate::differentiate::{approx_derivative_batch, DifferenceMethod, DifferentiateOptions};
+use crate::least_squares::{
+    least_squares, LeastSquaresMethod, LeastSquaresOptions, LeastSquaresStatus, ResidualFunction,
+    XScale,
+};
+use crate::linalg::{mat_t_vec, qr_pivoted};
+use crate::SwoopErrors;
+
+/// Algorithm used by `root`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootMethod {
+    /// Powell's hybrid method as implemented in MINPACK, which updates the Jacobian with Broyden
+    /// rank one updates between evaluations
+    #[default]
+    Hybr,
+    /// Levenberg-Marquardt as implemented in MINPACK, the least squares solution of the system
+    Lm,
+}
+
+/// Options for the `root` solver
+#[derive(Debug, Clone)]
+pub struct RootOptions {
+    /// Algorithm to use
+    pub method: RootMethod,
+    /// Tolerance for termination by the relative change of the variables
+    pub xtol: f64,
+    /// Tolerance for termination by the relative change of the sum of squares, only used by
+    /// `Lm`
+    pub ftol: f64,
+    /// Tolerance for termination by the cosine of the angle between the residuals and the
+    /// columns of the Jacobian, only used by `Lm`
+    pub gtol: f64,
+    /// Maximum evaluations of the function, including those used for finite differences.
+    /// Defaults to `100 * (n + 1)` with an analytic Jacobian and `200 * (n + 1)` otherwise for
+    /// `Hybr`, and to the defaults of `least_squares` for `Lm`
+    pub maxfev: Option<usize>,
+    /// Factor of the initial step bound, only used by `Hybr`
+    pub factor: f64,
+    /// Scale of each variable, the norms of the columns of the Jacobian if not given
+    pub diag: Option<Vec<f64>>,
+    /// Relative step size for the finite difference approximation of the Jacobian, defaults to
+    /// the square root of machine epsilon
+    pub diff_step: Option<f64>,
+}
+
+impl Default for RootOptions {
+    fn default() -> Self {
+        Self {
+            method: RootMethod::default(),
+            xtol: 1.49012e-8,
+            ftol: 1.49012e-8,
+            gtol: 0f64,
+            maxfev: None,
+            factor: 100f64,
+            diag: None,
+            diff_step: None,
+        }
+    }
+}
+
+/// Reason the `root` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootStatus {
+    /// The relative change of the variables is at most `xtol`, or the residuals are zero
+    StepTolerance,
+    /// The relative change of the sum of squares is at most `ftol`
+    FunctionTolerance,
+    /// Both the `ftol` and `xtol` termination conditions are satisfied
+    FunctionAndStepTolerance,
+    /// The residuals are orthogonal to the columns of the Jacobian within `gtol`
+    GradientTolerance,
+    /// The maximum number of evaluations of the function was exceeded
+    MaxFevExceeded,
+    /// `xtol` is too small, no further improvement of the solution is possible
+    XtolTooSmall,
+    /// The iteration is not making progress, as measured by the last five Jacobian evaluations
+    NoProgressJacobian,
+    /// The iteration is not making progress, as measured by the last ten iterations
+    NoProgressIterations,
+}
+
+/// Struct to represent the result of a `root` solve
+#[derive(Debug, Clone)]
+pub struct RootResult {
+    /// Residuals at the solution
+    pub fun: Vec<f64>,
+    /// Orthogonal factor `Q` of the QR factorisation of the final Jacobian, with column pivoting
+    /// for `Lm`
+    pub fjac: Vec<Vec<f64>>,
+    /// Upper triangular factor `R` of the QR factorisation of the final Jacobian
+    pub r: Vec<Vec<f64>>,
+    /// `Q^T fun`
+    pub qtf: Vec<f64>,
+    /// Number of evaluations of the function, including those used for finite differences
+    pub nfev: usize,
+    /// Number of evaluations of the Jacobian
+    pub njev: usize,
+    /// Reason the solver terminated
+    pub status: RootStatus,
+    /// Whether a root was found
+    pub success: bool,
+    /// The solution of the system
+    pub x: Vec<f64>,
+}
+
+impl From<LeastSquaresStatus> for RootStatus {
+    fn from(status: LeastSquaresStatus) -> Self {
+        match status {
+            LeastSquaresStatus::MaxFevExceeded => Self::MaxFevExceeded,
+            LeastSquaresStatus::GradientTolerance => Self::GradientTolerance,
+            LeastSquaresStatus::FunctionTolerance => Self::FunctionTolerance,
+            LeastSquaresStatus::StepTolerance => Self::StepTolerance,
+            LeastSquaresStatus::FunctionAndStepTolerance => Self::FunctionAndStepTolerance,
+        }
+    }
+}
+
+/// System of equations with counters of the evaluations
+struct System<'a, T: ResidualFunction> {
+    fun: &'a T,
+    /// Forward differences for the Jacobian when it is not given
+    diff_options: DifferentiateOptions,
+    nfev: usize,
+    njev: usize,
+}
+
+impl<T: ResidualFunction> System<'_, T> {
+    /// Evaluate the function at `x`
+    fn evaluate(&mut self, x: &[f64]) -> Vec<f64> {
+        self.nfev += 1;
+        self.fun.evaluate(x)
+    }
+
+    /// Jacobian of the function at `x`, where the function values are `f`
+    ///
+    /// # Errors
+    /// Will return `SwoopErrors::ArgumentError` if the number of function values changes
+    /// between the steps of the finite differences
+    fn jacobian(&mut self, x: &[f64], f: &[f64]) -> Result<Vec<Vec<f64>>, SwoopErrors> {
+        self.njev += 1;
+        if let Some(jacobian) = self.fun.jacobian(x) {
+            Ok(jacobian)
+        } else {
+            self.nfev += x.len();
+            let fun = self.fun;
+            approx_derivative_batch(
+                |points| fun.evaluate_batch(points),
+                x,
+                Some(f),
+                &self.diff_options,
+            )
+        }
+    }
+}
+
+/// Check the options of `root`
+fn check_options(options: &RootOptions, n: usize) -> Result<(), SwoopErrors> {
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    if options.xtol < 0f64 || options.ftol < 0f64 || options.gtol < 0f64 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    if options.factor <= 0f64 || !options.factor.is_finite() {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "factor must be positive and finite",
+        )));
+    }
+    if let Some(diag) = &options.diag {
+        if diag.len() != n || diag.iter().any(|d| *d <= 0f64 || !d.is_finite()) {
+            return Err(SwoopErrors::ArgumentError(String::from(
+                "diag must be positive and finite with one entry per variable",
+            )));
+        }
+    }
+    if options.diff_step.is_some_and(|h| h <= 0f64) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "diff_step must be positive",
+        )));
+    }
+    Ok(())
+}
+
+/// Find a root of a system of equations
+///
+/// Solve `F(x) = 0` starting from `x0`. `Hybr` requires as many equations as unknowns, `Lm`
+/// minimises the sum of squares of the equations and accepts more equations than unknowns.
+///
+/// # Parameters
+/// * `residual_function` - struct that implements the trait `ResidualFunction`, returning the
+///   value of each equation
+/// * `x0` - initial guess
+/// * `options` - solver options
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the function is not finite at
+/// `x0`, the number of equations does not suit the method, a tolerance is negative, `factor` or
+/// `diff_step` is not positive, `diag` is not positive or has the wrong length, or the number of
+/// function values changes between the steps of the finite differences
+pub async fn root<T: ResidualFunction>(
+    residual_function: T,
+    x0: &[f64],
+    options: RootOptions,
+) -> Result<RootResult, SwoopErrors> {
+    let n = x0.len();
+    check_options(&options, n)?;
+
+    match options.method {
+        RootMethod::Hybr => {
+            let mut system = System {
+                fun: &residual_function,
+                diff_options: DifferentiateOptions {
+                    method: DifferenceMethod::Forward,
+                    rel_step: options.diff_step,
+                    ..DifferentiateOptions::default()
+                },
+                nfev: 0,
+                njev: 0,
+            };
+            let f0 = system.evaluate(x0);
+            if f0.len() != n {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Method Hybr requires as many equations as variables",
+                )));
+            }
+            if f0.iter().any(|fi| !fi.is_finite()) {
+                return Err(SwoopErrors::ArgumentError(String::from(
+                    "Function is not finite in the initial point",
+                )));
+            }
+            let maxfev = options
+                .maxfev
+                .unwrap_or_else(|| match residual_function.jacobian(x0) {
+                    Some(_) => 100 * (n + 1),
+                    None => 200 * (n + 1),
+                });
+            let solution = hybr::hybr(&mut system, x0, f0, &options, maxfev)?;
+            Ok(RootResult {
+                nfev: system.nfev,
+                njev: system.njev,
+                success: solution.status == RootStatus::StepTolerance,
+                status: solution.status,
+                fun: solution.fun,
+                fjac: solution.q,
+                r: solution.r,
+                qtf: solution.qtf,
+                x: solution.x,
+            })
+        }
+        RootMethod::Lm => {
+            let result = least_squares(
+                &residual_function,
+                x0,
+                None,
+                LeastSquaresOptions {
+                    method: LeastSquaresMethod::Lm,
+                    ftol: options.ftol,
+                    xtol: options.xtol,
+                    gtol: options.gtol,
+                    x_scale: options.diag.as_ref().map_or(XScale::Jac, |diag| {
+                        XScale::Vector(diag.iter().map(|d| 1f64 / d).collect())
+                    }),
+                    diff_step: options.diff_step,
+                    max_nfev: options.maxfev,
+                    ..LeastSquaresOptions::default()
+                },
+            )
+            .await?;
+            let (q, r, _) = qr_pivoted(&result.jac, n);
+            let status = RootStatus::from(result.status);
+            Ok(RootResult {
+                qtf: mat_t_vec(&q, &result.fun, n),
+                fjac: q,
+                r,
+                nfev: result.nfev,
+                njev: result.njev,
+                success: status != RootStatus::MaxFevExceeded,
+                status,
+                fun: result.fun,
+                x: result.x,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::linalg::norm;
+    use approx::relative_eq;
+
+    /// System with the root `(0.8411639, 0.1588361)`
+    struct Cubic {
+        analytic: bool,
+    }
+
+    impl ResidualFunction for Cubic {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![
+                x[0] + 0.5 * (x[0] - x[1]).powi(3) - 1f64,
+                0.5 * (x[1] - x[0]).powi(3) + x[1],
+            ]
+        }
+
+        fn jacobian(&self, x: &[f64]) -> Option<Vec<Vec<f64>>> {
+            self.analytic.then(|| {
+                let d = 1.5 * (x[0] - x[1]).powi(2);
+                vec![vec![1f64 + d, -d], vec![-d, 1f64 + d]]
+            })
+        }
+    }
+
+    /// Broyden tridiagonal system, `(3 - 2 x_i) x_i - x_{i-1} - 2 x_{i+1} + 1 = 0`
+    struct BroydenTridiagonal {}
+
+    impl ResidualFunction for BroydenTridiagonal {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            let n = x.len();
+            (0..n)
+                .map(|i| {
+                    let previous = if i > 0 { x[i - 1] } else { 0f64 };
+                    let next = if i + 1 < n { x[i + 1] } else { 0f64 };
+                    (3f64 - 2f64 * x[i]) * x[i] - previous - 2f64 * next + 1f64
+                })
+                .collect()
+        }
+    }
+
+    /// Equation without a real root
+    struct NoRoot {}
+
+    impl ResidualFunction for NoRoot {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![x[0].powi(2) + 1f64]
+        }
+    }
+
+    /// Two equations in one unknown, consistent at `x = 1`
+    struct Line {}
+
+    impl ResidualFunction for Line {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            vec![x[0] - 1f64, 2f64 * x[0] - 2f64]
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cubic() -> Result<(), SwoopErrors> {
+        for method in [RootMethod::Hybr, RootMethod::Lm] {
+            for analytic in [true, false] {
+                let result = root(
+                    Cubic { analytic },
+                    &[0f64, 0f64],
+                    RootOptions {
+                        method,
+                        ..RootOptions::default()
+                    },
+                )
+                .await?;
+                assert!(result.success);
+                assert!(relative_eq!(result.x[0], 0.841_163_9, epsilon = 1e-7));
+                assert!(relative_eq!(result.x[1], 0.158_836_1, epsilon = 1e-7));
+                assert!(norm(&result.fun) < 1e-10);
+                assert!(result.njev >= 1);
+
+                // fjac is orthogonal and qtf its product with the residuals
+                for i in 0..2 {
+                    for j in 0..2 {
+                        let product: f64 =
+                            (0..2).map(|k| result.fjac[k][i] * result.fjac[k][j]).sum();
+                        let expected = if i == j { 1f64 } else { 0f64 };
+                        assert!(relative_eq!(product, expected, epsilon = 1e-12));
+                    }
+                }
+                let qtf = mat_t_vec(&result.fjac, &result.fun, 2);
+                assert!(relative_eq!(qtf[0], result.qtf[0], epsilon = 1e-15));
+                assert!(relative_eq!(qtf[1], result.qtf[1], epsilon = 1e-15));
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broyden_tridiagonal() -> Result<(), SwoopErrors> {
+        for method in [RootMethod::Hybr, RootMethod::Lm] {
+            let result = root(
+                BroydenTridiagonal {},
+                &[-1f64; 10],
+                RootOptions {
+                    method,
+                    ..RootOptions::default()
+                },
+            )
+            .await?;
+            assert!(result.success);
+            // xtol bounds the error of the solution, not of the residuals
+            assert!(norm(&BroydenTridiagonal {}.evaluate(&result.x)) < 1e-7);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_status() -> Result<(), SwoopErrors> {
+        let result = root(
+            BroydenTridiagonal {},
+            &[-1f64; 10],
+            RootOptions {
+                maxfev: Some(15),
+                ..RootOptions::default()
+            },
+        )
+        .await?;
+        assert_eq!(result.status, RootStatus::MaxFevExceeded);
+        assert!(!result.success);
+
+        // x^2 + 1 has no real root
+        let result = root(NoRoot {}, &[1f64], RootOptions::default()).await?;
+        assert!(!result.success);
+        assert!(result.x[0].abs() < 1e-3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_validation() {
+        let result = root(Cubic { analytic: true }, &[], RootOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+
+        let result = root(Line {}, &[0f64], RootOptions::default()).await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        let result = root(
+            Line {},
+            &[0f64],
+            RootOptions {
+                method: RootMethod::Lm,
+                ..RootOptions::default()
+            },
+        )
+        .await;
+        assert!(result.is_ok());
+
+        let result = root(
+            Cubic { analytic: true },
+            &[0f64, 0f64],
+            RootOptions {
+                diag: Some(vec![1f64]),
+                ..RootOptions::default()
+            },
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+    }
+}
@@ -0,0 +1,866 @@
+//! Large scale solvers for systems of equations that never form a dense Jacobian
+//!
+//! Each iteration takes the step `-J^{-1} F(x)`, optionally shortened by a line search, with an
+//! approximation of the inverse Jacobian. The quasi-Newton methods start from `J = -1 / alpha`
+//! and update the approximation with the observed changes of `x` and `F(x)`, storing only a few
+//! vectors per iteration. `NewtonKrylov` solves for the exact Newton step with a Krylov subspace
+//! method, approximating the products of the Jacobian with vectors by finite differences.
+//!
+//! # References
+//! * Kelley, "Solving Nonlinear Equations with Newton's Method", SIAM (2003)
+//! * Knoll and Keyes, "Jacobian-free Newton-Krylov methods: a survey of approaches and
+//!   applications", Journal of Computational Physics 193.2 (2004)
+//! * Eyert, "A comparative study on methods for convergence acceleration of iterative vector
+//!   sequences", Journal of Computational Physics 124.2 (1996)
+
+use super::krylov::{bicgstab, fgmres, KrylovMethod, MatVec};
+use crate::least_squares::ResidualFunction;
+use crate::linalg::{dot, inverse, mat_vec, norm, norm_inf};
+use crate::minimise_multivariate::MultivariateCallback;
+use crate::SwoopErrors;
+
+/// Minimum step of the line search, shorter steps are replaced by the full step
+const LINE_SEARCH_MIN_STEP: f64 = 1e-2;
+
+/// Approximation of the Jacobian used by `nonlin_solve`
+///
+/// `alpha` is the initial Jacobian approximation `-1 / alpha` of the quasi-Newton methods, which
+/// defaults to `0.5 * max(||x0||, 1) / ||F(x0)||`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NonlinMethod {
+    /// Broyden's good method, a rank one update of the inverse Jacobian such that the image of
+    /// the step is only changed along its direction
+    Broyden1 {
+        /// Initial Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+        /// Maximum rank of the update, the approximation restarts from `-1 / alpha` when it is
+        /// reached. Unlimited if not given
+        max_rank: Option<usize>,
+    },
+    /// Broyden's bad method, a rank one update of the inverse Jacobian of least change
+    Broyden2 {
+        /// Initial Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+        /// Maximum rank of the update, the approximation restarts from `-1 / alpha` when it is
+        /// reached. Unlimited if not given
+        max_rank: Option<usize>,
+    },
+    /// Anderson mixing over the last `m` steps, usually `0.01` for `w0` and `5` for `m`
+    Anderson {
+        /// Initial Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+        /// Regularisation weight for numerical stability
+        w0: f64,
+        /// Number of previous steps to retain
+        m: usize,
+    },
+    /// The constant Jacobian approximation `-1 / alpha`
+    LinearMixing {
+        /// Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+    },
+    /// Diagonal Broyden approximation of the Jacobian
+    DiagBroyden {
+        /// Initial Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+    },
+    /// Diagonal Jacobian approximation whose entries grow by `alpha` while the sign of the
+    /// corresponding residual is unchanged, up to `alphamax`, usually `1`
+    ExcitingMixing {
+        /// Initial Jacobian approximation is `-1 / alpha`
+        alpha: Option<f64>,
+        /// Largest entry of the diagonal inverse Jacobian
+        alphamax: f64,
+    },
+    /// Inexact Newton steps by a Krylov subspace method, usually with `20` for `inner_maxiter`
+    /// and `10` for `outer_k`
+    NewtonKrylov {
+        /// Krylov subspace method of the inner linear solves
+        method: KrylovMethod,
+        /// Maximum size of the Krylov subspace, or iterations of `Bicgstab`
+        inner_maxiter: usize,
+        /// Number of previous steps that augment the subspace of `Lgmres`
+        outer_k: usize,
+        /// Relative step of the finite differences, defaults to the square root of machine
+        /// epsilon
+        rdiff: Option<f64>,
+    },
+}
+
+/// Options for the `nonlin_solve` solver
+#[derive(Debug, Clone)]
+pub struct NonlinOptions {
+    /// Absolute tolerance of the maximum norm of the residuals
+    pub fatol: f64,
+    /// Tolerance of the maximum norm of the residuals relative to the initial residuals
+    pub ftol: f64,
+    /// Absolute tolerance of the maximum norm of the step
+    pub xatol: f64,
+    /// Tolerance of the maximum norm of the step relative to the maximum norm of `x`
+    pub xtol: f64,
+    /// Maximum iterations, defaults to `100 * (n + 1)`
+    pub maxiter: Option<usize>,
+    /// Shorten the steps with an Armijo backtracking line search on `||F(x)||^2`
+    pub line_search: bool,
+}
+
+impl Default for NonlinOptions {
+    fn default() -> Self {
+        Self {
+            fatol: f64::EPSILON.cbrt(),
+            ftol: f64::INFINITY,
+            xatol: f64::INFINITY,
+            xtol: f64::INFINITY,
+            maxiter: None,
+            line_search: true,
+        }
+    }
+}
+
+/// Reason the `nonlin_solve` solver terminated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonlinStatus {
+    /// All the tolerances are satisfied
+    Converged,
+    /// The maximum number of iterations was reached
+    MaxIterExceeded,
+    /// The callback requested the solver to stop
+    CallbackTerminated,
+}
+
+/// Struct to represent the result of a `nonlin_solve` solve
+#[derive(Debug, Clone)]
+pub struct NonlinResult {
+    /// Residuals at the solution
+    pub fun: Vec<f64>,
+    /// Number of evaluations of the function, including the line search and the finite
+    /// differences
+    pub nfev: usize,
+    /// Number of iterations
+    pub nit: usize,
+    /// Reason the solver terminated
+    pub status: NonlinStatus,
+    /// Whether the tolerances were satisfied
+    pub success: bool,
+    /// The solution of the system
+    pub x: Vec<f64>,
+}
+
+/// Approximation of the inverse Jacobian
+trait Approximation {
+    /// Approximate `J^{-1} f`, to the relative tolerance `tol` for iterative solves
+    fn solve(&mut self, f: &[f64], tol: f64, fun: MatVec<'_>) -> Vec<f64>;
+
+    /// Update the approximation at the new point `x` with residuals `f`, after the step `dx`
+    /// that changed the residuals by `df`
+    fn update(&mut self, x: &[f64], f: &[f64], dx: &[f64], df: &[f64]);
+}
+
+/// Inverse Jacobian `-alpha I + sum c_i d_i^T` of the Broyden methods
+struct LowRank {
+    alpha: f64,
+    cs: Vec<Vec<f64>>,
+    ds: Vec<Vec<f64>>,
+    max_rank: Option<usize>,
+    good: bool,
+}
+
+impl LowRank {
+    fn matvec(&self, v: &[f64]) -> Vec<f64> {
+        let mut w: Vec<f64> = v.iter().map(|vi| -self.alpha * vi).collect();
+        for (c, d) in self.cs.iter().zip(&self.ds) {
+            let scale = dot(d, v);
+            w.iter_mut().zip(c).for_each(|(wi, ci)| *wi += scale * ci);
+        }
+        w
+    }
+
+    fn rmatvec(&self, v: &[f64]) -> Vec<f64> {
+        let mut w: Vec<f64> = v.iter().map(|vi| -self.alpha * vi).collect();
+        for (c, d) in self.cs.iter().zip(&self.ds) {
+            let scale = dot(c, v);
+            w.iter_mut().zip(d).for_each(|(wi, di)| *wi += scale * di);
+        }
+        w
+    }
+}
+
+impl Approximation for LowRank {
+    fn solve(&mut self, f: &[f64], _tol: f64, _fun: MatVec<'_>) -> Vec<f64> {
+        self.matvec(f)
+    }
+
+    fn update(&mut self, _x: &[f64], _f: &[f64], dx: &[f64], df: &[f64]) {
+        // restart before the update to keep the secant condition
+        if self.max_rank.is_some_and(|rank| self.cs.len() >= rank) {
+            self.cs.clear();
+            self.ds.clear();
+        }
+        let v = if self.good {
+            self.rmatvec(dx)
+        } else {
+            df.to_vec()
+        };
+        let denominator = dot(df, &v);
+        if denominator == 0f64 || !denominator.is_finite() {
+            return;
+        }
+        let g_df = self.matvec(df);
+        self.cs
+            .push(dx.iter().zip(&g_df).map(|(a, b)| a - b).collect());
+        self.ds.push(v.iter().map(|vi| vi / denominator).collect());
+    }
+}
+
+/// Anderson mixing over the last `m` steps
+struct Anderson {
+    alpha: f64,
+    w0: f64,
+    m: usize,
+    dxs: Vec<Vec<f64>>,
+    dfs: Vec<Vec<f64>>,
+    a: Vec<Vec<f64>>,
+}
+
+impl Approximation for Anderson {
+    fn solve(&mut self, f: &[f64], _tol: f64, _fun: MatVec<'_>) -> Vec<f64> {
+        let mut dx: Vec<f64> = f.iter().map(|fi| -self.alpha * fi).collect();
+        if self.dxs.is_empty() {
+            return dx;
+        }
+        let df_f: Vec<f64> = self.dfs.iter().map(|df| dot(df, f)).collect();
+        let Some(inv) = inverse(&self.a) else {
+            // reset the approximation when the system is singular
+            self.dxs.clear();
+            self.dfs.clear();
+            return dx;
+        };
+        let gamma = mat_vec(&inv, &df_f);
+        for ((g, dxm), dfm) in gamma.iter().zip(&self.dxs).zip(&self.dfs) {
+            for ((d, a), b) in dx.iter_mut().zip(dxm).zip(dfm) {
+                *d += g * (a + self.alpha * b);
+            }
+        }
+        dx
+    }
+
+    fn update(&mut self, _x: &[f64], _f: &[f64], dx: &[f64], df: &[f64]) {
+        if self.m == 0 {
+            return;
+        }
+        self.dxs.push(dx.to_vec());
+        self.dfs.push(df.to_vec());
+        if self.dxs.len() > self.m {
+            self.dxs.remove(0);
+            self.dfs.remove(0);
+        }
+        let n = self.dxs.len();
+        self.a = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        let weight = if i == j { 1f64 + self.w0.powi(2) } else { 1f64 };
+                        weight * dot(&self.dfs[i], &self.dfs[j])
+                    })
+                    .collect()
+            })
+            .collect();
+    }
+}
+
+/// Diagonal inverse Jacobian `-diag(beta)`, updated by the Broyden method if `exciting` is not
+/// set and by exciting mixing otherwise
+struct Diagonal {
+    alpha: f64,
+    beta: Vec<f64>,
+    update: DiagonalUpdate,
+    last_f: Vec<f64>,
+}
+
+/// Update rule of a diagonal inverse Jacobian
+enum DiagonalUpdate {
+    Constant,
+    Broyden,
+    Exciting { alphamax: f64 },
+}
+
+impl Approximation for Diagonal {
+    fn solve(&mut self, f: &[f64], _tol: f64, _fun: MatVec<'_>) -> Vec<f64> {
+        f.iter().zip(&self.beta).map(|(fi, b)| -fi * b).collect()
+    }
+
+    fn update(&mut self, _x: &[f64], f: &[f64], dx: &[f64], df: &[f64]) {
+        match self.update {
+            DiagonalUpdate::Constant => {}
+            DiagonalUpdate::Broyden => {
+                // the diagonal Jacobian -1 / beta takes the secant update
+                let dx_norm2 = dot(dx, dx);
+                for ((b, dxi), dfi) in self.beta.iter_mut().zip(dx).zip(df) {
+                    let d = 1f64 / *b - (dfi + dxi / *b) * dxi / dx_norm2;
+                    *b = 1f64 / d;
+                }
+            }
+            DiagonalUpdate::Exciting { alphamax } => {
+                for ((b, fi), last) in self.beta.iter_mut().zip(f).zip(&self.last_f) {
+                    *b = if fi * last > 0f64 {
+                        *b + self.alpha
+                    } else {
+                        self.alpha
+                    };
+                    *b = b.clamp(0f64, alphamax);
+                }
+            }
+        }
+        self.last_f = f.to_vec();
+    }
+}
+
+/// Jacobian-free Newton-Krylov, with the products of the Jacobian approximated by forward
+/// differences
+struct Krylov {
+    method: KrylovMethod,
+    inner_maxiter: usize,
+    outer_k: usize,
+    rdiff: f64,
+    x0: Vec<f64>,
+    f0: Vec<f64>,
+    omega: f64,
+    outer_v: Vec<Vec<f64>>,
+}
+
+impl Krylov {
+    /// Update the finite difference step at the new point
+    fn update_step(&mut self) {
+        let mx = norm_inf(&self.x0);
+        let mf = norm_inf(&self.f0);
+        self.omega = self.rdiff * mx.max(1f64) / mf.max(1f64);
+    }
+}
+
+impl Approximation for Krylov {
+    fn solve(&mut self, f: &[f64], tol: f64, fun: MatVec<'_>) -> Vec<f64> {
+        let (x0, f0, omega) = (&self.x0, &self.f0, self.omega);
+        let mut matvec = |v: &[f64]| -> Vec<f64> {
+            let nv = norm(v);
+            if nv == 0f64 {
+                return vec![0f64; v.len()];
+            }
+            let sc = omega / nv;
+            let x: Vec<f64> = x0.iter().zip(v).map(|(xi, vi)| xi + sc * vi).collect();
+            fun(&x).iter().zip(f0).map(|(a, b)| (a - b) / sc).collect()
+        };
+        match self.method {
+            KrylovMethod::Gmres => fgmres(&mut matvec, f, self.inner_maxiter, tol, &[]),
+            KrylovMethod::Bicgstab => bicgstab(&mut matvec, f, self.inner_maxiter, tol),
+            KrylovMethod::Lgmres => {
+                let dx = fgmres(&mut matvec, f, self.inner_maxiter, tol, &self.outer_v);
+                let nx = norm(&dx);
+                if nx > 0f64 {
+                    // the previous steps augment the subspace of the next solves
+                    self.outer_v.push(dx.iter().map(|d| d / nx).collect());
+                    if self.outer_v.len() > self.outer_k {
+                        self.outer_v.remove(0);
+                    }
+                }
+                dx
+            }
+        }
+    }
+
+    fn update(&mut self, x: &[f64], f: &[f64], _dx: &[f64], _df: &[f64]) {
+        self.x0 = x.to_vec();
+        self.f0 = f.to_vec();
+        self.update_step();
+    }
+}
+
+/// Approximation of the inverse Jacobian of `method` at the initial point
+fn approximation(method: &NonlinMethod, x0: &[f64], f0: &[f64]) -> Box<dyn Approximation> {
+    let default_alpha = |alpha: Option<f64>| {
+        alpha.unwrap_or_else(|| {
+            let normf0 = norm(f0);
+            if normf0 == 0f64 {
+                1f64
+            } else {
+                0.5 * norm(x0).max(1f64) / normf0
+            }
+        })
+    };
+    let diagonal = |alpha: f64, update: DiagonalUpdate| {
+        Box::new(Diagonal {
+            alpha,
+            beta: vec![alpha; x0.len()],
+            update,
+            last_f: f0.to_vec(),
+        })
+    };
+    match *method {
+        NonlinMethod::Broyden1 { alpha, max_rank } | NonlinMethod::Broyden2 { alpha, max_rank } => {
+            Box::new(LowRank {
+                alpha: default_alpha(alpha),
+                cs: Vec::new(),
+                ds: Vec::new(),
+                max_rank,
+                good: matches!(method, NonlinMethod::Broyden1 { .. }),
+            })
+        }
+        NonlinMethod::Anderson { alpha, w0, m } => Box::new(Anderson {
+            alpha: default_alpha(alpha),
+            w0,
+            m,
+            dxs: Vec::new(),
+            dfs: Vec::new(),
+            a: Vec::new(),
+        }),
+        NonlinMethod::LinearMixing { alpha } => {
+            diagonal(default_alpha(alpha), DiagonalUpdate::Constant)
+        }
+        NonlinMethod::DiagBroyden { alpha } => {
+            diagonal(default_alpha(alpha), DiagonalUpdate::Broyden)
+        }
+        NonlinMethod::ExcitingMixing { alpha, alphamax } => {
+            diagonal(default_alpha(alpha), DiagonalUpdate::Exciting { alphamax })
+        }
+        NonlinMethod::NewtonKrylov {
+            method,
+            inner_maxiter,
+            outer_k,
+            rdiff,
+        } => {
+            let mut krylov = Krylov {
+                method,
+                inner_maxiter,
+                outer_k,
+                rdiff: rdiff.unwrap_or_else(|| f64::EPSILON.sqrt()),
+                x0: x0.to_vec(),
+                f0: f0.to_vec(),
+                omega: 0f64,
+                outer_v: Vec::new(),
+            };
+            krylov.update_step();
+            Box::new(krylov)
+        }
+    }
+}
+
+/// Armijo backtracking line search on `phi(s) = ||F(x + s dx)||^2`, with a quadratic and then
+/// cubic interpolation of `phi`. Returns the step, the new point and its residuals.
+fn line_search(fun: MatVec<'_>, x: &[f64], f: &[f64], dx: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let c1 = 1e-4;
+    let phi0 = dot(f, f);
+    // descent rate of the Newton direction
+    let derphi0 = -phi0;
+    let mut trial = |s: f64| {
+        let x_trial: Vec<f64> = x.iter().zip(dx).map(|(xi, di)| xi + s * di).collect();
+        let f_trial = fun(&x_trial);
+        let phi = dot(&f_trial, &f_trial);
+        (
+            x_trial,
+            f_trial,
+            if phi.is_nan() { f64::INFINITY } else { phi },
+        )
+    };
+    let sufficient = |s: f64, phi: f64| phi <= phi0 + c1 * s * derphi0;
+
+    let (mut alpha0, mut alpha1) = (1f64, 1f64);
+    let (x_full, f_full, mut phi_a0) = trial(alpha0);
+    if sufficient(alpha0, phi_a0) {
+        return (x_full, f_full);
+    }
+    alpha1 = -derphi0 * alpha1.powi(2) / 2f64 / (phi_a0 - phi0 - derphi0 * alpha1);
+    let (x1, f1, mut phi_a1) = trial(alpha1);
+    if sufficient(alpha1, phi_a1) {
+        return (x1, f1);
+    }
+    while alpha1 > LINE_SEARCH_MIN_STEP {
+        let factor = alpha0.powi(2) * alpha1.powi(2) * (alpha1 - alpha0);
+        let a = (alpha0.powi(2) * (phi_a1 - phi0 - derphi0 * alpha1)
+            - alpha1.powi(2) * (phi_a0 - phi0 - derphi0 * alpha0))
+            / factor;
+        let b = (-alpha0.powi(3) * (phi_a1 - phi0 - derphi0 * alpha1)
+            + alpha1.powi(3) * (phi_a0 - phi0 - derphi0 * alpha0))
+            / factor;
+        let mut alpha2 = (-b + (b.powi(2) - 3f64 * a * derphi0).abs().sqrt()) / (3f64 * a);
+        let (x2, f2, phi_a2) = trial(alpha2);
+        if sufficient(alpha2, phi_a2) {
+            return (x2, f2);
+        }
+        if alpha1 - alpha2 > alpha1 / 2f64 || 1f64 - alpha2 / alpha1 < 0.96 || !alpha2.is_finite() {
+            alpha2 = alpha1 / 2f64;
+        }
+        alpha0 = alpha1;
+        alpha1 = alpha2;
+        phi_a0 = phi_a1;
+        phi_a1 = phi_a2;
+    }
+    // no suitable step was found, take the full step
+    (x_full, f_full)
+}
+
+/// Check the options of `nonlin_solve`
+fn check_options(
+    method: &NonlinMethod,
+    options: &NonlinOptions,
+    n: usize,
+) -> Result<(), SwoopErrors> {
+    if n == 0 {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Initial guess must have at least one variable",
+        )));
+    }
+    let tolerances = [options.fatol, options.ftol, options.xatol, options.xtol];
+    if tolerances.iter().any(|tol| *tol < 0f64 || tol.is_nan()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Tolerance cannot be negative",
+        )));
+    }
+    let valid = match *method {
+        NonlinMethod::Broyden1 { alpha, .. }
+        | NonlinMethod::Broyden2 { alpha, .. }
+        | NonlinMethod::LinearMixing { alpha }
+        | NonlinMethod::DiagBroyden { alpha } => alpha.map_or(true, |a| a > 0f64),
+        NonlinMethod::Anderson { alpha, w0, .. } => alpha.map_or(true, |a| a > 0f64) && w0 >= 0f64,
+        NonlinMethod::ExcitingMixing { alpha, alphamax } => {
+            alpha.map_or(true, |a| a > 0f64) && alphamax > 0f64
+        }
+        NonlinMethod::NewtonKrylov {
+            inner_maxiter,
+            rdiff,
+            ..
+        } => inner_maxiter > 0 && rdiff.map_or(true, |r| r > 0f64),
+    };
+    if !valid {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The parameters of the method must be positive",
+        )));
+    }
+    Ok(())
+}
+
+/// Solve a large system of equations without a dense Jacobian
+///
+/// Find `x` such that `F(x) = 0`, with the steps of a quasi-Newton method or an inexact Newton
+/// method with a Krylov subspace solver. The solver stops when the maximum norm of the residuals
+/// is at most `fatol` and `ftol` times the initial residuals, and the maximum norm of the step is
+/// at most `xatol` and `xtol` times the maximum norm of `x`.
+///
+/// # Parameters
+/// * `residual_function` - struct that implements the trait `ResidualFunction`, returning as
+///   many values as there are variables
+/// * `x0` - initial guess
+/// * `method` - approximation of the Jacobian
+/// * `options` - solver options
+/// * `callback` - optional callback called after each iteration with the current point and the
+///   maximum norm of its residuals, returning `true` stops the solver
+///
+/// # Errors
+/// Will return `SwoopErrors::ArgumentError` if `x0` is empty, the function does not return one
+/// value per variable or is not finite at `x0`, a tolerance is negative or a parameter of the
+/// method is not positive, and `SwoopErrors::Other` if the approximate Jacobian gives a zero or
+/// non-finite step
+#[allow(clippy::too_many_lines)]
+pub async fn nonlin_solve<T: ResidualFunction>(
+    residual_function: T,
+    x0: &[f64],
+    method: &NonlinMethod,
+    options: NonlinOptions,
+    mut callback: Option<MultivariateCallback<'_>>,
+) -> Result<NonlinResult, SwoopErrors> {
+    let n = x0.len();
+    check_options(method, &options, n)?;
+    let mut nfev = 0;
+    let mut fun = |x: &[f64]| {
+        nfev += 1;
+        residual_function.evaluate(x)
+    };
+
+    let mut x = x0.to_vec();
+    let mut f = fun(&x);
+    if f.len() != n {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "The function must return one value per variable",
+        )));
+    }
+    if f.iter().any(|fi| !fi.is_finite()) {
+        return Err(SwoopErrors::ArgumentError(String::from(
+            "Function is not finite in the initial point",
+        )));
+    }
+    let mut jacobian = approximation(method, &x, &f);
+    let maxiter = options.maxiter.unwrap_or(100 * (n + 1));
+    let f0_norm = norm_inf(&f);
+    let mut f_norm = norm(&f);
+    let mut dx_norm = f64::INFINITY;
+
+    // forcing terms of the inexact Newton steps
+    let (gamma, eta_max, eta_threshold) = (0.9, 0.9999f64, 0.1);
+    let mut eta = 1e-3f64;
+
+    let mut nit = 0;
+    let status = loop {
+        let f_max = norm_inf(&f);
+        if f_max == 0f64
+            || (f_max <= options.fatol
+                && f_max / options.ftol <= f0_norm
+                && dx_norm <= options.xatol
+                && dx_norm / options.xtol <= norm_inf(&x))
+        {
+            break NonlinStatus::Converged;
+        }
+        if nit >= maxiter {
+            break NonlinStatus::MaxIterExceeded;
+        }
+        nit += 1;
+
+        let tol = eta.min(eta * f_norm);
+        let dx: Vec<f64> = jacobian
+            .solve(&f, tol, &mut fun)
+            .iter()
+            .map(|d| -d)
+            .collect();
+        if dx.iter().all(|d| *d == 0f64) || dx.iter().any(|d| !d.is_finite()) {
+            return Err(SwoopErrors::Other(anyhow::anyhow!(
+                "The Jacobian approximation gave a zero or non-finite step"
+            )));
+        }
+
+        let (x_new, f_new) = if options.line_search {
+            line_search(&mut fun, &x, &f, &dx)
+        } else {
+            let x_new: Vec<f64> = x.iter().zip(&dx).map(|(xi, di)| xi + di).collect();
+            let f_new = fun(&x_new);
+            (x_new, f_new)
+        };
+        let step: Vec<f64> = x_new.iter().zip(&x).map(|(a, b)| a - b).collect();
+        let df: Vec<f64> = f_new.iter().zip(&f).map(|(a, b)| a - b).collect();
+        dx_norm = norm_inf(&step);
+        jacobian.update(&x_new, &f_new, &step, &df);
+        x = x_new;
+        f = f_new;
+
+        if let Some(callback) = callback.as_mut() {
+            if callback(&x, norm_inf(&f)) {
+                break NonlinStatus::CallbackTerminated;
+            }
+        }
+
+        let f_norm_new = norm(&f);
+        let eta_a = gamma * f_norm_new.powi(2) / f_norm.powi(2);
+        eta = if gamma * eta.powi(2) < eta_threshold {
+            eta_max.min(eta_a)
+        } else {
+            eta_max.min(eta_a.max(gamma * eta.powi(2)))
+        };
+        f_norm = f_norm_new;
+    };
+
+    Ok(NonlinResult {
+        fun: f,
+        nfev,
+        nit,
+        success: status == NonlinStatus::Converged,
+        status,
+        x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::relative_eq;
+
+    /// Discretised `u'' = u^3 - 1` on `(0, 1)` with `u(0) = u(1) = 0`, in the variables
+    /// `x = u + 1`
+    struct Bvp {
+        n: usize,
+    }
+
+    impl ResidualFunction for Bvp {
+        #[allow(clippy::cast_precision_loss)]
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            let h2 = (1f64 / (self.n + 1) as f64).powi(2);
+            (0..self.n)
+                .map(|i| {
+                    let u = x[i] - 1f64;
+                    let left = if i > 0 { x[i - 1] - 1f64 } else { 0f64 };
+                    let right = if i + 1 < self.n {
+                        x[i + 1] - 1f64
+                    } else {
+                        0f64
+                    };
+                    (left - 2f64 * u + right) / h2 - u.powi(3) + 1f64
+                })
+                .collect()
+        }
+    }
+
+    /// Fixed point problem `F(x) = cos(x) / 2 - x` with a contraction `cos(x) / 2`
+    struct Contraction {}
+
+    impl ResidualFunction for Contraction {
+        fn evaluate(&self, x: &[f64]) -> Vec<f64> {
+            let n = x.len();
+            (0..n)
+                .map(|i| 0.5 * (x[i] + x[(i + 1) % n]).cos() / 2f64 - x[i])
+                .collect()
+        }
+    }
+
+    fn methods() -> Vec<NonlinMethod> {
+        vec![
+            NonlinMethod::Broyden1 {
+                alpha: None,
+                max_rank: None,
+            },
+            NonlinMethod::Broyden2 {
+                alpha: None,
+                max_rank: Some(20),
+            },
+            NonlinMethod::Anderson {
+                alpha: None,
+                w0: 0.01,
+                m: 5,
+            },
+            NonlinMethod::LinearMixing { alpha: Some(0.5) },
+            NonlinMethod::DiagBroyden { alpha: Some(0.5) },
+            NonlinMethod::ExcitingMixing {
+                alpha: Some(0.5),
+                alphamax: 1f64,
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_quasi_newton() -> Result<(), SwoopErrors> {
+        for method in methods() {
+            let result = nonlin_solve(
+                Contraction {},
+                &[0f64; 20],
+                &method,
+                NonlinOptions {
+                    fatol: 1e-10,
+                    ..NonlinOptions::default()
+                },
+                None,
+            )
+            .await?;
+            assert!(result.success, "{method:?}");
+            assert!(norm_inf(&result.fun) <= 1e-10);
+            // the solution is the fixed point of cos(2 x) / 4
+            for xi in &result.x {
+                assert!(relative_eq!(xi * 4f64, (2f64 * xi).cos(), epsilon = 1e-8));
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_newton_krylov() -> Result<(), SwoopErrors> {
+        let problem = Bvp { n: 200 };
+        for method in [
+            KrylovMethod::Gmres,
+            KrylovMethod::Bicgstab,
+            KrylovMethod::Lgmres,
+        ] {
+            let result = nonlin_solve(
+                &problem,
+                &vec![1f64; 200],
+                &NonlinMethod::NewtonKrylov {
+                    method,
+                    inner_maxiter: 50,
+                    outer_k: 10,
+                    rdiff: None,
+                },
+                NonlinOptions {
+                    fatol: 1e-8,
+                    ..NonlinOptions::default()
+                },
+                None,
+            )
+            .await?;
+            assert!(result.success, "{method:?}");
+            assert!(norm_inf(&problem.evaluate(&result.x)) <= 1e-8);
+            // the solution is symmetric with a maximum near 1/8 + 1 at the centre
+            assert!(relative_eq!(result.x[0], result.x[199], epsilon = 1e-6));
+            assert!(result.x[100] > 1.12 && result.x[100] < 1.125);
+        }
+
+        // the broyden methods also converge, without line search
+        let result = nonlin_solve(
+            &problem,
+            &vec![1f64; 200],
+            &NonlinMethod::Broyden1 {
+                alpha: None,
+                max_rank: None,
+            },
+            NonlinOptions {
+                fatol: 1e-8,
+                line_search: false,
+                maxiter: Some(10),
+                ..NonlinOptions::default()
+            },
+            None,
+        )
+        .await?;
+        assert_eq!(result.status, NonlinStatus::MaxIterExceeded);
+        assert!(!result.success);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stopping() -> Result<(), SwoopErrors> {
+        let method = NonlinMethod::LinearMixing { alpha: Some(0.5) };
+        let loose = nonlin_solve(
+            Contraction {},
+            &[0f64; 5],
+            &method,
+            NonlinOptions {
+                fatol: 1e-3,
+                ..NonlinOptions::default()
+            },
+            None,
+        )
+        .await?;
+        let tight = nonlin_solve(
+            Contraction {},
+            &[0f64; 5],
+            &method,
+            NonlinOptions {
+                fatol: 1e-12,
+                xatol: 1e-12,
+                ..NonlinOptions::default()
+            },
+            None,
+        )
+        .await?;
+        assert!(loose.success && tight.success);
+        assert!(loose.nit < tight.nit);
+
+        let mut calls = 0;
+        let mut callback = |_: &[f64], _: f64| {
+            calls += 1;
+            calls == 2
+        };
+        let result = nonlin_solve(
+            Contraction {},
+            &[0f64; 5],
+            &method,
+            NonlinOptions::default(),
+            Some(&mut callback),
+        )
+        .await?;
+        assert_eq!(result.status, NonlinStatus::CallbackTerminated);
+        assert_eq!(result.nit, 2);
+
+        let result = nonlin_solve(
+            Contraction {},
+            &[0f64; 5],
+            &NonlinMethod::LinearMixing { alpha: Some(-1f64) },
+            NonlinOptions::default(),
+            None,
+        )
+        .await;
+        assert!(matches!(result, Err(SwoopErrors::ArgumentError(_))));
+        Ok(())
+    }
+}